@@ -6,7 +6,7 @@ use failure::{Backtrace, Context, Fail};
 use log::error;
 
 use near_primitives::block::BlockValidityError;
-use near_primitives::challenge::{ChunkProofs, ChunkState};
+use near_primitives::challenge::{ChallengeBody, ChunkProofs};
 use near_primitives::errors::{EpochError, StorageError};
 use near_primitives::serialize::to_base;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
@@ -58,6 +58,11 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("The page limit must be greater than zero")]
+    InvalidPageLimit {
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
 }
 
 #[derive(Debug)]
@@ -136,9 +141,9 @@ pub enum ErrorKind {
     /// One of the chunks has invalid proofs
     #[fail(display = "Invalid Chunk Proofs")]
     InvalidChunkProofs(Box<ChunkProofs>),
-    /// Invalid chunk state.
+    /// Invalid chunk state, together with the chunk state challenge for it.
     #[fail(display = "Invalid Chunk State")]
-    InvalidChunkState(Box<ChunkState>),
+    InvalidChunkState(Box<ChallengeBody>),
     /// Invalid chunk mask
     #[fail(display = "Invalid Chunk Mask")]
     InvalidChunkMask,
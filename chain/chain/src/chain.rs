@@ -17,10 +17,12 @@ use near_primitives::challenge::{
     BlockDoubleSign, Challenge, ChallengeBody, ChallengesResult, ChunkProofs, ChunkState,
     MaybeEncodedShardChunk, SlashedValidator,
 };
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::challenge::{ChunkStateIncomingReceipts, ChunkStateV2};
 use near_primitives::checked_feature;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{
-    combine_hash, merklize, verify_path, Direction, MerklePath, MerklePathItem,
+    combine_hash, merklize, verify_path, Direction, MerklePath, MerklePathItem, PartialMerkleTree,
 };
 use near_primitives::receipt::Receipt;
 use near_primitives::sharding::{
@@ -36,17 +38,19 @@ use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
     AccountId, Balance, BlockExtra, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash,
-    NumBlocks, ShardId,
+    NumBlocks, ShardId, StateRoot,
 };
 use near_primitives::unwrap_or_return;
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_primitives::version::ProtocolFeature;
 use near_primitives::views::{
-    ExecutionOutcomeWithIdView, ExecutionStatusView, FinalExecutionOutcomeView,
-    FinalExecutionOutcomeWithReceiptView, FinalExecutionStatus, LightClientBlockView,
-    SignedTransactionView,
+    ExecutionOutcomeWithIdView, FinalExecutionOutcomeView, FinalExecutionOutcomeWithReceiptView,
+    FinalExecutionStatus, LightClientBlockView, SignedTransactionView,
+};
+use near_store::{
+    ColBlockMisc, ColState, ColStateDlInfos, ColStateHeaders, ColStateParts, ColTrieChanges,
+    ShardTries, Store, StoreUpdate, STATE_SYNC_PROGRESS_KEY,
 };
-use near_store::{ColState, ColStateHeaders, ColStateParts, ShardTries, StoreUpdate};
 
 use near_primitives::state_record::StateRecord;
 
@@ -66,7 +70,7 @@ use crate::{byzantine_assert, create_light_client_block_view, Doomslug};
 use crate::{metrics, DoomslugThresholdMode};
 #[cfg(feature = "delay_detector")]
 use delay_detector::DelayDetector;
-use near_primitives::shard_layout::ShardUId;
+use near_primitives::shard_layout::{get_block_shard_uid_rev, ShardUId};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 /// Maximum number of orphans chain can store.
@@ -637,6 +641,35 @@ impl Chain {
         Ok(())
     }
 
+    /// Returns the shards whose state may be removed: the node cares about them neither in the
+    /// epoch of the head nor in the next one, e.g. because it stopped tracking them. The state
+    /// can only be removed once `retain_shards_without_trie_changes` keeps the shard as well.
+    pub fn get_untracked_shards_with_state(
+        &self,
+        me: Option<&AccountId>,
+    ) -> Result<Vec<ShardUId>, Error> {
+        let head = self.head()?;
+        let store = self.store.owned_store();
+        let mut result = vec![];
+        for shard_id in 0..self.runtime_adapter.num_shards(&head.epoch_id)? {
+            if self.runtime_adapter.cares_about_shard(me, &head.last_block_hash, shard_id, true)
+                || self.runtime_adapter.will_care_about_shard(
+                    me,
+                    &head.last_block_hash,
+                    shard_id,
+                    true,
+                )
+            {
+                continue;
+            }
+            let shard_uid = self.runtime_adapter.shard_id_to_uid(shard_id, &head.epoch_id)?;
+            if store.iter_prefix(ColState, &shard_uid.to_bytes()).next().is_some() {
+                result.push(shard_uid);
+            }
+        }
+        Ok(result)
+    }
+
     pub fn clear_forks_data(
         &mut self,
         tries: ShardTries,
@@ -1043,6 +1076,7 @@ impl Chain {
                     ShardInfo(*shard_id, chunk.chunk_hash())
                 })
                 .collect(),
+            downloaded_parts: HashMap::new(),
         };
 
         let mut chain_store_update = ChainStoreUpdate::new(&mut self.store);
@@ -1080,6 +1114,10 @@ impl Chain {
                 chain_update.chain_store_update.save_block_height_processed(block_height);
                 chain_update.commit()?;
 
+                if let Err(err) = self.runtime_adapter.update_tracking(block.hash()) {
+                    error!(target: "chain", "Failed to update tracked shards for block {}: {}", block.hash(), err);
+                }
+
                 self.pending_states_to_patch = None;
 
                 if needs_to_start_fetching_state {
@@ -1781,22 +1819,127 @@ impl Chain {
         data: &Vec<u8>,
     ) -> Result<(), Error> {
         let shard_state_header = self.get_state_header(shard_id, sync_hash)?;
-        let chunk = shard_state_header.take_chunk();
-        let state_root = *chunk.take_header().take_inner().prev_state_root();
-        if !self.runtime_adapter.validate_state_part(&state_root, part_id, num_parts, data) {
-            byzantine_assert!(false);
-            return Err(ErrorKind::Other(
-                "set_state_part failed: validate_state_part failed".into(),
-            )
-            .into());
+        let state_root = shard_state_header.chunk_prev_state_root();
+        validate_and_save_state_part(
+            self.runtime_adapter.as_ref(),
+            self.store.owned_store().as_ref(),
+            &state_root,
+            shard_id,
+            sync_hash,
+            part_id,
+            num_parts,
+            data,
+        )
+    }
+
+    /// Returns for every part of the given shard state whether it is already downloaded, as
+    /// recorded by `mark_state_part_downloaded`. Used to resume state sync after a restart.
+    pub fn get_downloaded_state_parts(
+        &self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        num_parts: u64,
+    ) -> Result<Vec<bool>, Error> {
+        Ok(self
+            .get_state_sync_progress(&sync_hash)?
+            .and_then(|mut progress| progress.downloaded_parts.remove(&shard_id))
+            .filter(|downloaded| downloaded.len() as u64 == num_parts)
+            .unwrap_or_else(|| vec![false; num_parts as usize]))
+    }
+
+    /// Records that the given part of the shard state is validated and saved.
+    pub fn mark_state_part_downloaded(
+        &mut self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+        num_parts: u64,
+    ) -> Result<(), Error> {
+        let mut downloaded = self.get_downloaded_state_parts(shard_id, sync_hash, num_parts)?;
+        match downloaded.get_mut(part_id as usize) {
+            Some(done) if !*done => *done = true,
+            _ => return Ok(()),
         }
+        self.save_downloaded_state_parts(shard_id, sync_hash, Some(downloaded))
+    }
 
-        // Saving the part data.
-        let mut store_update = self.store.owned_store().store_update();
-        let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
-        store_update.set(ColStateParts, &key, data);
-        store_update.commit()?;
-        Ok(())
+    /// Catchups keep the download progress in their `StateSyncInfo`. The main state sync has no
+    /// `StateSyncInfo` in `ColStateDlInfos`, it would be taken for a catchup otherwise, so its
+    /// progress is kept under `STATE_SYNC_PROGRESS_KEY` instead.
+    fn get_state_sync_progress(
+        &self,
+        sync_hash: &CryptoHash,
+    ) -> Result<Option<StateSyncInfo>, Error> {
+        let store = self.store.owned_store();
+        if let Some(state_sync_info) = store.get_ser(ColStateDlInfos, sync_hash.as_ref())? {
+            return Ok(Some(state_sync_info));
+        }
+        let progress: Option<StateSyncInfo> =
+            store.get_ser(ColBlockMisc, STATE_SYNC_PROGRESS_KEY)?;
+        Ok(progress.filter(|progress| &progress.epoch_tail_hash == sync_hash))
+    }
+
+    /// Replaces the recorded download progress of the shard or, given `None`, forgets it.
+    /// Progress of the main state sync for another sync hash is dropped.
+    fn save_downloaded_state_parts(
+        &mut self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        downloaded: Option<Vec<bool>>,
+    ) -> Result<(), Error> {
+        let store = self.store.owned_store();
+        let is_catchup = store.exists(ColStateDlInfos, sync_hash.as_ref())?;
+        let mut progress =
+            self.get_state_sync_progress(&sync_hash)?.unwrap_or_else(|| StateSyncInfo {
+                epoch_tail_hash: sync_hash,
+                shards: vec![],
+                downloaded_parts: HashMap::new(),
+            });
+        match downloaded {
+            Some(downloaded) => {
+                progress.downloaded_parts.insert(shard_id, downloaded);
+            }
+            None => {
+                if progress.downloaded_parts.remove(&shard_id).is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        let mut chain_store_update = self.mut_store().store_update();
+        if is_catchup {
+            chain_store_update.add_state_dl_info(progress);
+        } else {
+            let mut store_update = store.store_update();
+            if progress.downloaded_parts.is_empty() {
+                store_update.delete(ColBlockMisc, STATE_SYNC_PROGRESS_KEY);
+            } else {
+                store_update.set_ser(ColBlockMisc, STATE_SYNC_PROGRESS_KEY, &progress)?;
+            }
+            chain_store_update.merge(store_update);
+        }
+        Ok(chain_store_update.commit()?)
+    }
+
+    /// Saves the headers received in Epoch Sync finalization and moves the header head to
+    /// `header_sync_init_header`, so that header sync continues from there.
+    /// Validity of the headers is checked by Epoch Sync.
+    pub fn save_epoch_sync_headers(
+        &mut self,
+        prev_epoch_headers: Vec<BlockHeader>,
+        cur_epoch_header: BlockHeader,
+        header_sync_init_header: BlockHeader,
+        header_sync_init_header_tree: PartialMerkleTree,
+    ) -> Result<(), Error> {
+        let mut chain_store_update = self.store.store_update();
+        for header in prev_epoch_headers {
+            chain_store_update.save_block_header_no_update_tree(header)?;
+        }
+        chain_store_update.save_block_header_no_update_tree(cur_epoch_header)?;
+        chain_store_update
+            .save_block_merkle_tree(*header_sync_init_header.hash(), header_sync_init_header_tree);
+        chain_store_update.force_save_header_head(&Tip::from_header(&header_sync_init_header))?;
+        chain_store_update.save_block_header_no_update_tree(header_sync_init_header)?;
+        chain_store_update.commit()
     }
 
     pub fn set_state_finalize(
@@ -1806,23 +1949,31 @@ impl Chain {
         num_parts: u64,
     ) -> Result<(), Error> {
         let shard_state_header = self.get_state_header(shard_id, sync_hash)?;
-        let mut height = shard_state_header.chunk_height_included();
         let state_root = shard_state_header.chunk_prev_state_root();
         let epoch_id = self.get_block_header(&sync_hash)?.epoch_id().clone();
 
-        for part_id in 0..num_parts {
-            let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
-            let part = self.store.owned_store().get(ColStateParts, &key)?.unwrap();
+        apply_state_parts(
+            self.runtime_adapter.as_ref(),
+            self.store.owned_store().as_ref(),
+            shard_id,
+            sync_hash,
+            &state_root,
+            num_parts,
+            &epoch_id,
+        )?;
 
-            self.runtime_adapter.apply_state_part(
-                shard_id,
-                &state_root,
-                part_id,
-                num_parts,
-                &part,
-                &epoch_id,
-            )?;
-        }
+        self.set_state_finalize_after_apply(shard_id, sync_hash)
+    }
+
+    /// Finishes state sync of the shard after all its state parts are applied with
+    /// `apply_state_parts`: applies the chunk and builds the chain up to `sync_hash`.
+    pub fn set_state_finalize_after_apply(
+        &mut self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+    ) -> Result<(), Error> {
+        let shard_state_header = self.get_state_header(shard_id, sync_hash)?;
+        let mut height = shard_state_header.chunk_height_included();
 
         // Applying the chunk starts here
         let mut chain_update = self.chain_update();
@@ -1851,6 +2002,8 @@ impl Chain {
         sync_hash: CryptoHash,
         num_parts: u64,
     ) -> Result<(), Error> {
+        // Forget the recorded progress, as the parts are removed.
+        self.save_downloaded_state_parts(shard_id, sync_hash, None)?;
         let mut chain_store_update = self.mut_store().store_update();
         chain_store_update.gc_col_state_parts(sync_hash, shard_id, num_parts)?;
         Ok(chain_store_update.commit()?)
@@ -1968,32 +2121,7 @@ impl Chain {
         transaction_hash: &CryptoHash,
     ) -> Result<FinalExecutionOutcomeView, Error> {
         let mut outcomes = self.get_recursive_transaction_results(transaction_hash)?;
-        let mut looking_for_id = (*transaction_hash).into();
-        let num_outcomes = outcomes.len();
-        let status = outcomes
-            .iter()
-            .find_map(|outcome_with_id| {
-                if outcome_with_id.id == looking_for_id {
-                    match &outcome_with_id.outcome.status {
-                        ExecutionStatusView::Unknown if num_outcomes == 1 => {
-                            Some(FinalExecutionStatus::NotStarted)
-                        }
-                        ExecutionStatusView::Unknown => Some(FinalExecutionStatus::Started),
-                        ExecutionStatusView::Failure(e) => {
-                            Some(FinalExecutionStatus::Failure(e.clone()))
-                        }
-                        ExecutionStatusView::SuccessValue(v) => {
-                            Some(FinalExecutionStatus::SuccessValue(v.clone()))
-                        }
-                        ExecutionStatusView::SuccessReceiptId(id) => {
-                            looking_for_id = id.clone();
-                            None
-                        }
-                    }
-                } else {
-                    None
-                }
-            })
+        let status = FinalExecutionStatus::from_outcomes(&outcomes)
             .expect("results should resolve to a final outcome");
         let receipts_outcome = outcomes.split_off(1);
         let transaction: SignedTransactionView = self
@@ -2741,21 +2869,50 @@ impl<'a> ChainUpdate<'a> {
         }
 
         for (shard_id, mut receipt_proofs) in receipt_proofs_by_shard_id {
-            let mut slice = [0u8; 32];
-            slice.copy_from_slice(block.hash().as_ref());
-            let mut rng: StdRng = SeedableRng::from_seed(slice);
-            receipt_proofs.shuffle(&mut rng);
+            shuffle_receipt_proofs(&mut receipt_proofs, block.hash());
             self.chain_store_update.save_incoming_receipt(&block.hash(), shard_id, receipt_proofs);
         }
 
         Ok(())
     }
 
+    /// Creates a challenge for the chunk in `block` whose state transition doesn't match the result
+    /// of applying the previous chunk.
+    ///
+    /// Challenges are only created by nodes that track the shard and find the mismatch while
+    /// applying the chunk themselves. Nodes don't re-execute chunks of the shards they don't track.
     pub fn create_chunk_state_challenge(
         &mut self,
         prev_block: &Block,
         block: &Block,
         chunk_header: &ShardChunkHeader,
+    ) -> Result<ChallengeBody, Error> {
+        let protocol_version =
+            self.runtime_adapter.get_epoch_protocol_version(block.header().epoch_id())?;
+        checked_feature!(
+            "protocol_feature_chunk_state_challenge",
+            ChunkStateChallenge,
+            protocol_version,
+            {
+                return self
+                    .create_chunk_state_v2_challenge(prev_block, block, chunk_header)
+                    .map(ChallengeBody::ChunkStateV2);
+            },
+            {
+                return self
+                    .create_legacy_chunk_state_challenge(prev_block, block, chunk_header)
+                    .map(ChallengeBody::ChunkState);
+            }
+        )
+    }
+
+    /// Creates a `ChunkState` challenge, which doesn't prove the receipts applied with the
+    /// previous chunk.
+    fn create_legacy_chunk_state_challenge(
+        &mut self,
+        prev_block: &Block,
+        block: &Block,
+        chunk_header: &ShardChunkHeader,
     ) -> Result<ChunkState, Error> {
         let chunk_shard_id = chunk_header.shard_id();
         let prev_chunk_header = &prev_block.chunks()[chunk_shard_id as usize];
@@ -2764,8 +2921,7 @@ impl<'a> ChainUpdate<'a> {
         let prev_chunk = self
             .chain_store_update
             .get_chain_store()
-            .get_chunk_clone_from_header(&prev_block.chunks()[chunk_shard_id as usize].clone())
-            .unwrap();
+            .get_chunk_clone_from_header(&prev_block.chunks()[chunk_shard_id as usize].clone())?;
         let receipt_proof_response: Vec<ReceiptProofResponse> =
             self.chain_store_update.get_incoming_receipts_for_shard(
                 chunk_shard_id,
@@ -2787,29 +2943,29 @@ impl<'a> ChainUpdate<'a> {
             &prev_block.hash(),
             chunk_shard_id,
         )?;
-        let apply_result = self
-            .runtime_adapter
-            .apply_transactions_with_optional_storage_proof(
-                chunk_shard_id,
-                prev_chunk_inner.prev_state_root(),
-                prev_chunk.height_included(),
-                prev_block.header().raw_timestamp(),
-                prev_chunk_inner.prev_block_hash(),
-                &prev_block.hash(),
-                &receipts,
-                prev_chunk.transactions(),
-                prev_chunk_inner.validator_proposals(),
-                prev_block.header().gas_price(),
-                prev_chunk_inner.gas_limit(),
-                &challenges_result,
-                *block.header().random_value(),
-                true,
-                true,
-                is_first_block_with_chunk_of_version,
-                None,
-            )
-            .unwrap();
-        let partial_state = apply_result.proof.unwrap().nodes;
+        let apply_result = self.runtime_adapter.apply_transactions_with_optional_storage_proof(
+            chunk_shard_id,
+            prev_chunk_inner.prev_state_root(),
+            prev_chunk.height_included(),
+            prev_block.header().raw_timestamp(),
+            prev_chunk_inner.prev_block_hash(),
+            &prev_block.hash(),
+            &receipts,
+            prev_chunk.transactions(),
+            prev_chunk_inner.validator_proposals(),
+            prev_block.header().gas_price(),
+            prev_chunk_inner.gas_limit(),
+            &challenges_result,
+            *block.header().random_value(),
+            true,
+            true,
+            is_first_block_with_chunk_of_version,
+            None,
+        )?;
+        let partial_state = apply_result
+            .proof
+            .ok_or_else(|| ErrorKind::Other("Storage proof was not generated".to_string()))?
+            .nodes;
         Ok(ChunkState {
             prev_block_header: prev_block.header().try_to_vec()?,
             block_header: block.header().try_to_vec()?,
@@ -2821,6 +2977,111 @@ impl<'a> ChainUpdate<'a> {
         })
     }
 
+    /// Creates a `ChunkStateV2` challenge. Only supported if the previous chunk is a new chunk in
+    /// `prev_block`.
+    #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+    fn create_chunk_state_v2_challenge(
+        &mut self,
+        prev_block: &Block,
+        block: &Block,
+        chunk_header: &ShardChunkHeader,
+    ) -> Result<ChunkStateV2, Error> {
+        let chunk_shard_id = chunk_header.shard_id();
+        let shard_index = chunk_shard_id as usize;
+        if !prev_block.header().chunk_mask().get(shard_index).cloned().unwrap_or(false) {
+            return Err(ErrorKind::Other(format!(
+                "Block {} has no new chunk for shard {}",
+                prev_block.hash(),
+                chunk_shard_id
+            ))
+            .into());
+        }
+        let prev_chunk_header = &prev_block.chunks()[shard_index];
+        let prev_merkle_proofs = Block::compute_chunk_headers_root(prev_block.chunks().iter()).1;
+        let merkle_proofs = Block::compute_chunk_headers_root(block.chunks().iter()).1;
+        let prev_chunk = self
+            .chain_store_update
+            .get_chain_store()
+            .get_chunk_clone_from_header(&prev_chunk_header.clone())?;
+
+        // The receipts applied with the previous chunk were sent by the chunks in the blocks after
+        // the last block with a new chunk for the shard.
+        let mut prev_block_ancestors = vec![];
+        let mut prev_incoming_receipts = vec![];
+        let mut receipts = vec![];
+        let mut receipts_block = prev_block.clone();
+        loop {
+            let receipt_proofs = self
+                .chain_store_update
+                .get_incoming_receipts(receipts_block.hash(), chunk_shard_id)
+                .map(|receipt_proofs| receipt_proofs.clone())
+                .unwrap_or_default();
+            receipts.extend(collect_receipts(&receipt_proofs));
+            prev_incoming_receipts.push(ChunkStateIncomingReceipts {
+                outgoing_receipts_roots: receipts_block
+                    .chunks()
+                    .iter()
+                    .map(|chunk| chunk.outgoing_receipts_root())
+                    .collect(),
+                receipt_proofs,
+            });
+
+            let header = self
+                .chain_store_update
+                .get_block_header(receipts_block.header().prev_hash())?
+                .clone();
+            prev_block_ancestors.push(header.try_to_vec()?);
+            if header.chunk_mask().get(shard_index).cloned().unwrap_or(false) {
+                break;
+            }
+            receipts_block = self.chain_store_update.get_block(header.hash())?.clone();
+        }
+        let gas_price =
+            self.chain_store_update.get_block_header(prev_block.header().prev_hash())?.gas_price();
+
+        let prev_chunk_inner = prev_chunk.cloned_header().take_inner();
+        let is_first_block_with_chunk_of_version = check_if_block_is_first_with_chunk_of_version(
+            &mut self.chain_store_update,
+            self.runtime_adapter.as_ref(),
+            prev_block.header().prev_hash(),
+            chunk_shard_id,
+        )?;
+        let apply_result = self.runtime_adapter.apply_transactions_with_optional_storage_proof(
+            chunk_shard_id,
+            prev_chunk_inner.prev_state_root(),
+            prev_block.header().height(),
+            prev_block.header().raw_timestamp(),
+            prev_chunk_inner.prev_block_hash(),
+            &prev_block.hash(),
+            &receipts,
+            prev_chunk.transactions(),
+            prev_chunk_inner.validator_proposals(),
+            gas_price,
+            prev_chunk_inner.gas_limit(),
+            prev_block.header().challenges_result(),
+            *prev_block.header().random_value(),
+            true,
+            true,
+            is_first_block_with_chunk_of_version,
+            None,
+        )?;
+        let partial_state = apply_result
+            .proof
+            .ok_or_else(|| ErrorKind::Other("Storage proof was not generated".to_string()))?
+            .nodes;
+        Ok(ChunkStateV2 {
+            prev_block_header: prev_block.header().try_to_vec()?,
+            block_header: block.header().try_to_vec()?,
+            prev_merkle_proof: prev_merkle_proofs[shard_index].clone(),
+            merkle_proof: merkle_proofs[shard_index].clone(),
+            prev_chunk,
+            chunk_header: chunk_header.clone(),
+            partial_state,
+            prev_block_ancestors,
+            prev_incoming_receipts,
+        })
+    }
+
     /// Applies chunks in separate threads, awaits finishing and processes results
     fn apply_chunks(
         &mut self,
@@ -2914,12 +3175,25 @@ impl<'a> ChainUpdate<'a> {
                     .map_err(|e| {
                         debug!(target: "chain", "Failed to validate chunk extra: {:?}", e);
                         byzantine_assert!(false);
+                        // Only the results of applying the previous chunk can be challenged.
+                        match e.kind() {
+                            ErrorKind::InvalidStateRoot
+                            | ErrorKind::InvalidOutcomesProof
+                            | ErrorKind::InvalidValidatorProposals
+                            | ErrorKind::InvalidGasUsed
+                            | ErrorKind::InvalidBalanceBurnt
+                            | ErrorKind::InvalidReceiptsProof => {}
+                            _ => return e,
+                        }
                         match self.create_chunk_state_challenge(&prev_block, &block, &chunk_header)
                         {
-                            Ok(chunk_state) => {
-                                Error::from(ErrorKind::InvalidChunkState(Box::new(chunk_state)))
+                            Ok(challenge_body) => {
+                                Error::from(ErrorKind::InvalidChunkState(Box::new(challenge_body)))
+                            }
+                            Err(err) => {
+                                debug!(target: "chain", "Failed to create chunk state challenge: {}", err);
+                                e
                             }
-                            Err(err) => err,
                         }
                     })?;
                     let receipt_proof_response: Vec<ReceiptProofResponse> =
@@ -3990,6 +4264,70 @@ impl<'a> ChainUpdate<'a> {
     }
 }
 
+/// Validates a state part downloaded for `sync_hash` against `state_root` and saves it in the
+/// store. Doesn't need `Chain`, so it can be run outside of the client actor.
+pub fn validate_and_save_state_part(
+    runtime_adapter: &dyn RuntimeAdapter,
+    store: &Store,
+    state_root: &StateRoot,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    part_id: u64,
+    num_parts: u64,
+    data: &Vec<u8>,
+) -> Result<(), Error> {
+    if !runtime_adapter.validate_state_part(state_root, part_id, num_parts, data) {
+        byzantine_assert!(false);
+        return Err(
+            ErrorKind::Other("set_state_part failed: validate_state_part failed".into()).into()
+        );
+    }
+
+    // Saving the part data.
+    let mut store_update = store.store_update();
+    let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
+    store_update.set(ColStateParts, &key, data);
+    store_update.commit()?;
+    Ok(())
+}
+
+/// Applies all the saved state parts of the shard to the trie.
+/// Doesn't need `Chain`, so it can be run outside of the client actor.
+/// Keeps only the shards which have no trie changes left. Trie changes reference the state of
+/// the shard, so it can only be removed once all the blocks in which the node applied chunks of
+/// the shard are garbage collected. Reads the whole column, so it is run by the sync jobs actor.
+pub fn retain_shards_without_trie_changes(store: &Store, shard_uids: &mut Vec<ShardUId>) {
+    if shard_uids.is_empty() {
+        return;
+    }
+    for (key, _) in store.iter(ColTrieChanges) {
+        if let Ok((_, shard_uid)) = get_block_shard_uid_rev(&key) {
+            shard_uids.retain(|uid| uid != &shard_uid);
+        }
+    }
+}
+
+pub fn apply_state_parts(
+    runtime_adapter: &dyn RuntimeAdapter,
+    store: &Store,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    state_root: &StateRoot,
+    num_parts: u64,
+    epoch_id: &EpochId,
+) -> Result<(), Error> {
+    for part_id in 0..num_parts {
+        let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
+        let part = store.get(ColStateParts, &key)?.ok_or_else(|| {
+            ErrorKind::Other(format!("Missing state part {} of shard {}", part_id, shard_id))
+        })?;
+
+        runtime_adapter
+            .apply_state_part(shard_id, state_root, part_id, num_parts, &part, epoch_id)?;
+    }
+    Ok(())
+}
+
 pub fn collect_receipts<'a, T>(receipt_proofs: T) -> Vec<Receipt>
 where
     T: IntoIterator<Item = &'a ReceiptProof>,
@@ -3997,6 +4335,15 @@ where
     receipt_proofs.into_iter().flat_map(|ReceiptProof(receipts, _)| receipts).cloned().collect()
 }
 
+/// Shuffles the receipts sent to a shard by the chunks in the block, given in the order of the
+/// shards they come from, into the order in which they are applied.
+pub fn shuffle_receipt_proofs(receipt_proofs: &mut Vec<ReceiptProof>, block_hash: &CryptoHash) {
+    let mut slice = [0u8; 32];
+    slice.copy_from_slice(block_hash.as_ref());
+    let mut rng: StdRng = SeedableRng::from_seed(slice);
+    receipt_proofs.shuffle(&mut rng);
+}
+
 pub fn collect_receipts_from_response(
     receipt_proof_response: &[ReceiptProofResponse],
 ) -> Vec<Receipt> {
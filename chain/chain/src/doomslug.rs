@@ -1,10 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use near_crypto::Signature;
 use near_primitives::block::{Approval, ApprovalInner};
+use near_primitives::challenge::ApprovalDoubleSign;
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{AccountId, ApprovalStake, Balance, BlockHeight, BlockHeightDelta};
 use near_primitives::validator_signer::ValidatorSigner;
@@ -20,6 +21,9 @@ const MAX_TIMER_ITERS: usize = 20;
 /// thus 10_000 heights in practice will mean on the order of one hundred entries.
 const MAX_HEIGHTS_AHEAD_TO_STORE_APPROVALS: BlockHeight = 10_000;
 
+/// How many heights below the tip to keep in the timeline (see `DoomslugHeightTimeline`).
+const MAX_HEIGHTS_BEHIND_TO_STORE_TIMELINE: BlockHeight = 100;
+
 /// The threshold for doomslug to create a block.
 /// `TwoThirds` means the block can only be produced if at least 2/3 of the stake is approving it,
 ///             and is what should be used in production (and what guarantees finality)
@@ -38,6 +42,29 @@ pub enum DoomslugBlockProductionReadiness {
     ReadySince(Instant),
 }
 
+/// An approval received by doomslug, as recorded in the timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoomslugApprovalRecord {
+    pub account_id: AccountId,
+    pub inner: ApprovalInner,
+    pub received: Instant,
+}
+
+/// What doomslug observed for a single target height. It is only kept for diagnostics and is not
+/// used in any of the decisions doomslug makes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DoomslugHeightTimeline {
+    /// The last approval received from each account that targets this height, in the order of
+    /// arrival.
+    pub approvals: Vec<DoomslugApprovalRecord>,
+    /// When the approvals for this height crossed the threshold for the first time.
+    pub threshold_reached: Option<Instant>,
+    /// When the block at this height became the tip.
+    pub tip_set: Option<Instant>,
+    /// When a block with doomslug finality at this height was first observed.
+    pub became_final: Option<Instant>,
+}
+
 struct DoomslugTimer {
     started: Instant,
     last_endorsement_sent: Instant,
@@ -80,7 +107,7 @@ struct DoomslugApprovalsTracker {
 /// provided.
 struct DoomslugApprovalsTrackersAtHeight {
     approval_trackers: HashMap<ApprovalInner, DoomslugApprovalsTracker>,
-    last_approval_per_account: HashMap<AccountId, ApprovalInner>,
+    last_approval_per_account: HashMap<AccountId, Approval>,
 }
 
 /// Contains all the logic for Doomslug, but no integration with chain or storage. The integration
@@ -104,6 +131,10 @@ pub struct Doomslug {
     /// How many approvals to have before producing a block. In production should be always `HalfStake`,
     ///    but for many tests we use `NoApprovals` to invoke more forkfulness
     threshold_mode: DoomslugThresholdMode,
+    /// Timeline of the recent heights, for diagnostics only
+    timeline: BTreeMap<BlockHeight, DoomslugHeightTimeline>,
+    /// Conflicting approvals received since they were last taken
+    approval_double_signs: Vec<ApprovalDoubleSign>,
 }
 
 impl DoomslugTimer {
@@ -239,7 +270,8 @@ impl DoomslugApprovalsTrackersAtHeight {
         stakes: &Vec<(ApprovalStake, bool)>,
         threshold_mode: DoomslugThresholdMode,
     ) -> DoomslugBlockProductionReadiness {
-        if let Some(last_parent) = self.last_approval_per_account.get(&approval.account_id) {
+        if let Some(last_approval) = self.last_approval_per_account.get(&approval.account_id) {
+            let last_parent = &last_approval.inner;
             let should_remove = self
                 .approval_trackers
                 .get_mut(last_parent)
@@ -271,7 +303,7 @@ impl DoomslugApprovalsTrackersAtHeight {
             return DoomslugBlockProductionReadiness::NotReady;
         }
 
-        self.last_approval_per_account.insert(approval.account_id.clone(), approval.inner.clone());
+        self.last_approval_per_account.insert(approval.account_id.clone(), approval.clone());
         self.approval_trackers
             .entry(approval.inner.clone())
             .or_insert_with(|| DoomslugApprovalsTracker::new(account_id_to_stakes, threshold_mode))
@@ -307,6 +339,8 @@ impl Doomslug {
             },
             signer,
             threshold_mode,
+            timeline: BTreeMap::new(),
+            approval_double_signs: vec![],
         }
     }
 
@@ -343,6 +377,18 @@ impl Doomslug {
         self.timer.started
     }
 
+    /// Returns what was observed for the recent heights: from `MAX_HEIGHTS_BEHIND_TO_STORE_TIMELINE`
+    /// heights below the tip up to the largest height approvals are kept for.
+    pub fn get_timeline(&self) -> &BTreeMap<BlockHeight, DoomslugHeightTimeline> {
+        &self.timeline
+    }
+
+    /// Returns the pairs of approvals for the same target height from the same account that
+    /// approve different parents, received since the last call.
+    pub fn take_approval_double_signs(&mut self) -> Vec<ApprovalDoubleSign> {
+        std::mem::take(&mut self.approval_double_signs)
+    }
+
     /// Is expected to be called periodically and processed the timer (`start_timer` in the paper)
     /// If the `cur_time` way ahead of last time the `process_timer` was called, will only process
     /// a bounded number of steps, to avoid an infinite loop in case of some bugs.
@@ -496,6 +542,16 @@ impl Doomslug {
         self.approval_tracking
             .retain(|h, _| *h > height && *h <= height + MAX_HEIGHTS_AHEAD_TO_STORE_APPROVALS);
 
+        let timeline = self.timeline.entry(height).or_default();
+        timeline.tip_set.get_or_insert(now);
+        if last_final_height > 0 {
+            let timeline = self.timeline.entry(last_final_height).or_default();
+            timeline.became_final.get_or_insert(now);
+        }
+        self.timeline =
+            self.timeline.split_off(&height.saturating_sub(MAX_HEIGHTS_BEHIND_TO_STORE_TIMELINE));
+        self.timeline.split_off(&(height + MAX_HEIGHTS_AHEAD_TO_STORE_APPROVALS + 1));
+
         self.endorsement_pending = true;
     }
 
@@ -510,13 +566,33 @@ impl Doomslug {
         stakes: &Vec<(ApprovalStake, bool)>,
     ) -> DoomslugBlockProductionReadiness {
         let threshold_mode = self.threshold_mode;
-        let ret = self
+        let approval_trackers_at_height = self
             .approval_tracking
             .entry(approval.target_height)
-            .or_insert_with(|| DoomslugApprovalsTrackersAtHeight::new())
-            .process_approval(now, approval, stakes, threshold_mode);
+            .or_insert_with(|| DoomslugApprovalsTrackersAtHeight::new());
+        if let Some(last_approval) =
+            approval_trackers_at_height.last_approval_per_account.get(&approval.account_id)
+        {
+            if last_approval.inner != approval.inner {
+                self.approval_double_signs.push(ApprovalDoubleSign {
+                    left_approval: last_approval.clone(),
+                    right_approval: approval.clone(),
+                });
+            }
+        }
+        let ret =
+            approval_trackers_at_height.process_approval(now, approval, stakes, threshold_mode);
+
+        let timeline = self.timeline.entry(approval.target_height).or_default();
+        timeline.approvals.retain(|record| record.account_id != approval.account_id);
+        timeline.approvals.push(DoomslugApprovalRecord {
+            account_id: approval.account_id.clone(),
+            inner: approval.inner.clone(),
+            received: now,
+        });
 
-        if ret != DoomslugBlockProductionReadiness::NotReady {
+        if let DoomslugBlockProductionReadiness::ReadySince(when) = ret {
+            timeline.threshold_reached.get_or_insert(when);
             if approval.target_height > self.largest_threshold_height {
                 self.largest_threshold_height = approval.target_height;
             }
@@ -1064,4 +1140,112 @@ mod tests {
             5
         );
     }
+
+    #[test]
+    fn test_doomslug_timeline() {
+        let accounts: Vec<(&str, u128)> = vec![("test1", 1), ("test2", 1), ("test3", 1)];
+        let stakes = accounts
+            .iter()
+            .map(|(account_id, stake)| ApprovalStake {
+                account_id: account_id.parse().unwrap(),
+                stake_this_epoch: *stake,
+                stake_next_epoch: *stake,
+                public_key: SecretKey::from_seed(KeyType::ED25519, account_id).public_key(),
+            })
+            .map(|stake| (stake, false))
+            .collect::<Vec<_>>();
+        let signers = accounts
+            .iter()
+            .map(|(account_id, _)| {
+                InMemoryValidatorSigner::from_seed(
+                    account_id.parse().unwrap(),
+                    KeyType::ED25519,
+                    account_id,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut ds = Doomslug::new(
+            0,
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+            None,
+            DoomslugThresholdMode::TwoThirds,
+        );
+
+        let now = Instant::now();
+        ds.set_tip(now, hash(&[1]), 1, 0);
+        ds.on_approval_message(now, &Approval::new(hash(&[1]), 1, 2, &signers[0]), &stakes);
+        ds.on_approval_message(now, &Approval::new(hash(&[1]), 1, 2, &signers[1]), &stakes);
+        // A newer approval from the same account replaces the previous one
+        let later = now + Duration::from_millis(100);
+        ds.on_approval_message(later, &Approval::new(hash(&[1]), 1, 2, &signers[0]), &stakes);
+        {
+            let timeline = ds.get_timeline().get(&2).unwrap();
+            let approvers = timeline
+                .approvals
+                .iter()
+                .map(|record| record.account_id.as_ref())
+                .collect::<Vec<_>>();
+            assert_eq!(approvers, vec!["test2", "test1"]);
+            assert_eq!(timeline.approvals[1].received, later);
+            assert_eq!(timeline.threshold_reached, None);
+        }
+
+        ds.on_approval_message(later, &Approval::new(hash(&[1]), 1, 2, &signers[2]), &stakes);
+        assert_eq!(ds.get_timeline().get(&2).unwrap().threshold_reached, Some(later));
+
+        let later = later + Duration::from_millis(100);
+        ds.set_tip(later, hash(&[2]), 2, 1);
+        assert_eq!(ds.get_timeline().get(&1).unwrap().tip_set, Some(now));
+        assert_eq!(ds.get_timeline().get(&1).unwrap().became_final, Some(later));
+        assert_eq!(ds.get_timeline().get(&2).unwrap().tip_set, Some(later));
+
+        // Old heights are eventually forgotten
+        ds.set_tip(later, hash(&[3]), 200, 199);
+        assert_eq!(ds.get_timeline().keys().next(), Some(&199));
+    }
+
+    #[test]
+    fn test_doomslug_approval_double_sign() {
+        let accounts: Vec<(&str, u128)> = vec![("test1", 1), ("test2", 1)];
+        let stakes = accounts
+            .iter()
+            .map(|(account_id, stake)| ApprovalStake {
+                account_id: account_id.parse().unwrap(),
+                stake_this_epoch: *stake,
+                stake_next_epoch: *stake,
+                public_key: SecretKey::from_seed(KeyType::ED25519, account_id).public_key(),
+            })
+            .map(|stake| (stake, false))
+            .collect::<Vec<_>>();
+        let signer =
+            InMemoryValidatorSigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let mut ds = Doomslug::new(
+            0,
+            Duration::from_millis(400),
+            Duration::from_millis(1000),
+            Duration::from_millis(100),
+            Duration::from_millis(3000),
+            None,
+            DoomslugThresholdMode::TwoThirds,
+        );
+
+        let now = Instant::now();
+        ds.set_tip(now, hash(&[1]), 1, 0);
+        let skip = Approval::new(hash(&[1]), 1, 3, &signer);
+        let endorsement = Approval::new(hash(&[2]), 2, 3, &signer);
+        ds.on_approval_message(now, &skip, &stakes);
+        // Receiving the same approval again is fine
+        ds.on_approval_message(now, &skip, &stakes);
+        assert!(ds.take_approval_double_signs().is_empty());
+
+        ds.on_approval_message(now, &endorsement, &stakes);
+        assert_eq!(
+            ds.take_approval_double_signs(),
+            vec![ApprovalDoubleSign { left_approval: skip, right_approval: endorsement }]
+        );
+        assert!(ds.take_approval_double_signs().is_empty());
+    }
 }
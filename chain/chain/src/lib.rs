@@ -2,8 +2,13 @@
 extern crate lazy_static;
 
 pub use chain::{collect_receipts, Chain, MAX_ORPHAN_SIZE};
-pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThresholdMode};
-pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
+pub use doomslug::{
+    Doomslug, DoomslugApprovalRecord, DoomslugBlockProductionReadiness, DoomslugHeightTimeline,
+    DoomslugThresholdMode,
+};
+pub use lightclient::{
+    create_light_client_block_view, get_epoch_block_producers_view, validate_light_client_block,
+};
 pub use near_chain_primitives::{self, Error, ErrorKind};
 pub use near_primitives::receipt::ReceiptResult;
 pub use store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
@@ -16,6 +21,7 @@ mod lightclient;
 mod metrics;
 pub mod migrations;
 pub mod missing_chunks;
+pub mod state_sync_dump;
 mod store;
 pub mod store_validator;
 pub mod test_utils;
@@ -1,6 +1,11 @@
-use near_chain_primitives::Error;
-use near_primitives::block::BlockHeader;
+use borsh::BorshSerialize;
+
+use near_chain_primitives::{Error, ErrorKind};
+use near_primitives::block::{Approval, ApprovalInner, BlockHeader};
+use near_primitives::block_header::BlockHeaderInnerLite;
 use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::merkle::combine_hash;
+use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::EpochId;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{BlockHeaderInnerLiteView, LightClientBlockView};
@@ -70,3 +75,62 @@ pub fn create_light_client_block_view(
         approvals_after_next,
     })
 }
+
+/// Validates the light client block against the ordered list of block producers of its epoch.
+///
+/// Checks that the block is endorsed by more than 2/3 of the stake of `block_producers` and that
+/// `next_bps` of the block match the `next_bp_hash` it commits to. The light client block must
+/// carry `next_bps`, which is always the case for the blocks recorded on epoch switches.
+pub fn validate_light_client_block(
+    light_client_block: &LightClientBlockView,
+    block_producers: &[ValidatorStake],
+) -> Result<(), Error> {
+    let inner_lite = BlockHeaderInnerLite::from(light_client_block.inner_lite.clone());
+    let inner_hash =
+        combine_hash(hash(&inner_lite.try_to_vec()?), light_client_block.inner_rest_hash);
+    let block_hash = combine_hash(inner_hash, light_client_block.prev_block_hash);
+    let next_block_hash = combine_hash(light_client_block.next_block_inner_hash, block_hash);
+
+    if light_client_block.approvals_after_next.len() != block_producers.len() {
+        return Err(ErrorKind::InvalidApprovals.into());
+    }
+    let data = Approval::get_data_for_sig(
+        &ApprovalInner::Endorsement(next_block_hash),
+        inner_lite.height + 2,
+    );
+    let mut total_stake = 0;
+    let mut approved_stake = 0;
+    for (approval, block_producer) in
+        light_client_block.approvals_after_next.iter().zip(block_producers.iter())
+    {
+        total_stake += block_producer.stake();
+        if let Some(signature) = approval {
+            if !signature.verify(&data, block_producer.public_key()) {
+                return Err(ErrorKind::InvalidSignature.into());
+            }
+            approved_stake += block_producer.stake();
+        }
+    }
+    if approved_stake <= total_stake * 2 / 3 {
+        return Err(ErrorKind::NotEnoughApprovals.into());
+    }
+
+    let next_bps = match &light_client_block.next_bps {
+        Some(next_bps) => next_bps,
+        None => return Err(ErrorKind::InvalidNextBPHash.into()),
+    };
+    let next_bps: Vec<ValidatorStake> =
+        next_bps.iter().cloned().map(|bp| bp.into_validator_stake()).collect();
+    #[cfg(feature = "protocol_feature_block_header_v3")]
+    {
+        // Epochs before `BlockHeaderV3` commit to the hash of the V1 validator stakes.
+        let next_bps_v1: Vec<_> = next_bps.iter().cloned().map(|bp| bp.into_v1()).collect();
+        if hash(&next_bps_v1.try_to_vec()?) == inner_lite.next_bp_hash {
+            return Ok(());
+        }
+    }
+    if hash(&next_bps.try_to_vec()?) != inner_lite.next_bp_hash {
+        return Err(ErrorKind::InvalidNextBPHash.into());
+    }
+    Ok(())
+}
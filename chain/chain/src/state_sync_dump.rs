@@ -0,0 +1,102 @@
+//! Local directory with state parts for state sync.
+//!
+//! The directory is populated by `state-viewer dump_state_parts` and can be used by a node to
+//! obtain the state for a sync hash without requesting it from peers. The layout is:
+//!
+//! ```text
+//! <dir>/<sync_hash>/shard_<shard_id>/header
+//! <dir>/<sync_hash>/shard_<shard_id>/part_<part_id>
+//! ```
+//!
+//! `header` is a borsh-serialized `ShardStateSyncResponseHeader`, parts are stored as is.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::syncing::ShardStateSyncResponseHeader;
+use near_primitives::types::ShardId;
+
+fn shard_dir(dir: &Path, sync_hash: &CryptoHash, shard_id: ShardId) -> PathBuf {
+    dir.join(sync_hash.to_string()).join(format!("shard_{}", shard_id))
+}
+
+pub fn state_header_path(dir: &Path, sync_hash: &CryptoHash, shard_id: ShardId) -> PathBuf {
+    shard_dir(dir, sync_hash, shard_id).join("header")
+}
+
+pub fn state_part_path(
+    dir: &Path,
+    sync_hash: &CryptoHash,
+    shard_id: ShardId,
+    part_id: u64,
+) -> PathBuf {
+    shard_dir(dir, sync_hash, shard_id).join(format!("part_{}", part_id))
+}
+
+pub fn write_state_header(
+    dir: &Path,
+    sync_hash: &CryptoHash,
+    shard_id: ShardId,
+    header: &ShardStateSyncResponseHeader,
+) -> io::Result<()> {
+    fs::create_dir_all(shard_dir(dir, sync_hash, shard_id))?;
+    fs::write(state_header_path(dir, sync_hash, shard_id), header.try_to_vec()?)
+}
+
+pub fn write_state_part(
+    dir: &Path,
+    sync_hash: &CryptoHash,
+    shard_id: ShardId,
+    part_id: u64,
+    part: &[u8],
+) -> io::Result<()> {
+    fs::create_dir_all(shard_dir(dir, sync_hash, shard_id))?;
+    fs::write(state_part_path(dir, sync_hash, shard_id, part_id), part)
+}
+
+/// Reads the state header of the shard, `None` if it is not in the directory.
+pub fn read_state_header(
+    dir: &Path,
+    sync_hash: &CryptoHash,
+    shard_id: ShardId,
+) -> io::Result<Option<ShardStateSyncResponseHeader>> {
+    match fs::read(state_header_path(dir, sync_hash, shard_id)) {
+        Ok(bytes) => Ok(Some(ShardStateSyncResponseHeader::try_from_slice(&bytes)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the state part of the shard, `None` if it is not in the directory.
+pub fn read_state_part(
+    dir: &Path,
+    sync_hash: &CryptoHash,
+    shard_id: ShardId,
+    part_id: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(state_part_path(dir, sync_hash, shard_id, part_id)) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write_state_part() {
+        let dir = tempfile::Builder::new().prefix("state_sync_dump").tempdir().unwrap();
+        let sync_hash = CryptoHash::default();
+        assert_eq!(read_state_part(dir.path(), &sync_hash, 1, 0).unwrap(), None);
+        write_state_part(dir.path(), &sync_hash, 1, 0, &[1, 2, 3]).unwrap();
+        assert_eq!(read_state_part(dir.path(), &sync_hash, 1, 0).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(read_state_part(dir.path(), &sync_hash, 0, 0).unwrap(), None);
+        assert!(read_state_header(dir.path(), &sync_hash, 1).unwrap().is_none());
+    }
+}
@@ -10,6 +10,7 @@ use chrono::Utc;
 
 use near_chain_primitives::error::{Error, ErrorKind};
 use near_primitives::block::{Approval, Tip};
+use near_primitives::challenge::MisbehaviorEvidenceRecord;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
@@ -40,13 +41,13 @@ use near_store::{
     ColBlocksToCatchup, ColChallengedBlocks, ColChunkExtra, ColChunkHashesByHeight,
     ColChunkPerHeightShard, ColChunks, ColEpochLightClientBlocks, ColGCCount,
     ColHeaderHashesByHeight, ColIncomingReceipts, ColInvalidChunks, ColLastBlockWithNewChunk,
-    ColNextBlockHashes, ColNextBlockWithNewChunk, ColOutcomeIds, ColOutgoingReceipts,
-    ColPartialChunks, ColProcessedBlockHeights, ColReceiptIdToShardId, ColReceipts, ColState,
-    ColStateChanges, ColStateDlInfos, ColStateHeaders, ColStateParts, ColTransactionResult,
-    ColTransactions, ColTrieChanges, DBCol, KeyForStateChanges, ShardTries, Store, StoreUpdate,
-    TrieChanges, WrappedTrieChanges, CHUNK_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY,
-    HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY, SHOULD_COL_GC,
-    TAIL_KEY,
+    ColMisbehaviorEvidence, ColNextBlockHashes, ColNextBlockWithNewChunk, ColOutcomeIds,
+    ColOutgoingReceipts, ColPartialChunks, ColProcessedBlockHeights, ColReceiptIdToShardId,
+    ColReceipts, ColState, ColStateChanges, ColStateDlInfos, ColStateHeaders, ColStateParts,
+    ColTransactionResult, ColTransactions, ColTrieChanges, DBCol, KeyForStateChanges, ShardTries,
+    Store, StoreUpdate, TrieChanges, WrappedTrieChanges, CHUNK_TAIL_KEY, FINAL_HEAD_KEY,
+    FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY,
+    SHOULD_COL_GC, TAIL_KEY,
 };
 
 use crate::byzantine_assert;
@@ -600,6 +601,32 @@ impl ChainStore {
         store_update.commit().map_err(|err| err.into())
     }
 
+    /// Saves evidence of misbehavior. Returns false if the same evidence was already saved.
+    pub fn save_misbehavior_evidence(
+        &mut self,
+        record: &MisbehaviorEvidenceRecord,
+    ) -> Result<bool, Error> {
+        let key = record.evidence.hash();
+        if self.store.exists(ColMisbehaviorEvidence, key.as_ref())? {
+            return Ok(false);
+        }
+        let mut store_update = self.store.store_update();
+        store_update.set_ser(ColMisbehaviorEvidence, key.as_ref(), record)?;
+        store_update.commit()?;
+        Ok(true)
+    }
+
+    /// Returns all the saved evidence of misbehavior, ordered by height.
+    pub fn get_misbehavior_evidence(&self) -> Result<Vec<MisbehaviorEvidenceRecord>, Error> {
+        let mut records = self
+            .store
+            .iter(ColMisbehaviorEvidence)
+            .map(|(_, value)| MisbehaviorEvidenceRecord::try_from_slice(value.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        records.sort_by_key(|record| record.height);
+        Ok(records)
+    }
+
     /// Retrieve the kinds of state changes occurred in a given block.
     ///
     /// We store different types of data, so we prefer to only expose minimal information about the
@@ -2408,7 +2435,8 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::ColEpochValidatorInfo
             | DBCol::ColBlockOrdinal
             | DBCol::_ColTransactionRefCount
-            | DBCol::ColCachedContractCode => {
+            | DBCol::ColCachedContractCode
+            | DBCol::ColMisbehaviorEvidence => {
                 unreachable!();
             }
         }
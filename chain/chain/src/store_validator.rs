@@ -1,11 +1,16 @@
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use borsh::BorshDeserialize;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
 use strum::IntoEnumIterator;
-use tracing::warn;
+use tracing::{info, warn};
 
 use near_chain_configs::GenesisConfig;
 use near_primitives::block::{Block, BlockHeader};
@@ -19,9 +24,10 @@ use near_primitives::syncing::{ShardStateSyncResponseHeader, StateHeaderKey, Sta
 use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{AccountId, BlockHeight, EpochId, GCCount, ShardId};
-use near_primitives::utils::get_block_shard_id_rev;
+use near_primitives::utils::{get_block_shard_id_rev, index_to_bytes};
 use near_store::{
-    decode_value_with_rc, DBCol, Store, TrieChanges, NUM_COLS, SHOULD_COL_GC, SKIP_COL_GC,
+    decode_value_with_rc, DBCol, Store, StoreUpdate, TrieChanges, NUM_COLS, SHOULD_COL_GC,
+    SKIP_COL_GC,
 };
 use validate::StoreValidatorError;
 
@@ -30,10 +36,27 @@ use near_primitives::shard_layout::get_block_shard_uid_rev;
 
 mod validate;
 
+/// Columns which validation consumes refcounts counted while validating other columns.
+const REFCOUNT_COLS: [DBCol; 3] =
+    [DBCol::ColTransactions, DBCol::ColReceipts, DBCol::ColBlockRefCount];
+
 fn to_string<T: std::fmt::Debug>(v: &T) -> String {
     format!("{:?}", v)
 }
 
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    v: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(v)
+}
+
+fn merge_refcounts(refcounts: &mut HashMap<CryptoHash, u64>, other: HashMap<CryptoHash, u64>) {
+    for (hash, refcount) in other {
+        *refcounts.entry(hash).or_default() += refcount;
+    }
+}
+
 #[derive(Debug)]
 pub struct StoreValidatorCache {
     head: BlockHeight,
@@ -63,15 +86,64 @@ impl StoreValidatorCache {
             genesis_blocks: vec![],
         }
     }
+
+    /// Cache to validate a single column in parallel with others, with Head and Tails filled in.
+    fn fork(&self) -> Self {
+        Self {
+            head: self.head,
+            header_head: self.header_head,
+            tail: self.tail,
+            chunk_tail: self.chunk_tail,
+            ..Self::new()
+        }
+    }
+
+    fn merge(&mut self, other: StoreValidatorCache) {
+        self.block_heights_less_tail.extend(other.block_heights_less_tail);
+        for (col, count) in other.gc_col.into_iter().enumerate() {
+            if count != 0 {
+                self.gc_col[col] = count;
+            }
+        }
+        merge_refcounts(&mut self.tx_refcount, other.tx_refcount);
+        merge_refcounts(&mut self.receipt_refcount, other.receipt_refcount);
+        merge_refcounts(&mut self.block_refcount, other.block_refcount);
+        self.genesis_blocks.extend(other.genesis_blocks);
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ErrorMessage {
     pub col: String,
     pub key: String,
+    #[serde(serialize_with = "serialize_display")]
     pub err: StoreValidatorError,
 }
 
+/// Inconsistency found by the validator that can be fixed using the data in the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StoreRepair {
+    /// Refcount of a Transaction doesn't match the number of Chunks including it.
+    TxRefcount { tx_hash: CryptoHash, found: u64, expected: u64 },
+    /// Refcount of a Receipt doesn't match the number of Chunks including it.
+    ReceiptRefcount { receipt_id: CryptoHash, found: u64, expected: u64 },
+    /// Refcount of a Block doesn't match the number of its children.
+    BlockRefcount { block_hash: CryptoHash, found: u64, expected: u64 },
+    /// Block can't be indexed by its Height in ColBlockPerHeight.
+    BlockPerHeight { block_hash: CryptoHash, height: BlockHeight, epoch_id: EpochId },
+    /// ShardChunk below Chunk Tail left behind by GC.
+    OrphanedChunk { chunk_hash: ChunkHash, height_created: BlockHeight },
+}
+
+/// Results of validation, serializable to JSON.
+#[derive(Debug, Serialize)]
+pub struct StoreValidatorReport<'a> {
+    pub tests: u64,
+    pub errors: &'a [ErrorMessage],
+    pub repairs: &'a [StoreRepair],
+    pub gc_counters: Vec<(String, u64)>,
+}
+
 pub struct StoreValidator {
     me: Option<AccountId>,
     config: GenesisConfig,
@@ -82,6 +154,7 @@ pub struct StoreValidator {
     start_time: Instant,
 
     pub errors: Vec<ErrorMessage>,
+    pub repairs: Vec<StoreRepair>,
     tests: u64,
 }
 
@@ -101,6 +174,7 @@ impl StoreValidator {
             timeout: None,
             start_time: Instant::now(),
             errors: vec![],
+            repairs: vec![],
             tests: 0,
         }
     }
@@ -133,9 +207,26 @@ impl StoreValidator {
     pub fn tests_done(&self) -> u64 {
         self.tests
     }
+    pub fn report(&self) -> StoreValidatorReport<'_> {
+        StoreValidatorReport {
+            tests: self.tests,
+            errors: &self.errors,
+            repairs: &self.repairs,
+            gc_counters: self.get_gc_counters(),
+        }
+    }
     fn process_error<K: std::fmt::Debug>(&mut self, err: StoreValidatorError, key: K, col: DBCol) {
         self.errors.push(ErrorMessage { key: to_string(&key), col: to_string(&col), err })
     }
+    fn add_repair(&mut self, repair: StoreRepair) {
+        self.repairs.push(repair)
+    }
+    fn is_timed_out(&self) -> bool {
+        match self.timeout {
+            Some(timeout) => self.start_time.elapsed() > Duration::from_millis(timeout),
+            None => false,
+        }
+    }
     fn validate_col(&mut self, col: DBCol) -> Result<(), StoreValidatorError> {
         for (key, value) in self.store.clone().iter_without_rc_logic(col) {
             let key_ref = key.as_ref();
@@ -332,10 +423,8 @@ impl StoreValidator {
                 }
                 _ => {}
             }
-            if let Some(timeout) = self.timeout {
-                if self.start_time.elapsed() > Duration::from_millis(timeout) {
-                    return Ok(());
-                }
+            if self.is_timed_out() {
+                return Ok(());
             }
         }
         Ok(())
@@ -354,21 +443,98 @@ impl StoreValidator {
             if let Err(e) = self.validate_col(col) {
                 self.process_error(e, col.to_string(), col)
             }
-            if let Some(timeout) = self.timeout {
-                if self.start_time.elapsed() > Duration::from_millis(timeout) {
-                    warn!(target: "adversary", "Store validator hit timeout at {:?} ({:?}/{:?})", col, col as usize, NUM_COLS);
-                    return;
-                }
+            if self.is_timed_out() {
+                warn!(target: "adversary", "Store validator hit timeout at {:?} ({:?}/{:?})", col, col as usize, NUM_COLS);
+                return;
             }
         }
-        if let Some(timeout) = self.timeout {
+        if self.is_timed_out() {
             // We didn't complete all Column checks and cannot do final checks, returning here
-            if self.start_time.elapsed() > Duration::from_millis(timeout) {
-                warn!(target: "adversary", "Store validator hit timeout before final checks");
+            warn!(target: "adversary", "Store validator hit timeout before final checks");
+            return;
+        }
+        self.validate_final();
+    }
+
+    /// Same as `validate`, but Columns are validated in parallel, each with its own cache.
+    /// Columns checking refcounts are validated after all refcounts are counted.
+    pub fn validate_parallel(&mut self) {
+        self.start_time = Instant::now();
+
+        if let Err(e) = validate::head_tail_validity(self) {
+            self.process_error(e, "HEAD / HEADER_HEAD / TAIL / CHUNK_TAIL", DBCol::ColBlockMisc)
+        }
+
+        let (refcount_cols, cols): (Vec<DBCol>, Vec<DBCol>) =
+            DBCol::iter().partition(|col| REFCOUNT_COLS.contains(col));
+        let done = AtomicUsize::new(0);
+        for cols in vec![cols, refcount_cols] {
+            let forks: Vec<(DBCol, StoreValidator)> =
+                cols.into_iter().map(|col| (col, self.fork(col))).collect();
+            let validators: Vec<StoreValidator> = forks
+                .into_par_iter()
+                .map(|(col, mut validator)| {
+                    validator.validate_single_col(col, &done);
+                    validator
+                })
+                .collect();
+            for validator in validators {
+                self.merge(validator);
+            }
+            if self.is_timed_out() {
+                warn!(target: "store_validator", "Store validator hit timeout before final checks");
                 return;
             }
         }
+        self.validate_final();
+    }
 
+    fn validate_single_col(&mut self, col: DBCol, done: &AtomicUsize) {
+        if let Err(e) = self.validate_col(col) {
+            self.process_error(e, col.to_string(), col)
+        }
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(target: "store_validator", "Validated {:?} ({}/{}): {} conditions, {} errors, {:?} elapsed", col, done, NUM_COLS, self.tests, self.errors.len(), self.start_time.elapsed());
+    }
+
+    /// Validator of a single Column sharing the store with this one. Refcounts consumed by
+    /// the Column are moved to the new validator.
+    fn fork(&mut self, col: DBCol) -> StoreValidator {
+        let mut inner = self.inner.fork();
+        match col {
+            DBCol::ColTransactions => {
+                inner.tx_refcount = std::mem::take(&mut self.inner.tx_refcount);
+            }
+            DBCol::ColReceipts => {
+                inner.receipt_refcount = std::mem::take(&mut self.inner.receipt_refcount);
+            }
+            DBCol::ColBlockRefCount => {
+                inner.block_refcount = std::mem::take(&mut self.inner.block_refcount);
+            }
+            _ => {}
+        }
+        StoreValidator {
+            me: self.me.clone(),
+            config: self.config.clone(),
+            runtime_adapter: self.runtime_adapter.clone(),
+            store: self.store.clone(),
+            inner,
+            timeout: self.timeout,
+            start_time: self.start_time,
+            errors: vec![],
+            repairs: vec![],
+            tests: 0,
+        }
+    }
+
+    fn merge(&mut self, other: StoreValidator) {
+        self.tests += other.tests;
+        self.errors.extend(other.errors);
+        self.repairs.extend(other.repairs);
+        self.inner.merge(other.inner);
+    }
+
+    fn validate_final(&mut self) {
         // Final checks
         // There is no more than one Block which Height is lower than Tail and not equal to Genesis
         if let Err(e) = validate::block_height_cmp_tail_final(self) {
@@ -391,6 +557,120 @@ impl StoreValidator {
         }
     }
 
+    /// Applies all repairs found by validation. Must not be called while a node uses the store.
+    pub fn repair(&self) -> Result<(), io::Error> {
+        let mut store_update = self.store.store_update();
+        let mut blocks_per_height: HashMap<BlockHeight, HashMap<EpochId, HashSet<CryptoHash>>> =
+            HashMap::new();
+        let mut orphaned_chunk_heights = HashSet::new();
+        for repair in self.repairs.iter() {
+            match repair {
+                StoreRepair::TxRefcount { tx_hash, found, expected } => {
+                    self.repair_refcount(
+                        &mut store_update,
+                        DBCol::ColTransactions,
+                        tx_hash,
+                        *found,
+                        *expected,
+                    )?;
+                }
+                StoreRepair::ReceiptRefcount { receipt_id, found, expected } => {
+                    self.repair_refcount(
+                        &mut store_update,
+                        DBCol::ColReceipts,
+                        receipt_id,
+                        *found,
+                        *expected,
+                    )?;
+                }
+                StoreRepair::BlockRefcount { block_hash, expected, .. } => {
+                    if *expected == 0 {
+                        store_update.delete(DBCol::ColBlockRefCount, block_hash.as_ref());
+                    } else {
+                        store_update.set_ser(
+                            DBCol::ColBlockRefCount,
+                            block_hash.as_ref(),
+                            expected,
+                        )?;
+                    }
+                }
+                StoreRepair::BlockPerHeight { block_hash, height, epoch_id } => {
+                    let block_hashes = match blocks_per_height.entry(*height) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => entry.insert(
+                            self.store
+                                .get_ser(DBCol::ColBlockPerHeight, &index_to_bytes(*height))?
+                                .unwrap_or_default(),
+                        ),
+                    };
+                    block_hashes.entry(epoch_id.clone()).or_default().insert(*block_hash);
+                }
+                StoreRepair::OrphanedChunk { chunk_hash, height_created } => {
+                    // Same as Chunk GC: refcounts of Txs and Receipts of the Chunk are decreased
+                    if let Some(chunk) =
+                        self.store.get_ser::<ShardChunk>(DBCol::ColChunks, chunk_hash.as_ref())?
+                    {
+                        for tx in chunk.transactions() {
+                            store_update.update_refcount(
+                                DBCol::ColTransactions,
+                                tx.get_hash().as_ref(),
+                                &[],
+                                -1,
+                            );
+                        }
+                        for receipt in chunk.receipts() {
+                            store_update.update_refcount(
+                                DBCol::ColReceipts,
+                                receipt.get_hash().as_ref(),
+                                &[],
+                                -1,
+                            );
+                        }
+                    }
+                    store_update.delete(DBCol::ColChunks, chunk_hash.as_ref());
+                    store_update.delete(DBCol::ColPartialChunks, chunk_hash.as_ref());
+                    store_update.delete(DBCol::ColInvalidChunks, chunk_hash.as_ref());
+                    orphaned_chunk_heights.insert(*height_created);
+                }
+            }
+        }
+        for (height, block_hashes) in blocks_per_height {
+            store_update.set_ser(
+                DBCol::ColBlockPerHeight,
+                &index_to_bytes(height),
+                &block_hashes,
+            )?;
+        }
+        // All Chunks below Chunk Tail must be GCed, so the whole Height is removed
+        for height in orphaned_chunk_heights {
+            store_update.delete(DBCol::ColChunkHashesByHeight, &index_to_bytes(height));
+        }
+        store_update.commit()
+    }
+
+    fn repair_refcount(
+        &self,
+        store_update: &mut StoreUpdate,
+        col: DBCol,
+        key: &CryptoHash,
+        found: u64,
+        expected: u64,
+    ) -> Result<(), io::Error> {
+        let rc_delta = expected as i64 - found as i64;
+        if rc_delta > 0 {
+            // Increasing refcount requires the value itself
+            match self.store.get(col, key.as_ref())? {
+                Some(value) => store_update.update_refcount(col, key.as_ref(), &value, rc_delta),
+                None => {
+                    warn!(target: "store_validator", "Can't repair refcount of {:?} in {:?}, value is not found", key, col)
+                }
+            }
+        } else if rc_delta < 0 {
+            store_update.update_refcount(col, key.as_ref(), &[], rc_delta);
+        }
+        Ok(())
+    }
+
     fn check<K: std::fmt::Debug, V>(
         &mut self,
         f: &dyn Fn(&mut StoreValidator, &K, &V) -> Result<(), StoreValidatorError>,
@@ -481,6 +761,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_parallel() {
+        let (chain, mut sv) = init();
+        sv.validate();
+        let mut sv_parallel = StoreValidator::new(
+            None,
+            sv.config.clone(),
+            sv.runtime_adapter.clone(),
+            chain.store().owned_store(),
+        );
+        sv_parallel.validate_parallel();
+        assert_eq!(sv.tests_done(), sv_parallel.tests_done());
+        assert_eq!(sv.num_failed(), sv_parallel.num_failed());
+        assert_eq!(sv.repairs.len(), sv_parallel.repairs.len());
+    }
+
+    #[test]
+    fn test_repair_block_per_height() {
+        let (mut chain, mut sv) = init();
+        let genesis_hash = *chain.get_block_by_height(0).unwrap().hash();
+        let mut store_update = chain.store().owned_store().store_update();
+        store_update.delete(DBCol::ColBlockPerHeight, &index_to_bytes(0));
+        store_update.commit().unwrap();
+        sv.validate();
+        match sv.repairs.iter().find(|repair| matches!(repair, StoreRepair::BlockPerHeight { .. }))
+        {
+            Some(StoreRepair::BlockPerHeight { block_hash, height: 0, .. }) => {
+                assert_eq!(*block_hash, genesis_hash)
+            }
+            _ => assert!(false),
+        }
+        sv.repair().unwrap();
+
+        let mut sv = StoreValidator::new(
+            None,
+            sv.config.clone(),
+            sv.runtime_adapter.clone(),
+            chain.store().owned_store(),
+        );
+        sv.validate();
+        assert!(!sv
+            .repairs
+            .iter()
+            .any(|repair| matches!(repair, StoreRepair::BlockPerHeight { .. })));
+    }
+
     #[test]
     fn test_validation_failed() {
         let (_chain, mut sv) = init();
@@ -22,6 +22,7 @@ use near_store::{
     FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY, NUM_COLS, SHOULD_COL_GC, TAIL_KEY,
 };
 
+use crate::store_validator::StoreRepair;
 use crate::StoreValidator;
 use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
 
@@ -29,8 +30,8 @@ use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
 pub enum StoreValidatorError {
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-    #[error("DB is corrupted")]
-    DBCorruption(#[from] Box<dyn std::error::Error>),
+    #[error("DB is corrupted, {0}")]
+    DBCorruption(String),
     #[error("Function {func_name:?}: data is invalid, {reason:?}")]
     InvalidData { func_name: String, reason: String },
     #[error("Function {func_name:?}: data that expected to exist in DB is not found, {reason:?}")]
@@ -41,6 +42,13 @@ pub enum StoreValidatorError {
     ValidationFailed { func_name: String, error: String },
 }
 
+impl From<Box<dyn std::error::Error>> for StoreValidatorError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        // The error is kept as a string so that validation results can be sent across threads.
+        StoreValidatorError::DBCorruption(error.to_string())
+    }
+}
+
 macro_rules! get_parent_function_name {
     () => {{
         fn f() {}
@@ -219,7 +227,7 @@ pub(crate) fn block_indexed_by_height(
     block: &Block,
 ) -> Result<(), StoreValidatorError> {
     let height = block.header().height();
-    let block_hashes: HashSet<CryptoHash> = unwrap_or_err_db!(
+    let block_hashes: HashSet<CryptoHash> = unwrap_or_err!(
         sv.store.get_ser::<HashMap<EpochId, HashSet<CryptoHash>>>(
             ColBlockPerHeight,
             &index_to_bytes(height)
@@ -227,11 +235,17 @@ pub(crate) fn block_indexed_by_height(
         "Can't get HashMap for Height {:?} from ColBlockPerHeight",
         height
     )
+    .unwrap_or_default()
     .values()
     .flatten()
     .cloned()
     .collect();
     if !block_hashes.contains(&block_hash) {
+        sv.add_repair(StoreRepair::BlockPerHeight {
+            block_hash: *block_hash,
+            height,
+            epoch_id: block.header().epoch_id().clone(),
+        });
         err!("Block {:?} is not found in ColBlockPerHeight", block);
     }
     Ok(())
@@ -265,12 +279,16 @@ pub(crate) fn chunk_hash_validity(
 
 pub(crate) fn chunk_tail_validity(
     sv: &mut StoreValidator,
-    _chunk_hash: &ChunkHash,
+    chunk_hash: &ChunkHash,
     shard_chunk: &ShardChunk,
 ) -> Result<(), StoreValidatorError> {
     let chunk_tail = sv.inner.chunk_tail;
     let height = shard_chunk.height_created();
     if height != sv.config.genesis_height && height < chunk_tail {
+        sv.add_repair(StoreRepair::OrphanedChunk {
+            chunk_hash: chunk_hash.clone(),
+            height_created: height,
+        });
         err!(
             "Invalid ShardChunk stored, chunk_tail = {:?}, ShardChunk = {:?}",
             chunk_tail,
@@ -815,6 +833,7 @@ pub(crate) fn tx_refcount(
 ) -> Result<(), StoreValidatorError> {
     let expected = sv.inner.tx_refcount.get(tx_hash).map(|&rc| rc).unwrap_or_default();
     if *refcount != expected {
+        sv.add_repair(StoreRepair::TxRefcount { tx_hash: *tx_hash, found: *refcount, expected });
         err!("Invalid tx refcount, expected {:?}, found {:?}", expected, refcount)
     } else {
         sv.inner.tx_refcount.remove(tx_hash);
@@ -829,6 +848,11 @@ pub(crate) fn receipt_refcount(
 ) -> Result<(), StoreValidatorError> {
     let expected = sv.inner.receipt_refcount.get(receipt_id).map(|&rc| rc).unwrap_or_default();
     if *refcount != expected {
+        sv.add_repair(StoreRepair::ReceiptRefcount {
+            receipt_id: *receipt_id,
+            found: *refcount,
+            expected,
+        });
         err!("Invalid receipt refcount, expected {:?}, found {:?}", expected, refcount)
     } else {
         sv.inner.receipt_refcount.remove(receipt_id);
@@ -841,8 +865,13 @@ pub(crate) fn block_refcount(
     block_hash: &CryptoHash,
     refcount: &u64,
 ) -> Result<(), StoreValidatorError> {
-    if let Some(found) = sv.inner.block_refcount.get(block_hash) {
-        if refcount != found {
+    if let Some(&found) = sv.inner.block_refcount.get(block_hash) {
+        if *refcount != found {
+            sv.add_repair(StoreRepair::BlockRefcount {
+                block_hash: *block_hash,
+                found: *refcount,
+                expected: found,
+            });
             err!("Invalid Block Refcount, expected {:?}, found {:?}", refcount, found)
         } else {
             sv.inner.block_refcount.remove(block_hash);
@@ -853,6 +882,14 @@ pub(crate) fn block_refcount(
         sv.store.get_ser::<BlockHeader>(ColBlockHeader, block_hash.as_ref()),
         "Can't get Block Header from DB"
     );
+    if header.height() != sv.config.genesis_height {
+        // No Block refers to this one
+        sv.add_repair(StoreRepair::BlockRefcount {
+            block_hash: *block_hash,
+            found: *refcount,
+            expected: 0,
+        });
+    }
     check_discrepancy!(
         header.height(),
         sv.config.genesis_height,
@@ -28,18 +28,19 @@ use near_primitives::sharding::ChunkHash;
 use near_primitives::state_record::StateRecord;
 use near_primitives::transaction::{
     Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus,
-    SignedTransaction, TransferAction,
+    SignedTransaction, Transaction, TransferAction,
 };
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
-    AccountId, ApprovalStake, Balance, BlockHeight, EpochId, Gas, Nonce, NumBlocks, NumShards,
-    ShardId, StateRoot, StateRootNode,
+    AccountId, ApprovalStake, Balance, BlockHeight, EpochHeight, EpochId, Gas, Nonce, NumBlocks,
+    NumShards, ShardId, StateRoot, StateRootNode,
 };
 use near_primitives::validator_signer::InMemoryValidatorSigner;
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
     AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    QueryRequest, QueryResponse, QueryResponseKind, StateOverrides, ViewStatePageResult,
+    ViewStateResult,
 };
 use near_store::test_utils::create_test_store;
 use near_store::{
@@ -50,7 +51,8 @@ use near_store::{
 use crate::chain::{Chain, NUM_EPOCHS_TO_KEEP_STORE_DATA};
 use crate::store::ChainStoreAccess;
 use crate::types::{
-    ApplyTransactionResult, BlockHeaderInfo, ChainGenesis, ValidatorInfoIdentifier,
+    ApplyTransactionResult, BlockHeaderInfo, ChainGenesis, SimulateTransactionResult,
+    ValidatorInfoIdentifier,
 };
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use crate::Doomslug;
@@ -565,6 +567,19 @@ impl RuntimeAdapter for KeyValueRuntime {
         false
     }
 
+    fn set_tracking(
+        &self,
+        _accounts: Vec<AccountId>,
+        _shards: Vec<ShardId>,
+        _head_hash: &CryptoHash,
+    ) -> Result<EpochHeight, Error> {
+        Err(ErrorKind::Other("Tracked shards are defined by validators".to_string()).into())
+    }
+
+    fn update_tracking(&self, _block_hash: &CryptoHash) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn validate_tx(
         &self,
         _gas_price: Balance,
@@ -604,6 +619,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         _prev_epoch_id: &EpochId,
         _prev_epoch_info: EpochInfo,
         _epoch_id: &EpochId,
+        _epoch_start_height: BlockHeight,
         _epoch_info: EpochInfo,
         _next_epoch_id: &EpochId,
         _next_epoch_info: EpochInfo,
@@ -846,6 +862,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         block_hash: &CryptoHash,
         _epoch_id: &EpochId,
         request: &QueryRequest,
+        _state_overrides: Option<&StateOverrides>,
     ) -> Result<QueryResponse, near_chain_primitives::error::QueryError> {
         match request {
             QueryRequest::ViewAccount { account_id, .. } => Ok(QueryResponse {
@@ -895,6 +912,15 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::ViewStatePage { .. } => Ok(QueryResponse {
+                kind: QueryResponseKind::ViewStatePage(ViewStatePageResult {
+                    values: Default::default(),
+                    proof: vec![],
+                    next_key: None,
+                }),
+                block_height,
+                block_hash: *block_hash,
+            }),
             QueryRequest::CallFunction { .. } => Ok(QueryResponse {
                 kind: QueryResponseKind::CallResult(CallResult {
                     result: Default::default(),
@@ -1068,6 +1094,20 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(PROTOCOL_VERSION)
     }
 
+    fn simulate_transaction(
+        &self,
+        _block_hash: &CryptoHash,
+        _block_height: BlockHeight,
+        _block_timestamp: u64,
+        _gas_price: Balance,
+        _random_seed: CryptoHash,
+        _state_roots: &[StateRoot],
+        _transaction: Transaction,
+        _signature: Option<Signature>,
+    ) -> Result<Result<SimulateTransactionResult, InvalidTxError>, Error> {
+        Err(ErrorKind::Other("Transaction simulation is not supported".to_string()).into())
+    }
+
     fn get_validator_info(
         &self,
         _epoch_id: ValidatorInfoIdentifier,
@@ -1081,6 +1121,7 @@ impl RuntimeAdapter for KeyValueRuntime {
             prev_epoch_kickout: vec![],
             epoch_start_height: 0,
             epoch_height: 1,
+            current_slashed: vec![],
         })
     }
 
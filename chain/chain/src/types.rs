@@ -21,17 +21,17 @@ use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{merklize, MerklePath};
 use near_primitives::receipt::{Receipt, ReceiptResult};
 use near_primitives::sharding::{ChunkHash, ReceiptList, ShardChunkHeader};
-use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction};
+use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction, Transaction};
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
-    AccountId, ApprovalStake, Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash,
-    NumBlocks, ShardId, StateRoot, StateRootNode,
+    AccountId, ApprovalStake, Balance, BlockHeight, BlockHeightDelta, EpochHeight, EpochId, Gas,
+    MerkleHash, NumBlocks, ShardId, StateChanges, StateRoot, StateRootNode,
 };
 use near_primitives::version::{
     ProtocolVersion, MIN_GAS_PRICE_NEP_92, MIN_GAS_PRICE_NEP_92_FIX, MIN_PROTOCOL_VERSION_NEP_92,
     MIN_PROTOCOL_VERSION_NEP_92_FIX,
 };
-use near_primitives::views::{EpochValidatorInfo, QueryRequest, QueryResponse};
+use near_primitives::views::{EpochValidatorInfo, QueryRequest, QueryResponse, StateOverrides};
 use near_store::{PartialStorage, ShardTries, Store, StoreUpdate, Trie, WrappedTrieChanges};
 
 #[cfg(feature = "protocol_feature_block_header_v3")]
@@ -90,6 +90,19 @@ pub struct ApplyTransactionResult {
     pub proof: Option<PartialStorage>,
 }
 
+/// Result of executing a transaction and the receipts it produced without saving anything.
+pub struct SimulateTransactionResult {
+    /// Executed transaction. Differs from the given one if it was not signed.
+    pub transaction: SignedTransaction,
+    /// Outcome of the transaction followed by the outcomes of the receipts in execution order.
+    pub outcomes: Vec<ExecutionOutcomeWithId>,
+    /// Changes of the state of all the shards made by the transaction and its receipts.
+    pub state_changes: StateChanges,
+    /// Whether the simulation hit its limits on blocks, receipts or gas before all the receipts
+    /// were executed.
+    pub incomplete: bool,
+}
+
 impl ApplyTransactionResult {
     /// Returns root and paths for all the outcomes in the result.
     pub fn compute_outcomes_proof(
@@ -463,6 +476,21 @@ pub trait RuntimeAdapter: Send + Sync {
         is_me: bool,
     ) -> bool;
 
+    /// Replaces the accounts and shards tracked by the client. `head_hash` is the current head
+    /// of the chain. The change takes effect two epochs after the epoch of the head, and is
+    /// reported by `will_care_about_shard` one epoch earlier so that the client catches up the
+    /// state. Returns the height of the epoch from which the change is in effect.
+    fn set_tracking(
+        &self,
+        accounts: Vec<AccountId>,
+        shards: Vec<ShardId>,
+        head_hash: &CryptoHash,
+    ) -> Result<EpochHeight, Error>;
+
+    /// Called for every processed block, brings the change made by `set_tracking` into effect
+    /// once the chain reaches the epoch from which it applies.
+    fn update_tracking(&self, block_hash: &CryptoHash) -> Result<(), Error>;
+
     /// Returns true, if given hash is last block in it's epoch.
     fn is_next_block_epoch_start(&self, parent_hash: &CryptoHash) -> Result<bool, Error>;
 
@@ -515,6 +543,7 @@ pub trait RuntimeAdapter: Send + Sync {
         prev_epoch_id: &EpochId,
         prev_epoch_info: EpochInfo,
         epoch_id: &EpochId,
+        epoch_start_height: BlockHeight,
         epoch_info: EpochInfo,
         next_epoch_id: &EpochId,
         next_epoch_info: EpochInfo,
@@ -610,6 +639,7 @@ pub trait RuntimeAdapter: Send + Sync {
     ) -> Result<ApplyTransactionResult, Error>;
 
     /// Query runtime with given `path` and `data`.
+    /// `state_overrides` are layered onto the state before executing `call_function` requests.
     fn query(
         &self,
         shard_uid: ShardUId,
@@ -620,8 +650,26 @@ pub trait RuntimeAdapter: Send + Sync {
         block_hash: &CryptoHash,
         epoch_id: &EpochId,
         request: &QueryRequest,
+        state_overrides: Option<&StateOverrides>,
     ) -> Result<QueryResponse, near_chain_primitives::error::QueryError>;
 
+    /// Executes the transaction and all the receipts it produces in the blocks following
+    /// `block_hash` on top of `state_roots`, the post-state roots of all the shards in that
+    /// block, without saving anything. If `signature` is `None`, the transaction is executed
+    /// with a temporary full access key of the signer.
+    /// Returns `Err(InvalidTxError)` inside if the transaction is invalid.
+    fn simulate_transaction(
+        &self,
+        block_hash: &CryptoHash,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        gas_price: Balance,
+        random_seed: CryptoHash,
+        state_roots: &[StateRoot],
+        transaction: Transaction,
+        signature: Option<Signature>,
+    ) -> Result<Result<SimulateTransactionResult, InvalidTxError>, Error>;
+
     fn get_validator_info(
         &self,
         epoch_id: ValidatorInfoIdentifier,
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
 use borsh::BorshDeserialize;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use borsh::BorshSerialize;
 
 use near_crypto::PublicKey;
 use near_primitives::block::{Block, BlockHeader};
@@ -8,20 +10,38 @@ use near_primitives::challenge::{
     BlockDoubleSign, Challenge, ChallengeBody, ChallengesResult, ChunkProofs, ChunkState,
     MaybeEncodedShardChunk,
 };
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::challenge::{ChunkStateIncomingReceipts, ChunkStateV2};
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::checked_feature;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::hash::hash;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::merklize;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::merkle::verify_path;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::receipt::Receipt;
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_primitives::sharding::ShardChunkHeaderV3;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::sharding::{ReceiptList, ReceiptProof};
 use near_primitives::sharding::{
     ShardChunk, ShardChunkHeader, ShardChunkHeaderV1, ShardChunkHeaderV2,
 };
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::validator_stake::ValidatorStakeIter;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::types::ShardId;
 use near_primitives::types::{AccountId, EpochId, Nonce};
 use near_store::PartialStorage;
 
 use crate::byzantine_assert;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use crate::chain::{collect_receipts, shuffle_receipt_proofs};
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use crate::migrations::is_first_epoch_with_protocol_version;
 use crate::types::ApplyTransactionResult;
 use crate::{ChainStore, Error, ErrorKind, RuntimeAdapter};
 
@@ -305,6 +325,57 @@ fn validate_chunk_proofs_challenge(
     return Err(ErrorKind::MaliciousChallenge.into());
 }
 
+/// Checks that the incoming receipts in the challenge are exactly the receipts sent to the shard
+/// of the challenged chunk by the chunks in the given blocks, and returns them in the order in
+/// which they were applied.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn collect_chunk_state_receipts(
+    shard_id: ShardId,
+    blocks: &[&BlockHeader],
+    incoming_receipts: &[ChunkStateIncomingReceipts],
+) -> Result<Vec<Receipt>, Error> {
+    let mut receipts = vec![];
+    for (block_header, incoming) in blocks.iter().zip(incoming_receipts) {
+        let (receipts_root, _) = merklize(&incoming.outgoing_receipts_roots);
+        if &receipts_root != block_header.chunk_receipts_root() {
+            return Err(ErrorKind::MaliciousChallenge.into());
+        }
+        let mut from_shard_ids: Vec<_> = incoming
+            .receipt_proofs
+            .iter()
+            .map(|ReceiptProof(_, shard_proof)| shard_proof.from_shard_id)
+            .collect();
+        from_shard_ids.sort();
+        let new_chunk_shard_ids: Vec<_> = block_header
+            .chunk_mask()
+            .iter()
+            .enumerate()
+            .filter(|(_, is_new_chunk)| **is_new_chunk)
+            .map(|(from_shard_id, _)| from_shard_id as ShardId)
+            .collect();
+        if from_shard_ids != new_chunk_shard_ids {
+            return Err(ErrorKind::MaliciousChallenge.into());
+        }
+        for ReceiptProof(block_receipts, shard_proof) in incoming.receipt_proofs.iter() {
+            let receipts_hash = hash(&ReceiptList(shard_id, block_receipts).try_to_vec()?);
+            if shard_proof.to_shard_id != shard_id
+                || !verify_path(
+                    incoming.outgoing_receipts_roots[shard_proof.from_shard_id as usize],
+                    &shard_proof.proof,
+                    &receipts_hash,
+                )
+            {
+                return Err(ErrorKind::MaliciousChallenge.into());
+            }
+        }
+        let mut receipt_proofs = incoming.receipt_proofs.clone();
+        receipt_proofs.sort_by_key(|ReceiptProof(_, shard_proof)| shard_proof.from_shard_id);
+        shuffle_receipt_proofs(&mut receipt_proofs, block_header.hash());
+        receipts.extend(collect_receipts(&receipt_proofs));
+    }
+    Ok(receipts)
+}
+
 fn validate_chunk_state_challenge(
     runtime_adapter: &dyn RuntimeAdapter,
     chunk_state: &ChunkState,
@@ -378,6 +449,132 @@ fn validate_chunk_state_challenge(
     }
 }
 
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn validate_chunk_state_v2_challenge(
+    runtime_adapter: &dyn RuntimeAdapter,
+    chunk_state: &ChunkStateV2,
+) -> Result<(CryptoHash, Vec<AccountId>), Error> {
+    let prev_block_header = BlockHeader::try_from_slice(&chunk_state.prev_block_header)?;
+    let block_header = BlockHeader::try_from_slice(&chunk_state.block_header)?;
+
+    // Validate previous chunk and block header.
+    validate_header_authorship(runtime_adapter, &prev_block_header)?;
+    let prev_chunk_header = chunk_state.prev_chunk.cloned_header();
+    let _ = validate_chunk_authorship(runtime_adapter, &prev_chunk_header)?;
+    if !Block::validate_chunk_header_proof(
+        &prev_chunk_header,
+        &prev_block_header.chunk_headers_root(),
+        &chunk_state.prev_merkle_proof,
+    ) {
+        return Err(ErrorKind::MaliciousChallenge.into());
+    }
+    let shard_id = chunk_state.chunk_header.shard_id();
+    if prev_chunk_header.shard_id() != shard_id
+        || !prev_block_header.chunk_mask().get(shard_id as usize).cloned().unwrap_or(false)
+        || !validate_chunk_proofs(&chunk_state.prev_chunk, runtime_adapter)
+    {
+        return Err(ErrorKind::MaliciousChallenge.into());
+    }
+
+    // Validate current chunk and block header.
+    validate_header_authorship(runtime_adapter, &block_header)?;
+    let chunk_producer = validate_chunk_authorship(runtime_adapter, &chunk_state.chunk_header)?;
+    if !Block::validate_chunk_header_proof(
+        &chunk_state.chunk_header,
+        &block_header.chunk_headers_root(),
+        &chunk_state.merkle_proof,
+    ) {
+        return Err(ErrorKind::MaliciousChallenge.into());
+    }
+    if block_header.prev_hash() != prev_block_header.hash()
+        || !block_header.chunk_mask().get(shard_id as usize).cloned().unwrap_or(false)
+    {
+        return Err(ErrorKind::MaliciousChallenge.into());
+    }
+
+    // Validate the blocks since the last block with a new chunk for the shard before the
+    // previous chunk, and the receipts applied with the previous chunk.
+    let ancestors = chunk_state
+        .prev_block_ancestors
+        .iter()
+        .map(|header| BlockHeader::try_from_slice(header))
+        .collect::<Result<Vec<_>, _>>()?;
+    let last_ancestor = match ancestors.last() {
+        Some(header) => header,
+        None => return Err(ErrorKind::MaliciousChallenge.into()),
+    };
+    let mut expected_hash = prev_block_header.prev_hash();
+    for (i, header) in ancestors.iter().enumerate() {
+        let is_new_chunk = header.chunk_mask().get(shard_id as usize).cloned().unwrap_or(false);
+        if header.hash() != expected_hash || is_new_chunk != (i + 1 == ancestors.len()) {
+            return Err(ErrorKind::MaliciousChallenge.into());
+        }
+        expected_hash = header.prev_hash();
+    }
+    if chunk_state.prev_incoming_receipts.len() != ancestors.len() {
+        return Err(ErrorKind::MaliciousChallenge.into());
+    }
+    let receipt_blocks: Vec<&BlockHeader> = std::iter::once(&prev_block_header)
+        .chain(ancestors.iter().take(ancestors.len() - 1))
+        .collect();
+    let receipts = collect_chunk_state_receipts(
+        shard_id,
+        &receipt_blocks,
+        &chunk_state.prev_incoming_receipts,
+    )?;
+    let is_first_block_with_chunk_of_version =
+        is_first_epoch_with_protocol_version(runtime_adapter, prev_block_header.prev_hash())?
+            && last_ancestor.epoch_id() != prev_block_header.epoch_id();
+
+    // Apply state transition and check that the result state and other data doesn't match.
+    let partial_storage = PartialStorage { nodes: chunk_state.partial_state.clone() };
+    let result = runtime_adapter
+        .check_state_transition(
+            partial_storage,
+            shard_id,
+            &prev_chunk_header.prev_state_root(),
+            prev_block_header.height(),
+            prev_block_header.raw_timestamp(),
+            &prev_chunk_header.prev_block_hash(),
+            &prev_block_header.hash(),
+            &receipts,
+            &chunk_state.prev_chunk.transactions(),
+            prev_chunk_header.validator_proposals(),
+            ancestors[0].gas_price(),
+            prev_chunk_header.gas_limit(),
+            prev_block_header.challenges_result(),
+            *prev_block_header.random_value(),
+            true,
+            is_first_block_with_chunk_of_version,
+        )
+        .map_err(|_| Error::from(ErrorKind::MaliciousChallenge))?;
+    let outcome_root = ApplyTransactionResult::compute_outcomes_proof(&result.outcomes).0;
+    let proposals_match = result.validator_proposals.len()
+        == chunk_state.chunk_header.validator_proposals().len()
+        && result
+            .validator_proposals
+            .iter()
+            .zip(chunk_state.chunk_header.validator_proposals())
+            .all(|(x, y)| x == &y);
+    let outgoing_receipts: Vec<Receipt> =
+        result.receipt_result.into_iter().flat_map(|(_, receipts)| receipts).collect();
+    let shard_layout = runtime_adapter.get_shard_layout(block_header.epoch_id())?;
+    let (outgoing_receipts_root, _) =
+        merklize(&runtime_adapter.build_receipts_hashes(&outgoing_receipts, &shard_layout));
+    if result.new_root != chunk_state.chunk_header.prev_state_root()
+        || outcome_root != chunk_state.chunk_header.outcome_root()
+        || !proposals_match
+        || result.total_gas_burnt != chunk_state.chunk_header.gas_used()
+        || result.total_balance_burnt != chunk_state.chunk_header.balance_burnt()
+        || outgoing_receipts_root != chunk_state.chunk_header.outgoing_receipts_root()
+    {
+        Ok((*block_header.hash(), vec![chunk_producer]))
+    } else {
+        // If all the data matches, this is actually valid chunk and challenge is malicious.
+        Err(ErrorKind::MaliciousChallenge.into())
+    }
+}
+
 /// Returns Some(block hash, vec![account_id]) of invalid block and who to slash if challenge is correct and None if incorrect.
 pub fn validate_challenge(
     runtime_adapter: &dyn RuntimeAdapter,
@@ -405,6 +602,18 @@ pub fn validate_challenge(
         ChallengeBody::ChunkState(chunk_state) => {
             validate_chunk_state_challenge(runtime_adapter, chunk_state)
         }
+        #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+        ChallengeBody::ChunkStateV2(chunk_state) => {
+            let protocol_version = runtime_adapter.get_epoch_protocol_version(epoch_id)?;
+            if !checked_feature!(
+                "protocol_feature_chunk_state_challenge",
+                ChunkStateChallenge,
+                protocol_version
+            ) {
+                return Err(ErrorKind::InvalidChallenge.into());
+            }
+            validate_chunk_state_v2_challenge(runtime_adapter, chunk_state)
+        }
     }
 }
 
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+
 use near_chain::test_utils::setup;
 use near_chain::Block;
 use near_logger_utils::init_test_logger;
 use near_primitives::merkle::PartialMerkleTree;
+use near_primitives::sharding::{ChunkHash, ShardInfo, StateSyncInfo};
 
 #[test]
 fn chain_sync_headers() {
@@ -24,3 +27,39 @@ fn chain_sync_headers() {
         .unwrap();
     assert_eq!(chain.header_head().unwrap().height, 4);
 }
+
+#[test]
+fn state_parts_download_progress() {
+    init_test_logger();
+    let (mut chain, _, _) = setup();
+    let sync_hash = *chain.genesis().hash();
+
+    // The main state sync has no `StateSyncInfo`, its progress is recorded nonetheless.
+    chain.mark_state_part_downloaded(0, sync_hash, 1, 3).unwrap();
+    chain.mark_state_part_downloaded(0, sync_hash, 1, 3).unwrap();
+    assert_eq!(
+        chain.get_downloaded_state_parts(0, sync_hash, 3).unwrap(),
+        vec![false, true, false]
+    );
+    // Progress recorded for another number of parts is ignored.
+    assert_eq!(chain.get_downloaded_state_parts(0, sync_hash, 2).unwrap(), vec![false, false]);
+    assert!(chain.store().iterate_state_sync_infos().is_empty());
+    chain.clear_downloaded_parts(0, sync_hash, 3).unwrap();
+    assert_eq!(chain.get_downloaded_state_parts(0, sync_hash, 3).unwrap(), vec![false; 3]);
+
+    // Catchups keep their progress in their `StateSyncInfo`.
+    let shards = vec![ShardInfo(0, ChunkHash::default())];
+    let mut chain_store_update = chain.mut_store().store_update();
+    chain_store_update.add_state_dl_info(StateSyncInfo {
+        epoch_tail_hash: sync_hash,
+        shards,
+        downloaded_parts: HashMap::new(),
+    });
+    chain_store_update.commit().unwrap();
+    chain.mark_state_part_downloaded(0, sync_hash, 0, 2).unwrap();
+    assert_eq!(chain.get_downloaded_state_parts(0, sync_hash, 2).unwrap(), vec![true, false]);
+    let state_sync_infos = chain.store().iterate_state_sync_infos();
+    assert_eq!(state_sync_infos.len(), 1);
+    assert_eq!(state_sync_infos[0].1.shards, vec![ShardInfo(0, ChunkHash::default())]);
+    assert_eq!(state_sync_infos[0].1.downloaded_parts.get(&0), Some(&vec![true, false]));
+}
@@ -773,6 +773,18 @@ impl ShardsManager {
         self.tx_pools.entry(shard_id).or_insert_with(TransactionPool::new).insert_transaction(tx)
     }
 
+    /// Returns all transactions that are currently in the pools, together with their shard.
+    pub fn get_pool_transactions(&self) -> impl Iterator<Item = (ShardId, &SignedTransaction)> {
+        self.tx_pools
+            .iter()
+            .flat_map(|(shard_id, pool)| pool.iter_transactions().map(move |tx| (*shard_id, tx)))
+    }
+
+    /// Returns the transaction with the given hash if it's in any of the pools.
+    pub fn get_pool_transaction(&self, tx_hash: &CryptoHash) -> Option<&SignedTransaction> {
+        self.tx_pools.values().find_map(|pool| pool.get_transaction(tx_hash))
+    }
+
     pub fn remove_transactions(
         &mut self,
         shard_id: ShardId,
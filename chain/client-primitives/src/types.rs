@@ -7,22 +7,24 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use near_chain_configs::ProtocolConfigView;
+use near_crypto::Signature;
 use near_network_primitives::types::{AccountOrPeerIdOrHash, KnownProducer, PeerInfo};
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::sharding::ChunkHash;
+use near_primitives::transaction::{SignedTransaction, Transaction};
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockHeightDelta, BlockReference, EpochHeight, EpochReference,
+    MaybeBlockId, NumBlocks, ShardId, TransactionOrReceiptId,
 };
 use near_primitives::utils::generate_random_string;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
     FinalExecutionOutcomeViewEnum, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
-    QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView, StateChangesRequestView,
-    StateChangesView,
+    MisbehaviorEvidenceView, QueryRequest, QueryResponse, ReceiptView, SimulatedTransactionView,
+    StateChangesKindsView, StateChangesRequestView, StateChangesView, StateOverrides,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 
@@ -105,6 +107,8 @@ impl Clone for DownloadStatus {
 pub enum ShardSyncStatus {
     StateDownloadHeader,
     StateDownloadParts,
+    StateDownloadScheduling,
+    StateDownloadApplying,
     StateDownloadFinalize,
     StateDownloadComplete,
     StateSplit,
@@ -263,11 +267,18 @@ pub struct Query {
     pub query_id: String,
     pub block_reference: BlockReference,
     pub request: QueryRequest,
+    /// Temporary state changes applied before executing a `call_function` request.
+    pub state_overrides: Option<StateOverrides>,
 }
 
 impl Query {
     pub fn new(block_reference: BlockReference, request: QueryRequest) -> Self {
-        Query { query_id: generate_random_string(10), block_reference, request }
+        Query {
+            query_id: generate_random_string(10),
+            block_reference,
+            request,
+            state_overrides: None,
+        }
     }
 }
 
@@ -309,6 +320,11 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("The page limit must be greater than zero")]
+    InvalidPageLimit {
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("Access key for public key {public_key} has never been observed on the node at block #{block_height}")]
     UnknownAccessKey {
         public_key: near_crypto::PublicKey,
@@ -422,6 +438,105 @@ impl Message for GetNetworkInfo {
     type Result = Result<NetworkInfoResponse, String>;
 }
 
+/// Returns what the node observed about the consensus for the last `num_heights` heights up to
+/// the head, and for the heights above the head it has received approvals for.
+pub struct GetConsensusTimeline {
+    pub num_heights: BlockHeightDelta,
+}
+
+/// An approval the node received as the block producer of the target height.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsensusApprovalView {
+    pub account_id: AccountId,
+    /// The approved block if the approval is an endorsement.
+    pub endorsement: Option<CryptoHash>,
+    /// The height of the last block the approver knew of if the approval is a skip.
+    pub skip_from_height: Option<BlockHeight>,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsensusChunkView {
+    pub shard_id: ShardId,
+    pub chunk_producer: Option<AccountId>,
+    /// Whether the block contains a new chunk for the shard.
+    pub included: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsensusHeightView {
+    pub height: BlockHeight,
+    /// The block at this height on the canonical chain.
+    pub block_hash: Option<CryptoHash>,
+    /// The block producer of the height, whether it produced a block or not.
+    pub block_producer: Option<AccountId>,
+    pub block_timestamp: Option<DateTime<Utc>>,
+    /// Whether the height is below the head, but has no block on the canonical chain.
+    pub skipped: bool,
+    pub chunks: Vec<ConsensusChunkView>,
+    pub approvals: Vec<ConsensusApprovalView>,
+    /// When the approvals received for the height crossed 2/3 of the stake.
+    pub threshold_reached_at: Option<DateTime<Utc>>,
+    /// When the block at the height became the head of the chain.
+    pub became_head_at: Option<DateTime<Utc>>,
+    /// When a block with doomslug finality at the height was first observed.
+    pub became_final_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsensusTimelineResponse {
+    pub head_height: BlockHeight,
+    /// Largest height for which a doomslug final block was observed.
+    pub largest_final_height: BlockHeight,
+    /// Largest height for which the node received enough approvals to produce a block.
+    pub largest_threshold_height: BlockHeight,
+    /// Largest height the node sent an approval for.
+    pub largest_target_height: BlockHeight,
+    /// The height the node will send a skip message for once the timer expires.
+    pub timer_height: BlockHeight,
+    pub timer_started_at: DateTime<Utc>,
+    pub heights: Vec<ConsensusHeightView>,
+}
+
+impl Message for GetConsensusTimeline {
+    type Result = Result<ConsensusTimelineResponse, String>;
+}
+
+/// Returns the hashes and sizes of the transactions in the transaction pools of the shards this
+/// node tracks. Full transactions are fetched one by one with `GetPoolTransaction`, so that the
+/// client actor doesn't copy the whole pool.
+pub struct GetPoolTransactions {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PoolTransactionInfo {
+    pub hash: CryptoHash,
+    pub size: u64,
+}
+
+impl Message for GetPoolTransactions {
+    type Result = Result<Vec<PoolTransactionInfo>, String>;
+}
+
+/// Returns the transaction with the given hash if it's in the transaction pool of one of the
+/// shards this node tracks.
+pub struct GetPoolTransaction {
+    pub tx_hash: CryptoHash,
+}
+
+impl Message for GetPoolTransaction {
+    type Result = Result<Option<SignedTransaction>, String>;
+}
+
+/// Returns the evidence of misbehavior detected by this node, of all validators if `account_id`
+/// isn't given.
+pub struct GetMisbehaviorEvidence {
+    pub account_id: Option<AccountId>,
+}
+
+impl Message for GetMisbehaviorEvidence {
+    type Result = Result<Vec<MisbehaviorEvidenceView>, String>;
+}
+
 pub struct GetGasPrice {
     pub block_id: MaybeBlockId,
 }
@@ -779,3 +894,101 @@ impl From<near_chain_primitives::Error> for GetProtocolConfigError {
         }
     }
 }
+
+/// Executes the transaction and all the receipts it produces on top of the state after the given
+/// block without saving anything. Unsigned transactions are executed with a temporary full access
+/// key of the signer.
+pub struct SimulateTransaction {
+    pub block_reference: BlockReference,
+    pub transaction: Transaction,
+    pub signature: Option<Signature>,
+}
+
+impl Message for SimulateTransaction {
+    type Result = Result<SimulatedTransactionView, SimulateTransactionError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SimulateTransactionError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("Block has never been observed: {0}")]
+    UnknownBlock(String),
+    #[error("Block is not synced yet")]
+    NotSyncedYet,
+    #[error("State of shard {0} is not available on this node")]
+    UnavailableShard(ShardId),
+    #[error("Transaction is invalid: {0}")]
+    InvalidTransaction(InvalidTxError),
+    #[error("Failed to simulate the transaction: {0}")]
+    InternalError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for SimulateTransactionError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error.kind() {
+            near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
+            near_chain_primitives::ErrorKind::DBNotFoundErr(s) => Self::UnknownBlock(s),
+            near_chain_primitives::ErrorKind::StorageError(err) => {
+                Self::InternalError(err.to_string())
+            }
+            near_chain_primitives::ErrorKind::Other(s) => Self::InternalError(s),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+/// Updates the client settings that can be changed without restarting the node.
+pub struct UpdateClientConfig {
+    /// Accounts to track, `None` keeps the current ones. Shards of newly tracked accounts are
+    /// caught up in the same way as shards assigned to the node for the next epoch.
+    pub tracked_accounts: Option<Vec<AccountId>>,
+    /// Maximum number of blocks to garbage collect at every garbage collection call.
+    pub gc_blocks_limit: NumBlocks,
+}
+
+impl Message for UpdateClientConfig {
+    type Result = ();
+}
+
+/// Starts or stops tracking accounts and shards on the running node. A message without any
+/// accounts and shards only returns the current tracking.
+#[derive(Default)]
+pub struct ChangeShardTracking {
+    pub track_accounts: Vec<AccountId>,
+    pub track_shards: Vec<ShardId>,
+    pub untrack_accounts: Vec<AccountId>,
+    pub untrack_shards: Vec<ShardId>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShardTrackingResponse {
+    pub tracked_accounts: Vec<AccountId>,
+    pub tracked_shards: Vec<ShardId>,
+    /// Height of the epoch from which the change is in effect, if the tracking was changed.
+    pub effective_epoch_height: Option<EpochHeight>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChangeShardTrackingError {
+    #[error("Shard {0} does not exist")]
+    UnknownShard(ShardId),
+    #[error("Failed to change tracked shards: {0}")]
+    Failed(String),
+}
+
+impl From<near_chain_primitives::Error> for ChangeShardTrackingError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        Self::Failed(error.to_string())
+    }
+}
+
+impl Message for ChangeShardTracking {
+    type Result = Result<ShardTrackingResponse, ChangeShardTrackingError>;
+}
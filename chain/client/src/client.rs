@@ -6,6 +6,7 @@ use std::iter;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+use borsh::BorshDeserialize;
 use cached::{Cached, SizedCache};
 use chrono::Utc;
 use log::{debug, error, info, warn};
@@ -25,7 +26,9 @@ use near_network::{
     EPOCH_SYNC_PEER_TIMEOUT_MS, EPOCH_SYNC_REQUEST_TIMEOUT_MS,
 };
 use near_primitives::block::{Approval, ApprovalInner, ApprovalMessage, Block, BlockHeader, Tip};
-use near_primitives::challenge::{Challenge, ChallengeBody};
+use near_primitives::challenge::{
+    BlockDoubleSign, Challenge, ChallengeBody, MisbehaviorEvidence, MisbehaviorEvidenceRecord,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath};
 use near_primitives::receipt::Receipt;
@@ -38,13 +41,16 @@ use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_primitives::types::NumBlocks;
-use near_primitives::types::{AccountId, ApprovalStake, BlockHeight, EpochId, ShardId};
+use near_primitives::types::{
+    AccountId, ApprovalStake, BlockHeight, EpochHeight, EpochId, ShardId,
+};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::{to_timestamp, MaybeValidated};
 use near_primitives::validator_signer::ValidatorSigner;
 
 use crate::metrics;
 use crate::sync::{BlockSync, EpochSync, HeaderSync, StateSync, StateSyncResult};
+use crate::sync_jobs_actor::{ApplyStatePartsRequest, ValidateStatePartRequest};
 use crate::SyncStatus;
 use near_client_primitives::types::{Error, ShardSyncDownload, ShardSyncStatus};
 use near_primitives::block_header::ApprovalType;
@@ -84,6 +90,12 @@ pub struct Client {
     pub block_sync: BlockSync,
     /// Keeps track of syncing state.
     pub state_sync: StateSync,
+    /// Schedules applying of downloaded state parts off the client thread.
+    /// If not set, the state parts are applied synchronously.
+    pub state_parts_task_scheduler: Option<Box<dyn Fn(ApplyStatePartsRequest)>>,
+    /// Schedules validation of state parts taken from the dump directory off the client thread.
+    /// If not set, the state parts are validated synchronously.
+    pub state_part_validation_scheduler: Option<Box<dyn Fn(ValidateStatePartRequest)>>,
     /// List of currently accumulated challenges.
     pub challenges: HashMap<CryptoHash, Challenge>,
     /// A ReedSolomon instance to reconstruct shard.
@@ -131,6 +143,7 @@ impl Client {
                 .collect(),
             Duration::from_millis(EPOCH_SYNC_REQUEST_TIMEOUT_MS),
             Duration::from_millis(EPOCH_SYNC_PEER_TIMEOUT_MS),
+            config.epoch_length,
         );
         let header_sync = HeaderSync::new(
             network_adapter.clone(),
@@ -141,7 +154,11 @@ impl Client {
         );
         let block_sync =
             BlockSync::new(network_adapter.clone(), config.block_fetch_horizon, config.archive);
-        let state_sync = StateSync::new(network_adapter.clone(), config.state_sync_timeout);
+        let state_sync = StateSync::new(
+            network_adapter.clone(),
+            config.state_sync_timeout,
+            config.state_sync_dump_dir.clone(),
+        );
         let num_block_producer_seats = config.num_block_producer_seats as usize;
         let data_parts = runtime_adapter.num_data_parts();
         let parity_parts = runtime_adapter.num_total_parts() - data_parts;
@@ -175,6 +192,8 @@ impl Client {
             header_sync,
             block_sync,
             state_sync,
+            state_parts_task_scheduler: None,
+            state_part_validation_scheduler: None,
             challenges: Default::default(),
             rs: ReedSolomonWrapper::new(data_parts, parity_parts),
             rebroadcasted_blocks: SizedCache::with_size(NUM_REBROADCAST_BLOCKS),
@@ -665,8 +684,14 @@ impl Client {
     }
 
     pub fn send_challenges(&mut self, challenges: Arc<RwLock<Vec<ChallengeBody>>>) {
+        let bodies: Vec<_> = challenges.write().unwrap().drain(..).collect();
+        for body in bodies.iter() {
+            if let ChallengeBody::BlockDoubleSign(block_double_sign) = body {
+                self.save_block_double_sign(block_double_sign);
+            }
+        }
         if let Some(validator_signer) = self.validator_signer.as_ref() {
-            for body in challenges.write().unwrap().drain(..) {
+            for body in bodies {
                 let challenge = Challenge::produce(body, &**validator_signer);
                 self.challenges.insert(challenge.hash, challenge.clone());
                 self.network_adapter.do_send(NetworkRequests::Challenge(challenge));
@@ -674,6 +699,47 @@ impl Client {
         }
     }
 
+    /// Saves the evidence of a block producer signing two blocks at the same height.
+    fn save_block_double_sign(&mut self, block_double_sign: &BlockDoubleSign) {
+        let header = match BlockHeader::try_from_slice(&block_double_sign.left_block_header) {
+            Ok(header) => header,
+            Err(err) => {
+                error!(target: "client", "Failed to decode double signed block header: {}", err);
+                return;
+            }
+        };
+        let block_producer = match self
+            .runtime_adapter
+            .get_block_producer(header.epoch_id(), header.height())
+        {
+            Ok(block_producer) => block_producer,
+            Err(err) => {
+                error!(target: "client", "Failed to get block producer of double signed block: {}", err);
+                return;
+            }
+        };
+        self.save_misbehavior_evidence(MisbehaviorEvidenceRecord {
+            account_id: block_producer,
+            epoch_id: header.epoch_id().clone(),
+            height: header.height(),
+            detected_at: to_timestamp(Utc::now()),
+            evidence: MisbehaviorEvidence::BlockDoubleSign(block_double_sign.clone()),
+        });
+    }
+
+    /// Saves evidence of misbehavior for operators to inspect.
+    fn save_misbehavior_evidence(&mut self, record: MisbehaviorEvidenceRecord) {
+        match self.chain.mut_store().save_misbehavior_evidence(&record) {
+            Ok(true) => {
+                warn!(target: "client", "Detected misbehavior of {} at height {}: {:?}", record.account_id, record.height, record.evidence);
+            }
+            Ok(false) => {}
+            Err(err) => {
+                error!(target: "client", "Failed to save misbehavior evidence: {}", err);
+            }
+        }
+    }
+
     pub fn process_block(
         &mut self,
         block: Block,
@@ -738,10 +804,7 @@ impl Client {
                     }
                     near_chain::ErrorKind::InvalidChunkState(chunk_state) => {
                         self.network_adapter.do_send(NetworkRequests::Challenge(
-                            Challenge::produce(
-                                ChallengeBody::ChunkState(*chunk_state),
-                                &**validator_signer,
-                            ),
+                            Challenge::produce(*chunk_state, &**validator_signer),
                         ));
                     }
                     _ => {}
@@ -1344,6 +1407,17 @@ impl Client {
             };
 
         self.doomslug.on_approval_message(Instant::now(), &approval, &block_producer_stakes);
+
+        // There is no challenge for conflicting approvals, so they are only saved as evidence.
+        for approval_double_sign in self.doomslug.take_approval_double_signs() {
+            self.save_misbehavior_evidence(MisbehaviorEvidenceRecord {
+                account_id: approval_double_sign.right_approval.account_id.clone(),
+                epoch_id: next_block_epoch_id.clone(),
+                height: approval_double_sign.right_approval.target_height,
+                detected_at: to_timestamp(Utc::now()),
+                evidence: MisbehaviorEvidence::ApprovalDoubleSign(approval_double_sign),
+            });
+        }
     }
 
     /// Forwards given transaction to upcoming validators.
@@ -1570,6 +1644,53 @@ impl Client {
         Ok(false)
     }
 
+    /// Returns the state sync downloading the state for `sync_hash`: either the main state sync
+    /// or one of the catchups.
+    pub fn get_state_sync_mut(&mut self, sync_hash: &CryptoHash) -> Option<&mut StateSync> {
+        if let SyncStatus::StateSync(state_sync_hash, _) = &self.sync_status {
+            if state_sync_hash == sync_hash {
+                return Some(&mut self.state_sync);
+            }
+        }
+        self.catchup_state_syncs.get_mut(sync_hash).map(|(state_sync, _)| state_sync)
+    }
+
+    /// Returns the download status of the shard state for `sync_hash`, either requested by the
+    /// main state sync or by one of the catchups.
+    pub fn get_shard_sync_download_mut(
+        &mut self,
+        shard_id: ShardId,
+        sync_hash: &CryptoHash,
+    ) -> Option<&mut ShardSyncDownload> {
+        if let SyncStatus::StateSync(state_sync_hash, shards_to_download) = &mut self.sync_status {
+            if state_sync_hash == sync_hash {
+                return shards_to_download.get_mut(&shard_id);
+            }
+        }
+        self.catchup_state_syncs
+            .get_mut(sync_hash)
+            .and_then(|(_, shards_to_download)| shards_to_download.get_mut(&shard_id))
+    }
+
+    /// Replaces the tracked accounts and shards, see `RuntimeAdapter::set_tracking`. Returns
+    /// the height of the epoch from which the change is in effect.
+    pub fn set_tracking(
+        &mut self,
+        accounts: Vec<AccountId>,
+        shards: Vec<ShardId>,
+    ) -> Result<EpochHeight, near_chain::Error> {
+        let head = self.chain.head()?;
+        let epoch_height = self.runtime_adapter.set_tracking(
+            accounts.clone(),
+            shards.clone(),
+            &head.last_block_hash,
+        )?;
+        info!(target: "client", "Tracking accounts {:?} and shards {:?} starting from epoch height {}", accounts, shards, epoch_height);
+        self.config.tracked_accounts = accounts;
+        self.config.tracked_shards = shards;
+        Ok(epoch_height)
+    }
+
     /// Walks through all the ongoing state syncs for future epochs and processes them
     pub fn run_catchup(
         &mut self,
@@ -1616,9 +1737,13 @@ impl Client {
                 }
             };
             let state_sync_timeout = self.config.state_sync_timeout;
+            let state_sync_dump_dir = self.config.state_sync_dump_dir.clone();
             let (state_sync, new_shard_sync) =
                 self.catchup_state_syncs.entry(sync_hash).or_insert_with(|| {
-                    (StateSync::new(network_adapter1, state_sync_timeout), new_shard_sync)
+                    (
+                        StateSync::new(network_adapter1, state_sync_timeout, state_sync_dump_dir),
+                        new_shard_sync,
+                    )
                 });
 
             debug!(
@@ -1634,6 +1759,8 @@ impl Client {
                 &self.runtime_adapter,
                 highest_height_peers,
                 state_sync_info.shards.iter().map(|tuple| tuple.0).collect(),
+                self.state_parts_task_scheduler.as_deref(),
+                self.state_part_validation_scheduler.as_deref(),
             )? {
                 StateSyncResult::Unchanged => {}
                 StateSyncResult::Changed(fetch_block) => {
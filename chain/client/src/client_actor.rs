@@ -1,14 +1,16 @@
 //! Client actor orchestrates Client and facilitates network connection.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use actix::{Actor, Addr, Arbiter, Context, Handler};
+use actix::{Actor, ActorFuture, Addr, Arbiter, AsyncContext, Context, Handler, WrapFuture};
 use actix_rt::ArbiterHandle;
 use chrono::Duration as OldDuration;
 use chrono::{DateTime, Utc};
+use futures::{future, FutureExt};
 use log::{debug, error, info, trace, warn};
 
 #[cfg(feature = "delay_detector")]
@@ -37,7 +39,10 @@ use near_performance_metrics;
 use near_performance_metrics_macros::{perf, perf_with_debug};
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::types::{BlockHeight, EpochId};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockHeightDelta, EpochHeight, EpochId, ShardId,
+};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::{from_timestamp, MaybeValidated};
 use near_primitives::validator_signer::ValidatorSigner;
@@ -50,14 +55,21 @@ use near_telemetry::TelemetryActor;
 use crate::client::Client;
 use crate::info::{InfoHelper, ValidatorInfoHelper};
 use crate::sync::{highest_height_peer, StateSync, StateSyncResult};
+use crate::sync_jobs_actor::{
+    start_sync_jobs_actor, ApplyStatePartsRequest, ApplyStatePartsResponse, ClearShardStateRequest,
+    SyncJobsActor, ValidateStatePartRequest, ValidateStatePartResponse,
+};
 #[cfg(feature = "adversarial")]
 use crate::AdversarialControls;
 use crate::StatusResponse;
 use near_client_primitives::types::{
-    Error, GetNetworkInfo, NetworkInfoResponse, ShardSyncDownload, ShardSyncStatus, Status,
-    StatusError, StatusSyncInfo, SyncStatus,
+    ChangeShardTracking, ChangeShardTrackingError, ConsensusApprovalView, ConsensusChunkView,
+    ConsensusHeightView, ConsensusTimelineResponse, Error, GetConsensusTimeline, GetNetworkInfo,
+    GetPoolTransaction, GetPoolTransactions, NetworkInfoResponse, PoolTransactionInfo,
+    ShardSyncDownload, ShardSyncStatus, ShardTrackingResponse, Status, StatusError, StatusSyncInfo,
+    SyncStatus, UpdateClientConfig,
 };
-use near_primitives::block_header::ApprovalType;
+use near_primitives::block_header::{ApprovalInner, ApprovalType};
 
 /// Multiplier on `max_block_time` to wait until deciding that chain stalled.
 const STATUS_WAIT_TIME_MULTIPLIER: u64 = 10;
@@ -66,6 +78,8 @@ const BLOCK_HORIZON: u64 = 500;
 /// `max_block_production_time` times this multiplier is how long we wait before rebroadcasting
 /// the current `head`
 const HEAD_STALL_MULTIPLIER: u32 = 4;
+/// Largest number of heights below the head returned by `GetConsensusTimeline`.
+const MAX_CONSENSUS_TIMELINE_HEIGHTS: BlockHeightDelta = 100;
 
 pub struct ClientActor {
     /// Adversarial controls
@@ -89,6 +103,10 @@ pub struct ClientActor {
     doomslug_timer_next_attempt: DateTime<Utc>,
     chunk_request_retry_next_attempt: DateTime<Utc>,
     sync_started: bool,
+    /// Validates and applies state parts downloaded during state sync.
+    sync_jobs_actor_addr: Addr<SyncJobsActor>,
+    /// Stops the removal of untracked shards state scheduled so far, set when tracking changes.
+    clear_shard_state_cancelled: Arc<AtomicBool>,
 }
 
 /// Blocks the program until given genesis time arrives.
@@ -128,14 +146,20 @@ impl ClientActor {
             info!(target: "client", "Starting validator node: {}", vs.validator_id());
         }
         let info_helper = InfoHelper::new(telemetry_actor, &config, validator_signer.clone());
+        let state_sync_threads = config.state_sync_threads;
         let client = Client::new(
             config,
             chain_genesis,
-            runtime_adapter,
+            runtime_adapter.clone(),
             network_adapter.clone(),
             validator_signer,
             enable_doomslug,
         )?;
+        let sync_jobs_actor_addr = start_sync_jobs_actor(
+            state_sync_threads,
+            runtime_adapter,
+            client.chain.store().owned_store(),
+        );
 
         let now = Utc::now();
         Ok(ClientActor {
@@ -161,6 +185,8 @@ impl ClientActor {
             doomslug_timer_next_attempt: now,
             chunk_request_retry_next_attempt: now,
             sync_started: false,
+            sync_jobs_actor_addr,
+            clear_shard_state_cancelled: Arc::new(AtomicBool::new(false)),
         })
     }
 }
@@ -169,6 +195,51 @@ impl Actor for ClientActor {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        // Apply downloaded state parts on the sync jobs actor.
+        let sync_jobs_actor_addr = self.sync_jobs_actor_addr.clone();
+        let client_addr = ctx.address();
+        self.client.state_parts_task_scheduler = Some(Box::new(
+            move |request: ApplyStatePartsRequest| {
+                let client_addr = client_addr.clone();
+                near_performance_metrics::actix::spawn(
+                    std::any::type_name::<Self>(),
+                    file!(),
+                    line!(),
+                    sync_jobs_actor_addr.send(request).then(move |result| {
+                        match result {
+                            Ok(response) => client_addr.do_send(response),
+                            Err(err) => {
+                                error!(target: "sync", "Failed to schedule applying state parts: {}", err)
+                            }
+                        }
+                        future::ready(())
+                    }),
+                );
+            },
+        ));
+        // Validate state parts taken from the dump directory on the sync jobs actor as well.
+        let sync_jobs_actor_addr = self.sync_jobs_actor_addr.clone();
+        let client_addr = ctx.address();
+        self.client.state_part_validation_scheduler = Some(Box::new(
+            move |request: ValidateStatePartRequest| {
+                let client_addr = client_addr.clone();
+                near_performance_metrics::actix::spawn(
+                    std::any::type_name::<Self>(),
+                    file!(),
+                    line!(),
+                    sync_jobs_actor_addr.send(request).then(move |result| {
+                        match result {
+                            Ok(response) => client_addr.do_send(response),
+                            Err(err) => {
+                                error!(target: "sync", "Failed to schedule state part validation: {}", err)
+                            }
+                        }
+                        future::ready(())
+                    }),
+                );
+            },
+        ));
+
         // Start syncing job.
         self.start_sync(ctx);
 
@@ -384,10 +455,13 @@ impl Handler<NetworkClientMessages> for ClientActor {
                         &mut self.client.sync_status
                     {
                         if hash == *sync_hash {
-                            if let Some(part_id) = state_response.part_id() {
-                                self.client
-                                    .state_sync
-                                    .received_requested_part(part_id, shard_id, hash);
+                            if let Some((part_id, data)) = state_response.part() {
+                                self.client.state_sync.received_requested_part(
+                                    *part_id,
+                                    shard_id,
+                                    hash,
+                                    data.len(),
+                                );
                             }
 
                             if let Some(shard_download) = shards_to_download.get_mut(&shard_id) {
@@ -407,8 +481,13 @@ impl Handler<NetworkClientMessages> for ClientActor {
                     if let Some((_, shards_to_download)) =
                         self.client.catchup_state_syncs.get_mut(&hash)
                     {
-                        if let Some(part_id) = state_response.part_id() {
-                            self.client.state_sync.received_requested_part(part_id, shard_id, hash);
+                        if let Some((part_id, data)) = state_response.part() {
+                            self.client.state_sync.received_requested_part(
+                                *part_id,
+                                shard_id,
+                                hash,
+                                data.len(),
+                            );
                         }
 
                         if let Some(shard_download) = shards_to_download.get_mut(&shard_id) {
@@ -423,6 +502,7 @@ impl Handler<NetworkClientMessages> for ClientActor {
                     download
                 };
 
+                let mut validate_request = None;
                 if let Some(shard_sync_download) = download {
                     match shard_sync_download.status {
                         ShardSyncStatus::StateDownloadHeader => {
@@ -457,17 +537,20 @@ impl Handler<NetworkClientMessages> for ClientActor {
                                     return NetworkClientResponses::NoResponse;
                                 }
                                 if !shard_sync_download.downloads[part_id as usize].done {
-                                    match self
-                                        .client
-                                        .chain
-                                        .set_state_part(shard_id, hash, part_id, num_parts, &data)
-                                    {
-                                        Ok(()) => {
-                                            shard_sync_download.downloads[part_id as usize].done =
-                                                true;
+                                    match self.client.chain.get_state_header(shard_id, hash) {
+                                        Ok(shard_state_header) => {
+                                            validate_request = Some(ValidateStatePartRequest {
+                                                shard_id,
+                                                sync_hash: hash,
+                                                state_root: shard_state_header
+                                                    .chunk_prev_state_root(),
+                                                part_id,
+                                                num_parts,
+                                                data,
+                                            });
                                         }
                                         Err(err) => {
-                                            error!(target: "sync", "State sync set_state_part error, shard = {}, part = {}, hash = {}: {:?}", shard_id, part_id, hash, err);
+                                            error!(target: "sync", "State sync get_state_header error, shard = {}, hash = {}: {:?}", shard_id, hash, err);
                                             shard_sync_download.downloads[part_id as usize].error =
                                                 true;
                                         }
@@ -481,14 +564,25 @@ impl Handler<NetworkClientMessages> for ClientActor {
                     error!(target: "sync", "State sync received hash {} that we're not expecting, potential malicious peer", hash);
                 }
 
+                if let Some(validate_request) = validate_request {
+                    self.validate_state_part(validate_request, ctx);
+                }
+
                 NetworkClientResponses::NoResponse
             }
-            NetworkClientMessages::EpochSyncResponse(_peer_id, _response) => {
-                // TODO #3488
+            NetworkClientMessages::EpochSyncResponse(peer_id, response) => {
+                self.client.epoch_sync.on_response(peer_id, response);
                 NetworkClientResponses::NoResponse
             }
-            NetworkClientMessages::EpochSyncFinalizationResponse(_peer_id, _response) => {
-                // TODO #3488
+            NetworkClientMessages::EpochSyncFinalizationResponse(peer_id, response) => {
+                if let Err(err) = self.client.epoch_sync.on_finalization_response(
+                    &mut self.client.sync_status,
+                    &mut self.client.chain,
+                    peer_id,
+                    response,
+                ) {
+                    error!(target: "sync", "Epoch sync: failed to finalize: {}", err);
+                }
                 NetworkClientResponses::NoResponse
             }
             NetworkClientMessages::PartialEncodedChunkRequest(part_request_msg, route_back) => {
@@ -543,6 +637,98 @@ impl Handler<NetworkClientMessages> for ClientActor {
     }
 }
 
+impl Handler<ApplyStatePartsResponse> for ClientActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: ApplyStatePartsResponse, _ctx: &mut Context<Self>) -> Self::Result {
+        if let Some(state_sync) = self.client.get_state_sync_mut(&msg.sync_hash) {
+            state_sync.set_apply_result(msg.sync_hash, msg.shard_id, msg.result);
+        } else {
+            debug!(target: "sync", "Applied state parts for hash {} that is not synced anymore", msg.sync_hash);
+        }
+    }
+}
+
+impl Handler<ValidateStatePartResponse> for ClientActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: ValidateStatePartResponse, _ctx: &mut Context<Self>) -> Self::Result {
+        self.on_state_part_validated(msg);
+    }
+}
+
+impl Handler<UpdateClientConfig> for ClientActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: UpdateClientConfig, _ctx: &mut Context<Self>) {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("update client config".into());
+        if let Some(tracked_accounts) = msg.tracked_accounts {
+            if tracked_accounts != self.client.config.tracked_accounts {
+                let tracked_shards = self.client.config.tracked_shards.clone();
+                if let Err(err) = self.set_tracking(tracked_accounts, tracked_shards) {
+                    error!(target: "client", "Failed to change tracked accounts: {}", err);
+                }
+            }
+        }
+        if msg.gc_blocks_limit != self.client.config.gc_blocks_limit {
+            info!(target: "client", "GC blocks limit changed to {}", msg.gc_blocks_limit);
+            self.client.config.gc_blocks_limit = msg.gc_blocks_limit;
+        }
+    }
+}
+
+impl Handler<ChangeShardTracking> for ClientActor {
+    type Result = Result<ShardTrackingResponse, ChangeShardTrackingError>;
+
+    #[perf]
+    fn handle(&mut self, msg: ChangeShardTracking, _ctx: &mut Context<Self>) -> Self::Result {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("change shard tracking".into());
+        let head = self.client.chain.head()?;
+        let num_shards = self.client.runtime_adapter.num_shards(&head.epoch_id)?;
+        if let Some(shard_id) = msg
+            .track_shards
+            .iter()
+            .chain(msg.untrack_shards.iter())
+            .find(|shard_id| **shard_id >= num_shards)
+        {
+            return Err(ChangeShardTrackingError::UnknownShard(*shard_id));
+        }
+
+        let mut tracked_accounts = self.client.config.tracked_accounts.clone();
+        for account_id in msg.track_accounts {
+            if !tracked_accounts.contains(&account_id) {
+                tracked_accounts.push(account_id);
+            }
+        }
+        tracked_accounts.retain(|account_id| !msg.untrack_accounts.contains(account_id));
+        let mut tracked_shards = self.client.config.tracked_shards.clone();
+        for shard_id in msg.track_shards {
+            if !tracked_shards.contains(&shard_id) {
+                tracked_shards.push(shard_id);
+            }
+        }
+        tracked_shards.retain(|shard_id| !msg.untrack_shards.contains(shard_id));
+
+        let effective_epoch_height = if tracked_accounts != self.client.config.tracked_accounts
+            || tracked_shards != self.client.config.tracked_shards
+        {
+            Some(self.set_tracking(tracked_accounts, tracked_shards)?)
+        } else {
+            None
+        };
+        Ok(ShardTrackingResponse {
+            tracked_accounts: self.client.config.tracked_accounts.clone(),
+            tracked_shards: self.client.config.tracked_shards.clone(),
+            effective_epoch_height,
+        })
+    }
+}
+
 impl Handler<Status> for ClientActor {
     type Result = Result<StatusResponse, StatusError>;
 
@@ -652,6 +838,164 @@ impl Handler<GetNetworkInfo> for ClientActor {
     }
 }
 
+impl Handler<GetPoolTransactions> for ClientActor {
+    type Result = Result<Vec<PoolTransactionInfo>, String>;
+
+    #[perf]
+    fn handle(&mut self, _msg: GetPoolTransactions, ctx: &mut Context<Self>) -> Self::Result {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("client get pool transactions".into());
+        self.check_triggers(ctx);
+
+        Ok(self
+            .client
+            .shards_mgr
+            .get_pool_transactions()
+            .map(|(_, tx)| PoolTransactionInfo { hash: tx.get_hash(), size: tx.get_size() })
+            .collect())
+    }
+}
+
+impl Handler<GetPoolTransaction> for ClientActor {
+    type Result = Result<Option<SignedTransaction>, String>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetPoolTransaction, ctx: &mut Context<Self>) -> Self::Result {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("client get pool transaction".into());
+        self.check_triggers(ctx);
+
+        Ok(self.client.shards_mgr.get_pool_transaction(&msg.tx_hash).cloned())
+    }
+}
+
+/// Only reachable through the RPC when admin methods are enabled, since it is handled on the
+/// same actor as block processing.
+impl Handler<GetConsensusTimeline> for ClientActor {
+    type Result = Result<ConsensusTimelineResponse, String>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetConsensusTimeline, ctx: &mut Context<Self>) -> Self::Result {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("client get consensus timeline".into());
+        self.check_triggers(ctx);
+
+        // Doomslug keeps monotonic `Instant`s, convert them to the wall clock time.
+        let now = Instant::now();
+        let utc_now = Utc::now();
+        let to_utc = |instant: Instant| {
+            utc_now
+                - OldDuration::from_std(now.saturating_duration_since(instant))
+                    .unwrap_or_else(|_| OldDuration::zero())
+        };
+
+        let head = self.client.chain.head().map_err(|err| err.to_string())?;
+        let num_heights = std::cmp::min(msg.num_heights, MAX_CONSENSUS_TIMELINE_HEIGHTS);
+        let start_height = (head.height + 1).saturating_sub(num_heights);
+        let doomslug = &self.client.doomslug;
+        let timeline = doomslug.get_timeline();
+        let end_height = timeline
+            .keys()
+            .next_back()
+            .map_or(head.height, |height| std::cmp::max(*height, head.height))
+            .min(head.height + num_heights);
+
+        let mut prev_hash = self
+            .client
+            .chain
+            .get_header_by_height(start_height.saturating_sub(1))
+            .ok()
+            .map(|header| *header.hash());
+        let mut heights = vec![];
+        for height in start_height..=end_height {
+            let record = timeline.get(&height);
+            if height > head.height && record.is_none() {
+                continue;
+            }
+            let header = if height <= head.height {
+                self.client.chain.get_header_by_height(height).ok().cloned()
+            } else {
+                None
+            };
+            let epoch_id = match (&header, &prev_hash) {
+                (Some(header), _) => Some(header.epoch_id().clone()),
+                (None, Some(prev_hash)) => {
+                    self.client.runtime_adapter.get_epoch_id_from_prev_block(prev_hash).ok()
+                }
+                (None, None) => None,
+            };
+            let block_producer = epoch_id.as_ref().and_then(|epoch_id| {
+                self.client.runtime_adapter.get_block_producer(epoch_id, height).ok()
+            });
+            let chunks = match (&header, &epoch_id) {
+                (Some(header), Some(epoch_id)) => header
+                    .chunk_mask()
+                    .iter()
+                    .enumerate()
+                    .map(|(shard_id, included)| ConsensusChunkView {
+                        shard_id: shard_id as ShardId,
+                        chunk_producer: self
+                            .client
+                            .runtime_adapter
+                            .get_chunk_producer(epoch_id, height, shard_id as ShardId)
+                            .ok(),
+                        included: *included,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            let approvals = record.map_or(vec![], |record| {
+                record
+                    .approvals
+                    .iter()
+                    .map(|approval| {
+                        let (endorsement, skip_from_height) = match &approval.inner {
+                            ApprovalInner::Endorsement(hash) => (Some(*hash), None),
+                            ApprovalInner::Skip(height) => (None, Some(*height)),
+                        };
+                        ConsensusApprovalView {
+                            account_id: approval.account_id.clone(),
+                            endorsement,
+                            skip_from_height,
+                            received_at: to_utc(approval.received),
+                        }
+                    })
+                    .collect()
+            });
+
+            heights.push(ConsensusHeightView {
+                height,
+                block_hash: header.as_ref().map(|header| *header.hash()),
+                block_producer,
+                block_timestamp: header
+                    .as_ref()
+                    .map(|header| from_timestamp(header.raw_timestamp())),
+                skipped: height <= head.height && header.is_none(),
+                chunks,
+                approvals,
+                threshold_reached_at: record
+                    .and_then(|record| record.threshold_reached)
+                    .map(to_utc),
+                became_head_at: record.and_then(|record| record.tip_set).map(to_utc),
+                became_final_at: record.and_then(|record| record.became_final).map(to_utc),
+            });
+            if let Some(header) = header {
+                prev_hash = Some(*header.hash());
+            }
+        }
+
+        Ok(ConsensusTimelineResponse {
+            head_height: head.height,
+            largest_final_height: doomslug.get_largest_final_height(),
+            largest_threshold_height: doomslug.get_largest_height_crossing_threshold(),
+            largest_target_height: doomslug.get_largest_target_height(),
+            timer_height: doomslug.get_timer_height(),
+            timer_started_at: to_utc(doomslug.get_timer_start()),
+            heights,
+        })
+    }
+}
+
 impl ClientActor {
     fn sign_announce_account(&self, epoch_id: &EpochId) -> Result<Signature, ()> {
         if let Some(validator_signer) = self.client.validator_signer.as_ref() {
@@ -940,9 +1284,49 @@ impl ClientActor {
 
             self.info_helper.block_processed(gas_used);
             self.check_send_announce_account(last_final_hash);
+            self.check_clear_untracked_shards_state(&block);
         }
     }
 
+    /// At the start of every epoch removes the state of shards the node no longer tracks.
+    fn check_clear_untracked_shards_state(&mut self, block: &Block) {
+        if self.client.config.archive || self.client.sync_status.is_syncing() {
+            return;
+        }
+        let head = unwrap_or_return!(self.client.chain.head());
+        if &head.last_block_hash != block.hash()
+            || !unwrap_or_return!(self
+                .client
+                .runtime_adapter
+                .is_next_block_epoch_start(block.header().prev_hash()))
+        {
+            return;
+        }
+        let me = self.client.validator_signer.as_ref().map(|vs| vs.validator_id().clone());
+        let shard_uids =
+            unwrap_or_return!(self.client.chain.get_untracked_shards_with_state(me.as_ref()));
+        if !shard_uids.is_empty() {
+            self.sync_jobs_actor_addr.do_send(ClearShardStateRequest {
+                shard_uids,
+                me,
+                cancelled: self.clear_shard_state_cancelled.clone(),
+            });
+        }
+    }
+
+    /// Replaces the tracked accounts and shards, see `Client::set_tracking`. The removal of
+    /// shard states scheduled so far is stopped, since some of the shards may be tracked again.
+    fn set_tracking(
+        &mut self,
+        accounts: Vec<AccountId>,
+        shards: Vec<ShardId>,
+    ) -> Result<EpochHeight, near_chain::Error> {
+        let epoch_height = self.client.set_tracking(accounts, shards)?;
+        self.clear_shard_state_cancelled.store(true, Ordering::SeqCst);
+        self.clear_shard_state_cancelled = Arc::new(AtomicBool::new(false));
+        Ok(epoch_height)
+    }
+
     /// Process block and execute callbacks.
     fn process_block(
         &mut self,
@@ -1285,6 +1669,16 @@ impl ClientActor {
                 self.check_send_announce_account(head.prev_block_hash);
             }
             wait_period = self.client.config.sync_check_period;
+        } else if self.client.config.epoch_sync_enabled
+            && !self.client.config.archive
+            && unwrap_or_run_later!(self.client.epoch_sync.run(
+                &mut self.client.sync_status,
+                &mut self.client.chain,
+                highest_height,
+                &self.network_info.highest_height_peers
+            ))
+        {
+            // Header and state sync start once Epoch Sync is finalized.
         } else {
             // Run each step of syncing separately.
             unwrap_or_run_later!(self.client.header_sync.run(
@@ -1353,6 +1747,8 @@ impl ClientActor {
                     &self.client.runtime_adapter,
                     &self.network_info.highest_height_peers,
                     shards_to_sync,
+                    self.client.state_parts_task_scheduler.as_deref(),
+                    self.client.state_part_validation_scheduler.as_deref(),
                 )) {
                     StateSyncResult::Unchanged => (),
                     StateSyncResult::Changed(fetch_block) => {
@@ -1430,6 +1826,57 @@ impl ClientActor {
         );
     }
 
+    /// Validates the state part on the sync jobs actor, which also saves it if it is valid.
+    fn validate_state_part(&mut self, request: ValidateStatePartRequest, ctx: &mut Context<Self>) {
+        ctx.spawn(self.sync_jobs_actor_addr.send(request).into_actor(self).then(
+            |result, act, _ctx| {
+                match result {
+                    Ok(response) => act.on_state_part_validated(response),
+                    Err(err) => {
+                        error!(target: "sync", "Failed to schedule state part validation: {}", err)
+                    }
+                }
+                actix::fut::ready(())
+            },
+        ));
+    }
+
+    fn on_state_part_validated(&mut self, response: ValidateStatePartResponse) {
+        let ValidateStatePartResponse { shard_id, sync_hash, part_id, result } = response;
+        let shard_sync_download =
+            match self.client.get_shard_sync_download_mut(shard_id, &sync_hash) {
+                Some(shard_sync_download) => shard_sync_download,
+                // State sync has finished or restarted in the meantime.
+                None => return,
+            };
+        if let ShardSyncStatus::StateDownloadParts = shard_sync_download.status {
+            let num_parts = shard_sync_download.downloads.len() as u64;
+            let mut newly_done = false;
+            if let Some(download) = shard_sync_download.downloads.get_mut(part_id as usize) {
+                match result {
+                    Ok(()) => {
+                        newly_done = !download.done;
+                        download.done = true;
+                    }
+                    Err(err) => {
+                        error!(target: "sync", "State sync set_state_part error, shard = {}, part = {}, hash = {}: {:?}", shard_id, part_id, sync_hash, err);
+                        download.error = true;
+                    }
+                }
+            }
+            // Only newly downloaded parts change the recorded progress.
+            if newly_done {
+                if let Err(err) = self
+                    .client
+                    .chain
+                    .mark_state_part_downloaded(shard_id, sync_hash, part_id, num_parts)
+                {
+                    error!(target: "sync", "State sync failed to record downloaded part, shard = {}, part = {}, hash = {}: {:?}", shard_id, part_id, sync_hash, err);
+                }
+            }
+        }
+    }
+
     /// Periodically log summary.
     fn log_summary(&self, ctx: &mut Context<Self>) {
         near_performance_metrics::actix::run_later(
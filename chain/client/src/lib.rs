@@ -2,11 +2,14 @@
 extern crate lazy_static;
 
 pub use near_client_primitives::types::{
-    Error, GetBlock, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk,
+    ChangeShardTracking, ChangeShardTrackingError, Error, GetBlock, GetBlockProof,
+    GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk, GetConsensusTimeline,
     GetExecutionOutcome, GetExecutionOutcomeResponse, GetExecutionOutcomesForBlock, GetGasPrice,
-    GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
-    GetStateChangesInBlock, GetStateChangesWithCauseInBlock, GetValidatorInfo, GetValidatorOrdered,
-    Query, QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    GetMisbehaviorEvidence, GetNetworkInfo, GetNextLightClientBlock, GetPoolTransaction,
+    GetPoolTransactions, GetProtocolConfig, GetReceipt, GetStateChanges, GetStateChangesInBlock,
+    GetStateChangesWithCauseInBlock, GetValidatorInfo, GetValidatorOrdered, PoolTransactionInfo,
+    Query, QueryError, ShardTrackingResponse, SimulateTransaction, SimulateTransactionError,
+    Status, StatusResponse, SyncStatus, TxStatus, TxStatusError, UpdateClientConfig,
 };
 
 pub use crate::client::Client;
@@ -20,5 +23,6 @@ mod client_actor;
 mod info;
 mod metrics;
 pub mod sync;
+mod sync_jobs_actor;
 pub mod test_utils;
 mod view_client;
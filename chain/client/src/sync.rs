@@ -1,6 +1,7 @@
-use near_chain::{ChainStoreAccess, Error};
-use std::cmp::min;
+use near_chain::{ChainStoreAccess, Error, ErrorKind};
+use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{ops::Add, time::Duration as TimeDuration};
@@ -12,22 +13,27 @@ use log::{debug, error, info, warn};
 use rand::seq::{IteratorRandom, SliceRandom};
 use rand::{thread_rng, Rng};
 
-use near_chain::{Chain, RuntimeAdapter};
+use near_chain::{state_sync_dump, validate_light_client_block, Chain, RuntimeAdapter};
 use near_network::types::{AccountOrPeerIdOrHash, NetworkResponses, ReasonForBan};
 use near_network::{FullPeerInfo, NetworkAdapter, NetworkRequests};
 use near_primitives::block::Tip;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
-use near_primitives::syncing::get_num_state_parts;
+use near_primitives::syncing::{
+    get_num_state_parts, EpochSyncFinalizationResponse, EpochSyncResponse,
+};
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta, EpochId, ShardId};
 use near_primitives::utils::to_timestamp;
+use near_primitives::views::LightClientBlockView;
 
 use cached::{Cached, SizedCache};
 use near_client_primitives::types::{
     DownloadStatus, ShardSyncDownload, ShardSyncStatus, SyncStatus,
 };
 
+use crate::sync_jobs_actor::{ApplyStatePartsRequest, ValidateStatePartRequest};
+
 /// Maximum number of block headers send over the network.
 pub const MAX_BLOCK_HEADERS: u64 = 512;
 
@@ -41,6 +47,9 @@ pub const MAX_STATE_PART_REQUEST: u64 = 16;
 /// Number of state parts already requested stored as pending.
 /// This number should not exceed MAX_STATE_PART_REQUEST times (number of peers in the network).
 pub const MAX_PENDING_PART: u64 = MAX_STATE_PART_REQUEST * 10000;
+/// Maximum number of state parts to take from the local dump directory per shard on each round.
+/// Parts are validated on the client thread, so this bounds the time of a single round.
+pub const MAX_STATE_PARTS_FROM_DUMP: u64 = 4;
 
 pub const NS_PER_SECOND: u128 = 1_000_000_000;
 
@@ -56,9 +65,13 @@ pub fn highest_height_peer(highest_height_peers: &Vec<FullPeerInfo>) -> Option<F
     }
 }
 
-/// Helper to keep track of the Epoch Sync
-// TODO #3488
-#[allow(dead_code)]
+/// Helper to keep track of the Epoch Sync.
+///
+/// Epoch Sync lets a fresh node skip downloading all the historical headers. Starting from the
+/// genesis block producers, it requests one light client block per epoch, each of which is
+/// endorsed by the block producers of its epoch and proves the block producers of the next one.
+/// Once peers report that the last proven epoch is the current one, the node requests the
+/// headers and epoch data needed to start state sync at the first block of that epoch.
 pub struct EpochSync {
     network_adapter: Arc<dyn NetworkAdapter>,
     /// Datastructure to keep track of when the last request to each peer was made.
@@ -83,8 +96,10 @@ pub struct EpochSync {
     request_timeout: Duration,
     /// How frequently to send request to the same peer
     peer_timeout: Duration,
+    /// Epoch Sync is only worth running if the network is at least this many blocks ahead.
+    min_blocks_ahead: BlockHeightDelta,
 
-    /// True, if all peers agreed that we're at the last Epoch.
+    /// True, if a strict majority of peers agreed that we're at the last Epoch.
     /// Only finalization is needed.
     have_all_epochs: bool,
     /// Whether the Epoch Sync was performed to completion previously.
@@ -93,7 +108,10 @@ pub struct EpochSync {
 
     pub sync_hash: CryptoHash,
 
+    /// Whether the response to the last request was received.
     received_epoch: bool,
+    /// Number of epochs proven so far.
+    synced_epochs: u64,
 
     is_just_started: bool,
 }
@@ -106,6 +124,7 @@ impl EpochSync {
         first_epoch_block_producers: Vec<ValidatorStake>,
         request_timeout: TimeDuration,
         peer_timeout: TimeDuration,
+        epoch_length: BlockHeightDelta,
     ) -> Self {
         Self {
             network_adapter,
@@ -119,13 +138,301 @@ impl EpochSync {
             last_request_peer_id: None,
             request_timeout: Duration::from_std(request_timeout).unwrap(),
             peer_timeout: Duration::from_std(peer_timeout).unwrap(),
+            min_blocks_ahead: 2 * epoch_length,
             received_epoch: false,
             have_all_epochs: false,
             done: false,
             sync_hash: CryptoHash::default(),
+            synced_epochs: 0,
             is_just_started: true,
         }
     }
+
+    /// Runs a step of Epoch Sync: sends the next request if the previous one was answered or
+    /// timed out. Returns whether Epoch Sync is still in progress.
+    pub fn run(
+        &mut self,
+        sync_status: &mut SyncStatus,
+        chain: &mut Chain,
+        highest_height: BlockHeight,
+        highest_height_peers: &Vec<FullPeerInfo>,
+    ) -> Result<bool, near_chain::Error> {
+        if self.done {
+            return Ok(false);
+        }
+
+        if self.is_just_started {
+            self.is_just_started = false;
+            // Only a fresh node benefits from skipping the headers, and archival nodes
+            // need them all anyway.
+            let head = chain.head()?;
+            if head.height != chain.genesis().height()
+                || highest_height < head.height + self.min_blocks_ahead
+            {
+                debug!(target: "sync", "Epoch sync: not needed, head {}, highest height {}", head.height, highest_height);
+                self.done = true;
+                return Ok(false);
+            }
+            info!(target: "sync", "Epoch sync: starting from epoch {:?}", self.current_epoch_id);
+        }
+
+        *sync_status = SyncStatus::EpochSync { epoch_ord: self.synced_epochs };
+
+        if !self.have_all_epochs
+            && !self.peers_reporting_up_to_date.is_empty()
+            && self.peers_reporting_up_to_date.len() * 2 > highest_height_peers.len()
+        {
+            info!(target: "sync", "Epoch sync: all epochs are synced, {:?} is the last one", self.next_epoch_id);
+            self.have_all_epochs = true;
+            self.received_epoch = true;
+        }
+
+        let now = Utc::now();
+        let request_expired = now - self.last_request_time > self.request_timeout;
+        if self.last_request_peer_id.is_some() && !self.received_epoch && !request_expired {
+            return Ok(true);
+        }
+        if self.last_request_peer_id.is_some() && !self.received_epoch {
+            debug!(target: "sync", "Epoch sync: request for {:?} to {:?} timed out", self.requested_epoch_id, self.last_request_peer_id);
+            if self.have_all_epochs {
+                // The network may have moved to the next epoch in the meantime, so check
+                // again whether there are more epochs to prove.
+                self.have_all_epochs = false;
+                self.peers_reporting_up_to_date.clear();
+            }
+        }
+
+        let peer_id = match self.choose_peer(highest_height_peers, now) {
+            Some(peer_id) => peer_id,
+            None => return Ok(true),
+        };
+        let epoch_id = self.next_epoch_id.clone();
+        debug!(target: "sync", "Epoch sync: requesting {} {:?} from {:?}", if self.have_all_epochs { "finalization of" } else { "epoch" }, epoch_id, peer_id);
+        if self.have_all_epochs {
+            self.network_adapter.do_send(NetworkRequests::EpochSyncFinalizationRequest {
+                peer_id: peer_id.clone(),
+                epoch_id: epoch_id.clone(),
+            });
+        } else {
+            self.network_adapter.do_send(NetworkRequests::EpochSyncRequest {
+                peer_id: peer_id.clone(),
+                epoch_id: epoch_id.clone(),
+            });
+        }
+        self.peer_to_last_request_time.insert(peer_id.clone(), now);
+        self.requested_epoch_id = epoch_id;
+        self.last_request_time = now;
+        self.last_request_peer_id = Some(peer_id);
+        self.received_epoch = false;
+        Ok(true)
+    }
+
+    /// Picks a random peer that was not requested recently and, unless only finalization is
+    /// left, has not reported yet that the next epoch is the current one.
+    fn choose_peer(
+        &self,
+        highest_height_peers: &Vec<FullPeerInfo>,
+        now: DateTime<Utc>,
+    ) -> Option<PeerId> {
+        highest_height_peers
+            .iter()
+            .map(|peer| &peer.peer_info.id)
+            .filter(|peer_id| {
+                self.have_all_epochs || !self.peers_reporting_up_to_date.contains(*peer_id)
+            })
+            .filter(|peer_id| match self.peer_to_last_request_time.get(*peer_id) {
+                Some(last_request_time) => now - *last_request_time >= self.peer_timeout,
+                None => true,
+            })
+            .choose(&mut thread_rng())
+            .cloned()
+    }
+
+    fn is_expected_response(&self, peer_id: &PeerId) -> bool {
+        !self.done && !self.received_epoch && self.last_request_peer_id.as_ref() == Some(peer_id)
+    }
+
+    fn ban_peer(&self, peer_id: PeerId, ban_reason: ReasonForBan) {
+        self.network_adapter.do_send(NetworkRequests::BanPeer { peer_id, ban_reason });
+    }
+
+    /// Processes the light client block of the next epoch, or the report that it is the current one.
+    pub fn on_response(&mut self, peer_id: PeerId, response: EpochSyncResponse) {
+        if !self.is_expected_response(&peer_id) || self.have_all_epochs {
+            debug!(target: "sync", "Epoch sync: unexpected response from {:?}", peer_id);
+            return;
+        }
+        self.received_epoch = true;
+        match response {
+            EpochSyncResponse::UpToDate => {
+                debug!(target: "sync", "Epoch sync: {:?} reports {:?} as the current epoch", peer_id, self.next_epoch_id);
+                self.peers_reporting_up_to_date.insert(peer_id);
+            }
+            EpochSyncResponse::Advance { light_client_block_view } => {
+                if let Err(err) = self.validate_light_client_block(&light_client_block_view) {
+                    warn!(target: "sync", "Epoch sync: invalid light client block for {:?} from {:?}: {}", self.next_epoch_id, peer_id, err);
+                    self.ban_peer(peer_id, ReasonForBan::EpochSyncInvalidResponse);
+                    return;
+                }
+                let next_bps = light_client_block_view.next_bps.unwrap_or_default();
+                self.current_epoch_id = self.next_epoch_id.clone();
+                self.next_epoch_id = EpochId(light_client_block_view.inner_lite.next_epoch_id);
+                self.next_block_producers =
+                    next_bps.into_iter().map(|bp| bp.into_validator_stake()).collect();
+                self.peers_reporting_up_to_date.clear();
+                self.synced_epochs += 1;
+                debug!(target: "sync", "Epoch sync: synced epoch {:?} at height {}, next epoch {:?}", self.current_epoch_id, light_client_block_view.inner_lite.height, self.next_epoch_id);
+            }
+        }
+    }
+
+    fn validate_light_client_block(
+        &self,
+        light_client_block_view: &LightClientBlockView,
+    ) -> Result<(), near_chain::Error> {
+        if light_client_block_view.inner_lite.epoch_id != self.next_epoch_id.0 {
+            return Err(ErrorKind::InvalidEpochHash.into());
+        }
+        validate_light_client_block(light_client_block_view, &self.next_block_producers)
+    }
+
+    /// Processes the data needed to start state sync at the first block of the last epoch.
+    /// On success, saves the headers, initializes the epoch manager and switches to state sync.
+    pub fn on_finalization_response(
+        &mut self,
+        sync_status: &mut SyncStatus,
+        chain: &mut Chain,
+        peer_id: PeerId,
+        response: EpochSyncFinalizationResponse,
+    ) -> Result<(), near_chain::Error> {
+        if !self.is_expected_response(&peer_id) || !self.have_all_epochs {
+            debug!(target: "sync", "Epoch sync: unexpected finalization response from {:?}", peer_id);
+            return Ok(());
+        }
+        self.received_epoch = true;
+        if let Err(err) = self.validate_finalization_response(&response) {
+            warn!(target: "sync", "Epoch sync: invalid finalization response for {:?} from {:?}: {}", self.next_epoch_id, peer_id, err);
+            self.ban_peer(peer_id, ReasonForBan::EpochSyncInvalidFinalizationResponse);
+            return Ok(());
+        }
+
+        let EpochSyncFinalizationResponse {
+            cur_epoch_header,
+            prev_epoch_headers,
+            header_sync_init_header,
+            header_sync_init_header_tree,
+            prev_epoch_first_block_info,
+            prev_epoch_prev_last_block_info,
+            prev_epoch_last_block_info,
+            prev_epoch_info,
+            cur_epoch_info,
+            next_epoch_info,
+        } = response;
+        let sync_hash = *cur_epoch_header.hash();
+        let prev_epoch_id = prev_epoch_last_block_info.epoch_id().clone();
+        chain.runtime_adapter.epoch_sync_init_epoch_manager(
+            prev_epoch_first_block_info,
+            prev_epoch_prev_last_block_info,
+            prev_epoch_last_block_info,
+            &prev_epoch_id,
+            prev_epoch_info,
+            cur_epoch_header.epoch_id(),
+            cur_epoch_header.height(),
+            cur_epoch_info,
+            cur_epoch_header.next_epoch_id(),
+            next_epoch_info,
+        )?;
+        chain.save_epoch_sync_headers(
+            prev_epoch_headers,
+            cur_epoch_header,
+            header_sync_init_header,
+            header_sync_init_header_tree,
+        )?;
+
+        info!(target: "sync", "Epoch sync: done after {} epochs, starting state sync at {}", self.synced_epochs, sync_hash);
+        self.done = true;
+        self.sync_hash = sync_hash;
+        *sync_status = SyncStatus::StateSync(sync_hash, HashMap::default());
+        Ok(())
+    }
+
+    /// Checks the finalization data against the block producers of the last epoch, which were
+    /// proven by the chain of light client blocks.
+    fn validate_finalization_response(
+        &self,
+        response: &EpochSyncFinalizationResponse,
+    ) -> Result<(), near_chain::Error> {
+        let cur_epoch_header = &response.cur_epoch_header;
+        if cur_epoch_header.epoch_id() != &self.next_epoch_id {
+            return Err(ErrorKind::InvalidEpochHash.into());
+        }
+        if response.header_sync_init_header.hash() != cur_epoch_header.hash() {
+            return Err(ErrorKind::Other(
+                "header sync must start at the first block of the epoch".to_string(),
+            )
+            .into());
+        }
+        if &response.header_sync_init_header_tree.root() != cur_epoch_header.block_merkle_root() {
+            return Err(ErrorKind::InvalidBlockMerkleRoot.into());
+        }
+
+        // The first block of the epoch must be produced by its block producer.
+        let cur_epoch_info = &response.cur_epoch_info;
+        let mut seen = HashSet::new();
+        let block_producers: Vec<ValidatorStake> = cur_epoch_info
+            .block_producers_settlement()
+            .iter()
+            .filter(|validator_id| seen.insert(**validator_id))
+            .map(|validator_id| cur_epoch_info.get_validator(*validator_id))
+            .collect();
+        if block_producers != self.next_block_producers {
+            return Err(ErrorKind::ValidatorError(
+                "block producers of the epoch do not match the proven ones".to_string(),
+            )
+            .into());
+        }
+        let block_producer = cur_epoch_info
+            .get_validator(cur_epoch_info.sample_block_producer(cur_epoch_header.height()));
+        if !cur_epoch_header.verify_block_producer(block_producer.public_key()) {
+            return Err(ErrorKind::InvalidSignature.into());
+        }
+        // Without the hash in the header nothing binds the epoch infos and block infos of the
+        // response to the proven chain.
+        match cur_epoch_header.epoch_sync_data_hash() {
+            Some(epoch_sync_data_hash)
+                if epoch_sync_data_hash == response.epoch_sync_data_hash() => {}
+            Some(_) => {
+                return Err(ErrorKind::Other("invalid epoch sync data hash".to_string()).into())
+            }
+            None => return Err(ErrorKind::Other("missing epoch sync data hash".to_string()).into()),
+        }
+
+        // The previous epoch headers must lead to the first block of the epoch.
+        let mut next_header = cur_epoch_header;
+        for header in response.prev_epoch_headers.iter().rev() {
+            if next_header.prev_hash() != header.hash()
+                || header.epoch_id() != &self.current_epoch_id
+            {
+                return Err(ErrorKind::Other("invalid previous epoch headers".to_string()).into());
+            }
+            next_header = header;
+        }
+        let prev_epoch_last_header = match response.prev_epoch_headers.last() {
+            Some(header) => header,
+            None => {
+                return Err(ErrorKind::Other("missing previous epoch headers".to_string()).into())
+            }
+        };
+        if response.prev_epoch_last_block_info.hash() != prev_epoch_last_header.hash()
+            || response.prev_epoch_prev_last_block_info.hash() != prev_epoch_last_header.prev_hash()
+            || response.prev_epoch_first_block_info.hash()
+                != response.prev_epoch_last_block_info.epoch_first_block()
+            || response.prev_epoch_last_block_info.epoch_id() != &self.current_epoch_id
+        {
+            return Err(ErrorKind::Other("invalid previous epoch block info".to_string()).into());
+        }
+        Ok(())
+    }
 }
 
 /// Helper to keep track of sync headers.
@@ -577,6 +884,31 @@ pub enum StateSyncResult {
     Completed,
 }
 
+/// Statistics of state parts downloads from a single target.
+/// Used to request more parts from the targets which serve them faster.
+#[derive(Default)]
+struct TargetDownloadStats {
+    /// Number of parts received from the target.
+    parts_received: u64,
+    /// Total size in bytes of the parts received from the target.
+    bytes_received: u64,
+    /// Total time in milliseconds between requesting the parts and receiving them.
+    response_time_ms: u64,
+    /// Number of part requests that timed out.
+    timeouts: u64,
+}
+
+impl TargetDownloadStats {
+    /// Bytes per second received from the target, `None` if nothing was received yet.
+    fn throughput(&self) -> Option<f64> {
+        if self.parts_received == 0 {
+            None
+        } else {
+            Some(self.bytes_received as f64 * 1000.0 / max(self.response_time_ms, 1) as f64)
+        }
+    }
+}
+
 struct PendingRequestStatus {
     missing_parts: usize,
     wait_until: DateTime<Utc>,
@@ -599,20 +931,37 @@ pub struct StateSync {
     last_time_block_requested: Option<DateTime<Utc>>,
 
     last_part_id_requested: HashMap<(AccountOrPeerIdOrHash, ShardId), PendingRequestStatus>,
-    /// Map from which part we requested to whom.
-    requested_target: SizedCache<(u64, CryptoHash), AccountOrPeerIdOrHash>,
+    /// Map from which part we requested to whom and when.
+    requested_target: SizedCache<(u64, CryptoHash), (AccountOrPeerIdOrHash, DateTime<Utc>)>,
+    /// Download statistics per target.
+    target_stats: HashMap<AccountOrPeerIdOrHash, TargetDownloadStats>,
+    /// Results of applying state parts, received from the sync jobs actor.
+    state_parts_apply_results: HashMap<(CryptoHash, ShardId), Result<(), near_chain::Error>>,
+
+    /// Local directory to take state headers and parts from before requesting them from peers.
+    dump_dir: Option<PathBuf>,
+    /// Next part to look for in the dump directory, per shard.
+    dump_dir_next_part: HashMap<ShardId, u64>,
 
     timeout: Duration,
 }
 
 impl StateSync {
-    pub fn new(network_adapter: Arc<dyn NetworkAdapter>, timeout: TimeDuration) -> Self {
+    pub fn new(
+        network_adapter: Arc<dyn NetworkAdapter>,
+        timeout: TimeDuration,
+        dump_dir: Option<PathBuf>,
+    ) -> Self {
         StateSync {
             network_adapter,
             state_sync_time: Default::default(),
             last_time_block_requested: None,
             last_part_id_requested: Default::default(),
             requested_target: SizedCache::with_size(MAX_PENDING_PART as usize),
+            target_stats: Default::default(),
+            state_parts_apply_results: Default::default(),
+            dump_dir,
+            dump_dir_next_part: Default::default(),
             timeout: Duration::from_std(timeout).unwrap(),
         }
     }
@@ -655,6 +1004,8 @@ impl StateSync {
         highest_height_peers: &Vec<FullPeerInfo>,
         tracking_shards: Vec<ShardId>,
         now: DateTime<Utc>,
+        state_parts_task_scheduler: Option<&dyn Fn(ApplyStatePartsRequest)>,
+        state_part_validation_scheduler: Option<&dyn Fn(ValidateStatePartRequest)>,
     ) -> Result<(bool, bool), near_chain::Error> {
         let mut all_done = true;
         let mut update_sync_status = false;
@@ -685,25 +1036,46 @@ impl StateSync {
                 init_sync_download.clone()
             });
             let mut this_done = false;
+            if let Some(dump_dir) = self.dump_dir.clone() {
+                self.load_from_dump_dir(
+                    &dump_dir,
+                    shard_id,
+                    chain,
+                    sync_hash,
+                    shard_sync_download,
+                    now,
+                    state_part_validation_scheduler,
+                );
+            }
             match shard_sync_download.status {
                 ShardSyncStatus::StateDownloadHeader => {
                     if shard_sync_download.downloads[0].done {
                         let shard_state_header = chain.get_state_header(shard_id, sync_hash)?;
                         let state_num_parts =
                             get_num_state_parts(shard_state_header.state_root_node().memory_usage);
+                        // Parts saved before a restart are valid already, no need to request them.
+                        let saved_parts = chain.get_downloaded_state_parts(
+                            shard_id,
+                            sync_hash,
+                            state_num_parts,
+                        )?;
+                        let num_saved_parts = saved_parts.iter().filter(|saved| **saved).count();
+                        if num_saved_parts > 0 {
+                            info!(target: "sync", "State sync resumes with {} of {} parts already downloaded, shard = {}, hash = {}", num_saved_parts, state_num_parts, shard_id, sync_hash);
+                        }
                         *shard_sync_download = ShardSyncDownload {
-                            downloads: vec![
-                                DownloadStatus {
+                            downloads: saved_parts
+                                .into_iter()
+                                .map(|saved| DownloadStatus {
                                     start_time: now,
                                     prev_update_time: now,
-                                    run_me: Arc::new(AtomicBool::new(true)),
+                                    run_me: Arc::new(AtomicBool::new(!saved)),
                                     error: false,
-                                    done: false,
+                                    done: saved,
                                     state_requests_count: 0,
                                     last_target: None,
-                                };
-                                state_num_parts as usize
-                            ],
+                                })
+                                .collect(),
                             status: ShardSyncStatus::StateDownloadParts,
                         };
                         need_shard = true;
@@ -744,15 +1116,78 @@ impl StateSync {
                         update_sync_status = true;
                         *shard_sync_download = ShardSyncDownload {
                             downloads: vec![],
-                            status: ShardSyncStatus::StateDownloadFinalize,
+                            status: ShardSyncStatus::StateDownloadScheduling,
                         };
                     }
                 }
+                ShardSyncStatus::StateDownloadScheduling => {
+                    let shard_state_header = chain.get_state_header(shard_id, sync_hash)?;
+                    let state_num_parts =
+                        get_num_state_parts(shard_state_header.state_root_node().memory_usage);
+                    let request = ApplyStatePartsRequest {
+                        shard_id,
+                        sync_hash,
+                        state_root: shard_state_header.chunk_prev_state_root(),
+                        num_parts: state_num_parts,
+                        epoch_id: chain.get_block_header(&sync_hash)?.epoch_id().clone(),
+                    };
+                    match state_parts_task_scheduler {
+                        // The result comes back through `set_apply_result`.
+                        Some(scheduler) => scheduler(request),
+                        None => {
+                            let result = near_chain::chain::apply_state_parts(
+                                runtime_adapter.as_ref(),
+                                chain.store().owned_store().as_ref(),
+                                request.shard_id,
+                                request.sync_hash,
+                                &request.state_root,
+                                request.num_parts,
+                                &request.epoch_id,
+                            );
+                            self.set_apply_result(sync_hash, shard_id, result);
+                        }
+                    }
+                    update_sync_status = true;
+                    *shard_sync_download = ShardSyncDownload {
+                        downloads: vec![],
+                        status: ShardSyncStatus::StateDownloadApplying,
+                    };
+                }
+                ShardSyncStatus::StateDownloadApplying => {
+                    if let Some(result) =
+                        self.state_parts_apply_results.remove(&(sync_hash, shard_id))
+                    {
+                        update_sync_status = true;
+                        match result {
+                            Ok(()) => {
+                                *shard_sync_download = ShardSyncDownload {
+                                    downloads: vec![],
+                                    status: ShardSyncStatus::StateDownloadFinalize,
+                                };
+                            }
+                            Err(e) => {
+                                // Cannot apply the downloaded parts, start from the very beginning.
+                                error!(target: "sync", "State sync apply parts error, shard = {}, hash = {}: {:?}", shard_id, sync_hash, e);
+                                let shard_state_header =
+                                    chain.get_state_header(shard_id, sync_hash)?;
+                                let state_num_parts = get_num_state_parts(
+                                    shard_state_header.state_root_node().memory_usage,
+                                );
+                                *shard_sync_download = init_sync_download.clone();
+                                chain.clear_downloaded_parts(
+                                    shard_id,
+                                    sync_hash,
+                                    state_num_parts,
+                                )?;
+                            }
+                        }
+                    }
+                }
                 ShardSyncStatus::StateDownloadFinalize => {
                     let shard_state_header = chain.get_state_header(shard_id, sync_hash)?;
                     let state_num_parts =
                         get_num_state_parts(shard_state_header.state_root_node().memory_usage);
-                    match chain.set_state_finalize(shard_id, sync_hash, state_num_parts) {
+                    match chain.set_state_finalize_after_apply(shard_id, sync_hash) {
                         Ok(_) => {
                             update_sync_status = true;
                             *shard_sync_download = ShardSyncDownload {
@@ -863,6 +1298,115 @@ impl StateSync {
         }
     }
 
+    /// Takes the state header or parts of the shard from the local dump directory, if it has them.
+    /// Whatever is missing there is requested from peers as usual. Parts are validated through
+    /// `state_part_validation_scheduler`, like the parts received from peers.
+    fn load_from_dump_dir(
+        &mut self,
+        dump_dir: &Path,
+        shard_id: ShardId,
+        chain: &mut Chain,
+        sync_hash: CryptoHash,
+        shard_sync_download: &mut ShardSyncDownload,
+        now: DateTime<Utc>,
+        state_part_validation_scheduler: Option<&dyn Fn(ValidateStatePartRequest)>,
+    ) {
+        match shard_sync_download.status {
+            ShardSyncStatus::StateDownloadHeader => {
+                self.dump_dir_next_part.remove(&shard_id);
+                let download = &mut shard_sync_download.downloads[0];
+                if download.done {
+                    return;
+                }
+                match state_sync_dump::read_state_header(dump_dir, &sync_hash, shard_id) {
+                    Ok(Some(header)) => match chain.set_state_header(shard_id, sync_hash, header) {
+                        Ok(()) => {
+                            info!(target: "sync", "State sync took header from {}, shard = {}, hash = {}", dump_dir.display(), shard_id, sync_hash);
+                            download.done = true;
+                            download.run_me.store(false, Ordering::SeqCst);
+                        }
+                        Err(err) => {
+                            error!(target: "sync", "State sync set_state_header error for header from {}, shard = {}, hash = {}: {:?}", dump_dir.display(), shard_id, sync_hash, err);
+                        }
+                    },
+                    Ok(None) => {}
+                    Err(err) => {
+                        error!(target: "sync", "State sync failed to read header from {}, shard = {}, hash = {}: {:?}", dump_dir.display(), shard_id, sync_hash, err);
+                    }
+                }
+            }
+            ShardSyncStatus::StateDownloadParts => {
+                let num_parts = shard_sync_download.downloads.len() as u64;
+                let state_root = match chain.get_state_header(shard_id, sync_hash) {
+                    Ok(header) => header.chunk_prev_state_root(),
+                    Err(err) => {
+                        error!(target: "sync", "State sync get_state_header error, shard = {}, hash = {}: {:?}", shard_id, sync_hash, err);
+                        return;
+                    }
+                };
+                let next_part = self.dump_dir_next_part.entry(shard_id).or_insert(0);
+                let mut loaded_parts = 0;
+                while *next_part < num_parts && loaded_parts < MAX_STATE_PARTS_FROM_DUMP {
+                    let part_id = *next_part;
+                    *next_part += 1;
+                    let download = &mut shard_sync_download.downloads[part_id as usize];
+                    if download.done {
+                        continue;
+                    }
+                    // Parts which are missing in the dump directory or fail to load from it are
+                    // requested from peers, the dump directory is not looked at again.
+                    let part = match state_sync_dump::read_state_part(
+                        dump_dir, &sync_hash, shard_id, part_id,
+                    ) {
+                        Ok(Some(part)) => part,
+                        Ok(None) => {
+                            debug!(target: "sync", "State sync didn't find part in {}, requesting it from peers, shard = {}, part = {}, hash = {}", dump_dir.display(), shard_id, part_id, sync_hash);
+                            continue;
+                        }
+                        Err(err) => {
+                            error!(target: "sync", "State sync failed to read part from {}, requesting it from peers, shard = {}, part = {}, hash = {}: {:?}", dump_dir.display(), shard_id, part_id, sync_hash, err);
+                            continue;
+                        }
+                    };
+                    loaded_parts += 1;
+                    match state_part_validation_scheduler {
+                        // The result comes back through the same path as for the parts received
+                        // from peers, an invalid part is requested from peers then.
+                        Some(scheduler) => {
+                            download.run_me.store(false, Ordering::SeqCst);
+                            download.prev_update_time = now;
+                            scheduler(ValidateStatePartRequest {
+                                shard_id,
+                                sync_hash,
+                                state_root,
+                                part_id,
+                                num_parts,
+                                data: part,
+                            });
+                        }
+                        None => match chain
+                            .set_state_part(shard_id, sync_hash, part_id, num_parts, &part)
+                        {
+                            Ok(()) => {
+                                download.done = true;
+                                download.run_me.store(false, Ordering::SeqCst);
+                                if let Err(err) = chain.mark_state_part_downloaded(
+                                    shard_id, sync_hash, part_id, num_parts,
+                                ) {
+                                    error!(target: "sync", "State sync failed to record downloaded part, shard = {}, part = {}, hash = {}: {:?}", shard_id, part_id, sync_hash, err);
+                                }
+                            }
+                            Err(err) => {
+                                error!(target: "sync", "State sync set_state_part error for part from {}, requesting it from peers, shard = {}, part = {}, hash = {}: {:?}", dump_dir.display(), shard_id, part_id, sync_hash, err);
+                            }
+                        },
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn sent_request_part(
         &mut self,
         target: AccountOrPeerIdOrHash,
@@ -870,7 +1414,7 @@ impl StateSync {
         shard_id: ShardId,
         sync_hash: CryptoHash,
     ) {
-        self.requested_target.cache_set((part_id, sync_hash), target.clone());
+        self.requested_target.cache_set((part_id, sync_hash), (target.clone(), Utc::now()));
 
         let timeout = self.timeout;
         self.last_part_id_requested
@@ -886,9 +1430,15 @@ impl StateSync {
         part_id: u64,
         shard_id: ShardId,
         sync_hash: CryptoHash,
+        part_size: usize,
     ) {
         let key = (part_id, sync_hash);
-        if let Some(target) = self.requested_target.cache_get(&key) {
+        if let Some((target, requested_at)) = self.requested_target.cache_remove(&key) {
+            let stats = self.target_stats.entry(target.clone()).or_default();
+            stats.parts_received += 1;
+            stats.bytes_received += part_size as u64;
+            stats.response_time_ms += max((Utc::now() - requested_at).num_milliseconds(), 0) as u64;
+
             if self.last_part_id_requested.get_mut(&(target.clone(), shard_id)).map_or(
                 false,
                 |request| {
@@ -914,7 +1464,16 @@ impl StateSync {
         highest_height_peers: &Vec<FullPeerInfo>,
     ) -> Result<Vec<AccountOrPeerIdOrHash>, Error> {
         // Remove candidates from pending list if request expired due to timeout
-        self.last_part_id_requested.retain(|_, request| !request.expired());
+        let target_stats = &mut self.target_stats;
+        self.last_part_id_requested.retain(|(target, _), request| {
+            if request.expired() {
+                target_stats.entry(target.clone()).or_default().timeouts +=
+                    request.missing_parts as u64;
+                false
+            } else {
+                true
+            }
+        });
 
         let prev_block_hash = chain.get_block_header(&sync_hash)?.prev_hash();
         let epoch_hash = runtime_adapter.get_epoch_id_from_prev_block(&prev_block_hash)?;
@@ -952,6 +1511,38 @@ impl StateSync {
             .collect::<Vec<_>>())
     }
 
+    /// Number of parts to request from `target` in one round.
+    /// Targets we know nothing about yet get the full `MAX_STATE_PART_REQUEST`, the others get
+    /// a share of it proportional to their throughput relative to the fastest known target.
+    fn part_request_limit(&self, target: &AccountOrPeerIdOrHash) -> u64 {
+        let stats = match self.target_stats.get(target) {
+            Some(stats) => stats,
+            None => return MAX_STATE_PART_REQUEST,
+        };
+        let throughput = match stats.throughput() {
+            Some(throughput) => throughput,
+            // Only timeouts so far.
+            None => return if stats.timeouts > 0 { 1 } else { MAX_STATE_PART_REQUEST },
+        };
+        let best_throughput = self
+            .target_stats
+            .values()
+            .filter_map(|stats| stats.throughput())
+            .fold(throughput, f64::max);
+        let limit = (MAX_STATE_PART_REQUEST as f64 * throughput / best_throughput).round() as u64;
+        min(max(limit, 1), MAX_STATE_PART_REQUEST)
+    }
+
+    /// Stores the result of applying state parts of the shard done by the sync jobs actor.
+    pub fn set_apply_result(
+        &mut self,
+        sync_hash: CryptoHash,
+        shard_id: ShardId,
+        apply_result: Result<(), Error>,
+    ) {
+        self.state_parts_apply_results.insert((sync_hash, shard_id), apply_result);
+    }
+
     /// Returns new ShardSyncDownload if successful, otherwise returns given shard_sync_download
     pub fn request_shard(
         &mut self,
@@ -1003,8 +1594,10 @@ impl StateSync {
                 );
             }
             ShardSyncStatus::StateDownloadParts => {
+                let limits =
+                    possible_targets.iter().map(|target| self.part_request_limit(target)).collect();
                 let possible_targets_sampler =
-                    SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+                    SamplerLimited::with_limits(possible_targets, limits);
 
                 // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
                 // Parts are ordered such that its index match its part_id.
@@ -1059,6 +1652,8 @@ impl StateSync {
         runtime_adapter: &Arc<dyn RuntimeAdapter>,
         highest_height_peers: &Vec<FullPeerInfo>,
         tracking_shards: Vec<ShardId>,
+        state_parts_task_scheduler: Option<&dyn Fn(ApplyStatePartsRequest)>,
+        state_part_validation_scheduler: Option<&dyn Fn(ValidateStatePartRequest)>,
     ) -> Result<StateSyncResult, near_chain::Error> {
         let prev_hash = chain.get_block_header(&sync_hash)?.prev_hash().clone();
         let now = Utc::now();
@@ -1085,6 +1680,8 @@ impl StateSync {
             highest_height_peers,
             tracking_shards,
             now,
+            state_parts_task_scheduler,
+            state_part_validation_scheduler,
         )?;
 
         if have_block && all_done {
@@ -1101,11 +1698,11 @@ impl StateSync {
 }
 
 /// Create an abstract collection of elements to be shuffled.
-/// Each element will appear in the shuffled output exactly `limit` times.
+/// Element `data[i]` will appear in the shuffled output exactly `limits[i]` times.
 /// Use it as an iterator to access the shuffled collection.
 ///
 /// ```rust,ignore
-/// let sampler = SamplerLimited::new(vec![1, 2, 3], 2);
+/// let sampler = SamplerLimited::with_limits(vec![1, 2, 3], vec![2, 2, 2]);
 ///
 /// let res = sampler.collect::<Vec<_>>();
 ///
@@ -1126,13 +1723,10 @@ struct SamplerLimited<T> {
 }
 
 impl<T> SamplerLimited<T> {
-    fn new(data: Vec<T>, limit: u64) -> Self {
-        if limit == 0 {
-            Self { data: vec![], limit: vec![] }
-        } else {
-            let len = data.len();
-            Self { data, limit: vec![limit; len] }
-        }
+    fn with_limits(data: Vec<T>, limits: Vec<u64>) -> Self {
+        assert_eq!(data.len(), limits.len());
+        let (data, limit) = data.into_iter().zip(limits).filter(|(_, limit)| *limit > 0).unzip();
+        Self { data, limit }
     }
 }
 
@@ -1177,6 +1771,9 @@ mod test {
     use near_primitives::block::{Approval, Block, GenesisId};
     use near_primitives::network::PeerId;
 
+    use actix::System;
+    use near_actix_test_utils::run_actix;
+
     use super::*;
     use crate::test_utils::TestEnv;
     use near_primitives::merkle::PartialMerkleTree;
@@ -1525,4 +2122,184 @@ mod test {
             blocks.iter().take(1).map(|b| *b.hash()).collect::<HashSet<_>>()
         );
     }
+
+    #[test]
+    fn test_epoch_sync_requests() {
+        let network_adapter = Arc::new(MockNetworkAdapter::default());
+        let mut chain_genesis = ChainGenesis::test();
+        chain_genesis.epoch_length = 5;
+        let mut env = TestEnv::new(chain_genesis, 1, 1);
+        let genesis = env.clients[0].chain.genesis_block().clone();
+        let new_epoch_sync = || {
+            EpochSync::new(
+                network_adapter.clone(),
+                genesis.header().epoch_id().clone(),
+                genesis.header().next_epoch_id().clone(),
+                vec![],
+                TimeDuration::from_secs(10),
+                TimeDuration::from_secs(0),
+                5,
+            )
+        };
+        let peer_infos = create_peer_infos(1);
+        let peer_id = peer_infos[0].peer_info.id.clone();
+        let mut sync_status = SyncStatus::AwaitingPeers;
+
+        // The network is within two epochs from genesis, so there is nothing to skip.
+        let mut epoch_sync = new_epoch_sync();
+        assert!(!epoch_sync
+            .run(&mut sync_status, &mut env.clients[0].chain, 9, &peer_infos)
+            .unwrap());
+        assert!(epoch_sync.done);
+        assert!(network_adapter.requests.read().unwrap().is_empty());
+
+        let mut epoch_sync = new_epoch_sync();
+        assert!(epoch_sync
+            .run(&mut sync_status, &mut env.clients[0].chain, 100, &peer_infos)
+            .unwrap());
+        assert!(matches!(sync_status, SyncStatus::EpochSync { epoch_ord: 0 }));
+        match network_adapter.requests.write().unwrap().pop_back().unwrap() {
+            NetworkRequests::EpochSyncRequest { peer_id: requested_peer_id, epoch_id } => {
+                assert_eq!(requested_peer_id, peer_id);
+                assert_eq!(&epoch_id, genesis.header().next_epoch_id());
+            }
+            request => panic!("unexpected network request {:?}", request),
+        }
+
+        // Nothing is requested while the request is pending.
+        assert!(epoch_sync
+            .run(&mut sync_status, &mut env.clients[0].chain, 100, &peer_infos)
+            .unwrap());
+        assert!(network_adapter.requests.read().unwrap().is_empty());
+
+        // Once the peer reports that the epoch is the current one, finalization is requested.
+        epoch_sync.on_response(peer_id.clone(), EpochSyncResponse::UpToDate);
+        assert!(epoch_sync
+            .run(&mut sync_status, &mut env.clients[0].chain, 100, &peer_infos)
+            .unwrap());
+        match network_adapter.requests.write().unwrap().pop_back().unwrap() {
+            NetworkRequests::EpochSyncFinalizationRequest {
+                peer_id: requested_peer_id,
+                epoch_id,
+            } => {
+                assert_eq!(requested_peer_id, peer_id);
+                assert_eq!(&epoch_id, genesis.header().next_epoch_id());
+            }
+            request => panic!("unexpected network request {:?}", request),
+        }
+        assert!(!epoch_sync.done);
+    }
+
+    #[test]
+    fn test_sampler_limited_with_limits() {
+        let sampler = SamplerLimited::with_limits(vec![1, 2, 3], vec![2, 0, 3]);
+        let res = sampler.collect::<Vec<_>>();
+        assert_eq!(res.len(), 5);
+        assert_eq!(res.iter().filter(|v| **v == 1).count(), 2);
+        assert_eq!(res.iter().filter(|v| **v == 2).count(), 0);
+        assert_eq!(res.iter().filter(|v| **v == 3).count(), 3);
+    }
+
+    #[test]
+    fn test_part_request_limit() {
+        run_actix(async {
+            let network_adapter = Arc::new(MockNetworkAdapter::default());
+            // Requests only expire when the test says so.
+            let mut state_sync =
+                StateSync::new(network_adapter.clone(), TimeDuration::from_secs(60), None);
+            let mut env = TestEnv::new(ChainGenesis::test(), 1, 1);
+            for height in 1..3 {
+                env.produce_block(0, height);
+            }
+            let me = env.clients[0].validator_signer.as_ref().map(|s| s.validator_id().clone());
+            let runtime_adapter = env.clients[0].runtime_adapter.clone();
+            let chain = &mut env.clients[0].chain;
+            let sync_hash = *chain.head_header().unwrap().hash();
+            let peers = (0..4)
+                .map(|_| FullPeerInfo {
+                    peer_info: PeerInfo::random(),
+                    chain_info: PeerChainInfoV2 { tracked_shards: vec![0], ..Default::default() },
+                    edge_info: EdgeInfo::default(),
+                })
+                .collect::<Vec<_>>();
+            let targets = peers
+                .iter()
+                .map(|peer| AccountOrPeerIdOrHash::PeerId(peer.peer_info.id.clone()))
+                .collect::<Vec<_>>();
+            let parts_download = |num_parts: u64| {
+                let now = Utc::now();
+                ShardSyncDownload {
+                    downloads: (0..num_parts)
+                        .map(|_| DownloadStatus {
+                            start_time: now,
+                            prev_update_time: now,
+                            run_me: Arc::new(AtomicBool::new(true)),
+                            error: false,
+                            done: false,
+                            state_requests_count: 0,
+                            last_target: None,
+                        })
+                        .collect(),
+                    status: ShardSyncStatus::StateDownloadParts,
+                }
+            };
+            let requested_parts = || {
+                let mut requested_parts = vec![];
+                while let Some(request) = network_adapter.pop() {
+                    if let NetworkRequests::StateRequestPart { part_id, target, .. } = request {
+                        requested_parts.push((part_id, target));
+                    }
+                }
+                requested_parts
+            };
+
+            // Nothing is known about the first three targets, each gets the full limit.
+            state_sync
+                .request_shard(
+                    &me,
+                    0,
+                    chain,
+                    &runtime_adapter,
+                    sync_hash,
+                    parts_download(3 * MAX_STATE_PART_REQUEST),
+                    &peers[..3].to_vec(),
+                )
+                .unwrap();
+            for (part_id, target) in requested_parts() {
+                // The first target is forty times faster than the second one, the third one
+                // never responds.
+                if target == targets[0] {
+                    state_sync.received_requested_part(part_id, 0, sync_hash, 4000);
+                } else if target == targets[1] {
+                    state_sync.received_requested_part(part_id, 0, sync_hash, 100);
+                }
+            }
+            // The request to the third target times out.
+            for request in state_sync.last_part_id_requested.values_mut() {
+                request.wait_until = Utc::now() - Duration::milliseconds(1);
+            }
+
+            state_sync
+                .request_shard(
+                    &me,
+                    0,
+                    chain,
+                    &runtime_adapter,
+                    sync_hash,
+                    parts_download(4 * MAX_STATE_PART_REQUEST),
+                    &peers,
+                )
+                .unwrap();
+            let requested_parts = requested_parts();
+            let num_requested = |target: &AccountOrPeerIdOrHash| {
+                requested_parts.iter().filter(|(_, t)| t == target).count() as u64
+            };
+            assert_eq!(num_requested(&targets[0]), MAX_STATE_PART_REQUEST);
+            assert!(num_requested(&targets[1]) >= 1);
+            assert!(num_requested(&targets[1]) < MAX_STATE_PART_REQUEST);
+            assert_eq!(num_requested(&targets[2]), 1);
+            assert_eq!(num_requested(&targets[3]), MAX_STATE_PART_REQUEST);
+            System::current().stop();
+        });
+    }
 }
@@ -0,0 +1,273 @@
+//! Sync jobs actor validates and applies state parts downloaded during state sync and removes
+//! the state of shards which are no longer tracked.
+//! This work is CPU and IO heavy, so it runs on a pool of threads separate from the client actor.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use actix::dev::MessageResponse;
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use log::{debug, error, info};
+
+use near_chain::chain::{
+    apply_state_parts, retain_shards_without_trie_changes, validate_and_save_state_part,
+};
+use near_chain::RuntimeAdapter;
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, EpochId, ShardId, StateRoot};
+use near_store::{ColBlockMisc, ShardUId, Store, HEAD_KEY};
+
+/// Number of trie nodes deleted in one transaction when removing the state of a shard.
+const CLEAR_SHARD_STATE_BATCH_SIZE: usize = 100_000;
+
+/// Request to validate a single state part and save it in the store.
+#[derive(Message)]
+#[rtype(result = "ValidateStatePartResponse")]
+pub struct ValidateStatePartRequest {
+    pub shard_id: ShardId,
+    pub sync_hash: CryptoHash,
+    pub state_root: StateRoot,
+    pub part_id: u64,
+    pub num_parts: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Message, MessageResponse)]
+#[rtype(result = "()")]
+pub struct ValidateStatePartResponse {
+    pub shard_id: ShardId,
+    pub sync_hash: CryptoHash,
+    pub part_id: u64,
+    pub result: Result<(), near_chain::Error>,
+}
+
+/// Request to apply all the saved state parts of the shard to the trie.
+#[derive(Message)]
+#[rtype(result = "ApplyStatePartsResponse")]
+pub struct ApplyStatePartsRequest {
+    pub shard_id: ShardId,
+    pub sync_hash: CryptoHash,
+    pub state_root: StateRoot,
+    pub num_parts: u64,
+    pub epoch_id: EpochId,
+}
+
+#[derive(Message, MessageResponse)]
+#[rtype(result = "()")]
+pub struct ApplyStatePartsResponse {
+    pub shard_id: ShardId,
+    pub sync_hash: CryptoHash,
+    pub result: Result<(), near_chain::Error>,
+}
+
+/// Request to remove the whole state of the shards which are no longer tracked.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ClearShardStateRequest {
+    pub shard_uids: Vec<ShardUId>,
+    /// Validator account of the node, if any.
+    pub me: Option<AccountId>,
+    /// Set when the tracked shards change, the removal is stopped then.
+    pub cancelled: Arc<AtomicBool>,
+}
+
+pub struct SyncJobsActor {
+    runtime_adapter: Arc<dyn RuntimeAdapter>,
+    store: Arc<Store>,
+}
+
+impl SyncJobsActor {
+    pub fn new(runtime_adapter: Arc<dyn RuntimeAdapter>, store: Arc<Store>) -> Self {
+        SyncJobsActor { runtime_adapter, store }
+    }
+
+    /// Whether the removal of the shard state may go on: it is not cancelled, and the node
+    /// still cares about the shard neither in the epoch of the head nor in the next one.
+    /// Checked before every batch, as the tracked shards may change during the removal.
+    fn can_clear_shard_state(
+        &self,
+        me: Option<&AccountId>,
+        shard_uid: ShardUId,
+        cancelled: &AtomicBool,
+    ) -> bool {
+        if cancelled.load(Ordering::SeqCst) {
+            return false;
+        }
+        let head: Tip = match self.store.get_ser(ColBlockMisc, HEAD_KEY) {
+            Ok(Some(head)) => head,
+            _ => return false,
+        };
+        let shard_id = shard_uid.shard_id as ShardId;
+        !self.runtime_adapter.cares_about_shard(me, &head.last_block_hash, shard_id, true)
+            && !self.runtime_adapter.will_care_about_shard(
+                me,
+                &head.last_block_hash,
+                shard_id,
+                true,
+            )
+    }
+
+    /// Removes the state of the shard in batches of `batch_size` trie nodes, until it is gone or
+    /// `can_clear_shard_state` stops the removal. Returns the number of removed trie nodes.
+    fn clear_shard_state(
+        &self,
+        shard_uid: ShardUId,
+        me: Option<&AccountId>,
+        cancelled: &AtomicBool,
+        batch_size: usize,
+    ) -> u64 {
+        info!(target: "client", "Removing state of shard {:?} which is no longer tracked", shard_uid);
+        let mut num_deleted = 0;
+        loop {
+            if !self.can_clear_shard_state(me, shard_uid, cancelled) {
+                info!(target: "client", "Stopped removing state of shard {:?} after {} trie nodes, the tracked shards have changed", shard_uid, num_deleted);
+                return num_deleted;
+            }
+            match self.runtime_adapter.get_tries().delete_shard_state_batch(shard_uid, batch_size) {
+                Ok(0) => {
+                    info!(target: "client", "Removed {} trie nodes of shard {:?}", num_deleted, shard_uid);
+                    return num_deleted;
+                }
+                Ok(num_deleted_in_batch) => num_deleted += num_deleted_in_batch,
+                Err(err) => {
+                    error!(target: "client", "Failed to remove state of shard {:?}: {}", shard_uid, err);
+                    return num_deleted;
+                }
+            }
+        }
+    }
+}
+
+impl Actor for SyncJobsActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<ValidateStatePartRequest> for SyncJobsActor {
+    type Result = ValidateStatePartResponse;
+
+    fn handle(&mut self, msg: ValidateStatePartRequest, _: &mut Self::Context) -> Self::Result {
+        let result = validate_and_save_state_part(
+            self.runtime_adapter.as_ref(),
+            self.store.as_ref(),
+            &msg.state_root,
+            msg.shard_id,
+            msg.sync_hash,
+            msg.part_id,
+            msg.num_parts,
+            &msg.data,
+        );
+        ValidateStatePartResponse {
+            shard_id: msg.shard_id,
+            sync_hash: msg.sync_hash,
+            part_id: msg.part_id,
+            result,
+        }
+    }
+}
+
+impl Handler<ApplyStatePartsRequest> for SyncJobsActor {
+    type Result = ApplyStatePartsResponse;
+
+    fn handle(&mut self, msg: ApplyStatePartsRequest, _: &mut Self::Context) -> Self::Result {
+        debug!(target: "sync", "Applying {} state parts, shard = {}, hash = {}", msg.num_parts, msg.shard_id, msg.sync_hash);
+        let result = apply_state_parts(
+            self.runtime_adapter.as_ref(),
+            self.store.as_ref(),
+            msg.shard_id,
+            msg.sync_hash,
+            &msg.state_root,
+            msg.num_parts,
+            &msg.epoch_id,
+        );
+        ApplyStatePartsResponse { shard_id: msg.shard_id, sync_hash: msg.sync_hash, result }
+    }
+}
+
+impl Handler<ClearShardStateRequest> for SyncJobsActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClearShardStateRequest, _: &mut Self::Context) -> Self::Result {
+        let mut shard_uids = msg.shard_uids;
+        retain_shards_without_trie_changes(self.store.as_ref(), &mut shard_uids);
+        for shard_uid in shard_uids {
+            self.clear_shard_state(
+                shard_uid,
+                msg.me.as_ref(),
+                &msg.cancelled,
+                CLEAR_SHARD_STATE_BATCH_SIZE,
+            );
+        }
+    }
+}
+
+/// Starts the sync jobs actor on a pool of `num_threads` threads.
+pub fn start_sync_jobs_actor(
+    num_threads: usize,
+    runtime_adapter: Arc<dyn RuntimeAdapter>,
+    store: Arc<Store>,
+) -> Addr<SyncJobsActor> {
+    SyncArbiter::start(num_threads, move || {
+        SyncJobsActor::new(runtime_adapter.clone(), store.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    use near_chain::test_utils::setup;
+    use near_chain::RuntimeAdapter;
+    use near_primitives::hash::hash;
+    use near_primitives::types::AccountId;
+    use near_store::{ColState, ShardUId, Store};
+
+    use super::SyncJobsActor;
+
+    fn write_shard_state(store: &Store, shard_uid: ShardUId, num_nodes: u64) {
+        let mut store_update = store.store_update();
+        for i in 0..num_nodes {
+            let value = i.to_le_bytes();
+            let key = [&shard_uid.to_bytes()[..], hash(&value).as_ref()].concat();
+            store_update.update_refcount(ColState, &key, &value, 1);
+        }
+        store_update.commit().unwrap();
+    }
+
+    fn num_shard_state_nodes(store: &Store, shard_uid: ShardUId) -> usize {
+        store.iter_prefix(ColState, &shard_uid.to_bytes()).count()
+    }
+
+    fn setup_actor() -> (SyncJobsActor, Arc<Store>) {
+        let (chain, runtime, _) = setup();
+        let store = chain.store().owned_store();
+        (SyncJobsActor::new(runtime as Arc<dyn RuntimeAdapter>, store.clone()), store)
+    }
+
+    #[test]
+    fn test_clear_shard_state() {
+        let (actor, store) = setup_actor();
+        let shard_uid = ShardUId { version: 0, shard_id: 0 };
+        write_shard_state(&store, shard_uid, 10);
+        assert_eq!(actor.clear_shard_state(shard_uid, None, &AtomicBool::new(false), 3), 10);
+        assert_eq!(num_shard_state_nodes(&store, shard_uid), 0);
+    }
+
+    #[test]
+    fn test_clear_shard_state_cancelled() {
+        let (actor, store) = setup_actor();
+        let shard_uid = ShardUId { version: 0, shard_id: 0 };
+        write_shard_state(&store, shard_uid, 10);
+
+        // Changing the tracked shards cancels the removal.
+        assert_eq!(actor.clear_shard_state(shard_uid, None, &AtomicBool::new(true), 3), 0);
+        assert_eq!(num_shard_state_nodes(&store, shard_uid), 10);
+
+        // The removal also stops once the node cares about the shard again, here as the
+        // validator of the shard.
+        let me = AccountId::test_account();
+        assert_eq!(actor.clear_shard_state(shard_uid, Some(&me), &AtomicBool::new(false), 3), 0);
+        assert_eq!(num_shard_state_nodes(&store, shard_uid), 10);
+    }
+}
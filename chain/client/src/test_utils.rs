@@ -1140,6 +1140,7 @@ impl TestEnv {
                 last_block.header().hash(),
                 last_block.header().epoch_id(),
                 &QueryRequest::ViewAccount { account_id },
+                None,
             )
             .unwrap();
         match response.kind {
@@ -1163,6 +1164,7 @@ impl TestEnv {
                 last_block.header().hash(),
                 last_block.header().epoch_id(),
                 &QueryRequest::ViewState { account_id, prefix: vec![].into() },
+                None,
             )
             .unwrap();
         match response.kind {
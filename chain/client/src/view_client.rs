@@ -4,6 +4,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::hash::Hash;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
@@ -21,10 +22,10 @@ use near_chain_configs::{ClientConfig, ProtocolConfigView};
 use near_client_primitives::types::{
     Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
     GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetNextLightClientBlockError,
-    GetProtocolConfig, GetProtocolConfigError, GetReceipt, GetReceiptError, GetStateChangesError,
-    GetStateChangesWithCauseInBlock, GetValidatorInfoError, Query, QueryError, TxStatus,
-    TxStatusError,
+    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMisbehaviorEvidence,
+    GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigError, GetReceipt,
+    GetReceiptError, GetStateChangesError, GetStateChangesWithCauseInBlock, GetValidatorInfoError,
+    Query, QueryError, SimulateTransaction, SimulateTransactionError, TxStatus, TxStatusError,
 };
 #[cfg(feature = "adversarial")]
 use near_network::types::NetworkAdversarialMessage;
@@ -41,8 +42,8 @@ use near_primitives::merkle::{merklize, PartialMerkleTree};
 use near_primitives::network::AnnounceAccount;
 use near_primitives::sharding::ShardChunk;
 use near_primitives::syncing::{
-    ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV1,
-    ShardStateSyncResponseV2,
+    EpochSyncFinalizationResponse, EpochSyncResponse, ShardStateSyncResponse,
+    ShardStateSyncResponseHeader, ShardStateSyncResponseV1, ShardStateSyncResponseV2,
 };
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochId, EpochReference, Finality,
@@ -52,8 +53,8 @@ use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
     FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView,
-    StateChangesView,
+    LightClientBlockView, MisbehaviorEvidenceView, QueryRequest, QueryResponse, ReceiptView,
+    SimulatedTransactionView, StateChangesKindsView, StateChangesView,
 };
 
 use crate::{
@@ -247,6 +248,7 @@ impl ViewClientActor {
             QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
             QueryRequest::CallFunction { account_id, .. } => account_id,
             QueryRequest::ViewCode { account_id, .. } => account_id,
+            QueryRequest::ViewStatePage { account_id, .. } => account_id,
         };
         let shard_id = self
             .runtime_adapter
@@ -279,6 +281,7 @@ impl ViewClientActor {
             header.hash(),
             header.epoch_id(),
             &msg.request,
+            msg.state_overrides.as_ref(),
         ) {
             Ok(query_response) => Ok(query_response),
             Err(query_error) => Err(match query_error {
@@ -324,6 +327,10 @@ impl ViewClientActor {
                     block_height,
                     block_hash,
                 },
+                near_chain::near_chain_primitives::error::QueryError::InvalidPageLimit {
+                    block_height,
+                    block_hash,
+                } => QueryError::InvalidPageLimit { block_height, block_hash },
             }),
         }
     }
@@ -480,6 +487,71 @@ impl ViewClientActor {
         Ok(headers)
     }
 
+    /// Returns the light client block of the epoch, which proves the block producers of the
+    /// next epoch, or `UpToDate` if the epoch is the current one.
+    fn get_epoch_sync_response(
+        &mut self,
+        epoch_id: &EpochId,
+    ) -> Result<Option<EpochSyncResponse>, near_chain::Error> {
+        if &self.chain.head()?.epoch_id == epoch_id {
+            return Ok(Some(EpochSyncResponse::UpToDate));
+        }
+        match self.chain.mut_store().get_epoch_light_client_block(&epoch_id.0) {
+            Ok(light_client_block_view) => Ok(Some(EpochSyncResponse::Advance {
+                light_client_block_view: light_client_block_view.clone(),
+            })),
+            Err(e) => match e.kind() {
+                ErrorKind::DBNotFoundErr(_) => Ok(None),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Collects the headers and epoch data that a node needs to start state sync at the first
+    /// block of the current epoch. Only the current epoch is served.
+    fn get_epoch_sync_finalization_response(
+        &mut self,
+        epoch_id: &EpochId,
+    ) -> Result<Option<EpochSyncFinalizationResponse>, near_chain::Error> {
+        let head = self.chain.head()?;
+        if &head.epoch_id != epoch_id {
+            return Ok(None);
+        }
+        let epoch_start_height =
+            self.runtime_adapter.get_epoch_start_height(&head.last_block_hash)?;
+        let cur_epoch_header = self.chain.get_header_by_height(epoch_start_height)?.clone();
+        let prev_epoch_last_header =
+            self.chain.get_block_header(cur_epoch_header.prev_hash())?.clone();
+        let prev_epoch_prev_last_header =
+            self.chain.get_block_header(prev_epoch_last_header.prev_hash())?.clone();
+        let header_sync_init_header_tree =
+            self.chain.mut_store().get_block_merkle_tree(cur_epoch_header.hash())?.clone();
+        let (
+            prev_epoch_first_block_info,
+            prev_epoch_prev_last_block_info,
+            prev_epoch_last_block_info,
+            prev_epoch_info,
+            cur_epoch_info,
+            next_epoch_info,
+        ) = self.runtime_adapter.get_epoch_sync_data(
+            prev_epoch_last_header.hash(),
+            epoch_id,
+            cur_epoch_header.next_epoch_id(),
+        )?;
+        Ok(Some(EpochSyncFinalizationResponse {
+            cur_epoch_header: cur_epoch_header.clone(),
+            prev_epoch_headers: vec![prev_epoch_prev_last_header, prev_epoch_last_header],
+            header_sync_init_header: cur_epoch_header,
+            header_sync_init_header_tree,
+            prev_epoch_first_block_info,
+            prev_epoch_prev_last_block_info,
+            prev_epoch_last_block_info,
+            prev_epoch_info,
+            cur_epoch_info,
+            next_epoch_info,
+        }))
+    }
+
     fn check_signature_account_announce(
         &self,
         announce_account: &AnnounceAccount,
@@ -965,6 +1037,102 @@ impl Handler<GetProtocolConfig> for ViewClientActor {
     }
 }
 
+impl Handler<SimulateTransaction> for ViewClientActor {
+    type Result = Result<SimulatedTransactionView, SimulateTransactionError>;
+
+    #[perf]
+    fn handle(&mut self, msg: SimulateTransaction, _: &mut Self::Context) -> Self::Result {
+        let block_header = match msg.block_reference {
+            BlockReference::Finality(finality) => {
+                let block_hash = self.get_block_hash_by_finality(&finality)?;
+                self.chain.get_block_header(&block_hash).map(Clone::clone)
+            }
+            BlockReference::BlockId(BlockId::Height(height)) => {
+                self.chain.get_header_by_height(height).map(Clone::clone)
+            }
+            BlockReference::BlockId(BlockId::Hash(hash)) => {
+                self.chain.get_block_header(&hash).map(Clone::clone)
+            }
+            BlockReference::SyncCheckpoint(sync_checkpoint) => {
+                if let Some(block_hash) =
+                    self.get_block_hash_by_sync_checkpoint(&sync_checkpoint)?
+                {
+                    self.chain.get_block_header(&block_hash).map(Clone::clone)
+                } else {
+                    return Err(SimulateTransactionError::NotSyncedYet);
+                }
+            }
+        }?;
+
+        // Receipts may go to any shard, so the state of all of them is needed.
+        let num_shards = self.runtime_adapter.num_shards(block_header.epoch_id())?;
+        let mut state_roots = vec![];
+        for shard_id in 0..num_shards {
+            let shard_uid =
+                self.runtime_adapter.shard_id_to_uid(shard_id, block_header.epoch_id())?;
+            let chunk_extra = self.chain.get_chunk_extra(block_header.hash(), &shard_uid).map_err(
+                |err| match err.kind() {
+                    ErrorKind::DBNotFoundErr(_) => {
+                        SimulateTransactionError::UnavailableShard(shard_id)
+                    }
+                    _ => err.into(),
+                },
+            )?;
+            state_roots.push(*chunk_extra.state_root());
+        }
+
+        let result = self
+            .runtime_adapter
+            .simulate_transaction(
+                block_header.hash(),
+                block_header.height(),
+                block_header.raw_timestamp(),
+                block_header.gas_price(),
+                *block_header.random_value(),
+                &state_roots,
+                msg.transaction,
+                msg.signature,
+            )?
+            .map_err(SimulateTransactionError::InvalidTransaction)?;
+
+        let mut outcomes: Vec<ExecutionOutcomeWithIdView> = result
+            .outcomes
+            .into_iter()
+            .map(|outcome_with_id| ExecutionOutcomeWithIdView {
+                proof: vec![],
+                block_hash: *block_header.hash(),
+                id: outcome_with_id.id,
+                outcome: outcome_with_id.outcome.into(),
+            })
+            .collect();
+        if outcomes.is_empty() {
+            return Err(SimulateTransactionError::Unreachable(
+                "Simulated transaction has no outcome".to_string(),
+            ));
+        }
+        // The status is not final if some receipts were not executed in the simulated blocks.
+        let status = if result.incomplete {
+            FinalExecutionStatus::Started
+        } else {
+            FinalExecutionStatus::from_outcomes(&outcomes).unwrap_or(FinalExecutionStatus::Started)
+        };
+        let gas_burnt = outcomes.iter().map(|outcome| outcome.outcome.gas_burnt).sum();
+        let receipts_outcome = outcomes.split_off(1);
+        let transaction_outcome = outcomes.pop().unwrap();
+        Ok(SimulatedTransactionView {
+            final_outcome: FinalExecutionOutcomeView {
+                status,
+                transaction: result.transaction.into(),
+                transaction_outcome,
+                receipts_outcome,
+            },
+            gas_burnt,
+            state_changes: result.state_changes.into_iter().map(Into::into).collect(),
+            incomplete: result.incomplete,
+        })
+    }
+}
+
 impl Handler<NetworkViewClientMessages> for ViewClientActor {
     type Result = NetworkViewClientResponses;
 
@@ -1267,13 +1435,27 @@ impl Handler<NetworkViewClientMessages> for ViewClientActor {
 
                 NetworkViewClientResponses::AnnounceAccount(filtered_announce_accounts)
             }
-            NetworkViewClientMessages::EpochSyncRequest { epoch_id: _epoch_id } => {
-                // TODO #3488
-                NetworkViewClientResponses::NoResponse
+            NetworkViewClientMessages::EpochSyncRequest { epoch_id } => {
+                match self.get_epoch_sync_response(&epoch_id) {
+                    Ok(Some(response)) => NetworkViewClientResponses::EpochSyncResponse(response),
+                    Ok(None) => NetworkViewClientResponses::NoResponse,
+                    Err(e) => {
+                        error!(target: "sync", "Failed to build epoch sync response for {:?}: {}", epoch_id, e);
+                        NetworkViewClientResponses::NoResponse
+                    }
+                }
             }
-            NetworkViewClientMessages::EpochSyncFinalizationRequest { epoch_id: _epoch_id } => {
-                // TODO #3488
-                NetworkViewClientResponses::NoResponse
+            NetworkViewClientMessages::EpochSyncFinalizationRequest { epoch_id } => {
+                match self.get_epoch_sync_finalization_response(&epoch_id) {
+                    Ok(Some(response)) => {
+                        NetworkViewClientResponses::EpochSyncFinalizationResponse(response)
+                    }
+                    Ok(None) => NetworkViewClientResponses::NoResponse,
+                    Err(e) => {
+                        error!(target: "sync", "Failed to build epoch sync finalization response for {:?}: {}", epoch_id, e);
+                        NetworkViewClientResponses::NoResponse
+                    }
+                }
             }
         }
     }
@@ -1291,6 +1473,24 @@ impl Handler<GetGasPrice> for ViewClientActor {
     }
 }
 
+impl Handler<GetMisbehaviorEvidence> for ViewClientActor {
+    type Result = Result<Vec<MisbehaviorEvidenceView>, String>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetMisbehaviorEvidence, _ctx: &mut Self::Context) -> Self::Result {
+        self.chain
+            .store()
+            .get_misbehavior_evidence()
+            .map_err(|err| err.to_string())?
+            .into_iter()
+            .filter(|record| {
+                msg.account_id.as_ref().map_or(true, |account_id| &record.account_id == account_id)
+            })
+            .map(|record| MisbehaviorEvidenceView::try_from(record).map_err(|err| err.to_string()))
+            .collect()
+    }
+}
+
 /// Starts the View Client in a new arbiter (thread).
 pub fn start_view_client(
     validator_account_id: Option<AccountId>,
@@ -18,7 +18,8 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolVersion, UPGRADABILITY_FIX_PROTOCOL_VERSION};
 use near_primitives::views::{
-    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, SlashedValidatorView,
+    ValidatorKickoutView,
 };
 use near_store::{ColBlockInfo, ColEpochInfo, ColEpochStart, Store, StoreUpdate};
 
@@ -72,6 +73,23 @@ pub struct EpochManager {
     largest_final_height: BlockHeight,
 }
 
+/// Validators slashed in the current epoch, sorted by account id.
+fn slashed_validators_view(slashed: &HashMap<AccountId, SlashState>) -> Vec<SlashedValidatorView> {
+    let mut slashed_validators: Vec<SlashedValidatorView> = slashed
+        .iter()
+        .filter_map(|(account_id, slash_state)| match slash_state {
+            SlashState::DoubleSign | SlashState::Other => Some(SlashedValidatorView {
+                account_id: account_id.clone(),
+                is_double_sign: slash_state == &SlashState::DoubleSign,
+            }),
+            // Slashed in one of the previous epochs.
+            SlashState::AlreadySlashed => None,
+        })
+        .collect();
+    slashed_validators.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+    slashed_validators
+}
+
 impl EpochManager {
     pub fn new_from_genesis_config(
         store: Arc<Store>,
@@ -148,21 +166,23 @@ impl EpochManager {
         prev_epoch_id: &EpochId,
         prev_epoch_info: EpochInfo,
         epoch_id: &EpochId,
+        epoch_start_height: BlockHeight,
         epoch_info: EpochInfo,
         next_epoch_id: &EpochId,
         next_epoch_info: EpochInfo,
     ) -> Result<StoreUpdate, EpochError> {
         let mut store_update = self.store.store_update();
+        // The blocks that would record the starts of these epochs are never processed.
+        let prev_epoch_start_height = *prev_epoch_first_block_info.height();
+        self.save_epoch_start(&mut store_update, prev_epoch_id, prev_epoch_start_height)?;
+        self.save_epoch_start(&mut store_update, epoch_id, epoch_start_height)?;
         self.save_block_info(&mut store_update, prev_epoch_first_block_info)?;
         self.save_block_info(&mut store_update, prev_epoch_prev_last_block_info)?;
         self.save_block_info(&mut store_update, prev_epoch_last_block_info)?;
         self.save_epoch_info(&mut store_update, &prev_epoch_id, prev_epoch_info)?;
         self.save_epoch_info(&mut store_update, &epoch_id, epoch_info)?;
         self.save_epoch_info(&mut store_update, &next_epoch_id, next_epoch_info)?;
-        // TODO #3488
-        // put unreachable! here to avoid warnings
-        unreachable!();
-        // Ok(store_update)
+        Ok(store_update)
     }
 
     /// # Parameters
@@ -364,6 +384,7 @@ impl EpochManager {
             validator_kickout,
             validator_block_chunk_stats,
             next_version,
+            slashed: slashed_validators,
         })
     }
 
@@ -981,9 +1002,10 @@ impl EpochManager {
         // This ugly code arises because of the incompatible types between `block_tracker` in `EpochInfoAggregator`
         // and `validator_block_chunk_stats` in `EpochSummary`. Rust currently has no support for Either type
         // in std.
-        let (current_validators, next_epoch_id, all_proposals) = match &epoch_identifier {
+        let (current_validators, next_epoch_id, all_proposals, slashed) = match &epoch_identifier {
             ValidatorInfoIdentifier::EpochId(id) => {
                 let epoch_summary = self.get_epoch_validator_info(id)?;
+                let slashed = slashed_validators_view(&epoch_summary.slashed);
                 let cur_validators = cur_epoch_info
                     .validators_iter()
                     .enumerate()
@@ -999,8 +1021,9 @@ impl EpochManager {
                             .collect::<Vec<ShardId>>();
                         shards.sort();
                         let (account_id, public_key, stake) = info.destructure();
+                        let is_slashed = slashed.iter().any(|s| s.account_id == account_id);
                         Ok(CurrentEpochValidatorInfo {
-                            is_slashed: false, // currently there is no slashing
+                            is_slashed,
                             account_id,
                             public_key,
                             stake,
@@ -1014,9 +1037,11 @@ impl EpochManager {
                     cur_validators,
                     EpochId(epoch_summary.prev_epoch_last_block_hash),
                     epoch_summary.all_proposals.into_iter().map(Into::into).collect(),
+                    slashed,
                 )
             }
             ValidatorInfoIdentifier::BlockHash(ref h) => {
+                let slashed = slashed_validators_view(self.get_block_info(h)?.slashed());
                 let aggregator = self.get_and_update_epoch_info_aggregator(&epoch_id, h, true)?;
                 let cur_validators = cur_epoch_info
                     .validators_iter()
@@ -1033,8 +1058,9 @@ impl EpochManager {
                             .collect::<Vec<ShardId>>();
                         shards.sort();
                         let (account_id, public_key, stake) = info.destructure();
+                        let is_slashed = slashed.iter().any(|s| s.account_id == account_id);
                         Ok(CurrentEpochValidatorInfo {
-                            is_slashed: false, // currently there is no slashing
+                            is_slashed,
                             account_id,
                             public_key,
                             stake,
@@ -1049,6 +1075,7 @@ impl EpochManager {
                     cur_validators,
                     next_epoch_id,
                     aggregator.all_proposals.into_iter().map(|(_, p)| p.into()).collect(),
+                    slashed,
                 )
             }
         };
@@ -1095,6 +1122,7 @@ impl EpochManager {
             prev_epoch_kickout,
             epoch_start_height,
             epoch_height,
+            current_slashed: slashed,
         })
     }
 
@@ -4083,4 +4111,68 @@ mod tests {
             epoch_manager.epoch_validators_ordered_unique.cache_get(&epoch_id).unwrap().clone();
         assert_eq!(epoch_validators_unique, epoch_validators_unique_in_cache);
     }
+
+    #[test]
+    fn test_init_after_epoch_sync_saves_epoch_starts() {
+        let validators = vec![("test1".parse().unwrap(), 1_000_000)];
+        let mut epoch_manager = setup_default_epoch_manager(validators.clone(), 2, 1, 2, 0, 90, 60);
+        let h = hash_range(6);
+        record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+        for i in 1..6 {
+            record_block(&mut epoch_manager, h[i - 1], h[i], i as u64, vec![]);
+        }
+        let epoch_id = epoch_manager.get_epoch_id(&h[5]).unwrap();
+        let epoch_first_block = *epoch_manager.get_block_info(&h[5]).unwrap().epoch_first_block();
+        let prev_epoch_last_block =
+            *epoch_manager.get_block_info(&epoch_first_block).unwrap().prev_hash();
+        let prev_epoch_last_block_info =
+            epoch_manager.get_block_info(&prev_epoch_last_block).unwrap().clone();
+        let prev_epoch_id = prev_epoch_last_block_info.epoch_id().clone();
+        assert_ne!(prev_epoch_id, epoch_id);
+        let prev_epoch_prev_last_block_info =
+            epoch_manager.get_block_info(prev_epoch_last_block_info.prev_hash()).unwrap().clone();
+        let prev_epoch_first_block_info = epoch_manager
+            .get_block_info(prev_epoch_last_block_info.epoch_first_block())
+            .unwrap()
+            .clone();
+        let next_epoch_id = epoch_manager.get_next_epoch_id(&h[5]).unwrap();
+        let prev_epoch_start = epoch_manager.get_epoch_start_from_epoch_id(&prev_epoch_id).unwrap();
+        let epoch_start = epoch_manager.get_epoch_start_from_epoch_id(&epoch_id).unwrap();
+
+        let mut synced_epoch_manager = setup_default_epoch_manager(validators, 2, 1, 2, 0, 90, 60);
+        synced_epoch_manager
+            .init_after_epoch_sync(
+                prev_epoch_first_block_info,
+                prev_epoch_prev_last_block_info,
+                prev_epoch_last_block_info,
+                &prev_epoch_id,
+                epoch_manager.get_epoch_info(&prev_epoch_id).unwrap().clone(),
+                &epoch_id,
+                epoch_start,
+                epoch_manager.get_epoch_info(&epoch_id).unwrap().clone(),
+                &next_epoch_id,
+                epoch_manager.get_epoch_info(&next_epoch_id).unwrap().clone(),
+            )
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Read the epoch starts from the store rather than from the cache.
+        let mut synced_epoch_manager = EpochManager::new(
+            synced_epoch_manager.store.clone(),
+            synced_epoch_manager.config.clone(),
+            PROTOCOL_VERSION,
+            synced_epoch_manager.reward_calculator,
+            vec![stake("test1".parse().unwrap(), 1_000_000)],
+        )
+        .unwrap();
+        assert_eq!(
+            synced_epoch_manager.get_epoch_start_from_epoch_id(&prev_epoch_id).unwrap(),
+            prev_epoch_start
+        );
+        assert_eq!(
+            synced_epoch_manager.get_epoch_start_from_epoch_id(&epoch_id).unwrap(),
+            epoch_start
+        );
+    }
 }
@@ -0,0 +1,65 @@
+use near_primitives::types::{AccountId, ShardId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub type RpcShardTrackingResponse = near_client_primitives::types::ShardTrackingResponse;
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct RpcShardTrackingRequest {
+    #[serde(default)]
+    pub accounts: Vec<AccountId>,
+    #[serde(default)]
+    pub shards: Vec<ShardId>,
+}
+
+impl RpcShardTrackingRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        match value {
+            None => Ok(Self::default()),
+            value => Ok(crate::utils::parse_params::<RpcShardTrackingRequest>(value)?),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcShardTrackingError {
+    #[error("Shard {shard_id} does not exist")]
+    UnknownShard { shard_id: ShardId },
+    #[error("{error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::ChangeShardTrackingError> for RpcShardTrackingError {
+    fn from(error: near_client_primitives::types::ChangeShardTrackingError) -> Self {
+        match error {
+            near_client_primitives::types::ChangeShardTrackingError::UnknownShard(shard_id) => {
+                Self::UnknownShard { shard_id }
+            }
+            near_client_primitives::types::ChangeShardTrackingError::Failed(error_message) => {
+                Self::InternalError { error_message }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcShardTrackingError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcShardTrackingError> for crate::errors::RpcError {
+    fn from(error: RpcShardTrackingError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcShardTrackingError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -0,0 +1,81 @@
+use near_primitives::types::BlockHeightDelta;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How many heights below the head are returned if the request doesn't specify it.
+const DEFAULT_CONSENSUS_TIMELINE_HEIGHTS: BlockHeightDelta = 20;
+
+#[derive(Serialize, Deserialize)]
+pub struct RpcConsensusTimelineRequest {
+    #[serde(default = "default_num_heights")]
+    pub num_heights: BlockHeightDelta,
+}
+
+fn default_num_heights() -> BlockHeightDelta {
+    DEFAULT_CONSENSUS_TIMELINE_HEIGHTS
+}
+
+impl Default for RpcConsensusTimelineRequest {
+    fn default() -> Self {
+        Self { num_heights: default_num_heights() }
+    }
+}
+
+impl RpcConsensusTimelineRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        match value {
+            None => Ok(Self::default()),
+            value => Ok(crate::utils::parse_params::<RpcConsensusTimelineRequest>(value)?),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcConsensusTimelineResponse {
+    #[serde(flatten)]
+    pub consensus_timeline_response: near_client_primitives::types::ConsensusTimelineResponse,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcConsensusTimelineError {
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::ConsensusTimelineResponse>
+    for RpcConsensusTimelineResponse
+{
+    fn from(
+        consensus_timeline_response: near_client_primitives::types::ConsensusTimelineResponse,
+    ) -> Self {
+        Self { consensus_timeline_response }
+    }
+}
+
+impl From<actix::MailboxError> for RpcConsensusTimelineError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<String> for RpcConsensusTimelineError {
+    fn from(error_message: String) -> Self {
+        Self::InternalError { error_message }
+    }
+}
+
+impl From<RpcConsensusTimelineError> for crate::errors::RpcError {
+    fn from(error: RpcConsensusTimelineError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcConsensusTimelineError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
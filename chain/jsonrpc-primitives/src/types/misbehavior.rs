@@ -0,0 +1,64 @@
+use near_primitives::types::AccountId;
+use near_primitives::views::MisbehaviorEvidenceView;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RpcMisbehaviorEvidenceRequest {
+    #[serde(default)]
+    pub account_id: Option<AccountId>,
+}
+
+impl RpcMisbehaviorEvidenceRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        match value {
+            None => Ok(Self::default()),
+            value => Ok(crate::utils::parse_params::<RpcMisbehaviorEvidenceRequest>(value)?),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMisbehaviorEvidenceResponse {
+    pub evidence: Vec<MisbehaviorEvidenceView>,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcMisbehaviorEvidenceError {
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<Vec<MisbehaviorEvidenceView>> for RpcMisbehaviorEvidenceResponse {
+    fn from(evidence: Vec<MisbehaviorEvidenceView>) -> Self {
+        Self { evidence }
+    }
+}
+
+impl From<actix::MailboxError> for RpcMisbehaviorEvidenceError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<String> for RpcMisbehaviorEvidenceError {
+    fn from(error_message: String) -> Self {
+        Self::InternalError { error_message }
+    }
+}
+
+impl From<RpcMisbehaviorEvidenceError> for crate::errors::RpcError {
+    fn from(error: RpcMisbehaviorEvidenceError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcMisbehaviorEvidenceError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
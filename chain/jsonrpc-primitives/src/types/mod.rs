@@ -1,13 +1,17 @@
+pub mod admin;
 pub mod blocks;
 pub mod changes;
 pub mod chunks;
 pub mod config;
+pub mod consensus;
 pub mod gas_price;
 pub mod light_client;
+pub mod misbehavior;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
 pub mod sandbox;
+pub mod simulation;
 pub mod status;
 pub mod transactions;
 pub mod validator;
@@ -10,6 +10,10 @@ pub struct RpcQueryRequest {
     pub block_reference: near_primitives::types::BlockReference,
     #[serde(flatten)]
     pub request: near_primitives::views::QueryRequest,
+    /// Temporary state changes layered onto the state before executing a `call_function`
+    /// request. Not allowed for other request types.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_overrides: Option<near_primitives::views::StateOverrides>,
 }
 
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
@@ -47,6 +51,11 @@ pub enum RpcQueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("The page limit must be greater than zero")]
+    InvalidPageLimit {
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("Access key for public key {public_key} has never been observed on the node")]
     UnknownAccessKey {
         public_key: near_crypto::PublicKey,
@@ -76,6 +85,8 @@ pub struct RpcQueryResponse {
 pub enum QueryResponseKind {
     ViewAccount(near_primitives::views::AccountView),
     ViewCode(near_primitives::views::ContractCodeView),
+    // Must come before `ViewState`, which would also match pages.
+    ViewStatePage(near_primitives::views::ViewStatePageResult),
     ViewState(near_primitives::views::ViewStateResult),
     CallResult(near_primitives::views::CallResult),
     AccessKey(near_primitives::views::AccessKeyView),
@@ -151,10 +162,21 @@ impl RpcQueryRequest {
             RpcQueryRequest {
                 request,
                 block_reference: near_primitives::types::BlockReference::latest(),
+                state_overrides: None,
             }
         } else {
             crate::utils::parse_params::<RpcQueryRequest>(value)?
         };
+        if query_request.state_overrides.is_some()
+            && !matches!(
+                query_request.request,
+                near_primitives::views::QueryRequest::CallFunction { .. }
+            )
+        {
+            return Err(crate::errors::RpcParseError(
+                "State overrides are only supported for call_function requests".to_string(),
+            ));
+        }
         Ok(query_request)
     }
 }
@@ -210,6 +232,10 @@ impl From<near_client_primitives::types::QueryError> for RpcQueryError {
                 block_height,
                 block_hash,
             } => Self::TooLargeContractState { contract_account_id, block_height, block_hash },
+            near_client_primitives::types::QueryError::InvalidPageLimit {
+                block_height,
+                block_hash,
+            } => Self::InvalidPageLimit { block_height, block_hash },
         }
     }
 }
@@ -245,6 +271,9 @@ impl From<near_primitives::views::QueryResponseKind> for QueryResponseKind {
             near_primitives::views::QueryResponseKind::AccessKeyList(access_key_list) => {
                 Self::AccessKeyList(access_key_list)
             }
+            near_primitives::views::QueryResponseKind::ViewStatePage(view_state_page_result) => {
+                Self::ViewStatePage(view_state_page_result)
+            }
         }
     }
 }
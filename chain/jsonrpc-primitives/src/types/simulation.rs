@@ -0,0 +1,158 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use near_primitives::borsh::BorshDeserialize;
+use near_primitives::types::AccountId;
+
+/// Transaction to simulate: either a signed transaction encoded as for `broadcast_tx_commit`, or
+/// an unsigned one which is executed with a temporary full access key of the signer.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SimulatedTransaction {
+    Signed {
+        signed_tx_base64: String,
+    },
+    Unsigned {
+        signer_id: AccountId,
+        receiver_id: AccountId,
+        actions: Vec<near_primitives::views::ActionView>,
+    },
+}
+
+#[derive(Deserialize)]
+struct RpcSimulateTransactionParams {
+    #[serde(flatten)]
+    block_reference: near_primitives::types::BlockReference,
+    #[serde(flatten)]
+    transaction: SimulatedTransaction,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcSimulateTransactionRequest {
+    pub block_reference: near_primitives::types::BlockReference,
+    pub transaction: near_primitives::transaction::Transaction,
+    pub signature: Option<near_crypto::Signature>,
+}
+
+impl RpcSimulateTransactionRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        let params = crate::utils::parse_params::<RpcSimulateTransactionParams>(value)?;
+        let (transaction, signature) = match params.transaction {
+            SimulatedTransaction::Signed { signed_tx_base64 } => {
+                let bytes = near_primitives_core::serialize::from_base64(&signed_tx_base64)
+                    .map_err(|err| crate::errors::RpcParseError(err.to_string()))?;
+                let signed_transaction =
+                    near_primitives::transaction::SignedTransaction::try_from_slice(&bytes)
+                        .map_err(|err| {
+                            crate::errors::RpcParseError(format!(
+                                "Failed to decode transaction: {}",
+                                err
+                            ))
+                        })?;
+                (signed_transaction.transaction, Some(signed_transaction.signature))
+            }
+            SimulatedTransaction::Unsigned { signer_id, receiver_id, actions } => {
+                let actions = actions
+                    .into_iter()
+                    .map(near_primitives::transaction::Action::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| {
+                        crate::errors::RpcParseError(format!("Failed to parse actions: {}", err))
+                    })?;
+                let transaction = near_primitives::transaction::Transaction {
+                    signer_id,
+                    // The key and the nonce are set by the node.
+                    public_key: near_crypto::PublicKey::empty(near_crypto::KeyType::ED25519),
+                    nonce: 0,
+                    receiver_id,
+                    block_hash: Default::default(),
+                    actions,
+                };
+                (transaction, None)
+            }
+        };
+        Ok(Self { block_reference: params.block_reference, transaction, signature })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcSimulateTransactionResponse {
+    #[serde(flatten)]
+    pub simulated_transaction: near_primitives::views::SimulatedTransactionView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSimulateTransactionError {
+    #[error("Block not found: {error_message}")]
+    UnknownBlock {
+        #[serde(skip_serializing)]
+        error_message: String,
+    },
+    #[error("There are no fully synchronized blocks yet")]
+    NotSyncedYet,
+    #[error("The node does not track the shard {shard_id}")]
+    UnavailableShard { shard_id: near_primitives::types::ShardId },
+    #[error("Transaction is invalid: {error:?}")]
+    InvalidTransaction { error: near_primitives::errors::InvalidTxError },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::SimulateTransactionError> for RpcSimulateTransactionError {
+    fn from(error: near_client_primitives::types::SimulateTransactionError) -> Self {
+        match error {
+            near_client_primitives::types::SimulateTransactionError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            near_client_primitives::types::SimulateTransactionError::UnknownBlock(
+                error_message,
+            ) => Self::UnknownBlock { error_message },
+            near_client_primitives::types::SimulateTransactionError::NotSyncedYet => {
+                Self::NotSyncedYet
+            }
+            near_client_primitives::types::SimulateTransactionError::UnavailableShard(shard_id) => {
+                Self::UnavailableShard { shard_id }
+            }
+            near_client_primitives::types::SimulateTransactionError::InvalidTransaction(error) => {
+                Self::InvalidTransaction { error }
+            }
+            near_client_primitives::types::SimulateTransactionError::InternalError(
+                error_message,
+            ) => Self::InternalError { error_message },
+            near_client_primitives::types::SimulateTransactionError::Unreachable(
+                ref error_message,
+            ) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", &error_message);
+                near_metrics::inc_counter_vec(
+                    &crate::metrics::RPC_UNREACHABLE_ERROR_COUNT,
+                    &["RpcSimulateTransactionError"],
+                );
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcSimulateTransactionError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcSimulateTransactionError> for crate::errors::RpcError {
+    fn from(error: RpcSimulateTransactionError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSimulateTransactionError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -258,6 +258,23 @@ impl JsonRpcClient {
     ) -> RpcRequest<near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse> {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_protocol_config", request)
     }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_consensus_timeline(
+        &self,
+        request: near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_consensus_timeline", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_misbehavior_evidence(
+        &self,
+        request: near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_misbehavior_evidence", request)
+    }
 }
 
 fn create_client() -> Client {
@@ -1,10 +1,11 @@
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use actix::Addr;
 use actix_cors::Cors;
 use actix_web::{http, middleware, web, App, Error as HttpError, HttpResponse, HttpServer};
 use futures::Future;
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt};
 use prometheus;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -13,10 +14,11 @@ use tracing::info;
 
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    ClientActor, GetBlock, GetBlockProof, GetChunk, GetExecutionOutcome, GetGasPrice,
-    GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, Query, Status, TxStatus,
-    TxStatusError, ViewClientActor,
+    ChangeShardTracking, ClientActor, GetBlock, GetBlockProof, GetChunk, GetConsensusTimeline,
+    GetExecutionOutcome, GetGasPrice, GetMisbehaviorEvidence, GetNetworkInfo,
+    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
+    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, Query, SimulateTransaction,
+    Status, TxStatus, TxStatusError, ViewClientActor,
 };
 pub use near_jsonrpc_client as client;
 use near_jsonrpc_primitives::errors::RpcError;
@@ -36,6 +38,8 @@ use near_primitives::views::FinalExecutionOutcomeViewEnum;
 
 mod metrics;
 
+const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
     pub polling_interval: Duration,
@@ -63,6 +67,9 @@ impl Default for RpcLimitsConfig {
     }
 }
 
+/// RPC limits shared with the running server, changes take effect for the next request.
+pub type RpcLimitsHandle = Arc<RwLock<RpcLimitsConfig>>;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RpcConfig {
     pub addr: String,
@@ -72,6 +79,11 @@ pub struct RpcConfig {
     pub polling_config: RpcPollingConfig,
     #[serde(default)]
     pub limits_config: RpcLimitsConfig,
+    /// Enables `admin_*` methods which change the behaviour of the node, and
+    /// `EXPERIMENTAL_consensus_timeline` which is served by the client actor and competes with
+    /// block processing. They must not be reachable by untrusted clients.
+    #[serde(default)]
+    pub enable_admin_methods: bool,
 }
 
 impl Default for RpcConfig {
@@ -82,6 +94,7 @@ impl Default for RpcConfig {
             cors_allowed_origins: vec!["*".to_owned()],
             polling_config: Default::default(),
             limits_config: Default::default(),
+            enable_admin_methods: false,
         }
     }
 }
@@ -200,7 +213,9 @@ struct JsonRpcHandler {
     client_addr: Addr<ClientActor>,
     view_client_addr: Addr<ViewClientActor>,
     polling_config: RpcPollingConfig,
+    limits_config: RpcLimitsHandle,
     genesis_config: GenesisConfig,
+    enable_admin_methods: bool,
 }
 
 impl JsonRpcHandler {
@@ -246,6 +261,42 @@ impl JsonRpcHandler {
 
         let response: Result<Value, RpcError> = match request.method.as_ref() {
             // Handlers ordered alphabetically
+            "admin_shard_tracking" if self.enable_admin_methods => {
+                let shard_tracking =
+                    self.change_shard_tracking(ChangeShardTracking::default()).await?;
+                serde_json::to_value(shard_tracking)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "admin_start_tracking" if self.enable_admin_methods => {
+                let rpc_shard_tracking_request =
+                    near_jsonrpc_primitives::types::admin::RpcShardTrackingRequest::parse(
+                        request.params,
+                    )?;
+                let shard_tracking = self
+                    .change_shard_tracking(ChangeShardTracking {
+                        track_accounts: rpc_shard_tracking_request.accounts,
+                        track_shards: rpc_shard_tracking_request.shards,
+                        ..Default::default()
+                    })
+                    .await?;
+                serde_json::to_value(shard_tracking)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "admin_stop_tracking" if self.enable_admin_methods => {
+                let rpc_shard_tracking_request =
+                    near_jsonrpc_primitives::types::admin::RpcShardTrackingRequest::parse(
+                        request.params,
+                    )?;
+                let shard_tracking = self
+                    .change_shard_tracking(ChangeShardTracking {
+                        untrack_accounts: rpc_shard_tracking_request.accounts,
+                        untrack_shards: rpc_shard_tracking_request.shards,
+                        ..Default::default()
+                    })
+                    .await?;
+                serde_json::to_value(shard_tracking)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "block" => {
                 let rpc_block_request =
                     near_jsonrpc_primitives::types::blocks::RpcBlockRequest::parse(request.params)?;
@@ -318,6 +369,16 @@ impl JsonRpcHandler {
                 let query_response = self.query(rpc_query_request).await;
                 process_query_response(query_response)
             }
+            "simulate_transaction" => {
+                let rpc_simulate_transaction_request =
+                    near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest::parse(
+                        request.params,
+                    )?;
+                let simulate_transaction_response =
+                    self.simulate_transaction(rpc_simulate_transaction_request).await?;
+                serde_json::to_value(simulate_transaction_response)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "status" => {
                 let status_response = self.status().await?;
                 serde_json::to_value(status_response)
@@ -377,6 +438,16 @@ impl JsonRpcHandler {
                 serde_json::to_value(broadcast_tx_sync_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_consensus_timeline" if self.enable_admin_methods => {
+                let rpc_consensus_timeline_request =
+                    near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineRequest::parse(
+                        request.params,
+                    )?;
+                let consensus_timeline =
+                    self.consensus_timeline(rpc_consensus_timeline_request).await?;
+                serde_json::to_value(consensus_timeline)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_genesis_config" => {
                 let genesis_config = self.genesis_config().await;
                 serde_json::to_value(genesis_config)
@@ -390,6 +461,16 @@ impl JsonRpcHandler {
                 serde_json::to_value(rpc_light_client_execution_proof_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_misbehavior_evidence" => {
+                let rpc_misbehavior_evidence_request =
+                    near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceRequest::parse(
+                        request.params,
+                    )?;
+                let misbehavior_evidence =
+                    self.misbehavior_evidence(rpc_misbehavior_evidence_request).await?;
+                serde_json::to_value(misbehavior_evidence)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_protocol_config" => {
                 let rpc_protocol_config_request =
                     near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest::parse(
@@ -448,6 +529,36 @@ impl JsonRpcHandler {
         response
     }
 
+    async fn change_shard_tracking(
+        &self,
+        msg: ChangeShardTracking,
+    ) -> Result<
+        near_jsonrpc_primitives::types::admin::RpcShardTrackingResponse,
+        near_jsonrpc_primitives::types::admin::RpcShardTrackingError,
+    > {
+        Ok(self.client_addr.send(msg).await??)
+    }
+
+    async fn simulate_transaction(
+        &self,
+        request_data: near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionResponse,
+        near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionError,
+    > {
+        let simulated_transaction = self
+            .view_client_addr
+            .send(SimulateTransaction {
+                block_reference: request_data.block_reference,
+                transaction: request_data.transaction,
+                signature: request_data.signature,
+            })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::simulation::RpcSimulateTransactionResponse {
+            simulated_transaction,
+        })
+    }
+
     async fn send_tx_async(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionRequest,
@@ -776,7 +887,8 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::query::RpcQueryResponse,
         near_jsonrpc_primitives::types::query::RpcQueryError,
     > {
-        let query = Query::new(request_data.block_reference, request_data.request);
+        let mut query = Query::new(request_data.block_reference, request_data.request);
+        query.state_overrides = request_data.state_overrides;
         Ok(self.view_client_addr.send(query).await??.into())
     }
 
@@ -934,6 +1046,34 @@ impl JsonRpcHandler {
         Ok(self.client_addr.send(GetNetworkInfo {}).await??.into())
     }
 
+    async fn consensus_timeline(
+        &self,
+        request_data: near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineResponse,
+        near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineError,
+    > {
+        Ok(self
+            .client_addr
+            .send(GetConsensusTimeline { num_heights: request_data.num_heights })
+            .await??
+            .into())
+    }
+
+    async fn misbehavior_evidence(
+        &self,
+        request_data: near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceResponse,
+        near_jsonrpc_primitives::types::misbehavior::RpcMisbehaviorEvidenceError,
+    > {
+        Ok(self
+            .view_client_addr
+            .send(GetMisbehaviorEvidence { account_id: request_data.account_id })
+            .await??
+            .into())
+    }
+
     async fn gas_price(
         &self,
         request_data: near_jsonrpc_primitives::types::gas_price::RpcGasPriceRequest,
@@ -1128,14 +1268,28 @@ impl JsonRpcHandler {
 }
 
 fn rpc_handler(
-    message: web::Json<Message>,
+    mut payload: web::Payload,
     handler: web::Data<JsonRpcHandler>,
 ) -> impl Future<Output = Result<HttpResponse, HttpError>> {
     let response = async move {
-        let message = handler.process(message.0).await?;
+        let json_payload_max_size =
+            handler.limits_config.read().expect(POISONED_LOCK_ERR).json_payload_max_size;
+        let mut body = web::BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk?;
+            if body.len() + chunk.len() > json_payload_max_size {
+                return Ok(HttpResponse::PayloadTooLarge().finish());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let message = match serde_json::from_slice::<Message>(&body) {
+            Ok(message) => message,
+            Err(err) => return Ok(HttpResponse::BadRequest().body(err.to_string())),
+        };
+        let message = handler.process(message).await?;
         Ok(HttpResponse::Ok().json(&message))
     };
-    response.boxed()
+    response.boxed_local()
 }
 
 fn status_handler(
@@ -1219,8 +1373,33 @@ pub fn start_http(
     client_addr: Addr<ClientActor>,
     view_client_addr: Addr<ViewClientActor>,
 ) -> Vec<(&'static str, actix_web::dev::Server)> {
-    let RpcConfig { addr, prometheus_addr, cors_allowed_origins, polling_config, limits_config } =
-        config;
+    let limits_config = Arc::new(RwLock::new(config.limits_config.clone()));
+    start_http_with_limits_handle(
+        config,
+        limits_config,
+        genesis_config,
+        client_addr,
+        view_client_addr,
+    )
+}
+
+/// Same as [`start_http`], but the limits are taken from `limits_config` instead of
+/// `config.limits_config`, so that they can be changed while the server is running.
+pub fn start_http_with_limits_handle(
+    config: RpcConfig,
+    limits_config: RpcLimitsHandle,
+    genesis_config: GenesisConfig,
+    client_addr: Addr<ClientActor>,
+    view_client_addr: Addr<ViewClientActor>,
+) -> Vec<(&'static str, actix_web::dev::Server)> {
+    let RpcConfig {
+        addr,
+        prometheus_addr,
+        cors_allowed_origins,
+        polling_config,
+        enable_admin_methods,
+        ..
+    } = config;
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr);
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
@@ -1232,9 +1411,10 @@ pub fn start_http(
                 client_addr: client_addr.clone(),
                 view_client_addr: view_client_addr.clone(),
                 polling_config,
+                limits_config: limits_config.clone(),
                 genesis_config: genesis_config.clone(),
+                enable_admin_methods,
             })
-            .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::post().to(rpc_handler)))
             .service(
@@ -8,6 +8,8 @@ use near_actix_test_utils::run_actix;
 use near_crypto::{KeyType, PublicKey, Signature};
 use near_jsonrpc::client::new_client;
 use near_jsonrpc_client::ChunkId;
+use near_jsonrpc_primitives::errors::RpcErrorKind;
+use near_jsonrpc_primitives::types::consensus::RpcConsensusTimelineRequest;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
 use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
 use near_logger_utils::init_test_logger;
@@ -164,6 +166,7 @@ fn test_query_account() {
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
                 request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -171,6 +174,7 @@ fn test_query_account() {
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::BlockId(BlockId::Height(0)),
                 request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -178,6 +182,7 @@ fn test_query_account() {
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::BlockId(BlockId::Hash(block_hash)),
                 request: QueryRequest::ViewAccount { account_id: "test".parse().unwrap() },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -227,6 +232,7 @@ fn test_query_access_keys() {
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
                 request: QueryRequest::ViewAccessKeyList { account_id: "test".parse().unwrap() },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -278,6 +284,7 @@ fn test_query_access_key() {
                         .parse()
                         .unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -303,6 +310,7 @@ fn test_query_state() {
                     account_id: "test".parse().unwrap(),
                     prefix: vec![].into(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -328,6 +336,7 @@ fn test_query_call_function() {
                     method_name: "method".to_string(),
                     args: vec![].into(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -353,6 +362,7 @@ fn test_query_contract_code() {
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
                 request: QueryRequest::ViewCode { account_id: "test".parse().unwrap() },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -564,6 +574,7 @@ fn test_query_view_account_non_existing_account_must_return_error() {
                 request: QueryRequest::ViewAccount {
                     account_id: "invalidaccount".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -586,6 +597,7 @@ fn test_view_access_key_non_existing_account_id_and_public_key_must_return_error
                     account_id: "\u{0}\u{0}\u{0}\u{0}\u{0}9".parse().unwrap(),
                     public_key: "99999999999999999999999999999999999999999999".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -613,6 +625,7 @@ fn test_call_function_non_existing_account_method_name() {
                         .parse()
                         .unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -634,6 +647,7 @@ fn test_view_access_key_list_non_existing_account() {
                 request: QueryRequest::ViewAccessKeyList {
                     account_id: "\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{c}\u{0}\u{0}\u{0}\u{0}\u{0}\u{0},".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -656,6 +670,7 @@ fn test_view_state_non_existing_account_invalid_prefix() {
                     account_id: "\u{0}\u{0}\u{0}\u{0}\u{0}\u{4}\u{0}\u{0}\u{0}\u{8}\u{0}\u{0}\u{0}\u{0}\u{0}eeeeeeeeeeeeeeeeeeeeeeeeeeeee".parse().unwrap(),
                     prefix: "eeeeeeeeeeee".as_bytes().to_vec().into(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -687,6 +702,100 @@ fn test_validators_non_existing_block_hash() {
     });
 }
 
+/// Consensus timeline is only served when admin methods are enabled.
+#[test]
+fn test_consensus_timeline_requires_admin_methods() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let res =
+            client.EXPERIMENTAL_consensus_timeline(RpcConsensusTimelineRequest::default()).await;
+        assert!(res.is_err());
+    });
+}
+
+#[test]
+fn test_shard_tracking_requires_admin_methods() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let res: Result<serde_json::Value, _> = test_utils::call_method(
+            &client.client,
+            &client.server_addr,
+            "admin_shard_tracking",
+            json!(null),
+        )
+        .await;
+        assert!(res.is_err());
+    });
+}
+
+#[test]
+fn test_admin_shard_tracking() {
+    init_test_logger();
+
+    run_actix(async {
+        let (_, addr) = test_utils::start_all_with_admin_methods(test_utils::NodeType::Validator);
+        let client = new_client(&format!("http://{}", addr));
+
+        actix::spawn(async move {
+            let shard_tracking: serde_json::Value = test_utils::call_method(
+                &client.client,
+                &client.server_addr,
+                "admin_shard_tracking",
+                json!(null),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                shard_tracking,
+                json!({
+                    "tracked_accounts": [],
+                    "tracked_shards": [],
+                    "effective_epoch_height": null,
+                })
+            );
+
+            let err = test_utils::call_method::<serde_json::Value>(
+                &client.client,
+                &client.server_addr,
+                "admin_start_tracking",
+                json!({ "shards": [100] }),
+            )
+            .await
+            .unwrap_err();
+            match err.error_struct {
+                Some(RpcErrorKind::HandlerError(error)) => {
+                    assert_eq!(
+                        error,
+                        json!({ "name": "UNKNOWN_SHARD", "info": { "shard_id": 100 } })
+                    );
+                }
+                error_struct => panic!("unexpected error {:?}", error_struct),
+            }
+
+            // The test runtime does not support changing the tracked shards, the error it returns
+            // is passed to the client as is.
+            let err = test_utils::call_method::<serde_json::Value>(
+                &client.client,
+                &client.server_addr,
+                "admin_start_tracking",
+                json!({ "shards": [0] }),
+            )
+            .await
+            .unwrap_err();
+            match err.error_struct {
+                Some(RpcErrorKind::InternalError(error)) => {
+                    let error_message = error["info"]["error_message"].as_str().unwrap();
+                    assert!(
+                        error_message.contains("Tracked shards are defined by validators"),
+                        "{}",
+                        error_message
+                    );
+                }
+                error_struct => panic!("unexpected error {:?}", error_struct),
+            }
+            System::current().stop();
+        });
+    });
+}
+
 #[test]
 fn test_get_chunk_with_object_in_params() {
     test_with_client!(test_utils::NodeType::NonValidator, client, async move {
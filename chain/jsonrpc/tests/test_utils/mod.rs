@@ -25,10 +25,24 @@ pub fn start_all(node_type: NodeType) -> (Addr<ViewClientActor>, String) {
     start_all_with_validity_period_and_no_epoch_sync(node_type, 100, false)
 }
 
+/// Same as `start_all`, but also serves the `admin_*` methods.
+pub fn start_all_with_admin_methods(node_type: NodeType) -> (Addr<ViewClientActor>, String) {
+    start_all_with_rpc_config(node_type, 100, false, true)
+}
+
 pub fn start_all_with_validity_period_and_no_epoch_sync(
     node_type: NodeType,
     transaction_validity_period: NumBlocks,
     enable_doomslug: bool,
+) -> (Addr<ViewClientActor>, String) {
+    start_all_with_rpc_config(node_type, transaction_validity_period, enable_doomslug, false)
+}
+
+fn start_all_with_rpc_config(
+    node_type: NodeType,
+    transaction_validity_period: NumBlocks,
+    enable_doomslug: bool,
+    enable_admin_methods: bool,
 ) -> (Addr<ViewClientActor>, String) {
     let (client_addr, view_client_addr) = setup_no_network_with_validity_period_and_no_epoch_sync(
         vec!["test1".parse().unwrap(), "test2".parse().unwrap()],
@@ -45,7 +59,7 @@ pub fn start_all_with_validity_period_and_no_epoch_sync(
     let addr = format!("127.0.0.1:{}", open_port());
 
     start_http(
-        RpcConfig::new(&addr),
+        RpcConfig { enable_admin_methods, ..RpcConfig::new(&addr) },
         TEST_GENESIS_CONFIG.clone(),
         client_addr.clone(),
         view_client_addr.clone(),
@@ -777,6 +777,14 @@ pub struct Ban {
     pub ban_reason: ReasonForBan,
 }
 
+/// Replaces the blacklist of the peer manager. Active connections to peers on the new
+/// blacklist are dropped.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetBlacklist {
+    pub blacklist: HashMap<IpAddr, BlockedPorts>,
+}
+
 /// Messages from PeerManager to Peer
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
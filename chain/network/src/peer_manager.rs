@@ -37,7 +37,7 @@ use crate::types::{
     NetworkViewClientResponses, OutboundTcpConnect, PeerIdOrHash, PeerList, PeerManagerRequest,
     PeerMessage, PeerRequest, PeerResponse, PeerType, PeersRequest, PeersResponse, Ping, Pong,
     QueryPeerStats, RawRoutedMessage, ReasonForBan, RoutedMessage, RoutedMessageBody,
-    RoutedMessageFrom, SendMessage, StateResponseInfo, SyncData, Unregister,
+    RoutedMessageFrom, SendMessage, SetBlacklist, StateResponseInfo, SyncData, Unregister,
 };
 use crate::types::{
     EdgeList, KnownPeerState, NetworkClientMessages, NetworkConfig, NetworkRequests,
@@ -1839,6 +1839,30 @@ impl Handler<Ban> for PeerManagerActor {
     }
 }
 
+impl Handler<SetBlacklist> for PeerManagerActor {
+    type Result = ();
+
+    #[perf]
+    fn handle(&mut self, msg: SetBlacklist, _ctx: &mut Self::Context) {
+        #[cfg(feature = "delay_detector")]
+        let _d = DelayDetector::new("set blacklist".into());
+        info!(target: "network", "Blacklist: {:?}", msg.blacklist);
+        self.config.blacklist = msg.blacklist;
+        for (peer_id, active_peer) in self.active_peers.iter() {
+            if active_peer
+                .full_peer_info
+                .peer_info
+                .addr
+                .as_ref()
+                .map_or(false, |addr| self.is_blacklisted(addr))
+            {
+                debug!(target: "network", "Stop active connection to blacklisted peer: {:?}", peer_id);
+                active_peer.addr.do_send(PeerManagerRequest::UnregisterPeer);
+            }
+        }
+    }
+}
+
 impl Handler<PeersRequest> for PeerManagerActor {
     type Result = PeerList;
 
@@ -99,6 +99,19 @@ impl TransactionPool {
         }
     }
 
+    /// Returns an iterator over all transactions in the pool, without any particular order.
+    pub fn iter_transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.transactions.values().flatten()
+    }
+
+    /// Returns the transaction with the given hash if it's in the pool.
+    pub fn get_transaction(&self, tx_hash: &CryptoHash) -> Option<&SignedTransaction> {
+        if !self.unique_transactions.contains(tx_hash) {
+            return None;
+        }
+        self.iter_transactions().find(|tx| &tx.get_hash() == tx_hash)
+    }
+
     pub fn len(&self) -> usize {
         self.unique_transactions.len()
     }
@@ -314,6 +327,28 @@ mod tests {
         assert_eq!(nonces, (1..=5).map(|a| vec![a, a + 20]).flatten().collect::<Vec<u64>>());
     }
 
+    #[test]
+    fn test_iter_transactions() {
+        let mut transactions = generate_transactions("alice.near", "alice.near", 1, 3);
+        transactions.extend(generate_transactions("bob.near", "bob.near", 1, 2));
+        let mut pool = TransactionPool::new();
+        for tx in transactions.clone() {
+            pool.insert_transaction(tx);
+        }
+
+        let mut pool_txs = pool.iter_transactions().cloned().collect::<Vec<_>>();
+        pool_txs.sort_by_key(|tx| tx.get_hash());
+        let mut expected_txs = transactions.clone();
+        expected_txs.sort_by_key(|tx| tx.get_hash());
+        assert_eq!(pool_txs, expected_txs);
+
+        let tx_hash = transactions[3].get_hash();
+        assert_eq!(pool.get_transaction(&tx_hash), Some(&transactions[3]));
+        pool.remove_transactions(&transactions[3..4]);
+        assert_eq!(pool.get_transaction(&tx_hash), None);
+        assert_eq!(pool.iter_transactions().count(), 4);
+    }
+
     /// Add transactions of nonce from 1..=3 and transactions with nonce 21..=31. Pull 10.
     /// Then try to get another 10.
     #[test]
@@ -389,6 +389,115 @@ pub(crate) async fn collect_transactions(
     }
 }
 
+/// Conditions of a `/search/transactions` request, matched against the
+/// transactions produced by `collect_transactions`.
+pub(crate) struct TransactionsFilter {
+    pub operator: crate::models::Operator,
+    pub transaction_identifier: Option<crate::models::TransactionIdentifier>,
+    pub account_identifier: Option<crate::models::AccountIdentifier>,
+    pub operation_type: Option<crate::models::OperationType>,
+}
+
+impl TransactionsFilter {
+    pub(crate) fn matches(&self, transaction: &crate::models::Transaction) -> bool {
+        let mut conditions = vec![];
+        if let Some(transaction_identifier) = &self.transaction_identifier {
+            conditions.push(transaction.transaction_identifier == *transaction_identifier);
+        }
+        if let Some(account_identifier) = &self.account_identifier {
+            // Without a sub-account in the condition, operations on any
+            // sub-account of the account match.
+            conditions.push(transaction.operations.iter().any(|operation| {
+                operation.account.address == account_identifier.address
+                    && (account_identifier.sub_account.is_none()
+                        || operation.account.sub_account == account_identifier.sub_account)
+            }));
+        }
+        if let Some(operation_type) = self.operation_type {
+            conditions.push(
+                transaction.operations.iter().any(|operation| operation.type_ == operation_type),
+            );
+        }
+        match self.operator {
+            crate::models::Operator::And => conditions.into_iter().all(|condition| condition),
+            crate::models::Operator::Or => {
+                conditions.is_empty() || conditions.into_iter().any(|condition| condition)
+            }
+        }
+    }
+}
+
+/// Appends the transactions of the block that match the filter.
+pub(crate) async fn collect_matching_transactions(
+    genesis: &Arc<Genesis>,
+    view_client_addr: &Addr<ViewClientActor>,
+    block: &near_primitives::views::BlockView,
+    filter: &TransactionsFilter,
+    transactions: &mut Vec<crate::models::BlockTransaction>,
+) -> Result<(), crate::errors::ErrorKind> {
+    let block_identifier: crate::models::BlockIdentifier = (&block.header).into();
+    let block_transactions =
+        collect_transactions(Arc::clone(genesis), Addr::clone(view_client_addr), block).await?;
+    transactions.extend(
+        block_transactions.into_iter().filter(|transaction| filter.matches(transaction)).map(
+            |transaction| crate::models::BlockTransaction {
+                block_identifier: block_identifier.clone(),
+                transaction,
+            },
+        ),
+    );
+    Ok(())
+}
+
+/// Finds the block whose state changes are attributed to the given Rosetta
+/// transaction identifier, so that a search by transaction does not need to
+/// scan blocks. Execution outcomes are looked up by the account they were
+/// executed on, so for `tx:` identifiers the signer has to be provided.
+/// Returns `None` if the block cannot be determined this way.
+pub(crate) async fn find_transaction_block_hash(
+    view_client_addr: &Addr<ViewClientActor>,
+    transaction_identifier: &crate::models::TransactionIdentifier,
+    signer_id: Option<&near_primitives::types::AccountId>,
+) -> Result<Option<near_primitives::hash::CryptoHash>, crate::errors::ErrorKind> {
+    let parse_hash = |hash: &str| {
+        hash.parse::<near_primitives::hash::CryptoHash>().map_err(|err| {
+            crate::errors::ErrorKind::InvalidInput(format!(
+                "Failed to parse Transaction Hash: {}",
+                err
+            ))
+        })
+    };
+    let id = if let Some(hash) = transaction_identifier.hash.strip_prefix("tx:") {
+        let sender_id = match signer_id {
+            Some(signer_id) => signer_id.clone(),
+            None => return Ok(None),
+        };
+        near_primitives::types::TransactionOrReceiptId::Transaction {
+            transaction_hash: parse_hash(hash)?,
+            sender_id,
+        }
+    } else if let Some(hash) = transaction_identifier.hash.strip_prefix("receipt:") {
+        let receipt_id = parse_hash(hash)?;
+        let receipt = view_client_addr
+            .send(near_client::GetReceipt { receipt_id })
+            .await?
+            .map_err(|err| crate::errors::ErrorKind::InternalError(err.to_string()))?;
+        match receipt {
+            Some(receipt) => near_primitives::types::TransactionOrReceiptId::Receipt {
+                receipt_id,
+                receiver_id: receipt.receiver_id,
+            },
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+    match view_client_addr.send(near_client::GetExecutionOutcome { id }).await? {
+        Ok(response) => Ok(Some(response.outcome_proof.block_hash)),
+        Err(_) => Ok(None),
+    }
+}
+
 /// This is used as a common denominator for matching Rosetta Operations to
 /// and from NEAR Actions (see From and TryFrom implementations).
 ///
@@ -1577,4 +1686,73 @@ mod tests {
             Err(crate::errors::ErrorKind::InvalidInput(_))
         ));
     }
+
+    #[test]
+    fn test_transactions_filter() {
+        let transfer = |account: &str, sub_account: Option<crate::models::SubAccount>| {
+            crate::models::Operation {
+                operation_identifier: crate::models::OperationIdentifier::new(&[]),
+                related_operations: None,
+                type_: crate::models::OperationType::Transfer,
+                status: Some(crate::models::OperationStatusKind::Success),
+                account: crate::models::AccountIdentifier {
+                    address: account.parse().unwrap(),
+                    sub_account: sub_account.map(Into::into),
+                },
+                amount: Some(crate::models::Amount::from_yoctonear(1)),
+                metadata: None,
+            }
+        };
+        let transaction = crate::models::Transaction {
+            transaction_identifier: crate::models::TransactionIdentifier {
+                hash: "tx:123".to_string(),
+            },
+            operations: vec![
+                transfer("alice.near", None),
+                transfer("bob.near", Some(crate::models::SubAccount::Locked)),
+            ],
+            metadata: crate::models::TransactionMetadata {
+                type_: crate::models::TransactionType::Transaction,
+            },
+        };
+        let filter = |operator,
+                      hash: Option<&str>,
+                      account: Option<crate::models::AccountIdentifier>,
+                      operation_type| TransactionsFilter {
+            operator,
+            transaction_identifier: hash
+                .map(|hash| crate::models::TransactionIdentifier { hash: hash.to_string() }),
+            account_identifier: account,
+            operation_type,
+        };
+        let and = crate::models::Operator::And;
+        let or = crate::models::Operator::Or;
+        let locked_bob = crate::models::AccountIdentifier {
+            address: "bob.near".parse().unwrap(),
+            sub_account: Some(crate::models::SubAccount::Locked.into()),
+        };
+        let liquid_for_storage_alice = crate::models::AccountIdentifier {
+            address: "alice.near".parse().unwrap(),
+            sub_account: Some(crate::models::SubAccount::LiquidBalanceForStorage.into()),
+        };
+
+        assert!(filter(and, None, None, None).matches(&transaction));
+        assert!(filter(or, None, None, None).matches(&transaction));
+        assert!(filter(and, Some("tx:123"), None, None).matches(&transaction));
+        assert!(!filter(and, Some("tx:456"), None, None).matches(&transaction));
+        assert!(filter(and, None, Some("bob.near".parse().unwrap()), None).matches(&transaction));
+        assert!(filter(and, None, Some(locked_bob), None).matches(&transaction));
+        assert!(!filter(and, None, Some(liquid_for_storage_alice), None).matches(&transaction));
+        assert!(!filter(and, Some("tx:123"), None, Some(crate::models::OperationType::Stake))
+            .matches(&transaction));
+        assert!(filter(or, Some("tx:123"), None, Some(crate::models::OperationType::Stake))
+            .matches(&transaction));
+        assert!(!filter(
+            or,
+            Some("tx:456"),
+            Some("carol.near".parse().unwrap()),
+            Some(crate::models::OperationType::Stake)
+        )
+        .matches(&transaction));
+    }
 }
@@ -4,6 +4,8 @@ pub struct RosettaRpcConfig {
     pub cors_allowed_origins: Vec<String>,
     #[serde(default)]
     pub limits: RosettaRpcLimitsConfig,
+    #[serde(default)]
+    pub contract_balances: RosettaRpcContractBalancesConfig,
 }
 
 impl Default for RosettaRpcConfig {
@@ -12,6 +14,7 @@ impl Default for RosettaRpcConfig {
             addr: "0.0.0.0:3040".to_owned(),
             cors_allowed_origins: vec!["*".to_owned()],
             limits: RosettaRpcLimitsConfig::default(),
+            contract_balances: RosettaRpcContractBalancesConfig::default(),
         }
     }
 }
@@ -32,3 +35,11 @@ impl Default for RosettaRpcLimitsConfig {
         Self { input_payload_max_size: 10 * 1024 * 1024 }
     }
 }
+
+/// Balances held by staking pool and lockup contracts are reported by calling
+/// view methods of these contracts, which is opt-in since it executes contract
+/// code on the node.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct RosettaRpcContractBalancesConfig {
+    pub enabled: bool,
+}
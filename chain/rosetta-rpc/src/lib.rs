@@ -27,6 +27,13 @@ mod utils;
 pub const BASE_PATH: &str = "";
 pub const API_VERSION: &str = "1.4.4";
 
+/// Maximum number of events returned by a single `/events/blocks` call.
+const MAX_EVENTS_LIMIT: u64 = 100;
+/// Maximum number of transactions requested from a single `/search/transactions` call.
+const MAX_SEARCH_LIMIT: u64 = 100;
+/// Maximum number of blocks scanned by a single `/search/transactions` call.
+const MAX_SEARCH_SCANNED_BLOCKS: u64 = 100;
+
 /// Get List of Available Networks
 ///
 /// This endpoint returns a list of NetworkIdentifiers that the Rosetta server
@@ -330,10 +337,20 @@ async fn block_transaction_details(
 /// requests with unique AccountIdentifiers. It is also possible to perform a
 /// historical balance lookup (if the server supports it) by passing in an
 /// optional BlockIdentifier.
+///
+/// Besides the LIQUID, LOCKED (staked by a validator) and
+/// LIQUID_BALANCE_FOR_STORAGE balances of the account itself, balances held for
+/// the account by a staking pool (STAKING_POOL_STAKED, STAKING_POOL_UNSTAKED) or
+/// by its lockup contract (LOCKUP_LOCKED, LOCKUP_LIQUID) can be requested with
+/// the contract account in `metadata.contract_account_id`. These are read by
+/// calling view methods of the contract at the same block, which has to be
+/// enabled in the node config (`rosetta_rpc.contract_balances.enabled`). A
+/// lockup contract has to be owned by the account.
 async fn account_balance(
     genesis: web::Data<Arc<Genesis>>,
     client_addr: web::Data<Addr<ClientActor>>,
     view_client_addr: web::Data<Addr<ViewClientActor>>,
+    contract_balances_config: web::Data<crate::config::RosettaRpcContractBalancesConfig>,
     body: Json<models::AccountBalanceRequest>,
 ) -> Result<Json<models::AccountBalanceResponse>, models::Error> {
     let Json(models::AccountBalanceRequest {
@@ -368,33 +385,82 @@ async fn account_balance(
         .await?
         .map_err(|err| errors::ErrorKind::NotFound(err.to_string()))?;
 
-    let (block_hash, block_height, account_info) =
-        match crate::utils::query_account(block_id, account_identifier.address, &view_client_addr)
-            .await
-        {
-            Ok(account_info_response) => account_info_response,
-            Err(crate::errors::ErrorKind::NotFound(_)) => (
-                block.header.hash,
-                block.header.height,
-                near_primitives::account::Account::new(0, 0, Default::default(), 0).into(),
-            ),
-            Err(err) => return Err(err.into()),
-        };
+    let (block_hash, block_height, account_info) = match crate::utils::query_account(
+        block_id,
+        account_identifier.address.clone(),
+        &view_client_addr,
+    )
+    .await
+    {
+        Ok(account_info_response) => account_info_response,
+        Err(crate::errors::ErrorKind::NotFound(_)) => (
+            block.header.hash,
+            block.header.height,
+            near_primitives::account::Account::new(0, 0, Default::default(), 0).into(),
+        ),
+        Err(err) => return Err(err.into()),
+    };
 
     let account_balances = crate::utils::RosettaAccountBalances::from_account(
         account_info,
         &genesis.config.runtime_config,
     );
 
-    let balance = if let Some(sub_account) = account_identifier.sub_account {
-        match sub_account.address {
+    let balance = match account_identifier.sub_account {
+        None => account_balances.liquid,
+        Some(sub_account) => match sub_account.address {
+            crate::models::SubAccount::Liquid => account_balances.liquid,
             crate::models::SubAccount::Locked => account_balances.locked,
             crate::models::SubAccount::LiquidBalanceForStorage => {
                 account_balances.liquid_for_storage
             }
-        }
-    } else {
-        account_balances.liquid
+            crate::models::SubAccount::StakingPoolStaked
+            | crate::models::SubAccount::StakingPoolUnstaked
+            | crate::models::SubAccount::LockupLocked
+            | crate::models::SubAccount::LockupLiquid => {
+                if !contract_balances_config.enabled {
+                    return Err(errors::ErrorKind::InvalidInput(
+                        "Balances held by contracts are not enabled on this node".to_string(),
+                    )
+                    .into());
+                }
+                let contract_account_id = sub_account
+                    .metadata
+                    .map(|metadata| metadata.contract_account_id)
+                    .ok_or_else(|| {
+                        errors::ErrorKind::InvalidInput(format!(
+                            "{:?} sub-account requires contract_account_id in metadata",
+                            sub_account.address
+                        ))
+                    })?;
+                let (method_name, args) = sub_account
+                    .address
+                    .contract_view(&account_identifier.address)
+                    .expect("contract sub-accounts have a view method");
+                // Query the same block the account was read at, so that all
+                // the balances of the response are consistent.
+                let contract_block_id: near_primitives::types::BlockReference =
+                    near_primitives::types::BlockId::Hash(block_hash).into();
+                if let Some(owner_method_name) = sub_account.address.contract_owner_view() {
+                    crate::utils::verify_contract_owner(
+                        contract_block_id.clone(),
+                        &contract_account_id,
+                        owner_method_name,
+                        &account_identifier.address,
+                        &view_client_addr,
+                    )
+                    .await?;
+                }
+                crate::utils::query_contract_balance(
+                    contract_block_id,
+                    contract_account_id,
+                    method_name,
+                    args,
+                    &view_client_addr,
+                )
+                .await?
+            }
+        },
     };
 
     Ok(Json(models::AccountBalanceResponse {
@@ -407,20 +473,49 @@ async fn account_balance(
 }
 
 #[api_v2_operation]
-/// Get All Mempool Transactions (not implemented)
+/// Get All Mempool Transactions
 ///
 /// Get all Transaction Identifiers in the mempool
 ///
-/// NOTE: The mempool is short-lived, so it is currently not implemented.
+/// NOTE: Only transactions in the pools of the shards tracked by this node are
+/// returned. The mempool is short-lived, so transactions may disappear between
+/// the calls.
 async fn mempool(
-    _client_addr: web::Data<Addr<ClientActor>>,
-    _body: Json<models::NetworkRequest>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    body: Json<models::NetworkRequest>,
 ) -> Result<Json<models::MempoolResponse>, models::Error> {
-    Ok(Json(models::MempoolResponse { transaction_identifiers: vec![] }))
+    let Json(models::NetworkRequest { network_identifier }) = body;
+
+    // TODO: reduce copy-paste
+    let status = client_addr
+        .send(near_client::Status { is_health_check: false })
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    if status.chain_id != network_identifier.network {
+        return Err(models::Error {
+            code: 2,
+            message: "Wrong network (chain id)".to_string(),
+            retriable: true,
+        });
+    }
+
+    let transactions = client_addr
+        .send(near_client::GetPoolTransactions {})
+        .await?
+        .map_err(errors::ErrorKind::InternalError)?;
+
+    Ok(Json(models::MempoolResponse {
+        transaction_identifiers: transactions
+            .iter()
+            .map(|transaction| models::TransactionIdentifier {
+                hash: format!("tx:{}", transaction.hash.to_base()),
+            })
+            .collect(),
+    }))
 }
 
 #[api_v2_operation]
-/// Get a Mempool Transaction (not implemented)
+/// Get a Mempool Transaction
 ///
 /// Get a transaction in the mempool by its Transaction Identifier. This is a
 /// separate request than fetching a block transaction (/block/transaction)
@@ -431,13 +526,272 @@ async fn mempool(
 /// endpoint, it is ok that returned transactions are only estimates of what may
 /// actually be included in a block.
 ///
-/// NOTE: The mempool is short-lived, so this method does not make a lot of
-/// sense to be implemented.
+/// The operations are the ones the transaction actions translate to (the same
+/// as /construction/parse returns), fees and receipts are not included.
 async fn mempool_transaction(
-    _client_addr: web::Data<Addr<ClientActor>>,
-    _body: Json<models::MempoolTransactionRequest>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    body: Json<models::MempoolTransactionRequest>,
 ) -> Result<Json<models::MempoolTransactionResponse>, models::Error> {
-    Err(errors::ErrorKind::InternalError("Not implemented yet".to_string()).into())
+    let Json(models::MempoolTransactionRequest { network_identifier, transaction_identifier }) =
+        body;
+
+    // TODO: reduce copy-paste
+    let status = client_addr
+        .send(near_client::Status { is_health_check: false })
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    if status.chain_id != network_identifier.network {
+        return Err(models::Error {
+            code: 2,
+            message: "Wrong network (chain id)".to_string(),
+            retriable: true,
+        });
+    }
+
+    let tx_hash: near_primitives::hash::CryptoHash = transaction_identifier
+        .hash
+        .strip_prefix("tx:")
+        .unwrap_or(&transaction_identifier.hash)
+        .parse()
+        .map_err(|err| {
+            errors::ErrorKind::InvalidInput(format!("Failed to parse Transaction Hash: {}", err))
+        })?;
+    let transaction = client_addr
+        .send(near_client::GetPoolTransaction { tx_hash })
+        .await?
+        .map_err(errors::ErrorKind::InternalError)?
+        .ok_or_else(|| {
+            errors::ErrorKind::NotFound(format!(
+                "Transaction {} is not in the mempool",
+                transaction_identifier.hash
+            ))
+        })?;
+
+    let near_primitives::transaction::Transaction {
+        actions,
+        signer_id: sender_account_id,
+        receiver_id: receiver_account_id,
+        ..
+    } = transaction.transaction;
+    let near_actions =
+        crate::adapters::NearActions { sender_account_id, receiver_account_id, actions };
+
+    Ok(Json(models::MempoolTransactionResponse {
+        transaction: models::Transaction {
+            transaction_identifier: models::TransactionIdentifier {
+                hash: format!("tx:{}", tx_hash.to_base()),
+            },
+            operations: near_actions.into(),
+            metadata: models::TransactionMetadata { type_: models::TransactionType::Transaction },
+        },
+    }))
+}
+
+#[api_v2_operation]
+/// Get a range of BlockEvents
+///
+/// `/events/blocks` allows the caller to query a sequence of BlockEvents
+/// indicating which blocks were added and removed from storage to reach the
+/// current state. Following BlockEvents allows lightweight clients to update
+/// their state without needing to implement their own syncing logic.
+///
+/// NEAR only reports final blocks, which are never removed, so the stream
+/// consists of BLOCK_ADDED events, and the sequence of an event is the height
+/// of its block. Heights without a block are skipped.
+async fn events_blocks(
+    genesis: web::Data<Arc<Genesis>>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    view_client_addr: web::Data<Addr<ViewClientActor>>,
+    body: Json<models::EventsBlocksRequest>,
+) -> Result<Json<models::EventsBlocksResponse>, models::Error> {
+    let Json(models::EventsBlocksRequest { network_identifier, offset, limit }) = body;
+
+    // TODO: reduce copy-paste
+    let status = client_addr
+        .send(near_client::Status { is_health_check: false })
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    if status.chain_id != network_identifier.network {
+        return Err(models::Error {
+            code: 2,
+            message: "Wrong network (chain id)".to_string(),
+            retriable: true,
+        });
+    }
+
+    let limit = crate::utils::parse_limit(limit, MAX_EVENTS_LIMIT)?;
+    let final_height = crate::utils::get_final_block_height(&view_client_addr).await?;
+    let genesis_height = genesis.config.genesis_height;
+    let start_height = match offset {
+        Some(offset) => std::cmp::max(crate::utils::parse_offset(offset)?, genesis_height),
+        None => std::cmp::max(final_height.saturating_sub(limit - 1), genesis_height),
+    };
+    let end_height = std::cmp::min(start_height.saturating_add(limit - 1), final_height);
+
+    let blocks = futures::future::join_all((start_height..=end_height).map(|height| {
+        view_client_addr
+            .send(near_client::GetBlock(near_primitives::types::BlockId::Height(height).into()))
+    }))
+    .await;
+    let mut events = vec![];
+    for block in blocks {
+        let block = match block? {
+            Ok(block) => block,
+            // Heights without a block are not an error, the block was just skipped.
+            Err(near_client_primitives::types::GetBlockError::UnknownBlock { .. }) => continue,
+            Err(err) => return Err(errors::ErrorKind::InternalError(err.to_string()).into()),
+        };
+        let block_identifier: models::BlockIdentifier = (&block.header).into();
+        events.push(models::BlockEvent {
+            sequence: block_identifier.index,
+            block_identifier,
+            type_: models::BlockEventType::BlockAdded,
+        });
+    }
+
+    Ok(Json(models::EventsBlocksResponse {
+        max_sequence: final_height.try_into().unwrap(),
+        events,
+    }))
+}
+
+#[api_v2_operation]
+/// Search for Transactions
+///
+/// `/search/transactions` allows the caller to search for transactions that
+/// meet certain conditions. Some conditions include matching a transaction
+/// hash, containing an operation with a certain account identifier, or
+/// containing an operation of a certain type.
+///
+/// Blocks are scanned from `max_block` down to genesis, so searching deep into
+/// the history requires an archival node. `offset` is the number of blocks
+/// below `max_block` that were already scanned, and every call scans at most
+/// a fixed number of blocks, so a call may return fewer than `limit` (or no)
+/// transactions while `next_offset` is still populated. All matching
+/// transactions of a scanned block are returned, so `limit` may be exceeded
+/// by the transactions of the last block. `total_count` is the number of
+/// transactions returned, since counting all the matches would require
+/// scanning the whole chain.
+///
+/// Searching by a `tx:` transaction identifier together with the signer
+/// account identifier, or by a `receipt:` identifier, looks up the block that
+/// executed it directly instead of scanning.
+async fn search_transactions(
+    genesis: web::Data<Arc<Genesis>>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    view_client_addr: web::Data<Addr<ViewClientActor>>,
+    body: Json<models::SearchTransactionsRequest>,
+) -> Result<Json<models::SearchTransactionsResponse>, models::Error> {
+    let Json(models::SearchTransactionsRequest {
+        network_identifier,
+        operator,
+        max_block,
+        offset,
+        limit,
+        transaction_identifier,
+        account_identifier,
+        type_,
+    }) = body;
+
+    // TODO: reduce copy-paste
+    let status = client_addr
+        .send(near_client::Status { is_health_check: false })
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    if status.chain_id != network_identifier.network {
+        return Err(models::Error {
+            code: 2,
+            message: "Wrong network (chain id)".to_string(),
+            retriable: true,
+        });
+    }
+
+    let limit = crate::utils::parse_limit(limit, MAX_SEARCH_LIMIT)?;
+    let offset = offset.map(crate::utils::parse_offset).transpose()?.unwrap_or(0);
+    let final_height = crate::utils::get_final_block_height(&view_client_addr).await?;
+    let max_block = match max_block {
+        Some(max_block) => std::cmp::min(crate::utils::parse_offset(max_block)?, final_height),
+        None => final_height,
+    };
+    let filter = crate::adapters::TransactionsFilter {
+        operator: operator.unwrap_or(models::Operator::And),
+        transaction_identifier,
+        account_identifier,
+        operation_type: type_,
+    };
+
+    let mut transactions = vec![];
+
+    if let Some(transaction_identifier) = &filter.transaction_identifier {
+        let signer_id = filter.account_identifier.as_ref().map(|account| &account.address);
+        let block_hash = crate::adapters::find_transaction_block_hash(
+            &view_client_addr,
+            transaction_identifier,
+            signer_id,
+        )
+        .await?;
+        if let Some(block_hash) = block_hash {
+            let block = view_client_addr
+                .send(near_client::GetBlock(
+                    near_primitives::types::BlockId::Hash(block_hash).into(),
+                ))
+                .await?
+                .map_err(|err| errors::ErrorKind::NotFound(err.to_string()))?;
+            // With the `and` operator no other block can match.
+            if filter.operator == models::Operator::And {
+                if block.header.height <= max_block {
+                    crate::adapters::collect_matching_transactions(
+                        &genesis,
+                        &view_client_addr,
+                        &block,
+                        &filter,
+                        &mut transactions,
+                    )
+                    .await?;
+                }
+                return Ok(Json(models::SearchTransactionsResponse {
+                    total_count: transactions.len().try_into().unwrap(),
+                    transactions,
+                    next_offset: None,
+                }));
+            }
+        }
+    }
+
+    let genesis_height = genesis.config.genesis_height;
+    let mut scanned_blocks = offset;
+    while transactions.len() < limit as usize && scanned_blocks - offset < MAX_SEARCH_SCANNED_BLOCKS
+    {
+        let height = match max_block.checked_sub(scanned_blocks) {
+            Some(height) if height >= genesis_height => height,
+            _ => break,
+        };
+        scanned_blocks += 1;
+        let block = match view_client_addr
+            .send(near_client::GetBlock(near_primitives::types::BlockId::Height(height).into()))
+            .await?
+        {
+            Ok(block) => block,
+            // The block at this height was skipped.
+            Err(near_client_primitives::types::GetBlockError::UnknownBlock { .. }) => continue,
+            Err(err) => return Err(errors::ErrorKind::InternalError(err.to_string()).into()),
+        };
+        crate::adapters::collect_matching_transactions(
+            &genesis,
+            &view_client_addr,
+            &block,
+            &filter,
+            &mut transactions,
+        )
+        .await?;
+    }
+
+    let exhausted = max_block < genesis_height || max_block - genesis_height < scanned_blocks;
+    Ok(Json(models::SearchTransactionsResponse {
+        total_count: transactions.len().try_into().unwrap(),
+        transactions,
+        next_offset: if exhausted { None } else { Some(scanned_blocks.try_into().unwrap()) },
+    }))
 }
 
 #[api_v2_operation]
@@ -887,7 +1241,8 @@ pub fn start_rosetta_rpc(
     client_addr: Addr<ClientActor>,
     view_client_addr: Addr<ViewClientActor>,
 ) -> actix_web::dev::Server {
-    let crate::config::RosettaRpcConfig { addr, cors_allowed_origins, limits } = config;
+    let crate::config::RosettaRpcConfig { addr, cors_allowed_origins, limits, contract_balances } =
+        config;
     HttpServer::new(move || {
         let json_config = web::JsonConfig::default()
             .limit(limits.input_payload_max_size)
@@ -907,6 +1262,7 @@ pub fn start_rosetta_rpc(
             .data(Arc::clone(&genesis))
             .data(client_addr.clone())
             .data(view_client_addr.clone())
+            .data(contract_balances.clone())
             .wrap(get_cors(&cors_allowed_origins))
             .wrap_api()
             .service(web::resource("/network/list").route(web::post().to(network_list)))
@@ -922,6 +1278,10 @@ pub fn start_rosetta_rpc(
             .service(
                 web::resource("/mempool/transaction").route(web::post().to(mempool_transaction)),
             )
+            .service(web::resource("/events/blocks").route(web::post().to(events_blocks)))
+            .service(
+                web::resource("/search/transactions").route(web::post().to(search_transactions)),
+            )
             .service(
                 web::resource("/construction/derive").route(web::post().to(construction_derive)),
             )
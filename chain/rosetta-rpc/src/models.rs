@@ -161,6 +161,30 @@ pub(crate) struct Block {
      * pub metadata: Option<serde_json::Value>, */
 }
 
+/// BlockEvent represents the addition or removal of a BlockIdentifier from
+/// storage. Streaming BlockEvents allows lightweight clients to update their
+/// own state without needing to implement their own syncing logic.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BlockEvent {
+    /// sequence is the unique identifier of a BlockEvent within the context of
+    /// a NetworkIdentifier.
+    pub sequence: i64,
+
+    pub block_identifier: BlockIdentifier,
+
+    #[serde(rename = "type")]
+    pub type_: BlockEventType,
+}
+
+/// BlockEventType determines if a BlockEvent represents the addition or
+/// removal of a block.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BlockEventType {
+    BlockAdded,
+    BlockRemoved,
+}
+
 /// The block_identifier uniquely identifies a block in a particular network.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
 pub(crate) struct BlockIdentifier {
@@ -222,6 +246,15 @@ pub(crate) struct BlockTransactionResponse {
     pub transaction: Transaction,
 }
 
+/// BlockTransaction contains a populated Transaction and the BlockIdentifier
+/// that contains it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BlockTransaction {
+    pub block_identifier: BlockIdentifier,
+
+    pub transaction: Transaction,
+}
+
 /// ConstructionDeriveRequest is passed to the `/construction/derive`
 /// endpoint. Network is provided in the request because some blockchains
 /// have different address formats for different networks.
@@ -551,6 +584,39 @@ impl actix_web::ResponseError for Error {
     }
 }
 
+/// EventsBlocksRequest is utilized to fetch a sequence of BlockEvents
+/// indicating which blocks were added and removed from storage to reach the
+/// current state.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct EventsBlocksRequest {
+    pub network_identifier: NetworkIdentifier,
+
+    /// offset is the offset into the event stream to sync events from. If this
+    /// field is not populated, we return the limit events backwards from tip.
+    /// If this is set to 0, we start from the beginning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+
+    /// limit is the maximum number of events to fetch in one call. The
+    /// implementation may return <= limit events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+/// EventsBlocksResponse contains an ordered collection of BlockEvents and the
+/// max retrievable sequence.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct EventsBlocksResponse {
+    /// max_sequence is the maximum available sequence number to fetch.
+    pub max_sequence: i64,
+
+    /// events is an array of BlockEvents indicating the order to add and
+    /// remove blocks to maintain a canonical view of blockchain state.
+    /// Lightweight clients can use this event stream to update state without
+    /// implementing their own block syncing logic.
+    pub events: Vec<BlockEvent>,
+}
+
 /// A MempoolResponse contains all transaction identifiers in the mempool for a
 /// particular network_identifier.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
@@ -845,6 +911,16 @@ pub(crate) struct OperationStatus {
     pub successful: bool,
 }
 
+/// Operator is used by query-related endpoints to determine how to apply
+/// conditions. If this field is not populated, the default and value will be
+/// used.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Operator {
+    Or,
+    And,
+}
+
 /// When fetching data by BlockIdentifier, it may be possible to only specify
 /// the index or hash. If neither property is specified, it is assumed that the
 /// client is making a request at the current block.
@@ -891,16 +967,132 @@ pub(crate) struct Peer {
      * pub metadata: Option<serde_json::Value>, */
 }
 
+/// SearchTransactionsRequest is used to search for transactions matching a set
+/// of provided conditions in canonical blocks.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct SearchTransactionsRequest {
+    pub network_identifier: NetworkIdentifier,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<Operator>,
+
+    /// max_block is the largest block index to consider when searching for
+    /// transactions. If this field is not populated, the current block is
+    /// considered the max_block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_block: Option<i64>,
+
+    /// offset is the offset into the query result to start returning
+    /// transactions. If any search conditions are changed, the query offset
+    /// will change and you must restart your search iteration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+
+    /// limit is the maximum number of transactions to return in one call. The
+    /// implementation may return <= limit transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_identifier: Option<TransactionIdentifier>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_identifier: Option<AccountIdentifier>,
+
+    /// type is the network-specific operation type.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<OperationType>,
+    /* Rosetta Spec also optionally provides:
+     *
+     * pub coin_identifier: Option<CoinIdentifier>,
+     * pub currency: Option<Currency>,
+     * pub status: Option<String>,
+     * pub address: Option<String>,
+     * pub success: Option<bool>, */
+}
+
+/// SearchTransactionsResponse contains an ordered collection of
+/// BlockTransactions that match the query in SearchTransactionsRequest. These
+/// BlockTransactions are sorted from most recent block to oldest block.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct SearchTransactionsResponse {
+    /// transactions is an array of BlockTransactions sorted by most recent
+    /// BlockIdentifier (meaning that transactions in recent blocks appear
+    /// first).
+    pub transactions: Vec<BlockTransaction>,
+
+    /// total_count is the number of results for a given search. Callers
+    /// typically use this value to concurrently fetch results by offset or to
+    /// display a virtual page number associated with results.
+    pub total_count: i64,
+
+    /// next_offset is the next offset to use when paginating through
+    /// transaction results. If this field is not populated, there are no more
+    /// transactions to query.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<i64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub(crate) enum SubAccount {
+    /// The same balance as the one of the account without a sub-account.
+    Liquid,
     LiquidBalanceForStorage,
     Locked,
+    /// Balance the account has staked in the staking pool contract given in
+    /// the sub-account metadata.
+    StakingPoolStaked,
+    /// Balance the account has unstaked but not yet withdrawn from the staking
+    /// pool contract given in the sub-account metadata.
+    StakingPoolUnstaked,
+    /// Balance still locked in the lockup contract (given in the sub-account
+    /// metadata) owned by the account.
+    LockupLocked,
+    /// Balance the owner can already transfer out of the lockup contract
+    /// (given in the sub-account metadata).
+    LockupLiquid,
+}
+
+impl SubAccount {
+    /// View method of the contract and its arguments that report the balance
+    /// of the sub-account, for sub-accounts whose balance is held by a
+    /// contract.
+    pub(crate) fn contract_view(
+        &self,
+        account_id: &near_primitives::types::AccountId,
+    ) -> Option<(&'static str, Vec<u8>)> {
+        let account_args = || {
+            serde_json::to_vec(&serde_json::json!({ "account_id": account_id }))
+                .expect("JSON serialization never fails")
+        };
+        match self {
+            Self::Liquid | Self::LiquidBalanceForStorage | Self::Locked => None,
+            Self::StakingPoolStaked => Some(("get_account_staked_balance", account_args())),
+            Self::StakingPoolUnstaked => Some(("get_account_unstaked_balance", account_args())),
+            Self::LockupLocked => Some(("get_locked_amount", b"{}".to_vec())),
+            Self::LockupLiquid => Some(("get_liquid_owners_balance", b"{}".to_vec())),
+        }
+    }
+
+    /// View method of the contract that reports the account it belongs to,
+    /// for sub-accounts whose balance view is not already scoped to the
+    /// account (the staking pool views take the account id as argument).
+    pub(crate) fn contract_owner_view(&self) -> Option<&'static str> {
+        match self {
+            Self::LockupLocked | Self::LockupLiquid => Some("get_owner_account_id"),
+            Self::Liquid
+            | Self::LiquidBalanceForStorage
+            | Self::Locked
+            | Self::StakingPoolStaked
+            | Self::StakingPoolUnstaked => None,
+        }
+    }
 }
 
 impl From<SubAccount> for crate::models::SubAccountIdentifier {
     fn from(sub_account: SubAccount) -> Self {
-        crate::models::SubAccountIdentifier { address: sub_account }
+        crate::models::SubAccountIdentifier { address: sub_account, metadata: None }
     }
 }
 
@@ -912,14 +1104,20 @@ pub(crate) struct SubAccountIdentifier {
     /// The SubAccount address may be a cryptographic value or some other
     /// identifier (ex: bonded) that uniquely specifies a SubAccount.
     pub address: SubAccount,
-    /* Rosetta Spec also optionally provides:
-     *
-     * /// If the SubAccount address is not sufficient to uniquely specify a
-     * /// SubAccount, any other identifying information can be stored here.  It is
-     * /// important to note that two SubAccounts with identical addresses but
-     * /// differing metadata will not be considered equal by clients.
-     * #[serde(skip_serializing_if = "Option::is_none")]
-     * pub metadata: Option<serde_json::Value>, */
+
+    /// If the SubAccount address is not sufficient to uniquely specify a
+    /// SubAccount, any other identifying information can be stored here.  It is
+    /// important to note that two SubAccounts with identical addresses but
+    /// differing metadata will not be considered equal by clients.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<SubAccountMetadata>,
+}
+
+/// Has to be specified for sub-accounts whose balance is held by a contract.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct SubAccountMetadata {
+    /// The staking pool or lockup contract account.
+    pub contract_account_id: near_primitives::types::AccountId,
 }
 
 /// In blockchains with sharded state, the SubNetworkIdentifier is required to
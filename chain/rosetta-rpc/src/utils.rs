@@ -1,3 +1,5 @@
+use std::convert::TryInto;
+
 use actix::Addr;
 use futures::StreamExt;
 
@@ -334,6 +336,156 @@ pub(crate) async fn query_account(
     }
 }
 
+pub(crate) fn parse_limit(
+    limit: Option<i64>,
+    max_limit: u64,
+) -> Result<u64, crate::errors::ErrorKind> {
+    match limit {
+        None => Ok(max_limit),
+        Some(limit) if limit > 0 => Ok(std::cmp::min(limit as u64, max_limit)),
+        Some(limit) => Err(crate::errors::ErrorKind::InvalidInput(format!(
+            "limit must be positive, got {}",
+            limit
+        ))),
+    }
+}
+
+pub(crate) fn parse_offset(offset: i64) -> Result<u64, crate::errors::ErrorKind> {
+    offset.try_into().map_err(|_| {
+        crate::errors::ErrorKind::InvalidInput(format!(
+            "expected a non-negative number, got {}",
+            offset
+        ))
+    })
+}
+
+pub(crate) async fn get_final_block_height(
+    view_client_addr: &Addr<ViewClientActor>,
+) -> Result<near_primitives::types::BlockHeight, crate::errors::ErrorKind> {
+    let final_block = view_client_addr
+        .send(near_client::GetBlock(near_primitives::types::BlockReference::Finality(
+            near_primitives::types::Finality::Final,
+        )))
+        .await?
+        .map_err(|err| crate::errors::ErrorKind::InternalError(err.to_string()))?;
+    Ok(final_block.header.height)
+}
+
+/// Calls a view method of the contract and returns the raw result.
+async fn query_contract_view(
+    block_id: near_primitives::types::BlockReference,
+    contract_account_id: &near_primitives::types::AccountId,
+    method_name: &str,
+    args: Vec<u8>,
+    view_client_addr: &Addr<ViewClientActor>,
+) -> Result<Vec<u8>, crate::errors::ErrorKind> {
+    let query = near_client::Query::new(
+        block_id,
+        near_primitives::views::QueryRequest::CallFunction {
+            account_id: contract_account_id.clone(),
+            method_name: method_name.to_string(),
+            args: args.into(),
+        },
+    );
+    match view_client_addr.send(query).await? {
+        Ok(query_response) => match query_response.kind {
+            near_primitives::views::QueryResponseKind::CallResult(call_result) => {
+                Ok(call_result.result)
+            }
+            _ => Err(crate::errors::ErrorKind::InternalInvariantError(format!(
+                "queried CallFunction, but received {:?}.",
+                query_response.kind
+            ))),
+        },
+        Err(err) => match err {
+            near_client_primitives::types::QueryError::UnknownAccount { .. }
+            | near_client_primitives::types::QueryError::NoContractCode { .. } => {
+                Err(crate::errors::ErrorKind::NotFound(err.to_string()))
+            }
+            near_client_primitives::types::QueryError::ContractExecutionError { .. } => {
+                Err(crate::errors::ErrorKind::InvalidInput(format!(
+                    "{} does not implement {}: {}",
+                    contract_account_id, method_name, err
+                )))
+            }
+            _ => Err(crate::errors::ErrorKind::InternalError(err.to_string())),
+        },
+    }
+}
+
+/// Parses a JSON string returned by a view method (e.g. `"1000"` or
+/// `"alice.near"`).
+fn parse_contract_view_result<T: std::str::FromStr>(
+    contract_account_id: &near_primitives::types::AccountId,
+    method_name: &str,
+    result: &[u8],
+) -> Result<T, crate::errors::ErrorKind> {
+    serde_json::from_slice::<String>(result).ok().and_then(|value| value.parse().ok()).ok_or_else(
+        || {
+            crate::errors::ErrorKind::InvalidInput(format!(
+                "{} of {} returned an unexpected result: {}",
+                method_name,
+                contract_account_id,
+                String::from_utf8_lossy(result)
+            ))
+        },
+    )
+}
+
+/// Calls a view method of the contract that returns a balance encoded as a
+/// JSON string (e.g. `"1000"`), which is how staking pool and lockup contracts
+/// report balances.
+pub(crate) async fn query_contract_balance(
+    block_id: near_primitives::types::BlockReference,
+    contract_account_id: near_primitives::types::AccountId,
+    method_name: &str,
+    args: Vec<u8>,
+    view_client_addr: &Addr<ViewClientActor>,
+) -> Result<near_primitives::types::Balance, crate::errors::ErrorKind> {
+    let result =
+        query_contract_view(block_id, &contract_account_id, method_name, args, view_client_addr)
+            .await?;
+    parse_contract_view_result(&contract_account_id, method_name, &result)
+}
+
+/// Makes sure the contract holding the balance of a sub-account belongs to
+/// `account_id`, so that the balance of a lockup contract cannot be reported
+/// for an account other than its owner.
+pub(crate) async fn verify_contract_owner(
+    block_id: near_primitives::types::BlockReference,
+    contract_account_id: &near_primitives::types::AccountId,
+    owner_method_name: &str,
+    account_id: &near_primitives::types::AccountId,
+    view_client_addr: &Addr<ViewClientActor>,
+) -> Result<(), crate::errors::ErrorKind> {
+    let result = query_contract_view(
+        block_id,
+        contract_account_id,
+        owner_method_name,
+        b"{}".to_vec(),
+        view_client_addr,
+    )
+    .await?;
+    check_contract_owner(contract_account_id, owner_method_name, &result, account_id)
+}
+
+fn check_contract_owner(
+    contract_account_id: &near_primitives::types::AccountId,
+    owner_method_name: &str,
+    result: &[u8],
+    account_id: &near_primitives::types::AccountId,
+) -> Result<(), crate::errors::ErrorKind> {
+    let owner_account_id: near_primitives::types::AccountId =
+        parse_contract_view_result(contract_account_id, owner_method_name, result)?;
+    if &owner_account_id != account_id {
+        return Err(crate::errors::ErrorKind::InvalidInput(format!(
+            "{} belongs to {}, not to {}",
+            contract_account_id, owner_account_id, account_id
+        )));
+    }
+    Ok(())
+}
+
 pub(crate) async fn query_accounts(
     block_id: &near_primitives::types::BlockReference,
     account_ids: impl Iterator<Item = &near_primitives::types::AccountId>,
@@ -445,3 +597,76 @@ where
         self.known_value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staking_pool_sub_account_balance() {
+        let account_id: near_primitives::types::AccountId = "alice.near".parse().unwrap();
+        let pool_account_id: near_primitives::types::AccountId = "pool.near".parse().unwrap();
+        for (sub_account, expected_method_name) in vec![
+            (crate::models::SubAccount::StakingPoolStaked, "get_account_staked_balance"),
+            (crate::models::SubAccount::StakingPoolUnstaked, "get_account_unstaked_balance"),
+        ] {
+            let (method_name, args) = sub_account.contract_view(&account_id).unwrap();
+            assert_eq!(method_name, expected_method_name);
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&args).unwrap(),
+                serde_json::json!({ "account_id": "alice.near" })
+            );
+            // The balance views are scoped to the account, so the pool owner is not checked.
+            assert_eq!(sub_account.contract_owner_view(), None);
+        }
+        let balance: near_primitives::types::Balance =
+            parse_contract_view_result(&pool_account_id, "get_account_staked_balance", b"\"42\"")
+                .unwrap();
+        assert_eq!(balance, 42);
+        assert!(matches!(
+            parse_contract_view_result::<near_primitives::types::Balance>(
+                &pool_account_id,
+                "get_account_staked_balance",
+                b"42"
+            ),
+            Err(crate::errors::ErrorKind::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_lockup_sub_account_balance() {
+        let account_id: near_primitives::types::AccountId = "alice.near".parse().unwrap();
+        let lockup_account_id: near_primitives::types::AccountId =
+            "0123456789abcdef0123456789abcdef01234567.lockup.near".parse().unwrap();
+        for (sub_account, expected_method_name) in vec![
+            (crate::models::SubAccount::LockupLocked, "get_locked_amount"),
+            (crate::models::SubAccount::LockupLiquid, "get_liquid_owners_balance"),
+        ] {
+            let (method_name, args) = sub_account.contract_view(&account_id).unwrap();
+            assert_eq!(method_name, expected_method_name);
+            assert_eq!(args, b"{}".to_vec());
+            assert_eq!(sub_account.contract_owner_view(), Some("get_owner_account_id"));
+        }
+        let balance: near_primitives::types::Balance =
+            parse_contract_view_result(&lockup_account_id, "get_locked_amount", b"\"1000\"")
+                .unwrap();
+        assert_eq!(balance, 1000);
+
+        check_contract_owner(
+            &lockup_account_id,
+            "get_owner_account_id",
+            b"\"alice.near\"",
+            &account_id,
+        )
+        .unwrap();
+        assert!(matches!(
+            check_contract_owner(
+                &lockup_account_id,
+                "get_owner_account_id",
+                b"\"bob.near\"",
+                &account_id,
+            ),
+            Err(crate::errors::ErrorKind::InvalidInput(_))
+        ));
+    }
+}
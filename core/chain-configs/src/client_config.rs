@@ -1,5 +1,6 @@
 //! Chain Client Configuration
 use std::cmp::min;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
@@ -53,6 +54,11 @@ pub struct ClientConfig {
     pub header_sync_expected_height_per_second: u64,
     /// How long to wait for a response during state sync
     pub state_sync_timeout: Duration,
+    /// Number of threads to validate and apply state parts during state sync.
+    pub state_sync_threads: usize,
+    /// Directory with state parts dumped by `state-viewer dump_state_parts`.
+    /// If set, state sync takes the state from there before requesting it from peers.
+    pub state_sync_dump_dir: Option<PathBuf>,
     /// Minimum number of peers to start syncing.
     pub min_num_peers: usize,
     /// Period between logging summary information.
@@ -132,6 +138,8 @@ impl ClientConfig {
             header_sync_progress_timeout: Duration::from_secs(2),
             header_sync_stall_ban_timeout: Duration::from_secs(30),
             state_sync_timeout: Duration::from_secs(TEST_STATE_SYNC_TIMEOUT),
+            state_sync_threads: 1,
+            state_sync_dump_dir: None,
             header_sync_expected_height_per_second: 1,
             min_num_peers: 1,
             log_summary_period: Duration::from_secs(10),
@@ -1,6 +1,7 @@
 mod client_config;
 mod genesis_config;
 pub mod genesis_validate;
+pub mod records_dump;
 
 pub use client_config::{ClientConfig, LogSummaryStyle, TEST_STATE_SYNC_TIMEOUT};
 pub use genesis_config::{
@@ -0,0 +1,312 @@
+//! Compact binary format to dump the state of a shard as a stream of raw trie key-values.
+//!
+//! A file starts with `MAGIC`, the format version and the shard id as little-endian `u32` and
+//! `u64`. Each record is the `u32` length of the key, the key, the `u32` length of the value and
+//! the value. The file ends with `u32::MAX` in place of a key length, the number of records as
+//! `u64` and the sha256 of all preceding bytes, so that truncated or corrupted dumps are detected
+//! while reading.
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::digest::Digest;
+
+use near_primitives::state_record::StateRecordKind;
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
+use near_primitives::types::{AccountId, ShardId};
+
+pub const MAGIC: &[u8; 8] = b"NEARSTRD";
+pub const FORMAT_VERSION: u32 = 1;
+const END_MARKER: u32 = u32::MAX;
+
+/// Genesis config matching the dumped records, stored next to the shard files.
+pub const GENESIS_CONFIG_FILE: &str = "genesis_config.json";
+
+/// Path of the file with the records of `shard_id` in the dump directory `dir`.
+pub fn shard_records_path(dir: &Path, shard_id: ShardId) -> PathBuf {
+    dir.join(format!("shard_{}.records", shard_id))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Selects the raw trie key-values to dump.
+#[derive(Debug, Clone, Default)]
+pub struct RecordsFilter {
+    /// Keep only records of accounts starting with one of the prefixes. Empty keeps all accounts.
+    pub account_prefixes: Vec<String>,
+    /// Keep only records of these kinds. `None` keeps all kinds.
+    pub kinds: Option<HashSet<StateRecordKind>>,
+}
+
+impl RecordsFilter {
+    /// Delayed receipts are kept regardless of `account_prefixes`: dropping some of them would
+    /// leave gaps in the delayed receipts queue of the shard.
+    pub fn accepts(&self, key: &[u8]) -> bool {
+        let kind = match StateRecordKind::from_raw_key(key) {
+            Some(kind) => kind,
+            None => return false,
+        };
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&kind) {
+                return false;
+            }
+        }
+        if self.account_prefixes.is_empty() || kind == StateRecordKind::DelayedReceipt {
+            return true;
+        }
+        match parse_account_id_from_raw_key(key) {
+            Ok(Some(account_id)) => self.accepts_account(&account_id),
+            _ => false,
+        }
+    }
+
+    /// Whether records of `account_id` are kept by `account_prefixes`, regardless of `kinds`.
+    pub fn accepts_account(&self, account_id: &AccountId) -> bool {
+        self.account_prefixes.is_empty()
+            || self
+                .account_prefixes
+                .iter()
+                .any(|prefix| account_id.as_ref().starts_with(prefix.as_str()))
+    }
+}
+
+pub struct RecordsWriter<W: Write> {
+    writer: W,
+    digest: sha2::Sha256,
+    num_records: u64,
+}
+
+impl RecordsWriter<BufWriter<File>> {
+    pub fn create(path: &Path, shard_id: ShardId) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), shard_id)
+    }
+}
+
+impl<W: Write> RecordsWriter<W> {
+    pub fn new(writer: W, shard_id: ShardId) -> io::Result<Self> {
+        let mut records_writer = Self { writer, digest: sha2::Sha256::new(), num_records: 0 };
+        records_writer.write_hashed(MAGIC)?;
+        records_writer.write_hashed(&FORMAT_VERSION.to_le_bytes())?;
+        records_writer.write_hashed(&shard_id.to_le_bytes())?;
+        Ok(records_writer)
+    }
+
+    fn write_hashed(&mut self, data: &[u8]) -> io::Result<()> {
+        self.digest.update(data);
+        self.writer.write_all(data)
+    }
+
+    pub fn write(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.write_hashed(&(key.len() as u32).to_le_bytes())?;
+        self.write_hashed(key)?;
+        self.write_hashed(&(value.len() as u32).to_le_bytes())?;
+        self.write_hashed(value)?;
+        self.num_records += 1;
+        Ok(())
+    }
+
+    /// Writes the number of records and the checksum, returns the number of records written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        let num_records = self.num_records;
+        self.write_hashed(&END_MARKER.to_le_bytes())?;
+        self.write_hashed(&num_records.to_le_bytes())?;
+        let Self { mut writer, digest, .. } = self;
+        writer.write_all(&digest.finalize())?;
+        writer.flush()?;
+        Ok(num_records)
+    }
+}
+
+/// Iterates over the records of a dump. The last item is an error if the dump is truncated or its
+/// checksum doesn't match.
+pub struct RecordsReader<R: Read> {
+    reader: R,
+    digest: sha2::Sha256,
+    shard_id: ShardId,
+    num_records: u64,
+    finished: bool,
+}
+
+impl RecordsReader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordsReader<R> {
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut records_reader = Self {
+            reader,
+            digest: sha2::Sha256::new(),
+            shard_id: 0,
+            num_records: 0,
+            finished: false,
+        };
+        let mut magic = [0u8; 8];
+        records_reader.read_hashed(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a state records dump".to_string()));
+        }
+        let version = records_reader.read_u32()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!("Unsupported records dump version {}", version)));
+        }
+        let mut shard_id = [0u8; 8];
+        records_reader.read_hashed(&mut shard_id)?;
+        records_reader.shard_id = ShardId::from_le_bytes(shard_id);
+        Ok(records_reader)
+    }
+
+    pub fn shard_id(&self) -> ShardId {
+        self.shard_id
+    }
+
+    fn read_hashed(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(buf)?;
+        self.digest.update(&*buf);
+        Ok(())
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.read_hashed(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_vec(&mut self, len: u32) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; len as usize];
+        self.read_hashed(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let key_len = self.read_u32()?;
+        if key_len == END_MARKER {
+            let mut num_records = [0u8; 8];
+            self.read_hashed(&mut num_records)?;
+            let num_records = u64::from_le_bytes(num_records);
+            let expected_checksum = self.digest.clone().finalize();
+            let mut checksum = [0u8; 32];
+            self.reader.read_exact(&mut checksum)?;
+            if num_records != self.num_records {
+                return Err(invalid_data(format!(
+                    "Expected {} records, read {}",
+                    num_records, self.num_records
+                )));
+            }
+            if checksum[..] != expected_checksum[..] {
+                return Err(invalid_data("Records dump checksum mismatch".to_string()));
+            }
+            return Ok(None);
+        }
+        let key = self.read_vec(key_len)?;
+        let value_len = self.read_u32()?;
+        let value = self.read_vec(value_len)?;
+        self.num_records += 1;
+        Ok(Some((key, value)))
+    }
+}
+
+impl<R: Read> Iterator for RecordsReader<R> {
+    type Item = io::Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::trie_key::TrieKey;
+
+    use super::*;
+
+    fn write_records(records: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = vec![];
+        let mut writer = RecordsWriter::new(&mut buf, 3).unwrap();
+        for (key, value) in records {
+            writer.write(key, value).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), records.len() as u64);
+        buf
+    }
+
+    fn test_records() -> Vec<(Vec<u8>, Vec<u8>)> {
+        vec![
+            (b"key1".to_vec(), b"value1".to_vec()),
+            (b"key2".to_vec(), vec![]),
+            (b"key3".to_vec(), vec![7; 1000]),
+        ]
+    }
+
+    #[test]
+    fn test_records_roundtrip() {
+        let records = test_records();
+        let buf = write_records(&records);
+        let reader = RecordsReader::new(buf.as_slice()).unwrap();
+        assert_eq!(reader.shard_id(), 3);
+        let read: Vec<_> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn test_records_corrupted() {
+        let mut buf = write_records(&test_records());
+        // Flip a byte of the last value.
+        let len = buf.len();
+        buf[len - 50] ^= 1;
+        let result: io::Result<Vec<_>> = RecordsReader::new(buf.as_slice()).unwrap().collect();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let buf = write_records(&test_records());
+        let result: io::Result<Vec<_>> =
+            RecordsReader::new(&buf[..buf.len() - 10]).unwrap().collect();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_records_filter() {
+        let data_key = |account_id: &str| {
+            TrieKey::ContractData { account_id: account_id.parse().unwrap(), key: b"a".to_vec() }
+                .to_vec()
+        };
+        let account_key = |account_id: &str| {
+            TrieKey::Account { account_id: account_id.parse().unwrap() }.to_vec()
+        };
+        let delayed_receipt_key = TrieKey::DelayedReceipt { index: 0 }.to_vec();
+
+        let filter = RecordsFilter::default();
+        assert!(filter.accepts(&data_key("alice.near")));
+        assert!(filter.accepts(&delayed_receipt_key));
+
+        let filter = RecordsFilter { account_prefixes: vec!["ali".to_string()], kinds: None };
+        assert!(filter.accepts(&data_key("alice.near")));
+        assert!(filter.accepts(&account_key("alice.near")));
+        assert!(!filter.accepts(&account_key("bob.near")));
+        assert!(filter.accepts(&delayed_receipt_key));
+
+        let filter = RecordsFilter {
+            account_prefixes: vec![],
+            kinds: Some(vec![StateRecordKind::Account].into_iter().collect()),
+        };
+        assert!(filter.accepts(&account_key("bob.near")));
+        assert!(!filter.accepts(&data_key("alice.near")));
+        assert!(!filter.accepts(&delayed_receipt_key));
+    }
+}
@@ -3,10 +3,13 @@ use serde::{Deserialize, Serialize};
 
 use near_crypto::Signature;
 
+use crate::block_header::Approval;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::MerklePath;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use crate::sharding::ReceiptProof;
 use crate::sharding::{EncodedShardChunk, ShardChunk, ShardChunkHeader};
-use crate::types::AccountId;
+use crate::types::{AccountId, BlockHeight, EpochId};
 use crate::validator_signer::ValidatorSigner;
 
 /// Serialized TrieNodeWithSize
@@ -28,6 +31,40 @@ impl std::fmt::Display for BlockDoubleSign {
     }
 }
 
+/// Two approvals signed by the same account for the same target height that approve different
+/// parents. The protocol has no challenge for them, so they are only kept as evidence.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub struct ApprovalDoubleSign {
+    pub left_approval: Approval,
+    pub right_approval: Approval,
+}
+
+/// Conflicting messages signed by the same validator.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub enum MisbehaviorEvidence {
+    BlockDoubleSign(BlockDoubleSign),
+    ApprovalDoubleSign(ApprovalDoubleSign),
+}
+
+impl MisbehaviorEvidence {
+    /// Key of the evidence in storage, the same evidence detected twice is only stored once.
+    pub fn hash(&self) -> CryptoHash {
+        hash(&self.try_to_vec().expect("Failed to serialize"))
+    }
+}
+
+/// Evidence of misbehavior together with the validator it implicates, as detected by this node.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub struct MisbehaviorEvidenceRecord {
+    pub account_id: AccountId,
+    pub epoch_id: EpochId,
+    /// Height of the blocks or target height of the approvals.
+    pub height: BlockHeight,
+    /// Timestamp in nanoseconds when the evidence was detected.
+    pub detected_at: u64,
+    pub evidence: MisbehaviorEvidence,
+}
+
 /// Invalid chunk (body of the chunk doesn't match proofs or invalid encoding).
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
 pub struct ChunkProofs {
@@ -68,6 +105,45 @@ pub struct ChunkState {
     pub partial_state: PartialState,
 }
 
+/// Receipts sent to a shard by the chunks included in a block.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub struct ChunkStateIncomingReceipts {
+    /// Outgoing receipts roots of all the chunks in the block, to verify `receipt_proofs` against.
+    pub outgoing_receipts_roots: Vec<CryptoHash>,
+    /// Receipts for the shard from each new chunk in the block.
+    pub receipt_proofs: Vec<ReceiptProof>,
+}
+
+/// Same as `ChunkState`, but also proves the receipts that were applied with the previous chunk,
+/// so that the state transition can be replayed exactly as the chunk producer did.
+/// The previous chunk must be a new chunk in the previous block and the invalid chunk must be a new
+/// chunk in the block.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
+pub struct ChunkStateV2 {
+    /// Encoded prev block header.
+    pub prev_block_header: Vec<u8>,
+    /// Encoded block header that contains invalid chunk.
+    pub block_header: Vec<u8>,
+    /// Merkle proof in inclusion of prev chunk.
+    pub prev_merkle_proof: MerklePath,
+    /// Previous chunk that contains transactions.
+    pub prev_chunk: ShardChunk,
+    /// Merkle proof of inclusion of this chunk.
+    pub merkle_proof: MerklePath,
+    /// Invalid chunk header.
+    pub chunk_header: ShardChunkHeader,
+    /// Partial state that was affected by transactions of given chunk.
+    pub partial_state: PartialState,
+    /// Encoded headers of the ancestors of the prev block, newest first, down to the last block
+    /// with a new chunk for the shard before the previous chunk.
+    pub prev_block_ancestors: Vec<Vec<u8>>,
+    /// Receipts applied together with the previous chunk, sent by the chunks in the prev block and
+    /// in all but the last of `prev_block_ancestors`, in the same order.
+    pub prev_incoming_receipts: Vec<ChunkStateIncomingReceipts>,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
 // TODO(#1313): Use Box
 #[allow(clippy::large_enum_variant)]
@@ -75,6 +151,8 @@ pub enum ChallengeBody {
     BlockDoubleSign(BlockDoubleSign),
     ChunkProofs(ChunkProofs),
     ChunkState(ChunkState),
+    #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+    ChunkStateV2(ChunkStateV2),
 }
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
@@ -491,7 +491,7 @@ pub struct ValidatorWeight(ValidatorId, u64);
 
 #[cfg(feature = "protocol_feature_block_header_v3")]
 pub mod epoch_info {
-    use crate::epoch_manager::ValidatorWeight;
+    use crate::epoch_manager::{SlashState, ValidatorWeight};
     use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
     use crate::types::{BlockChunkValidatorStats, ValidatorKickoutReason};
     use crate::version::PROTOCOL_VERSION;
@@ -997,12 +997,14 @@ pub mod epoch_info {
         pub validator_block_chunk_stats: HashMap<AccountId, BlockChunkValidatorStats>,
         /// Protocol version for next epoch.
         pub next_version: ProtocolVersion,
+        /// Slashed validators as of the last block of the epoch
+        pub slashed: HashMap<AccountId, SlashState>,
     }
 }
 
 #[cfg(not(feature = "protocol_feature_block_header_v3"))]
 pub mod epoch_info {
-    use crate::epoch_manager::ValidatorWeight;
+    use crate::epoch_manager::{SlashState, ValidatorWeight};
     use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
     use crate::types::{BlockChunkValidatorStats, ValidatorKickoutReason};
     use borsh::{BorshDeserialize, BorshSerialize};
@@ -1174,6 +1176,8 @@ pub mod epoch_info {
         pub validator_block_chunk_stats: HashMap<AccountId, BlockChunkValidatorStats>,
         /// Protocol version for next epoch.
         pub next_version: ProtocolVersion,
+        /// Slashed validators as of the last block of the epoch
+        pub slashed: HashMap<AccountId, SlashState>,
     }
 }
 
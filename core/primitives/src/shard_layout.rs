@@ -176,7 +176,6 @@ pub fn get_block_shard_uid(block_hash: &CryptoHash, shard_uid: &ShardUId) -> Vec
     res
 }
 
-#[allow(unused)]
 pub fn get_block_shard_uid_rev(
     key: &[u8],
 ) -> Result<(CryptoHash, ShardUId), Box<dyn std::error::Error>> {
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use borsh::{BorshDeserialize, BorshSerialize};
 use reed_solomon_erasure::galois_8::{Field, ReedSolomon};
 use serde::{Deserialize, Serialize};
@@ -61,6 +63,9 @@ pub struct StateSyncInfo {
     pub epoch_tail_hash: CryptoHash,
     /// Shards to fetch state
     pub shards: Vec<ShardInfo>,
+    /// For every shard whose state parts are being downloaded, whether each part is already
+    /// validated and saved. Allows to resume the download after a restart.
+    pub downloaded_parts: HashMap<ShardId, Vec<bool>>,
 }
 
 #[cfg(feature = "protocol_feature_block_header_v3")]
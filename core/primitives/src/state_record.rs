@@ -164,3 +164,53 @@ pub fn state_record_to_account_id(state_record: &StateRecord) -> &AccountId {
 pub fn is_contract_code_key(key: &[u8]) -> bool {
     &key[0..1] == col::CONTRACT_CODE
 }
+
+/// Kind of a `StateRecord`, used to select records when dumping the state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateRecordKind {
+    Account,
+    Data,
+    Contract,
+    AccessKey,
+    ReceivedData,
+    PostponedReceipt,
+    DelayedReceipt,
+}
+
+impl StateRecordKind {
+    /// Kind of the record stored under a raw trie key. Keys that are not records by themselves,
+    /// like pending data counts or delayed receipt indices, belong to the receipts they track.
+    pub fn from_raw_key(key: &[u8]) -> Option<Self> {
+        match key.get(0..1)? {
+            col::ACCOUNT => Some(StateRecordKind::Account),
+            col::CONTRACT_DATA => Some(StateRecordKind::Data),
+            col::CONTRACT_CODE => Some(StateRecordKind::Contract),
+            col::ACCESS_KEY => Some(StateRecordKind::AccessKey),
+            col::RECEIVED_DATA => Some(StateRecordKind::ReceivedData),
+            col::POSTPONED_RECEIPT_ID | col::PENDING_DATA_COUNT | col::POSTPONED_RECEIPT => {
+                Some(StateRecordKind::PostponedReceipt)
+            }
+            col::DELAYED_RECEIPT_INDICES | col::DELAYED_RECEIPT => {
+                Some(StateRecordKind::DelayedReceipt)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for StateRecordKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "account" => Ok(StateRecordKind::Account),
+            "data" => Ok(StateRecordKind::Data),
+            "contract" => Ok(StateRecordKind::Contract),
+            "access_key" => Ok(StateRecordKind::AccessKey),
+            "received_data" => Ok(StateRecordKind::ReceivedData),
+            "postponed_receipt" => Ok(StateRecordKind::PostponedReceipt),
+            "delayed_receipt" => Ok(StateRecordKind::DelayedReceipt),
+            _ => Err(format!("Unknown state record kind {}", s)),
+        }
+    }
+}
@@ -3,7 +3,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use crate::block_header::BlockHeader;
 use crate::epoch_manager::block_info::BlockInfo;
 use crate::epoch_manager::epoch_info::EpochInfo;
-use crate::hash::CryptoHash;
+use crate::hash::{hash, CryptoHash};
 use crate::merkle::{MerklePath, PartialMerkleTree};
 use crate::receipt::Receipt;
 use crate::sharding::{
@@ -213,6 +213,38 @@ pub struct EpochSyncFinalizationResponse {
     pub next_epoch_info: EpochInfo,
 }
 
+impl EpochSyncFinalizationResponse {
+    /// Hash of the epoch data, which is committed to by the first block of the current epoch.
+    pub fn epoch_sync_data_hash(&self) -> CryptoHash {
+        get_epoch_sync_data_hash(
+            &self.prev_epoch_first_block_info,
+            &self.prev_epoch_prev_last_block_info,
+            &self.prev_epoch_last_block_info,
+            &self.prev_epoch_info,
+            &self.cur_epoch_info,
+            &self.next_epoch_info,
+        )
+    }
+}
+
+/// Hash of the data that is necessary to prove epochs in Epoch Sync.
+pub fn get_epoch_sync_data_hash(
+    prev_epoch_first_block_info: &BlockInfo,
+    prev_epoch_prev_last_block_info: &BlockInfo,
+    prev_epoch_last_block_info: &BlockInfo,
+    prev_epoch_info: &EpochInfo,
+    cur_epoch_info: &EpochInfo,
+    next_epoch_info: &EpochInfo,
+) -> CryptoHash {
+    let mut data = prev_epoch_first_block_info.try_to_vec().unwrap();
+    data.extend(prev_epoch_prev_last_block_info.try_to_vec().unwrap());
+    data.extend(prev_epoch_last_block_info.try_to_vec().unwrap());
+    data.extend(prev_epoch_info.try_to_vec().unwrap());
+    data.extend(cur_epoch_info.try_to_vec().unwrap());
+    data.extend(next_epoch_info.try_to_vec().unwrap());
+    hash(data.as_slice())
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Eq, PartialEq, Debug, Clone)]
 pub enum EpochSyncResponse {
     UpToDate,
@@ -1041,7 +1041,8 @@ pub trait CompiledContractCache: Send + Sync {
 
 /// Provides information about current epoch validators.
 /// Used to break dependency between epoch manager and runtime.
-pub trait EpochInfoProvider {
+/// Shared between threads when the runtime processes receipts in parallel.
+pub trait EpochInfoProvider: Sync {
     /// Get current stake of a validator in the given epoch.
     /// If the account is not a validator, returns `None`.
     fn validator_stake(
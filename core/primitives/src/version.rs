@@ -13,7 +13,7 @@ pub struct Version {
 pub type DbVersion = u32;
 
 /// Current version of the database.
-pub const DB_VERSION: DbVersion = 27;
+pub const DB_VERSION: DbVersion = 29;
 
 /// Protocol version type.
 pub use near_primitives_core::types::ProtocolVersion;
@@ -109,6 +109,9 @@ pub enum ProtocolFeature {
     SimpleNightshade,
     #[cfg(feature = "protocol_feature_chunk_only_producers")]
     ChunkOnlyProducers,
+    /// Chunk state challenges that prove the receipts applied with the challenged chunk.
+    #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+    ChunkStateChallenge,
 }
 
 /// Current latest stable version of the protocol.
@@ -119,7 +122,7 @@ pub const PROTOCOL_VERSION: ProtocolVersion = 47;
 
 /// Current latest nightly version of the protocol.
 #[cfg(feature = "nightly_protocol")]
-pub const PROTOCOL_VERSION: ProtocolVersion = 115;
+pub const PROTOCOL_VERSION: ProtocolVersion = 116;
 
 impl ProtocolFeature {
     pub const fn protocol_version(self) -> ProtocolVersion {
@@ -148,6 +151,8 @@ impl ProtocolFeature {
             ProtocolFeature::SimpleNightshade => 114,
             #[cfg(feature = "protocol_feature_chunk_only_producers")]
             ProtocolFeature::ChunkOnlyProducers => 115,
+            #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+            ProtocolFeature::ChunkStateChallenge => 116,
         }
     }
 }
@@ -3,6 +3,7 @@
 //! These types should only change when we cannot avoid this. Thus, when the counterpart internal
 //! type gets changed, the view should preserve the old shape and only re-map the necessary bits
 //! from the source structure in the relevant `From<SourceStruct>` impl.
+use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::sync::Arc;
@@ -16,12 +17,14 @@ use near_crypto::{PublicKey, Signature};
 use crate::account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission};
 use crate::block::{Block, BlockHeader};
 use crate::block_header::{
-    BlockHeaderInnerLite, BlockHeaderInnerRest, BlockHeaderInnerRestV2, BlockHeaderV1,
+    Approval, BlockHeaderInnerLite, BlockHeaderInnerRest, BlockHeaderInnerRestV2, BlockHeaderV1,
     BlockHeaderV2,
 };
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use crate::block_header::{BlockHeaderInnerRestV3, BlockHeaderV3};
-use crate::challenge::{Challenge, ChallengesResult};
+use crate::challenge::{
+    Challenge, ChallengesResult, MisbehaviorEvidence, MisbehaviorEvidenceRecord, SlashedValidator,
+};
 use crate::contract::ContractCode;
 use crate::errors::TxExecutionError;
 use crate::hash::{hash, CryptoHash};
@@ -213,6 +216,18 @@ pub struct ViewStateResult {
     pub proof: TrieProofPath,
 }
 
+/// A page of the contract state, returned for `QueryRequest::ViewStatePage`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ViewStatePageResult {
+    pub values: Vec<StateItem>,
+    pub proof: TrieProofPath,
+    /// Key, serialized in base64, to pass as `start_key` to fetch the next page of the state.
+    /// `None` if there are no more items under the requested prefix.
+    // Always present in JSON, so that pages and `ViewStateResult` can be told apart.
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub next_key: Option<String>,
+}
+
 #[derive(
     BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default,
 )]
@@ -252,6 +267,7 @@ pub enum QueryResponseKind {
     CallResult(CallResult),
     AccessKey(AccessKeyView),
     AccessKeyList(AccessKeyList),
+    ViewStatePage(ViewStatePageResult),
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -281,8 +297,58 @@ pub enum QueryRequest {
         #[serde(rename = "args_base64", with = "base64_format")]
         args: FunctionArgs,
     },
+    /// Same as `ViewState`, but returns the state in pages, so that the state of accounts larger
+    /// than the state size limit of the node can be read too.
+    ViewStatePage {
+        account_id: AccountId,
+        #[serde(rename = "prefix_base64", with = "base64_format")]
+        prefix: StoreKey,
+        /// Key, relative to the account, to start the iteration from, see
+        /// `ViewStatePageResult::next_key`.
+        #[serde(
+            rename = "start_key_base64",
+            with = "option_base64_format",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        start_key: Option<Vec<u8>>,
+        /// Maximum number of items to return, must be positive. The state size limit of the node
+        /// bounds the size of a single page.
+        limit: u64,
+    },
+}
+
+/// Temporary changes to the state of an account that are applied before executing a view
+/// function call. Overrides are never persisted.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct AccountStateOverride {
+    /// Replaces the contract code of the account.
+    #[serde(
+        rename = "code_base64",
+        with = "option_base64_format",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub code: Option<Vec<u8>>,
+    /// Replaces the liquid balance of the account.
+    #[serde(with = "option_u128_dec_format", default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Balance>,
+    /// Sets or removes (when `value` is `None`) contract storage entries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageOverride>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct StorageOverride {
+    #[serde(rename = "key_base64", with = "base64_format")]
+    pub key: StoreKey,
+    #[serde(rename = "value_base64", with = "option_base64_format", default)]
+    pub value: Option<Vec<u8>>,
 }
 
+/// Per-account state overrides for view function calls.
+pub type StateOverrides = BTreeMap<AccountId, AccountStateOverride>;
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq, Clone)]
 pub struct QueryResponse {
     pub kind: QueryResponseKind,
@@ -342,6 +408,57 @@ impl From<Challenge> for ChallengeView {
     }
 }
 
+/// Conflicting messages signed by the same validator, see `MisbehaviorEvidence`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum MisbehaviorEvidenceKindView {
+    BlockDoubleSign { left_block_header: BlockHeaderView, right_block_header: BlockHeaderView },
+    ApprovalDoubleSign { left_approval: Approval, right_approval: Approval },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MisbehaviorEvidenceView {
+    pub account_id: AccountId,
+    pub epoch_id: CryptoHash,
+    pub height: BlockHeight,
+    #[serde(with = "u64_dec_format")]
+    pub detected_at: u64,
+    pub evidence: MisbehaviorEvidenceKindView,
+}
+
+impl TryFrom<MisbehaviorEvidenceRecord> for MisbehaviorEvidenceView {
+    type Error = std::io::Error;
+
+    fn try_from(record: MisbehaviorEvidenceRecord) -> Result<Self, Self::Error> {
+        let evidence = match record.evidence {
+            MisbehaviorEvidence::BlockDoubleSign(block_double_sign) => {
+                MisbehaviorEvidenceKindView::BlockDoubleSign {
+                    left_block_header: BlockHeader::try_from_slice(
+                        &block_double_sign.left_block_header,
+                    )?
+                    .into(),
+                    right_block_header: BlockHeader::try_from_slice(
+                        &block_double_sign.right_block_header,
+                    )?
+                    .into(),
+                }
+            }
+            MisbehaviorEvidence::ApprovalDoubleSign(approval_double_sign) => {
+                MisbehaviorEvidenceKindView::ApprovalDoubleSign {
+                    left_approval: approval_double_sign.left_approval,
+                    right_approval: approval_double_sign.right_approval,
+                }
+            }
+        };
+        Ok(Self {
+            account_id: record.account_id,
+            epoch_id: record.epoch_id.0,
+            height: record.height,
+            detected_at: record.detected_at,
+            evidence,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BlockHeaderView {
     pub height: BlockHeight,
@@ -968,6 +1085,38 @@ impl Default for FinalExecutionStatus {
     }
 }
 
+impl FinalExecutionStatus {
+    /// Status of the transaction given the outcome of the transaction followed by the outcomes of
+    /// the receipts, where every receipt goes after the one that produced it. `None` if the
+    /// receipt that determines the status is not among the outcomes.
+    pub fn from_outcomes(outcomes: &[ExecutionOutcomeWithIdView]) -> Option<Self> {
+        let mut looking_for_id = outcomes.first()?.id;
+        let num_outcomes = outcomes.len();
+        outcomes.iter().find_map(|outcome_with_id| {
+            if outcome_with_id.id == looking_for_id {
+                match &outcome_with_id.outcome.status {
+                    ExecutionStatusView::Unknown if num_outcomes == 1 => {
+                        Some(FinalExecutionStatus::NotStarted)
+                    }
+                    ExecutionStatusView::Unknown => Some(FinalExecutionStatus::Started),
+                    ExecutionStatusView::Failure(e) => {
+                        Some(FinalExecutionStatus::Failure(e.clone()))
+                    }
+                    ExecutionStatusView::SuccessValue(v) => {
+                        Some(FinalExecutionStatus::SuccessValue(v.clone()))
+                    }
+                    ExecutionStatusView::SuccessReceiptId(id) => {
+                        looking_for_id = *id;
+                        None
+                    }
+                }
+            } else {
+                None
+            }
+        })
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum ServerError {
     TxExecutionError(TxExecutionError),
@@ -1429,6 +1578,26 @@ pub struct EpochValidatorInfo {
     pub epoch_start_height: BlockHeight,
     /// Epoch height
     pub epoch_height: EpochHeight,
+    /// Validators slashed in the current epoch up to the given block, or in the whole epoch for
+    /// finished epochs.
+    #[serde(default)]
+    pub current_slashed: Vec<SlashedValidatorView>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct SlashedValidatorView {
+    pub account_id: AccountId,
+    /// Validators slashed for double signing only lose part of their stake.
+    pub is_double_sign: bool,
+}
+
+impl From<SlashedValidator> for SlashedValidatorView {
+    fn from(slashed_validator: SlashedValidator) -> Self {
+        Self {
+            account_id: slashed_validator.account_id,
+            is_double_sign: slashed_validator.is_double_sign,
+        }
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -1700,3 +1869,18 @@ impl From<StateChangeWithCause> for StateChangeWithCauseView {
 }
 
 pub type StateChangesView = Vec<StateChangeWithCauseView>;
+
+/// Outcome of a transaction executed on top of the state after the given block without saving
+/// any of its results. The outcomes refer to that block.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedTransactionView {
+    #[serde(flatten)]
+    pub final_outcome: FinalExecutionOutcomeView,
+    /// Gas burnt by the transaction and all of its receipts.
+    pub gas_burnt: Gas,
+    /// Changes of the state made by the transaction and its receipts.
+    pub state_changes: StateChangesView,
+    /// True if the simulation was stopped at its limits before all the receipts were executed.
+    /// The status is not final then.
+    pub incomplete: bool,
+}
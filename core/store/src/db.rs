@@ -119,10 +119,12 @@ pub enum DBCol {
     ColEpochValidatorInfo = 47,
     /// Header Hashes indexed by Height
     ColHeaderHashesByHeight = 48,
+    /// Evidence of validators signing conflicting blocks or approvals, indexed by its hash
+    ColMisbehaviorEvidence = 49,
 }
 
 // Do not move this line from enum DBCol
-pub const NUM_COLS: usize = 49;
+pub const NUM_COLS: usize = 50;
 
 impl std::fmt::Display for DBCol {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
@@ -176,6 +178,7 @@ impl std::fmt::Display for DBCol {
             Self::ColCachedContractCode => "cached code",
             Self::ColEpochValidatorInfo => "epoch validator info",
             Self::ColHeaderHashesByHeight => "header hashes indexed by their height",
+            Self::ColMisbehaviorEvidence => "misbehavior evidence",
         };
         write!(formatter, "{}", desc)
     }
@@ -209,6 +212,7 @@ lazy_static! {
         col_gc[DBCol::ColEpochValidatorInfo as usize] = false; // https://github.com/nearprotocol/nearcore/pull/2952
         col_gc[DBCol::ColEpochStart as usize] = false; // https://github.com/nearprotocol/nearcore/pull/2952
         col_gc[DBCol::ColCachedContractCode as usize] = false;
+        col_gc[DBCol::ColMisbehaviorEvidence as usize] = false; // evidence is kept for operators
         col_gc
     };
 }
@@ -249,6 +253,7 @@ pub const LARGEST_TARGET_HEIGHT_KEY: &[u8; 21] = b"LARGEST_TARGET_HEIGHT";
 pub const VERSION_KEY: &[u8; 7] = b"VERSION";
 pub const GENESIS_JSON_HASH_KEY: &[u8; 17] = b"GENESIS_JSON_HASH";
 pub const GENESIS_STATE_ROOTS_KEY: &[u8; 19] = b"GENESIS_STATE_ROOTS";
+pub const STATE_SYNC_PROGRESS_KEY: &[u8; 19] = b"STATE_SYNC_PROGRESS";
 
 pub struct DBTransaction {
     pub ops: Vec<DBOp>,
@@ -11,6 +11,7 @@ use near_primitives::block_header::BlockHeader;
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::epoch_manager::epoch_info::EpochInfoV1;
+use near_primitives::epoch_manager::SlashState;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{merklize, PartialMerkleTree};
 use near_primitives::receipt::{DelayedReceiptIndices, Receipt, ReceiptEnum};
@@ -24,7 +25,7 @@ use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
 use near_primitives::trie_key::TrieKey;
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_primitives::types::validator_stake::ValidatorStake;
-use near_primitives::types::{AccountId, Balance};
+use near_primitives::types::{AccountId, Balance, ShardId};
 use near_primitives::utils::{
     create_receipt_id_from_transaction, get_block_shard_id, index_to_bytes,
 };
@@ -741,6 +742,37 @@ pub fn migrate_26_to_27(path: &Path, is_archival: bool) {
     set_store_version(&store, 27);
 }
 
+pub fn migrate_27_to_28(path: &Path) {
+    let store = create_store(path);
+    let mut store_update = store.store_update();
+    // `EpochSummary` has a new last field with slashed validators. It's unknown for finished
+    // epochs, so an empty map is appended to the serialized value, whatever the format of
+    // validator stakes is.
+    let no_slashed = HashMap::<AccountId, SlashState>::new().try_to_vec().unwrap();
+    for (key, value) in store.iter_without_rc_logic(DBCol::ColEpochValidatorInfo) {
+        let mut value = value.into_vec();
+        value.extend_from_slice(&no_slashed);
+        store_update.set(DBCol::ColEpochValidatorInfo, &key, &value);
+    }
+    store_update.commit().unwrap();
+    set_store_version(&store, 28);
+}
+
+pub fn migrate_28_to_29(path: &Path) {
+    let store = create_store(path);
+    let mut store_update = store.store_update();
+    // `StateSyncInfo` has a new last field with the download progress of state parts, which
+    // starts empty.
+    let no_downloaded_parts = HashMap::<ShardId, Vec<bool>>::new().try_to_vec().unwrap();
+    for (key, value) in store.iter_without_rc_logic(DBCol::ColStateDlInfos) {
+        let mut value = value.into_vec();
+        value.extend_from_slice(&no_downloaded_parts);
+        store_update.set(DBCol::ColStateDlInfos, &key, &value);
+    }
+    store_update.commit().unwrap();
+    set_store_version(&store, 29);
+}
+
 #[cfg(feature = "protocol_feature_block_header_v3")]
 pub fn migrate_18_to_new_validator_stake(store: &Store) {
     use near_primitives::epoch_manager::block_info::{BlockInfo, BlockInfoV1};
@@ -761,6 +793,7 @@ pub fn migrate_18_to_new_validator_stake(store: &Store) {
         pub validator_kickout: HashMap<AccountId, ValidatorKickoutReason>,
         pub validator_block_chunk_stats: HashMap<AccountId, BlockChunkValidatorStats>,
         pub next_version: ProtocolVersion,
+        pub slashed: HashMap<AccountId, SlashState>,
     }
 
     #[derive(BorshDeserialize)]
@@ -792,6 +825,7 @@ pub fn migrate_18_to_new_validator_stake(store: &Store) {
         validator_kickout: info.validator_kickout,
         validator_block_chunk_stats: info.validator_block_chunk_stats,
         next_version: info.next_version,
+        slashed: info.slashed,
     })
     .unwrap();
 
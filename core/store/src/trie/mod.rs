@@ -460,6 +460,26 @@ impl TrieChanges {
     pub fn empty(old_root: StateRoot) -> Self {
         TrieChanges { old_root, new_root: old_root, insertions: vec![], deletions: vec![] }
     }
+
+    /// Adds the inserted nodes and values to `overlay`, so that the new root can be read with
+    /// `ShardTries::get_trie_with_overlay` without saving the changes.
+    pub fn add_insertions_to_overlay(&self, overlay: &mut Arc<HashMap<CryptoHash, Vec<u8>>>) {
+        let overlay = Arc::make_mut(overlay);
+        for insertion in self.insertions.iter() {
+            overlay.insert(insertion.trie_node_or_value_hash, insertion.trie_node_or_value.clone());
+        }
+    }
+}
+
+/// Storage shared between tries reading the same state from different threads.
+#[derive(Clone)]
+pub struct SharedTrieStorage(TrieCachingStorage);
+
+impl SharedTrieStorage {
+    /// Creates a new trie with its own touched nodes counter on top of the shared storage.
+    pub fn trie(&self) -> Trie {
+        Trie::new(Box::new(self.0.clone()), self.0.shard_uid)
+    }
 }
 
 /// Result of applying state part to Trie.
@@ -475,6 +495,12 @@ impl Trie {
         Trie { storage: store, counter: TouchedNodesCounter::default() }
     }
 
+    /// Returns a handle to the storage of this trie that can be sent to other threads, or `None`
+    /// if the storage keeps per-trie state, like recorded or partial storage.
+    pub fn shared_storage(&self) -> Option<SharedTrieStorage> {
+        self.storage.as_caching_storage().cloned().map(SharedTrieStorage)
+    }
+
     pub fn recording_reads(&self) -> Self {
         let storage =
             self.storage.as_caching_storage().expect("Storage should be TrieCachingStorage");
@@ -12,7 +12,7 @@ use near_primitives::types::{
 };
 
 use crate::db::{DBCol, DBOp, DBTransaction};
-use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
+use crate::trie::trie_storage::{TrieCache, TrieCachingStorage, TrieOverlayStorage};
 use crate::trie::TrieRefcountChange;
 use crate::{StorageError, Store, StoreUpdate, Trie, TrieChanges, TrieUpdate};
 
@@ -80,10 +80,52 @@ impl ShardTries {
         self.get_trie_for_shard_internal(shard_uid, true)
     }
 
+    /// Trie which reads the nodes in `overlay` from memory instead of the store, see
+    /// `TrieChanges::add_insertions_to_overlay`.
+    pub fn get_trie_with_overlay(
+        &self,
+        shard_uid: ShardUId,
+        overlay: Arc<HashMap<CryptoHash, Vec<u8>>>,
+    ) -> Trie {
+        let base = TrieCachingStorage::new(
+            self.store.clone(),
+            self.view_caches[&shard_uid].clone(),
+            shard_uid,
+        );
+        Trie::new(Box::new(TrieOverlayStorage { base, overlay }), shard_uid)
+    }
+
     pub fn get_store(&self) -> Arc<Store> {
         self.store.clone()
     }
 
+    /// Deletes at most `batch_size` trie nodes of the shard and returns the number of deleted
+    /// nodes, zero once the whole state of the shard is removed.
+    ///
+    /// The nodes are deleted regardless of their reference counts. This is only safe when
+    /// nothing refers to the state of the shard anymore: no trie changes of the shard are left
+    /// to garbage collect, and the node neither cares nor will care about the shard, so no new
+    /// state of it is written. The caller must check the latter before every batch, since
+    /// tracking of the shard may be resumed and its state caught up into the same `ShardUId`.
+    pub fn delete_shard_state_batch(
+        &self,
+        shard_uid: ShardUId,
+        batch_size: usize,
+    ) -> std::io::Result<u64> {
+        let keys: Vec<_> = self
+            .store
+            .iter_prefix(DBCol::ColState, &shard_uid.to_bytes())
+            .map(|(key, _)| key)
+            .take(batch_size)
+            .collect();
+        let mut store_update = StoreUpdate::new_with_tries(self.clone());
+        for key in keys.iter() {
+            store_update.delete(DBCol::ColState, key);
+        }
+        store_update.commit()?;
+        Ok(keys.len() as u64)
+    }
+
     pub fn update_cache(&self, transaction: &DBTransaction) -> std::io::Result<()> {
         let mut shards = self
             .caches
@@ -98,7 +140,12 @@ impl ShardTries {
                     shards.entry(shard_uid).or_insert(vec![]).push((hash, Some(value.clone())));
                 }
                 DBOp::Insert { col, .. } if *col == DBCol::ColState => unreachable!(),
-                DBOp::Delete { col, .. } if *col == DBCol::ColState => unreachable!(),
+                DBOp::Delete { col, ref key } if *col == DBCol::ColState => {
+                    // Delete is possible in delete_shard_state_batch
+                    let (shard_uid, hash) =
+                        TrieCachingStorage::get_shard_uid_and_hash_from_key(key)?;
+                    shards.entry(shard_uid).or_insert(vec![]).push((hash, None));
+                }
                 DBOp::DeleteAll { col } if *col == DBCol::ColState => {
                     // Delete is possible in reset_data_pre_state_sync
                     for (_, cache) in self.caches.iter() {
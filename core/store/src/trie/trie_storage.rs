@@ -118,6 +118,22 @@ impl TrieStorage for TrieMemoryPartialStorage {
     }
 }
 
+/// Storage which reads the nodes inserted by not saved `TrieChanges` from memory and all the
+/// other nodes from the store. Used to execute several chunks in a row without saving anything.
+pub struct TrieOverlayStorage {
+    pub(crate) base: TrieCachingStorage,
+    pub(crate) overlay: Arc<HashMap<CryptoHash, Vec<u8>>>,
+}
+
+impl TrieStorage for TrieOverlayStorage {
+    fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Vec<u8>, StorageError> {
+        match self.overlay.get(hash) {
+            Some(value) => Ok(value.clone()),
+            None => self.base.retrieve_raw_bytes(hash),
+        }
+    }
+}
+
 /// Maximum number of cache entries.
 #[cfg(not(feature = "no_cache"))]
 const TRIE_MAX_CACHE_SIZE: usize = 10000;
@@ -129,6 +145,7 @@ const TRIE_MAX_CACHE_SIZE: usize = 1;
 /// Note that Trie inner nodes are always smaller than this.
 const TRIE_LIMIT_CACHED_VALUE_SIZE: usize = 4000;
 
+#[derive(Clone)]
 pub struct TrieCachingStorage {
     pub(crate) store: Arc<Store>,
     pub(crate) cache: TrieCache,
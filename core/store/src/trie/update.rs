@@ -12,6 +12,7 @@ use crate::StorageError;
 use super::{Trie, TrieIterator};
 use near_primitives::trie_key::TrieKey;
 use std::rc::Rc;
+use std::sync::Arc;
 
 /// Key-value update. Contains a TrieKey and a value.
 pub struct TrieKeyValueUpdate {
@@ -26,6 +27,8 @@ pub type TrieUpdates = BTreeMap<Vec<u8>, TrieKeyValueUpdate>;
 pub struct TrieUpdate {
     pub trie: Rc<Trie>,
     root: CryptoHash,
+    /// Changes committed before this update was forked, shared with the other forks.
+    base: Option<Arc<RawStateChanges>>,
     committed: RawStateChanges,
     prospective: TrieUpdates,
 }
@@ -53,7 +56,67 @@ impl<'a> TrieUpdateValuePtr<'a> {
 
 impl TrieUpdate {
     pub fn new(trie: Rc<Trie>, root: CryptoHash) -> Self {
-        TrieUpdate { trie, root, committed: Default::default(), prospective: Default::default() }
+        TrieUpdate {
+            trie,
+            root,
+            base: None,
+            committed: Default::default(),
+            prospective: Default::default(),
+        }
+    }
+
+    /// Creates an update on top of the changes committed to another update, see
+    /// `share_committed`. Its own commits can be moved back with `merge_fork`.
+    pub fn new_fork(trie: Rc<Trie>, root: CryptoHash, base: Arc<RawStateChanges>) -> Self {
+        TrieUpdate {
+            trie,
+            root,
+            base: Some(base),
+            committed: Default::default(),
+            prospective: Default::default(),
+        }
+    }
+
+    /// Moves the committed changes out so that forks can be created on top of them, possibly on
+    /// other threads. This update must not be used until the changes are given back with
+    /// `restore_committed`.
+    pub fn share_committed(&mut self) -> Arc<RawStateChanges> {
+        assert!(self.prospective.is_empty(), "Cannot fork an update with uncommitted changes.");
+        assert!(self.base.is_none(), "Cannot fork an update that is a fork itself.");
+        Arc::new(std::mem::take(&mut self.committed))
+    }
+
+    /// Takes back the changes given out by `share_committed`. All forks must be dropped by now.
+    pub fn restore_committed(&mut self, committed: Arc<RawStateChanges>) {
+        self.committed = Arc::try_unwrap(committed)
+            .unwrap_or_else(|_| panic!("All forks should be dropped before restoring the update"));
+    }
+
+    /// Returns the changes committed to a fork since it was created.
+    pub fn into_fork_changes(self) -> RawStateChanges {
+        assert!(self.prospective.is_empty(), "Fork cannot be merged with uncommitted changes.");
+        self.committed
+    }
+
+    /// Appends the changes of a fork created on top of this update, as if they were committed to
+    /// this update directly.
+    pub fn merge_fork(&mut self, changes: RawStateChanges) {
+        assert!(self.prospective.is_empty(), "Cannot merge a fork with uncommitted changes.");
+        for (raw_key, RawStateChangesWithTrieKey { trie_key, changes }) in changes.into_iter() {
+            self.committed
+                .entry(raw_key)
+                .or_insert_with(|| RawStateChangesWithTrieKey { trie_key, changes: Vec::new() })
+                .changes
+                .extend(changes);
+        }
+    }
+
+    fn last_committed(&self, key: &[u8]) -> Option<&Option<Vec<u8>>> {
+        self.committed
+            .get(key)
+            .or_else(|| self.base.as_ref().and_then(|base| base.get(key)))
+            .and_then(|changes_with_trie_key| changes_with_trie_key.changes.last())
+            .map(|RawStateChange { data, .. }| data)
     }
 
     pub fn trie(&self) -> &Trie {
@@ -64,10 +127,8 @@ impl TrieUpdate {
         let key = key.to_vec();
         if let Some(key_value) = self.prospective.get(&key) {
             return Ok(key_value.value.as_ref().map(<Vec<u8>>::clone));
-        } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
-            if let Some(RawStateChange { data, .. }) = changes_with_trie_key.changes.last() {
-                return Ok(data.as_ref().map(<Vec<u8>>::clone));
-            }
+        } else if let Some(data) = self.last_committed(&key) {
+            return Ok(data.as_ref().map(<Vec<u8>>::clone));
         }
 
         self.trie.get(&self.root, &key)
@@ -77,10 +138,8 @@ impl TrieUpdate {
         let key = key.to_vec();
         if let Some(key_value) = self.prospective.get(&key) {
             return Ok(key_value.value.as_ref().map(TrieUpdateValuePtr::MemoryRef));
-        } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
-            if let Some(RawStateChange { data, .. }) = changes_with_trie_key.changes.last() {
-                return Ok(data.as_ref().map(TrieUpdateValuePtr::MemoryRef));
-            }
+        } else if let Some(data) = self.last_committed(&key) {
+            return Ok(data.as_ref().map(TrieUpdateValuePtr::MemoryRef));
         }
         self.trie.get_ref(&self.root, &key).map(|option| {
             option.map(|(length, hash)| TrieUpdateValuePtr::HashAndSize(&self.trie, length, hash))
@@ -116,6 +175,7 @@ impl TrieUpdate {
 
     pub fn finalize(self) -> Result<(TrieChanges, Vec<RawStateChangesWithTrieKey>), StorageError> {
         assert!(self.prospective.is_empty(), "Finalize cannot be called with uncommitted changes.");
+        assert!(self.base.is_none(), "Forks must be merged instead of finalized.");
         let TrieUpdate { trie, root, committed, .. } = self;
         let mut state_changes = Vec::with_capacity(committed.len());
         let trie_changes = trie.update(
@@ -136,6 +196,7 @@ impl TrieUpdate {
 
     pub fn finalize_genesis(self) -> Result<TrieChanges, StorageError> {
         assert!(self.prospective.is_empty(), "Finalize cannot be called with uncommitted changes.");
+        assert!(self.base.is_none(), "Forks must be merged instead of finalized.");
         let TrieUpdate { trie, root, committed, .. } = self;
         let trie_changes = trie.update(
             &root,
@@ -227,8 +288,8 @@ impl<'a> TrieUpdateIterator<'a> {
             None => None,
         };
         trie_iter.seek(&start_offset)?;
-        let committed_iter = state_update.committed.range(start_offset.clone()..).map(
-            |(raw_key, changes_with_trie_key)| {
+        let last_change =
+            |(raw_key, changes_with_trie_key): (&'a Vec<u8>, &'a RawStateChangesWithTrieKey)| {
                 (
                     raw_key,
                     &changes_with_trie_key
@@ -238,14 +299,23 @@ impl<'a> TrieUpdateIterator<'a> {
                         .expect("Committed entry should have at least one change.")
                         .data,
                 )
-            },
-        );
+            };
+        let committed_iter = state_update.committed.range(start_offset.clone()..).map(last_change);
+        let committed_iter: Box<dyn Iterator<Item = _>> = match &state_update.base {
+            Some(base) => Box::new(MergeIter {
+                left: (Box::new(base.range(start_offset.clone()..).map(last_change))
+                    as Box<dyn Iterator<Item = _>>)
+                    .peekable(),
+                right: (Box::new(committed_iter) as Box<dyn Iterator<Item = _>>).peekable(),
+            }),
+            None => Box::new(committed_iter),
+        };
         let prospective_iter = state_update
             .prospective
             .range(start_offset..)
             .map(|(raw_key, key_value)| (raw_key, &key_value.value));
         let overlay_iter = MergeIter {
-            left: (Box::new(committed_iter) as Box<dyn Iterator<Item = _>>).peekable(),
+            left: committed_iter.peekable(),
             right: (Box::new(prospective_iter) as Box<dyn Iterator<Item = _>>).peekable(),
         }
         .peekable();
@@ -347,6 +417,8 @@ mod tests {
 
     use super::*;
     use crate::ShardUId;
+    use borsh::BorshSerialize;
+    use near_primitives::hash::hash;
     const SHARD_VERSION: u32 = 1;
     const COMPLEX_SHARD_UID: ShardUId = ShardUId { version: SHARD_VERSION, shard_id: 0 };
 
@@ -506,4 +578,46 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn trie_fork_merge() {
+        let tries = create_tries();
+        let cause = |n: u8| StateChangeCause::TransactionProcessing { tx_hash: hash(&[n]) };
+        let mut expected = tries.new_trie_update(ShardUId::default(), CryptoHash::default());
+        let mut trie_update = tries.new_trie_update(ShardUId::default(), CryptoHash::default());
+        for update in [&mut expected, &mut trie_update].iter_mut() {
+            update.set(test_key(b"dog".to_vec()), b"puppy".to_vec());
+            update.set(test_key(b"cat".to_vec()), b"kitten".to_vec());
+            update.commit(cause(0));
+        }
+        expected.remove(test_key(b"dog".to_vec()));
+        expected.set(test_key(b"dog2".to_vec()), b"puppy".to_vec());
+        expected.commit(cause(1));
+
+        let base = trie_update.share_committed();
+        let mut fork =
+            TrieUpdate::new_fork(trie_update.trie.clone(), CryptoHash::default(), base.clone());
+        assert_eq!(fork.get(&test_key(b"dog".to_vec())), Ok(Some(b"puppy".to_vec())));
+        fork.remove(test_key(b"dog".to_vec()));
+        fork.set(test_key(b"dog2".to_vec()), b"puppy".to_vec());
+        fork.commit(cause(1));
+        assert_eq!(fork.get(&test_key(b"dog".to_vec())), Ok(None));
+        let values: Result<Vec<Vec<u8>>, _> =
+            fork.iter(&test_key(vec![]).to_vec()).unwrap().collect();
+        assert_eq!(
+            values.unwrap(),
+            vec![test_key(b"cat".to_vec()).to_vec(), test_key(b"dog2".to_vec()).to_vec()]
+        );
+        let changes = fork.into_fork_changes();
+        trie_update.restore_committed(base);
+        trie_update.merge_fork(changes);
+
+        let (expected_trie_changes, expected_state_changes) = expected.finalize().unwrap();
+        let (trie_changes, state_changes) = trie_update.finalize().unwrap();
+        assert_eq!(trie_changes.new_root, expected_trie_changes.new_root);
+        assert_eq!(
+            state_changes.try_to_vec().unwrap(),
+            expected_state_changes.try_to_vec().unwrap()
+        );
+    }
 }
@@ -12,7 +12,8 @@ use indicatif::{ProgressBar, ProgressStyle};
 use crate::state_dump::StateDump;
 use near_chain::types::BlockHeaderInfo;
 use near_chain::{Block, Chain, ChainStore, RuntimeAdapter};
-use near_chain_configs::Genesis;
+use near_chain_configs::records_dump::{shard_records_path, RecordsReader, GENESIS_CONFIG_FILE};
+use near_chain_configs::{Genesis, GenesisConfig, GenesisRecords};
 use near_crypto::{InMemorySigner, KeyType};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::block::{genesis_chunks, Tip};
@@ -24,10 +25,57 @@ use near_primitives::state_record::StateRecord;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{AccountId, Balance, EpochId, ShardId, StateChangeCause, StateRoot};
 use near_store::{
-    create_store, get_account, set_access_key, set_account, set_code, Store, TrieUpdate,
+    create_store, get_account, set_access_key, set_account, set_code, set_genesis_hash,
+    set_genesis_state_roots, ShardTries, Store, TrieUpdate,
 };
+use nearcore::config::GENESIS_CONFIG_FILENAME;
 use nearcore::{get_store_path, NightshadeRuntime};
 
+/// Writes the raw trie key-values of a records dump into the state of each shard. Returns the
+/// state roots.
+fn load_records_dump(
+    store: Arc<Store>,
+    genesis_config: &GenesisConfig,
+    dump_dir: &Path,
+) -> Result<Vec<StateRoot>> {
+    // Apply records in batches for memory efficiency reasons.
+    const BATCH_SIZE: usize = 100_000;
+    let shard_layout = &genesis_config.shard_layout;
+    let tries = ShardTries::new(store, shard_layout.version(), shard_layout.num_shards());
+    let mut roots = vec![];
+    for shard_id in 0..shard_layout.num_shards() {
+        let reader = RecordsReader::open(&shard_records_path(dump_dir, shard_id))?;
+        if reader.shard_id() != shard_id {
+            return Err(format!(
+                "Expected records of shard {}, got {}",
+                shard_id,
+                reader.shard_id()
+            )
+            .into());
+        }
+        let shard_uid = ShardUId { version: shard_layout.version(), shard_id: shard_id as u32 };
+        let trie = tries.get_trie_for_shard(shard_uid);
+        let mut root = StateRoot::default();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut num_records = 0;
+        let mut records = reader.peekable();
+        while let Some(item) = records.next() {
+            let (key, value) = item?;
+            batch.push((key, Some(value)));
+            num_records += 1;
+            if batch.len() == BATCH_SIZE || records.peek().is_none() {
+                let trie_changes = trie.update(&root, batch.drain(..))?;
+                let (store_update, new_root) = tries.apply_all(&trie_changes, shard_uid)?;
+                store_update.commit()?;
+                root = new_root;
+            }
+        }
+        println!("Shard {}: loaded {} records", shard_id, num_records);
+        roots.push(root);
+    }
+    Ok(roots)
+}
+
 fn get_account_id(account_index: u64) -> AccountId {
     AccountId::try_from(format!("near_{}_{}", account_index, account_index)).unwrap()
 }
@@ -94,6 +142,44 @@ impl GenesisBuilder {
         Self::from_config_and_store(home_dir, genesis, store)
     }
 
+    /// Starts from the state in a records dump of `state-viewer dump_state_records` instead of
+    /// the records of a genesis. The genesis config is taken from the dump and written to the
+    /// home directory, which must not have a different one already.
+    pub fn from_records_dump(home_dir: &Path, dump_dir: &Path, store: Arc<Store>) -> Result<Self> {
+        let config = GenesisConfig::from_file(dump_dir.join(GENESIS_CONFIG_FILE));
+        // The records are in the dump, so the genesis can't be validated against them.
+        let genesis =
+            Genesis { config, records: GenesisRecords(vec![]), records_file: PathBuf::new() };
+        // The node reads its genesis config from the home directory, so it has to be the one the
+        // records were dumped with.
+        let genesis_path = home_dir.join(GENESIS_CONFIG_FILENAME);
+        if genesis_path.exists() {
+            let home_genesis = Genesis {
+                config: GenesisConfig::from_file(&genesis_path),
+                records: GenesisRecords(vec![]),
+                records_file: PathBuf::new(),
+            };
+            if home_genesis.json_hash() != genesis.json_hash() {
+                return Err(format!(
+                    "Genesis config {} differs from the one in the records dump {}",
+                    genesis_path.display(),
+                    dump_dir.join(GENESIS_CONFIG_FILE).display()
+                )
+                .into());
+            }
+        } else {
+            genesis.to_file(&genesis_path);
+        }
+        let roots = load_records_dump(store.clone(), &genesis.config, dump_dir)?;
+        // With the genesis state roots in the store the runtime doesn't compute them from the
+        // records of the genesis.
+        let mut store_update = store.store_update();
+        set_genesis_hash(&mut store_update, &genesis.json_hash());
+        set_genesis_state_roots(&mut store_update, &roots);
+        store_update.commit()?;
+        Ok(Self::from_config_and_store(home_dir, Arc::new(genesis), store))
+    }
+
     pub fn print_progress(mut self) -> Self {
         self.print_progress = true;
         self
@@ -18,18 +18,26 @@ fn main() {
                 .help("Directory for config and data (default \"~/.near\")")
                 .takes_value(true),
         )
-        .arg(Arg::with_name("additional-accounts-num").long("additional-accounts-num").required(true).takes_value(true).help("Number of additional accounts per shard to add directly to the trie (TESTING ONLY)"))
+        .arg(Arg::with_name("additional-accounts-num").long("additional-accounts-num").required_unless("records-dump").takes_value(true).help("Number of additional accounts per shard to add directly to the trie (TESTING ONLY)"))
+        .arg(Arg::with_name("records-dump").long("records-dump").takes_value(true).help("Directory with the records and genesis config written by `state-viewer dump_state_records` to start the state from"))
         .get_matches();
 
     let home_dir = matches.value_of("home").map(|dir| Path::new(dir)).unwrap();
     let additional_accounts_num = matches
         .value_of("additional-accounts-num")
         .map(|x| x.parse::<u64>().expect("Failed to parse number of additional accounts."))
-        .unwrap();
-    let near_config = load_config(home_dir);
+        .unwrap_or_default();
 
     let store = create_store(&get_store_path(home_dir));
-    GenesisBuilder::from_config_and_store(home_dir, Arc::new(near_config.genesis), store)
+    let genesis_builder = match matches.value_of("records-dump") {
+        Some(dump_dir) => GenesisBuilder::from_records_dump(home_dir, Path::new(dump_dir), store)
+            .expect("Failed to load records dump"),
+        None => {
+            let near_config = load_config(home_dir);
+            GenesisBuilder::from_config_and_store(home_dir, Arc::new(near_config.genesis), store)
+        }
+    };
+    genesis_builder
         .add_additional_accounts(additional_accounts_num)
         .add_additional_accounts_contract(near_test_contracts::tiny_contract().to_vec())
         .print_progress()
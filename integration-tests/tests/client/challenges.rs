@@ -17,6 +17,8 @@ use near_crypto::{InMemorySigner, KeyType, Signer};
 use near_logger_utils::init_test_logger;
 use near_network::test_utils::MockNetworkAdapter;
 use near_network::NetworkRequests;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::challenge::ChunkStateV2;
 use near_primitives::challenge::{
     BlockDoubleSign, Challenge, ChallengeBody, ChunkProofs, MaybeEncodedShardChunk,
 };
@@ -26,11 +28,15 @@ use near_primitives::num_rational::Rational;
 use near_primitives::receipt::Receipt;
 use near_primitives::runtime::config_store::RuntimeConfigStore;
 use near_primitives::serialize::BaseDecode;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::sharding::ReceiptProof;
 use near_primitives::sharding::{EncodedShardChunk, ReedSolomonWrapper};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, EpochId, StateRoot};
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::InMemoryValidatorSigner;
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+use near_primitives::version::ProtocolFeature;
 use near_primitives::version::PROTOCOL_VERSION;
 use near_store::test_utils::create_test_store;
 use nearcore::config::{GenesisExt, FISHERMEN_THRESHOLD};
@@ -267,27 +273,79 @@ fn challenge(
     )
 }
 
-#[test]
-fn test_verify_chunk_invalid_state_challenge() {
+fn setup_chunk_state_env(genesis: &Genesis) -> TestEnv {
     let store1 = create_test_store();
-    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
-    let transaction_validity_period = genesis.config.transaction_validity_period;
     let runtimes: Vec<Arc<dyn RuntimeAdapter>> = vec![Arc::new(nearcore::NightshadeRuntime::new(
         Path::new("."),
         store1,
-        &genesis,
+        genesis,
         vec![],
         vec![],
         None,
         None,
         RuntimeConfigStore::test(),
     ))];
-    let mut env = TestEnv::new_with_runtime(ChainGenesis::test(), 1, 1, runtimes);
+    TestEnv::new_with_runtime(ChainGenesis::test(), 1, 1, runtimes)
+}
+
+fn create_chunk_state_challenge(
+    client: &mut Client,
+    genesis: &Genesis,
+    prev_block: &Block,
+    block: &Block,
+) -> Result<ChallengeBody, Error> {
+    use near_chain::chain::{ChainUpdate, OrphanBlockPool};
+    let genesis_block = client.chain.genesis_block().clone();
+    let chain = &mut client.chain;
+    let adapter = chain.runtime_adapter.clone();
+    let epoch_length = chain.epoch_length;
+    let empty_block_pool = OrphanBlockPool::new();
+    let empty_chunks_pool = MissingChunksPool::new();
+    let chain_genesis = ChainGenesis::from(genesis);
+    let economics_config = BlockEconomicsConfig::from(&chain_genesis);
+
+    let mut chain_update = ChainUpdate::new(
+        chain.mut_store(),
+        adapter,
+        &empty_block_pool,
+        &empty_chunks_pool,
+        epoch_length,
+        &economics_config,
+        DoomslugThresholdMode::NoApprovals,
+        &genesis_block,
+        genesis.config.transaction_validity_period,
+        None,
+    );
+
+    chain_update.create_chunk_state_challenge(prev_block, block, &block.chunks()[0])
+}
+
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn validate_chunk_state_challenge(
+    client: &Client,
+    chunk_state: ChunkStateV2,
+    block: &Block,
+) -> Result<(CryptoHash, Vec<AccountId>), Error> {
+    let challenge = Challenge::produce(
+        ChallengeBody::ChunkStateV2(chunk_state),
+        &*client.validator_signer.as_ref().unwrap().clone(),
+    );
+    validate_challenge(
+        &*client.chain.runtime_adapter,
+        &block.header().epoch_id(),
+        &block.header().prev_hash(),
+        &challenge,
+    )
+}
+
+#[test]
+fn test_verify_chunk_invalid_state_challenge() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    let mut env = setup_chunk_state_env(&genesis);
     let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
     let validator_signer =
         InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
     let genesis_hash = *env.clients[0].chain.genesis().hash();
-    let genesis_block = env.clients[0].chain.genesis_block().clone();
     env.produce_block(0, 1);
     env.clients[0].process_tx(
         SignedTransaction::send_money(
@@ -379,38 +437,33 @@ fn test_verify_chunk_invalid_state_challenge() {
         block_merkle_tree.root(),
     );
 
-    let challenge_body = {
-        use near_chain::chain::{ChainUpdate, OrphanBlockPool};
-        let chain = &mut client.chain;
-        let adapter = chain.runtime_adapter.clone();
-        let epoch_length = chain.epoch_length;
-        let empty_block_pool = OrphanBlockPool::new();
-        let empty_chunks_pool = MissingChunksPool::new();
-        let chain_genesis = ChainGenesis::from(&genesis);
-        let economics_config = BlockEconomicsConfig::from(&chain_genesis);
-
-        let mut chain_update = ChainUpdate::new(
-            chain.mut_store(),
-            adapter,
-            &empty_block_pool,
-            &empty_chunks_pool,
-            epoch_length,
-            &economics_config,
-            DoomslugThresholdMode::NoApprovals,
-            &genesis_block,
-            transaction_validity_period,
-            None,
-        );
-
-        chain_update.create_chunk_state_challenge(&last_block, &block, &block.chunks()[0]).unwrap()
-    };
+    let challenge_body =
+        create_chunk_state_challenge(client, &genesis, &last_block, &block).unwrap();
     {
+        let (prev_merkle_proof, merkle_proof, partial_state) = match &challenge_body {
+            ChallengeBody::ChunkState(chunk_state) => (
+                &chunk_state.prev_merkle_proof,
+                &chunk_state.merkle_proof,
+                &chunk_state.partial_state,
+            ),
+            #[cfg(feature = "protocol_feature_chunk_state_challenge")]
+            ChallengeBody::ChunkStateV2(chunk_state) => {
+                assert_eq!(chunk_state.prev_block_ancestors.len(), 1);
+                assert_eq!(chunk_state.prev_incoming_receipts.len(), 1);
+                (
+                    &chunk_state.prev_merkle_proof,
+                    &chunk_state.merkle_proof,
+                    &chunk_state.partial_state,
+                )
+            }
+            body => panic!("Expected a chunk state challenge, got {:?}", body),
+        };
         let prev_merkle_proofs = Block::compute_chunk_headers_root(last_block.chunks().iter()).1;
         let merkle_proofs = Block::compute_chunk_headers_root(block.chunks().iter()).1;
-        assert_eq!(prev_merkle_proofs[0], challenge_body.prev_merkle_proof);
-        assert_eq!(merkle_proofs[0], challenge_body.merkle_proof);
+        assert_eq!(&prev_merkle_proofs[0], prev_merkle_proof);
+        assert_eq!(&merkle_proofs[0], merkle_proof);
         assert_eq!(
-            challenge_body.partial_state.0,
+            partial_state.0,
             vec![
                 vec![
                     1, 5, 0, 10, 178, 228, 151, 124, 13, 70, 6, 146, 31, 193, 111, 108, 60, 102,
@@ -427,8 +480,7 @@ fn test_verify_chunk_invalid_state_challenge() {
             ],
         );
     }
-    let challenge =
-        Challenge::produce(ChallengeBody::ChunkState(challenge_body), &validator_signer);
+    let challenge = Challenge::produce(challenge_body, &validator_signer);
     let runtime_adapter = client.chain.runtime_adapter.clone();
     assert_eq!(
         validate_challenge(
@@ -454,6 +506,137 @@ fn test_verify_chunk_invalid_state_challenge() {
     }
 }
 
+/// Produces blocks with a transfer in them and returns the client and the blocks by height.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn produce_blocks_with_receipts(genesis: &Genesis, num_blocks: u64) -> (TestEnv, Vec<Block>) {
+    let mut env = setup_chunk_state_env(genesis);
+    let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    env.produce_block(0, 1);
+    env.clients[0].process_tx(
+        SignedTransaction::send_money(
+            1,
+            "test0".parse().unwrap(),
+            "test1".parse().unwrap(),
+            &signer,
+            1000,
+            genesis_hash,
+        ),
+        false,
+        false,
+    );
+    for height in 2..=num_blocks {
+        env.produce_block(0, height);
+    }
+    let blocks = (0..=num_blocks)
+        .map(|height| env.clients[0].chain.get_block_by_height(height).unwrap().clone())
+        .collect();
+    (env, blocks)
+}
+
+/// Creates a `ChunkStateV2` challenge for the only chunk in `block`.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn create_chunk_state_v2_challenge(
+    client: &mut Client,
+    genesis: &Genesis,
+    prev_block: &Block,
+    block: &Block,
+) -> ChunkStateV2 {
+    match create_chunk_state_challenge(client, genesis, prev_block, block).unwrap() {
+        ChallengeBody::ChunkStateV2(chunk_state) => chunk_state,
+        body => panic!("Expected a ChunkStateV2 challenge, got {:?}", body),
+    }
+}
+
+/// Genesis at the first protocol version with `ChunkStateV2` challenges.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn chunk_state_v2_genesis() -> Genesis {
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.protocol_version = ProtocolFeature::ChunkStateChallenge.protocol_version();
+    genesis
+}
+
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+fn has_incoming_receipts(chunk_state: &ChunkStateV2) -> bool {
+    chunk_state.prev_incoming_receipts.iter().any(|incoming| {
+        incoming.receipt_proofs.iter().any(|ReceiptProof(receipts, _)| !receipts.is_empty())
+    })
+}
+
+/// Challenging valid state transitions, with and without incoming receipts, is malicious.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+#[test]
+fn test_verify_chunk_state_malicious_challenge_valid_chunk() {
+    let genesis = chunk_state_v2_genesis();
+    let (mut env, blocks) = produce_blocks_with_receipts(&genesis, 5);
+    let client = &mut env.clients[0];
+    let mut applied_receipts = false;
+    for height in 2..blocks.len() {
+        let chunk_state =
+            create_chunk_state_v2_challenge(client, &genesis, &blocks[height - 1], &blocks[height]);
+        applied_receipts |= has_incoming_receipts(&chunk_state);
+        assert_eq!(
+            validate_chunk_state_challenge(client, chunk_state, &blocks[height])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::MaliciousChallenge
+        );
+    }
+    assert!(applied_receipts);
+}
+
+/// Without the receipts, the valid chunk would not match the state transition, so the verifier
+/// must not accept incoming receipts that were not sent to the shard.
+#[cfg(feature = "protocol_feature_chunk_state_challenge")]
+#[test]
+fn test_verify_chunk_state_challenge_tampered_receipts() {
+    let genesis = chunk_state_v2_genesis();
+    let (mut env, blocks) = produce_blocks_with_receipts(&genesis, 5);
+    let client = &mut env.clients[0];
+    let (chunk_state, block) = (2..blocks.len())
+        .map(|height| {
+            let chunk_state = create_chunk_state_v2_challenge(
+                client,
+                &genesis,
+                &blocks[height - 1],
+                &blocks[height],
+            );
+            (chunk_state, &blocks[height])
+        })
+        .find(|(chunk_state, _)| has_incoming_receipts(chunk_state))
+        .unwrap();
+
+    // Drop the receipts but keep the proof.
+    let mut dropped_receipts = chunk_state.clone();
+    for incoming in dropped_receipts.prev_incoming_receipts.iter_mut() {
+        for ReceiptProof(receipts, _) in incoming.receipt_proofs.iter_mut() {
+            receipts.clear();
+        }
+    }
+    assert_eq!(
+        validate_chunk_state_challenge(client, dropped_receipts, block).unwrap_err().kind(),
+        ErrorKind::MaliciousChallenge
+    );
+
+    // Drop the receipt proofs altogether.
+    let mut dropped_proofs = chunk_state.clone();
+    for incoming in dropped_proofs.prev_incoming_receipts.iter_mut() {
+        incoming.receipt_proofs.clear();
+    }
+    assert_eq!(
+        validate_chunk_state_challenge(client, dropped_proofs, block).unwrap_err().kind(),
+        ErrorKind::MaliciousChallenge
+    );
+
+    // Claim that the previous chunk was applied with the receipts of fewer blocks.
+    let mut dropped_blocks = chunk_state;
+    dropped_blocks.prev_incoming_receipts.pop();
+    assert_eq!(
+        validate_chunk_state_challenge(client, dropped_blocks, block).unwrap_err().kind(),
+        ErrorKind::MaliciousChallenge
+    );
+}
+
 /// Receive invalid state transition in chunk as next chunk producer.
 /// TODO(2445): Enable challenges when they are working correctly.
 #[test]
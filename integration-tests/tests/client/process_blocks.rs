@@ -2020,6 +2020,7 @@ fn test_data_reset_before_state_sync() {
             &head.last_block_hash,
             head_block.header().epoch_id(),
             &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+            None,
         )
         .unwrap();
     assert!(matches!(response.kind, QueryResponseKind::ViewAccount(_)));
@@ -2034,6 +2035,7 @@ fn test_data_reset_before_state_sync() {
         &head.last_block_hash,
         head_block.header().epoch_id(),
         &QueryRequest::ViewAccount { account_id: "test_account".parse().unwrap() },
+        None,
     );
     // TODO(#3742): ViewClient still has data in cache by current design.
     assert!(response.is_ok());
@@ -2734,6 +2736,7 @@ fn test_query_final_state() {
                 last_final_block.hash(),
                 last_final_block.header().epoch_id(),
                 &QueryRequest::ViewAccount { account_id },
+                None,
             )
             .unwrap();
         match response.kind {
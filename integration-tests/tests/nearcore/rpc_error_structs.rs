@@ -424,6 +424,7 @@ fn test_query_rpc_account_view_unknown_block_must_return_error() {
                 request: near_primitives::views::QueryRequest::ViewAccount {
                     account_id: "near.0".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await;
 
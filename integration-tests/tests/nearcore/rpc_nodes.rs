@@ -285,6 +285,7 @@ fn test_query_rpc_account_view_must_succeed() {
                 request: near_primitives::views::QueryRequest::ViewAccount {
                     account_id: "near.0".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await
             .unwrap();
@@ -323,6 +324,7 @@ fn test_query_rpc_account_view_account_doesnt_exist_must_return_error() {
                 request: near_primitives::views::QueryRequest::ViewAccount {
                     account_id: "accountdoesntexist.0".parse().unwrap(),
                 },
+                state_overrides: None,
             })
             .await;
 
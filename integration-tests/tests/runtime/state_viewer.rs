@@ -5,7 +5,11 @@ use near_primitives::{
     account::Account,
     hash::hash as sha256,
     hash::CryptoHash,
-    views::{StateItem, ViewApplyState},
+    serialize::{from_base64, to_base64},
+    views::{
+        AccountStateOverride, StateItem, StateOverrides, StorageOverride, ViewApplyState,
+        ViewStatePageResult,
+    },
 };
 use near_primitives::{
     test_utils::MockEpochInfoProvider,
@@ -105,6 +109,64 @@ fn test_view_call_with_args() {
     assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
 }
 
+fn view_call_with_state_overrides(
+    state_overrides: &StateOverrides,
+    method_name: &str,
+    args: &[u8],
+) -> Result<Vec<u8>, errors::CallFunctionError> {
+    let (viewer, mut root) = get_test_trie_viewer();
+    viewer.apply_state_overrides(&mut root, state_overrides)?;
+    let mut logs = vec![];
+    let view_state = ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    viewer.call_function(
+        root,
+        view_state,
+        &"test.contract".parse().unwrap(),
+        method_name,
+        args,
+        &mut logs,
+        &MockEpochInfoProvider::default(),
+    )
+}
+
+#[test]
+fn test_view_call_with_state_overrides() {
+    let key = 1u64.to_le_bytes().to_vec();
+    let value = 7u64.to_le_bytes().to_vec();
+    let mut state_overrides = StateOverrides::new();
+    state_overrides.insert(
+        "test.contract".parse().unwrap(),
+        AccountStateOverride {
+            code: None,
+            balance: Some(42),
+            storage: vec![StorageOverride { key: key.clone().into(), value: Some(value.clone()) }],
+        },
+    );
+
+    let balance =
+        view_call_with_state_overrides(&state_overrides, "ext_account_balance", &[]).unwrap();
+    assert_eq!(balance, 42u128.to_le_bytes().to_vec());
+    let result = view_call_with_state_overrides(&state_overrides, "read_value", &key).unwrap();
+    assert_eq!(result, value);
+}
+
+#[test]
+fn test_view_call_with_state_overrides_unknown_account() {
+    let mut state_overrides = StateOverrides::new();
+    state_overrides.insert("unknown.near".parse().unwrap(), AccountStateOverride::default());
+    let err = view_call_with_state_overrides(&state_overrides, "run_test", &[]).unwrap_err();
+    assert!(matches!(err, errors::CallFunctionError::AccountDoesNotExist { .. }));
+}
+
 #[test]
 fn test_view_state() {
     let (_, tries, root) = get_runtime_and_trie();
@@ -151,6 +213,69 @@ fn test_view_state() {
     );
 }
 
+#[test]
+fn test_view_state_paginated() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let mut state_update = tries.new_trie_update(shard_uid, root);
+    for key in &[b"key1", b"key2", b"key3", b"kez1"] {
+        state_update.set(
+            TrieKey::ContractData { account_id: alice_account(), key: key.to_vec() },
+            b"value".to_vec(),
+        );
+    }
+    set_account(
+        &mut state_update,
+        alice_account(),
+        &Account::new(0, 0, CryptoHash::default(), 50_001),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().0;
+    let (db_changes, new_root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+    db_changes.commit().unwrap();
+
+    let state_update = tries.new_trie_update(shard_uid, new_root);
+    let trie_viewer = TrieViewer::new(Some(50_000), None);
+    let keys = |result: &ViewStatePageResult| {
+        result.values.iter().map(|item| from_base64(&item.key).unwrap()).collect::<Vec<_>>()
+    };
+
+    let result =
+        trie_viewer.view_state_page(&state_update, &alice_account(), b"key", None, 2).unwrap();
+    assert_eq!(keys(&result), vec![b"key1".to_vec(), b"key2".to_vec()]);
+    let next_key = from_base64(result.next_key.as_ref().unwrap()).unwrap();
+    assert_eq!(next_key, b"key3".to_vec());
+
+    let result = trie_viewer
+        .view_state_page(&state_update, &alice_account(), b"key", Some(next_key.as_slice()), 2)
+        .unwrap();
+    assert_eq!(keys(&result), vec![b"key3".to_vec()]);
+    assert_eq!(result.next_key, None);
+
+    // A start key before the prefix starts from the beginning of the prefix.
+    let result = trie_viewer
+        .view_state_page(&state_update, &alice_account(), b"kez", Some(&b"a"[..]), 10)
+        .unwrap();
+    assert_eq!(keys(&result), vec![b"kez1".to_vec()]);
+    assert_eq!(result.next_key, None);
+
+    // The state size limit bounds the size of a page.
+    let trie_viewer = TrieViewer::new(Some(20), None);
+    let result =
+        trie_viewer.view_state_page(&state_update, &alice_account(), b"", None, 10).unwrap();
+    assert_eq!(keys(&result), vec![b"key1".to_vec(), b"key2".to_vec()]);
+    assert_eq!(result.next_key, Some(to_base64(b"key3")));
+}
+
+#[test]
+fn test_view_state_page_zero_limit() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    let trie_viewer = TrieViewer::default();
+    let result = trie_viewer.view_state_page(&state_update, &alice_account(), b"", None, 0);
+    assert!(matches!(result, Err(errors::ViewStateError::InvalidPageLimit)));
+}
+
 #[test]
 fn test_view_state_too_large() {
     let (_, tries, root) = get_runtime_and_trie();
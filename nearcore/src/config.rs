@@ -2,7 +2,7 @@ use std::convert::TryInto;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -309,6 +309,10 @@ fn default_view_client_threads() -> usize {
     4
 }
 
+fn default_state_sync_threads() -> usize {
+    4
+}
+
 fn default_doomslug_step_period() -> Duration {
     Duration::from_millis(100)
 }
@@ -426,6 +430,13 @@ pub struct Config {
     pub gc_blocks_limit: NumBlocks,
     #[serde(default = "default_view_client_threads")]
     pub view_client_threads: usize,
+    /// Number of threads to validate and apply state parts during state sync.
+    #[serde(default = "default_state_sync_threads")]
+    pub state_sync_threads: usize,
+    /// Directory with state parts dumped by `state-viewer dump_state_parts`, relative to the
+    /// home directory. If set, state sync takes the state from there before asking peers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_sync_dump_dir: Option<PathBuf>,
     pub epoch_sync_enabled: bool,
     #[serde(default = "default_view_client_throttle_period")]
     pub view_client_throttle_period: Duration,
@@ -434,6 +445,18 @@ pub struct Config {
     /// If set, overrides value in genesis configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_gas_burnt_view: Option<Gas>,
+    /// Additional log filter directives in `RUST_LOG` format, e.g. `"chain=debug,network=warn"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_filter: Option<String>,
+    /// Compile the contracts deployed in the tracked shards in the background on start, e.g.
+    /// after a VM upgrade. Stale cached contracts are removed by `state-viewer
+    /// precompile_contracts --purge` while the node is stopped.
+    #[serde(default)]
+    pub precompile_contracts_on_start: bool,
+    /// Execute independent receipts of a chunk in parallel. The resulting state is the same as
+    /// with sequential execution.
+    #[serde(default)]
+    pub parallel_receipts: bool,
 }
 
 impl Default for Config {
@@ -457,9 +480,14 @@ impl Default for Config {
             gc_blocks_limit: default_gc_blocks_limit(),
             epoch_sync_enabled: true,
             view_client_threads: default_view_client_threads(),
+            state_sync_threads: default_state_sync_threads(),
+            state_sync_dump_dir: None,
             view_client_throttle_period: default_view_client_throttle_period(),
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
             max_gas_burnt_view: None,
+            log_filter: None,
+            precompile_contracts_on_start: false,
+            parallel_receipts: false,
         }
     }
 }
@@ -657,6 +685,8 @@ impl NearConfig {
                     .consensus
                     .header_sync_expected_height_per_second,
                 state_sync_timeout: config.consensus.state_sync_timeout,
+                state_sync_threads: config.state_sync_threads,
+                state_sync_dump_dir: config.state_sync_dump_dir.clone(),
                 min_num_peers: config.consensus.min_num_peers,
                 log_summary_period: Duration::from_secs(10),
                 produce_empty_blocks: config.consensus.produce_empty_blocks,
@@ -746,6 +776,11 @@ impl NearConfig {
 }
 
 impl NearConfig {
+    /// Config as it was loaded from `config.json`, without command line overrides.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// Test tool to save configs back to the folder.
     /// Useful for dynamic creating testnet configs and then saving them in different folders.
     pub fn save_to_dir(&self, dir: &Path) {
@@ -1190,12 +1225,15 @@ pub fn load_config_without_genesis_records(dir: &Path) -> NearConfig {
         None
     };
     let network_signer = NodeKeyFile::from_file(&dir.join(&config.node_key_file));
-    NearConfig::new(
+    let mut near_config = NearConfig::new(
         config,
         Genesis::new_with_path(genesis_config, genesis_records_file),
         network_signer.into(),
         validator_signer,
-    )
+    );
+    near_config.client_config.state_sync_dump_dir =
+        near_config.config.state_sync_dump_dir.as_ref().map(|path| dir.join(path));
+    near_config
 }
 
 pub fn load_config(dir: &Path) -> NearConfig {
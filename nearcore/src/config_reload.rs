@@ -0,0 +1,199 @@
+//! Applying changes of `config.json` to a running node.
+//!
+//! Only the fields listed in `RELOADABLE_FIELDS` can be changed without restarting the node.
+//! Changes to any other field are reported and ignored until the next restart.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use actix::Addr;
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use near_client::{ClientActor, UpdateClientConfig};
+#[cfg(feature = "json_rpc")]
+use near_jsonrpc::RpcLimitsHandle;
+use near_network::types::SetBlacklist;
+use near_network::utils::blacklist_from_iter;
+use near_network::PeerManagerActor;
+
+use crate::config::{Config, CONFIG_FILENAME};
+
+#[cfg(feature = "json_rpc")]
+const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
+
+/// Fields of `Config` that are applied to the running node, nested fields are separated by dots.
+/// `log_filter` is not applied by the reloader itself, the caller owns the tracing subscriber.
+pub const RELOADABLE_FIELDS: &[&str] = &[
+    "tracked_accounts",
+    "gc_blocks_limit",
+    "log_filter",
+    "network.blacklist",
+    "rpc.limits_config",
+];
+
+fn is_reloadable(field: &str) -> bool {
+    RELOADABLE_FIELDS.iter().any(|reloadable| {
+        field == *reloadable
+            || (field.starts_with(reloadable) && field[reloadable.len()..].starts_with('.'))
+    })
+}
+
+fn collect_changed_fields(old: &Value, new: &Value, path: &str, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => collect_changed_fields(old, new, &field, changed),
+                    _ => changed.push(field),
+                }
+            }
+        }
+        _ => {
+            if old != new {
+                changed.push(path.to_string());
+            }
+        }
+    }
+}
+
+/// Returns the fields which differ between the two configs, nested fields are separated by dots.
+pub fn changed_fields(old: &Config, new: &Config) -> Vec<String> {
+    let old = serde_json::to_value(old).expect("Error serializing the config.");
+    let new = serde_json::to_value(new).expect("Error serializing the config.");
+    let mut changed = vec![];
+    collect_changed_fields(&old, &new, "", &mut changed);
+    changed
+}
+
+/// Reloads `config.json` and sends the reloadable settings to the actors of the running node.
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    /// Config the node is running with.
+    config: Config,
+    client: Addr<ClientActor>,
+    network: Addr<PeerManagerActor>,
+    #[cfg(feature = "json_rpc")]
+    rpc_limits: Option<RpcLimitsHandle>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        home_dir: &Path,
+        config: Config,
+        client: Addr<ClientActor>,
+        network: Addr<PeerManagerActor>,
+        #[cfg(feature = "json_rpc")] rpc_limits: Option<RpcLimitsHandle>,
+    ) -> Self {
+        Self {
+            config_path: home_dir.join(CONFIG_FILENAME),
+            config,
+            client,
+            network,
+            #[cfg(feature = "json_rpc")]
+            rpc_limits,
+        }
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Config the node is running with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Reads the config file and applies the changes of reloadable fields. Returns true if any
+    /// of them has changed.
+    pub fn reload(&mut self) -> bool {
+        let new_config = match fs::read_to_string(&self.config_path)
+            .map_err(|err| err.to_string())
+            .and_then(|content| {
+                serde_json::from_str::<Config>(&content).map_err(|err| err.to_string())
+            }) {
+            Ok(config) => config,
+            Err(err) => {
+                error!(target: "near", "Failed to reload {}: {}", self.config_path.display(), err);
+                return false;
+            }
+        };
+        let (reloadable, rejected): (Vec<_>, Vec<_>) =
+            changed_fields(&self.config, &new_config).into_iter().partition(|f| is_reloadable(f));
+        if !rejected.is_empty() {
+            warn!(target: "near", "Ignoring changes of {} in {}: these fields can only be changed by restarting the node", rejected.join(", "), self.config_path.display());
+        }
+        if reloadable.is_empty() {
+            return false;
+        }
+        info!(target: "near", "Reloading {} from {}", reloadable.join(", "), self.config_path.display());
+
+        self.config.tracked_accounts = new_config.tracked_accounts;
+        self.config.gc_blocks_limit = new_config.gc_blocks_limit;
+        self.config.log_filter = new_config.log_filter;
+        self.config.network.blacklist = new_config.network.blacklist;
+        #[cfg(feature = "json_rpc")]
+        if let (Some(rpc), Some(new_rpc)) = (self.config.rpc.as_mut(), new_config.rpc) {
+            rpc.limits_config = new_rpc.limits_config;
+        }
+        self.apply(reloadable.iter().any(|field| field == "tracked_accounts"));
+        true
+    }
+
+    fn apply(&self, tracked_accounts_changed: bool) {
+        // Tracked accounts may have been changed at runtime, so they are only sent if they were
+        // changed in the file.
+        self.client.do_send(UpdateClientConfig {
+            tracked_accounts: if tracked_accounts_changed {
+                Some(self.config.tracked_accounts.clone())
+            } else {
+                None
+            },
+            gc_blocks_limit: self.config.gc_blocks_limit,
+        });
+        self.network.do_send(SetBlacklist {
+            blacklist: blacklist_from_iter(self.config.network.blacklist.clone()),
+        });
+        #[cfg(feature = "json_rpc")]
+        if let (Some(rpc_limits), Some(rpc)) = (self.rpc_limits.as_ref(), self.config.rpc.as_ref())
+        {
+            *rpc_limits.write().expect(POISONED_LOCK_ERR) = rpc.limits_config.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_fields() {
+        let config = Config::default();
+        let mut new_config = config.clone();
+        assert!(changed_fields(&config, &new_config).is_empty());
+
+        new_config.gc_blocks_limit += 1;
+        new_config.network.blacklist = vec!["127.0.0.1".to_string()];
+        new_config.network.max_num_peers += 1;
+        new_config.log_filter = Some("chain=debug".to_string());
+        let changed = changed_fields(&config, &new_config);
+        assert_eq!(
+            changed,
+            vec!["gc_blocks_limit", "log_filter", "network.blacklist", "network.max_num_peers"]
+        );
+        let rejected: Vec<_> = changed.into_iter().filter(|f| !is_reloadable(f)).collect();
+        assert_eq!(rejected, vec!["network.max_num_peers"]);
+    }
+
+    #[test]
+    fn test_is_reloadable() {
+        assert!(is_reloadable("rpc.limits_config"));
+        assert!(is_reloadable("rpc.limits_config.json_payload_max_size"));
+        assert!(!is_reloadable("rpc.limits_config_extra"));
+        assert!(!is_reloadable("rpc"));
+        assert!(!is_reloadable("tracked_shards"));
+    }
+}
@@ -9,7 +9,7 @@ use actix_web;
 use near_rust_allocator_proxy::allocator::reset_memory_usage_max;
 use tracing::{error, info, trace};
 
-use near_chain::ChainGenesis;
+use near_chain::{ChainGenesis, ChainStore, ChainStoreAccess};
 #[cfg(feature = "adversarial")]
 use near_client::AdversarialControls;
 use near_client::{start_client, start_view_client, ClientActor, ViewClientActor};
@@ -24,19 +24,24 @@ use near_store::migrations::{
     migrate_25_to_26, migrate_6_to_7, migrate_7_to_8, migrate_8_to_9, migrate_9_to_10,
     set_store_version,
 };
-use near_store::migrations::{migrate_20_to_21, migrate_26_to_27};
+use near_store::migrations::{
+    migrate_20_to_21, migrate_26_to_27, migrate_27_to_28, migrate_28_to_29,
+};
 use near_store::{create_store, Store};
 use near_telemetry::TelemetryActor;
 
 pub use crate::config::{init_configs, load_config, load_test_config, NearConfig, NEAR_BASE};
+pub use crate::config_reload::ConfigReloader;
 use crate::migrations::{
     migrate_12_to_13, migrate_18_to_19, migrate_19_to_20, migrate_22_to_23, migrate_23_to_24,
     migrate_24_to_25,
 };
 pub use crate::runtime::NightshadeRuntime;
 use near_primitives::runtime::config_store::RuntimeConfigStore;
+use near_primitives::types::BlockHeight;
 
 pub mod config;
+pub mod config_reload;
 pub mod migrations;
 mod runtime;
 mod shard_tracker;
@@ -234,6 +239,17 @@ pub fn apply_store_migrations(path: &Path, near_config: &NearConfig) {
         info!(target: "near", "Migrate DB from version 26 to 27");
         migrate_26_to_27(&path, near_config.client_config.archive);
     }
+    if db_version <= 27 {
+        info!(target: "near", "Migrate DB from version 27 to 28");
+        // version 27 => 28: add column for misbehavior evidence and slashed validators to
+        // epoch summaries
+        migrate_27_to_28(&path);
+    }
+    if db_version <= 28 {
+        info!(target: "near", "Migrate DB from version 28 to 29");
+        // version 28 => 29: add state parts download progress to state sync infos
+        migrate_28_to_29(&path);
+    }
     #[cfg(feature = "nightly_protocol")]
     {
         let store = create_store(&path);
@@ -270,23 +286,67 @@ pub fn init_and_migrate_store(home_dir: &Path, near_config: &NearConfig) -> Arc<
 pub struct NearNode {
     pub client: Addr<ClientActor>,
     pub view_client: Addr<ViewClientActor>,
+    /// Applies changes of the config file to the running node.
+    pub config_reloader: ConfigReloader,
     pub arbiters: Vec<ArbiterHandle>,
     pub rpc_servers: Vec<(&'static str, actix_web::dev::Server)>,
 }
 
+/// Compiles the contracts deployed in the tracked shards at the head of the chain on a background
+/// thread, so that the first calls to them after a VM upgrade or state sync are not slowed down.
+/// Stale entries are not purged: the cache may hold contracts precompiled for the protocol version
+/// of the next epoch or compiled by the running node, purging is left to the offline
+/// `state-viewer precompile_contracts --purge`.
+fn spawn_contracts_cache_warmup(
+    store: Arc<Store>,
+    runtime: Arc<NightshadeRuntime>,
+    genesis_height: BlockHeight,
+) {
+    std::thread::Builder::new()
+        .name("contracts-cache-warmup".to_string())
+        .spawn(move || {
+            let mut chain_store = ChainStore::new(store, genesis_height);
+            let result = chain_store
+                .head()
+                .and_then(|head| chain_store.get_block(&head.last_block_hash).map(Clone::clone))
+                .and_then(|block| {
+                    let state_roots: Vec<_> =
+                        block.chunks().iter().map(|chunk| chunk.prev_state_root()).collect();
+                    runtime.warmup_contracts_cache(block.header().prev_hash(), &state_roots, false)
+                });
+            match result {
+                Ok((stats, _)) => info!(target: "near", "Contracts cache warmup done: {:?}", stats),
+                Err(err) => error!(target: "near", "Contracts cache warmup failed: {}", err),
+            }
+        })
+        .expect("Failed to spawn contracts cache warmup thread");
+}
+
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> NearNode {
     let store = init_and_migrate_store(home_dir, &config);
+    let file_config = config.config().clone();
+
+    let runtime = Arc::new(
+        NightshadeRuntime::new(
+            home_dir,
+            Arc::clone(&store),
+            &config.genesis,
+            config.client_config.tracked_accounts.clone(),
+            config.client_config.tracked_shards.clone(),
+            config.client_config.trie_viewer_state_size_limit,
+            config.client_config.max_gas_burnt_view,
+            RuntimeConfigStore::new(Some(&config.genesis.config.runtime_config)),
+        )
+        .with_parallel_receipts(file_config.parallel_receipts),
+    );
 
-    let runtime = Arc::new(NightshadeRuntime::new(
-        home_dir,
-        Arc::clone(&store),
-        &config.genesis,
-        config.client_config.tracked_accounts.clone(),
-        config.client_config.tracked_shards.clone(),
-        config.client_config.trie_viewer_state_size_limit,
-        config.client_config.max_gas_burnt_view,
-        RuntimeConfigStore::new(Some(&config.genesis.config.runtime_config)),
-    ));
+    if file_config.precompile_contracts_on_start {
+        spawn_contracts_cache_warmup(
+            Arc::clone(&store),
+            Arc::clone(&runtime),
+            config.genesis.config.genesis_height,
+        );
+    }
 
     let telemetry = TelemetryActor::new(config.telemetry_config.clone()).start();
     let chain_genesis = ChainGenesis::from(&config.genesis);
@@ -321,9 +381,15 @@ pub fn start_with_config(home_dir: &Path, config: NearConfig) -> NearNode {
     let mut rpc_servers = Vec::new();
 
     #[cfg(feature = "json_rpc")]
-    if let Some(rpc_config) = config.rpc_config {
-        rpc_servers.extend_from_slice(&near_jsonrpc::start_http(
+    let rpc_limits = config
+        .rpc_config
+        .as_ref()
+        .map(|rpc_config| Arc::new(std::sync::RwLock::new(rpc_config.limits_config.clone())));
+    #[cfg(feature = "json_rpc")]
+    if let (Some(rpc_config), Some(rpc_limits)) = (config.rpc_config, rpc_limits.clone()) {
+        rpc_servers.extend_from_slice(&near_jsonrpc::start_http_with_limits_handle(
             rpc_config,
+            rpc_limits,
             config.genesis.config.clone(),
             client_actor.clone(),
             view_client.clone(),
@@ -355,7 +421,16 @@ pub fn start_with_config(home_dir: &Path, config: NearConfig) -> NearNode {
         PeerManagerActor::new(store, network_config, client_actor1, view_client1).unwrap()
     });
 
-    network_adapter.set_recipient(network_actor.recipient());
+    network_adapter.set_recipient(network_actor.clone().recipient());
+
+    let config_reloader = ConfigReloader::new(
+        home_dir,
+        file_config,
+        client_actor.clone(),
+        network_actor,
+        #[cfg(feature = "json_rpc")]
+        rpc_limits,
+    );
 
     rpc_servers.shrink_to_fit();
 
@@ -368,6 +443,7 @@ pub fn start_with_config(home_dir: &Path, config: NearConfig) -> NearNode {
     NearNode {
         client: client_actor,
         view_client,
+        config_reloader,
         rpc_servers,
         arbiters: vec![client_arbiter_handle, arbiter.handle()],
     }
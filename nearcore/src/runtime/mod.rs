@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 
 use borsh::ser::BorshSerialize;
@@ -9,12 +10,14 @@ use borsh::BorshDeserialize;
 use tracing::{debug, error, info, warn};
 
 use near_chain::chain::NUM_EPOCHS_TO_KEEP_STORE_DATA;
-use near_chain::types::{ApplyTransactionResult, BlockHeaderInfo, ValidatorInfoIdentifier};
+use near_chain::types::{
+    ApplyTransactionResult, BlockHeaderInfo, SimulateTransactionResult, ValidatorInfoIdentifier,
+};
 use near_chain::{BlockHeader, Error, ErrorKind, RuntimeAdapter};
 #[cfg(feature = "protocol_feature_block_header_v3")]
 use near_chain::{Doomslug, DoomslugThresholdMode};
 use near_chain_configs::{Genesis, GenesisConfig, ProtocolConfig};
-use near_crypto::{PublicKey, Signature};
+use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
 use near_epoch_manager::EpochManager;
 use near_pool::types::PoolIterator;
 use near_primitives::account::{AccessKey, Account};
@@ -26,29 +29,34 @@ use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::epoch_manager::EpochConfig;
 use near_primitives::errors::{EpochError, InvalidTxError, RuntimeError};
 use near_primitives::hash::{hash, CryptoHash};
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
 use near_primitives::sharding::ChunkHash;
 use near_primitives::state_record::{state_record_to_account_id, StateRecord};
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::syncing::get_epoch_sync_data_hash;
+use near_primitives::transaction::{SignedTransaction, Transaction};
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
     AccountId, ApprovalStake, Balance, BlockHeight, CompiledContractCache, EpochHeight, EpochId,
-    EpochInfoProvider, Gas, MerkleHash, NumShards, ShardId, StateChangeCause, StateRoot,
-    StateRootNode,
+    EpochInfoProvider, Gas, MerkleHash, NumShards, ShardId, StateChangeCause, StateChanges,
+    StateChangesExt, StateRoot, StateRootNode,
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
     AccessKeyInfoView, CallResult, EpochValidatorInfo, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewApplyState, ViewStateResult,
+    QueryResponseKind, StateOverrides, ViewApplyState, ViewStatePageResult, ViewStateResult,
 };
-use near_vm_runner::precompile_contract;
+use near_vm_runner::{precompile_contract, VMKind};
 
 use near_store::{
-    get_genesis_hash, get_genesis_state_roots, set_genesis_hash, set_genesis_state_roots,
-    ApplyStatePartResult, ColState, PartialStorage, ShardTries, Store, StoreCompiledContractCache,
-    StoreUpdate, Trie, WrappedTrieChanges,
+    get_genesis_hash, get_genesis_state_roots, set_access_key, set_genesis_hash,
+    set_genesis_state_roots, ApplyStatePartResult, ColState, PartialStorage, ShardTries, Store,
+    StoreCompiledContractCache, StoreUpdate, Trie, TrieUpdate, WrappedTrieChanges,
 };
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::cache::{
+    precompile_contracts, purge_compiled_contracts, ContractsPrecompilationStats,
+};
 use node_runtime::state_viewer::TrieViewer;
 use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
@@ -69,6 +77,15 @@ pub mod errors;
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 const STATE_DUMP_FILE: &str = "state_dump";
 const GENESIS_ROOTS_FILE: &str = "genesis_roots";
+/// Maximum number of blocks in which receipts of a simulated transaction are executed.
+const MAX_SIMULATED_BLOCKS: u64 = 100;
+/// Maximum number of receipts of a simulated transaction that are executed.
+const MAX_SIMULATED_RECEIPTS: usize = 1_000;
+/// Maximum total gas burnt while simulating a transaction. Delayed receipts of the shards, which
+/// are executed as well, count against it too.
+const MAX_SIMULATED_GAS: Gas = 1_000_000_000_000_000;
+/// Seed of the key unsigned transactions are signed with during simulation.
+const SIMULATION_KEY_SEED: &str = "simulation";
 
 /// Wrapper type for epoch manager to get avoid implementing trait for foreign types.
 pub struct SafeEpochManager(pub Arc<RwLock<EpochManager>>);
@@ -132,7 +149,7 @@ pub struct NightshadeRuntime {
     trie_viewer: TrieViewer,
     pub runtime: Runtime,
     epoch_manager: SafeEpochManager,
-    shard_tracker: ShardTracker,
+    shard_tracker: RwLock<ShardTracker>,
     genesis_state_roots: Vec<StateRoot>,
     migration_data: Arc<MigrationData>,
 }
@@ -182,12 +199,18 @@ impl NightshadeRuntime {
             runtime,
             trie_viewer,
             epoch_manager: SafeEpochManager(epoch_manager),
-            shard_tracker,
+            shard_tracker: RwLock::new(shard_tracker),
             genesis_state_roots: state_roots,
             migration_data: Arc::new(load_migration_data(&genesis.config.chain_id)),
         }
     }
 
+    /// Sets whether independent receipts of a chunk are executed in parallel.
+    pub fn with_parallel_receipts(mut self, parallel_receipts: bool) -> Self {
+        self.runtime = Runtime::new().with_parallel_receipts(parallel_receipts);
+        self
+    }
+
     fn get_epoch_height_from_prev_block(
         &self,
         prev_block_hash: &CryptoHash,
@@ -202,6 +225,56 @@ impl NightshadeRuntime {
         epoch_manager.get_epoch_id(hash).map_err(Error::from)
     }
 
+    /// Compiles the contracts deployed in the tracked shards into the compiled contracts cache.
+    /// `state_roots` are the states of all shards on top of `prev_block_hash`, the VM config is
+    /// the one of the next block. If `purge_stale` is set, removes the cached contracts which are
+    /// not deployed in these states or were compiled by another VM or with another config.
+    /// Returns the precompilation stats and the number of purged contracts.
+    pub fn warmup_contracts_cache(
+        &self,
+        prev_block_hash: &CryptoHash,
+        state_roots: &[StateRoot],
+        purge_stale: bool,
+    ) -> Result<(ContractsPrecompilationStats, u64), Error> {
+        let epoch_id = self.get_epoch_id_from_prev_block(prev_block_hash)?;
+        let protocol_version = self.get_epoch_protocol_version(&epoch_id)?;
+        let runtime_config = self.runtime_config_store.get_config(protocol_version);
+        let cache = StoreCompiledContractCache { store: self.store.clone() };
+        let mut cache_keys = HashSet::new();
+        let mut stats = ContractsPrecompilationStats::default();
+        // Same as in `precompile_contracts`, leave half of the threads to the node.
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(std::cmp::max(rayon::current_num_threads() / 2, 1))
+            .build()
+            .unwrap();
+        for (shard_id, state_root) in state_roots.iter().enumerate() {
+            let shard_id = shard_id as ShardId;
+            if !self.cares_about_shard(None, prev_block_hash, shard_id, true) {
+                continue;
+            }
+            let trie = self.get_trie_for_shard(shard_id, prev_block_hash)?;
+            let shard_stats = precompile_contracts(
+                &trie,
+                state_root,
+                VMKind::default(),
+                &runtime_config.wasm_config,
+                &cache,
+                &mut cache_keys,
+                &thread_pool,
+            )
+            .map_err(|err| Error::from(ErrorKind::StorageError(err)))?;
+            info!(target: "runtime", "Precompiled contracts of shard {}: {:?}", shard_id, shard_stats);
+            stats.merge(shard_stats);
+        }
+        let purged = if purge_stale {
+            purge_compiled_contracts(&self.store, &cache_keys)
+                .map_err(|err| Error::from(ErrorKind::Other(err.to_string())))?
+        } else {
+            0
+        };
+        Ok((stats, purged))
+    }
+
     fn genesis_state_from_dump(store: Arc<Store>, home_dir: &Path) -> Vec<StateRoot> {
         error!(target: "near", "Loading genesis from a state dump file. Do not use this outside of genesis-tools");
         let mut state_file = home_dir.to_path_buf();
@@ -1049,7 +1122,8 @@ impl RuntimeAdapter for NightshadeRuntime {
         shard_id: ShardId,
         is_me: bool,
     ) -> bool {
-        self.shard_tracker.care_about_shard(account_id, parent_hash, shard_id, is_me)
+        let shard_tracker = self.shard_tracker.read().expect(POISONED_LOCK_ERR);
+        shard_tracker.care_about_shard(account_id, parent_hash, shard_id, is_me)
     }
 
     fn will_care_about_shard(
@@ -1059,7 +1133,23 @@ impl RuntimeAdapter for NightshadeRuntime {
         shard_id: ShardId,
         is_me: bool,
     ) -> bool {
-        self.shard_tracker.will_care_about_shard(account_id, parent_hash, shard_id, is_me)
+        let shard_tracker = self.shard_tracker.read().expect(POISONED_LOCK_ERR);
+        shard_tracker.will_care_about_shard(account_id, parent_hash, shard_id, is_me)
+    }
+
+    fn set_tracking(
+        &self,
+        accounts: Vec<AccountId>,
+        shards: Vec<ShardId>,
+        head_hash: &CryptoHash,
+    ) -> Result<EpochHeight, Error> {
+        let mut shard_tracker = self.shard_tracker.write().expect(POISONED_LOCK_ERR);
+        shard_tracker.set_tracking(accounts, shards, head_hash)
+    }
+
+    fn update_tracking(&self, block_hash: &CryptoHash) -> Result<(), Error> {
+        let mut shard_tracker = self.shard_tracker.write().expect(POISONED_LOCK_ERR);
+        shard_tracker.update_tracking(block_hash).map_err(Error::from)
     }
 
     fn is_next_block_epoch_start(&self, parent_hash: &CryptoHash) -> Result<bool, Error> {
@@ -1142,13 +1232,14 @@ impl RuntimeAdapter for NightshadeRuntime {
             cur_epoch_info,
             next_epoch_info,
         ) = self.get_epoch_sync_data(prev_epoch_last_block_hash, epoch_id, next_epoch_id)?;
-        let mut data = prev_epoch_first_block_info.try_to_vec().unwrap();
-        data.extend(prev_epoch_prev_last_block_info.try_to_vec().unwrap());
-        data.extend(prev_epoch_last_block_info.try_to_vec().unwrap());
-        data.extend(prev_epoch_info.try_to_vec().unwrap());
-        data.extend(cur_epoch_info.try_to_vec().unwrap());
-        data.extend(next_epoch_info.try_to_vec().unwrap());
-        Ok(hash(data.as_slice()))
+        Ok(get_epoch_sync_data_hash(
+            &prev_epoch_first_block_info,
+            &prev_epoch_prev_last_block_info,
+            &prev_epoch_last_block_info,
+            &prev_epoch_info,
+            &cur_epoch_info,
+            &next_epoch_info,
+        ))
     }
 
     // TODO #3488 this likely to be updated
@@ -1184,6 +1275,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         prev_epoch_id: &EpochId,
         prev_epoch_info: EpochInfo,
         epoch_id: &EpochId,
+        epoch_start_height: BlockHeight,
         epoch_info: EpochInfo,
         next_epoch_id: &EpochId,
         next_epoch_info: EpochInfo,
@@ -1197,6 +1289,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                 prev_epoch_id,
                 prev_epoch_info,
                 epoch_id,
+                epoch_start_height,
                 epoch_info,
                 next_epoch_id,
                 next_epoch_info,
@@ -1337,6 +1430,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         block_hash: &CryptoHash,
         epoch_id: &EpochId,
         request: &QueryRequest,
+        state_overrides: Option<&StateOverrides>,
     ) -> Result<QueryResponse, near_chain::near_chain_primitives::error::QueryError> {
         match request {
             QueryRequest::ViewAccount { account_id } => {
@@ -1396,6 +1490,7 @@ impl RuntimeAdapter for NightshadeRuntime {
                         &mut logs,
                         &self.epoch_manager,
                         current_protocol_version,
+                        state_overrides,
                     )
                     .map_err(|err| near_chain::near_chain_primitives::error::QueryError::from_call_function_error(err, block_height, *block_hash))?;
                 Ok(QueryResponse {
@@ -1423,6 +1518,29 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
+            QueryRequest::ViewStatePage { account_id, prefix, start_key, limit } => {
+                let view_state_page_result = self
+                    .view_state_page(
+                        &shard_uid,
+                        *state_root,
+                        account_id,
+                        prefix.as_ref(),
+                        start_key.as_deref(),
+                        *limit,
+                    )
+                    .map_err(|err| {
+                        near_chain::near_chain_primitives::error::QueryError::from_view_state_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewStatePage(view_state_page_result),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
             QueryRequest::ViewAccessKeyList { account_id } => {
                 let access_key_list =
                     self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
@@ -1465,6 +1583,235 @@ impl RuntimeAdapter for NightshadeRuntime {
         }
     }
 
+    fn simulate_transaction(
+        &self,
+        block_hash: &CryptoHash,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        gas_price: Balance,
+        random_seed: CryptoHash,
+        state_roots: &[StateRoot],
+        mut transaction: Transaction,
+        signature: Option<Signature>,
+    ) -> Result<Result<SimulateTransactionResult, InvalidTxError>, Error> {
+        let epoch_id = self.get_epoch_id_from_prev_block(block_hash)?;
+        let epoch_height = self.get_epoch_height_from_prev_block(block_hash)?;
+        let current_protocol_version = self.get_epoch_protocol_version(&epoch_id)?;
+        let shard_layout = self.get_shard_layout(&epoch_id)?;
+        if state_roots.len() as NumShards != shard_layout.num_shards() {
+            return Err(ErrorKind::Other(
+                "Transactions cannot be simulated on the last block before resharding".to_string(),
+            )
+            .into());
+        }
+        let shard_uids: Vec<_> = (0..shard_layout.num_shards())
+            .map(|shard_id| ShardUId::from_shard_id_and_layout(shard_id, &shard_layout))
+            .collect();
+        let tries = self.get_tries();
+        let mut state_roots = state_roots.to_vec();
+        let mut overlays = vec![Arc::new(HashMap::new()); state_roots.len()];
+
+        let signer_shard_id = account_id_to_shard_id(&transaction.signer_id, &shard_layout);
+        let (transaction, temporary_key) = match signature {
+            Some(signature) => (SignedTransaction::new(signature, transaction), None),
+            None => {
+                // Adds a full access key to the signer in the simulated state and signs the
+                // transaction with it.
+                let signer = InMemorySigner::from_seed(
+                    transaction.signer_id.clone(),
+                    KeyType::ED25519,
+                    SIMULATION_KEY_SEED,
+                );
+                let shard_id = signer_shard_id as usize;
+                let trie =
+                    tries.get_trie_with_overlay(shard_uids[shard_id], overlays[shard_id].clone());
+                let mut state_update = TrieUpdate::new(Rc::new(trie), state_roots[shard_id]);
+                set_access_key(
+                    &mut state_update,
+                    signer.account_id.clone(),
+                    signer.public_key(),
+                    &AccessKey::full_access(),
+                );
+                state_update.commit(StateChangeCause::InitialState);
+                let (trie_changes, _) = state_update
+                    .finalize()
+                    .map_err(|err| Error::from(ErrorKind::StorageError(err)))?;
+                trie_changes.add_insertions_to_overlay(&mut overlays[shard_id]);
+                state_roots[shard_id] = trie_changes.new_root;
+                transaction.public_key = signer.public_key();
+                transaction.nonce = 1;
+                let signature = signer.sign(transaction.get_hash_and_size().0.as_ref());
+                (SignedTransaction::new(signature, transaction), Some(signer))
+            }
+        };
+
+        let runtime_config = self.runtime_config_store.get_config(current_protocol_version);
+        let cache: Arc<dyn CompiledContractCache> =
+            Arc::new(StoreCompiledContractCache { store: self.store.clone() });
+        // Ids of the transaction, the receipts it produced and the data receipts they sent.
+        let mut simulated_ids: HashSet<CryptoHash> = HashSet::new();
+        simulated_ids.insert(transaction.get_hash());
+        let mut data_ids = HashSet::new();
+        let mut outcomes = vec![];
+        let mut raw_state_changes = vec![];
+        let mut transactions = vec![transaction.clone()];
+        let mut receipts: Vec<Receipt> = vec![];
+        let mut prev_block_hash = *block_hash;
+        let mut num_simulated_receipts = 0;
+        let mut total_gas_burnt: Gas = 0;
+        let mut incomplete = false;
+
+        for index in 0..MAX_SIMULATED_BLOCKS {
+            if transactions.is_empty() && receipts.is_empty() {
+                break;
+            }
+            num_simulated_receipts += receipts.len();
+            if num_simulated_receipts > MAX_SIMULATED_RECEIPTS {
+                incomplete = true;
+                break;
+            }
+            let simulated_block_hash = hash(&(prev_block_hash, index).try_to_vec()?);
+            let mut apply_state = ApplyState {
+                block_index: block_height + 1 + index,
+                prev_block_hash,
+                block_hash: simulated_block_hash,
+                epoch_id: epoch_id.clone(),
+                epoch_height,
+                gas_price,
+                block_timestamp,
+                gas_limit: None,
+                random_seed,
+                current_protocol_version,
+                config: runtime_config.clone(),
+                cache: Some(cache.clone()),
+                is_new_chunk: true,
+                migration_data: Arc::clone(&self.migration_data),
+                migration_flags: MigrationFlags::default(),
+            };
+            let mut outgoing_receipts = vec![];
+            for (shard_id, shard_uid) in shard_uids.iter().enumerate() {
+                let shard_transactions: Vec<_> = if shard_id as ShardId == signer_shard_id {
+                    std::mem::take(&mut transactions)
+                } else {
+                    vec![]
+                };
+                let shard_receipts: Vec<_> = receipts
+                    .iter()
+                    .filter(|receipt| {
+                        account_id_to_shard_id(&receipt.receiver_id, &shard_layout)
+                            == shard_id as ShardId
+                    })
+                    .cloned()
+                    .collect();
+                if shard_transactions.is_empty() && shard_receipts.is_empty() {
+                    continue;
+                }
+                // The runtime stops executing receipts only once the limit is reached, so when it
+                // is not, nothing was left undone.
+                apply_state.gas_limit = Some(MAX_SIMULATED_GAS - total_gas_burnt);
+                let trie = tries.get_trie_with_overlay(*shard_uid, overlays[shard_id].clone());
+                let apply_result = match self.runtime.apply(
+                    trie,
+                    state_roots[shard_id],
+                    &None,
+                    &apply_state,
+                    &shard_receipts,
+                    &shard_transactions,
+                    &self.epoch_manager,
+                    None,
+                ) {
+                    Ok(apply_result) => apply_result,
+                    Err(RuntimeError::InvalidTxError(err)) => return Ok(Err(err)),
+                    Err(RuntimeError::StorageError(err)) => {
+                        return Err(ErrorKind::StorageError(err).into())
+                    }
+                    Err(RuntimeError::ValidatorError(err)) => return Err(err.into()),
+                    Err(err) => return Err(ErrorKind::Other(err.to_string()).into()),
+                };
+                apply_result.trie_changes.add_insertions_to_overlay(&mut overlays[shard_id]);
+                state_roots[shard_id] = apply_result.state_root;
+                total_gas_burnt += apply_result
+                    .outcomes
+                    .iter()
+                    .map(|outcome| outcome.outcome.gas_burnt)
+                    .sum::<Gas>();
+                if total_gas_burnt >= MAX_SIMULATED_GAS {
+                    incomplete = true;
+                }
+
+                // Delayed receipts of the shard are executed as well, only the results of the
+                // simulated transaction are kept.
+                for outcome in apply_result.outcomes {
+                    if simulated_ids.contains(&outcome.id) {
+                        simulated_ids.extend(outcome.outcome.receipt_ids.iter().cloned());
+                        outcomes.push(outcome);
+                    }
+                }
+                for receipt in apply_result.outgoing_receipts {
+                    let is_simulated = match &receipt.receipt {
+                        ReceiptEnum::Action(action_receipt) => {
+                            if !simulated_ids.contains(&receipt.receipt_id) {
+                                continue;
+                            }
+                            data_ids.extend(
+                                action_receipt
+                                    .output_data_receivers
+                                    .iter()
+                                    .map(|receiver| receiver.data_id),
+                            );
+                            true
+                        }
+                        ReceiptEnum::Data(data_receipt) => data_ids.contains(&data_receipt.data_id),
+                    };
+                    if is_simulated {
+                        simulated_ids.insert(receipt.receipt_id);
+                        outgoing_receipts.push(receipt);
+                    }
+                }
+                for mut raw_change in apply_result.state_changes {
+                    if let (TrieKey::AccessKey { public_key, .. }, Some(signer)) =
+                        (&raw_change.trie_key, temporary_key.as_ref())
+                    {
+                        if public_key == &signer.public_key {
+                            continue;
+                        }
+                    }
+                    raw_change.changes.retain(|change| match &change.cause {
+                        StateChangeCause::TransactionProcessing { tx_hash } => {
+                            simulated_ids.contains(tx_hash)
+                        }
+                        StateChangeCause::ActionReceiptProcessingStarted { receipt_hash }
+                        | StateChangeCause::ActionReceiptGasReward { receipt_hash }
+                        | StateChangeCause::ReceiptProcessing { receipt_hash }
+                        | StateChangeCause::PostponedReceipt { receipt_hash } => {
+                            simulated_ids.contains(receipt_hash)
+                        }
+                        _ => false,
+                    });
+                    if !raw_change.changes.is_empty() {
+                        raw_state_changes.push(raw_change);
+                    }
+                }
+                if incomplete {
+                    break;
+                }
+            }
+            if incomplete {
+                break;
+            }
+            receipts = outgoing_receipts;
+            prev_block_hash = simulated_block_hash;
+        }
+        if !receipts.is_empty() {
+            incomplete = true;
+        }
+        if incomplete {
+            debug!(target: "runtime", "Simulation of transaction {} stopped at its limits", transaction.get_hash());
+        }
+
+        let state_changes = StateChanges::from_changes(raw_state_changes.into_iter().map(Ok))?;
+        Ok(Ok(SimulateTransactionResult { transaction, outcomes, state_changes, incomplete }))
+    }
     fn get_validator_info(
         &self,
         epoch_id: ValidatorInfoIdentifier,
@@ -1685,9 +2032,22 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         logs: &mut Vec<String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
+        state_overrides: Option<&StateOverrides>,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
         let tries = self.tries.read().expect(POISONED_LOCK_ERR);
-        let state_update = tries.new_trie_update_view(*shard_uid, state_root);
+        let mut state_update = tries.new_trie_update_view(*shard_uid, state_root);
+        if let Some(state_overrides) = state_overrides {
+            self.trie_viewer.apply_state_overrides(&mut state_update, state_overrides)?;
+        }
+        // Code supplied through overrides is arbitrary user input, so it must not end up in the
+        // persistent compiled contract cache, which is never garbage collected.
+        let overrides_code =
+            state_overrides.map_or(false, |overrides| overrides.values().any(|o| o.code.is_some()));
+        let cache: Option<Arc<dyn CompiledContractCache>> = if overrides_code {
+            None
+        } else {
+            Some(Arc::new(StoreCompiledContractCache { store: tries.get_store() }))
+        };
         let view_state = ViewApplyState {
             block_height: height,
             prev_block_hash: *prev_block_hash,
@@ -1696,7 +2056,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             epoch_height,
             block_timestamp,
             current_protocol_version,
-            cache: Some(Arc::new(StoreCompiledContractCache { store: tries.get_store() })),
+            cache,
         };
         self.trie_viewer.call_function(
             state_update,
@@ -1744,6 +2104,20 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         let state_update = tries.new_trie_update_view(*shard_uid, state_root);
         self.trie_viewer.view_state(&state_update, account_id, prefix)
     }
+
+    fn view_state_page(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<ViewStatePageResult, node_runtime::state_viewer::errors::ViewStateError> {
+        let tries = self.tries.read().expect(POISONED_LOCK_ERR);
+        let state_update = tries.new_trie_update_view(*shard_uid, state_root);
+        self.trie_viewer.view_state_page(&state_update, account_id, prefix, start_key, limit)
+    }
 }
 
 #[cfg(test)]
@@ -1753,18 +2127,22 @@ mod test {
 
     use num_rational::Rational;
 
-    use near_crypto::{InMemorySigner, KeyType, Signer};
     use near_logger_utils::init_test_logger;
     use near_primitives::block::Tip;
     use near_primitives::challenge::SlashedValidator;
     use near_primitives::receipt::ReceiptResult;
-    use near_primitives::transaction::{Action, DeleteAccountAction, StakeAction};
-    use near_primitives::types::{BlockHeightDelta, Nonce, ValidatorId, ValidatorKickoutReason};
+    use near_primitives::test_utils::MockEpochInfoProvider;
+    use near_primitives::transaction::{Action, DeleteAccountAction, StakeAction, TransferAction};
+    use near_primitives::types::{
+        BlockHeightDelta, Nonce, StateChangeValue, ValidatorId, ValidatorKickoutReason,
+    };
     use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+    use near_primitives::version::PROTOCOL_VERSION;
     use near_primitives::views::{
-        AccountView, CurrentEpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+        AccountStateOverride, AccountView, CurrentEpochValidatorInfo, NextEpochValidatorInfo,
+        SlashedValidatorView, ValidatorKickoutView,
     };
-    use near_store::create_store;
+    use near_store::{create_store, DBCol};
 
     use crate::config::{mainnet_genesis, GenesisExt, TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
     use crate::get_store_path;
@@ -2559,6 +2937,79 @@ mod test {
         assert_eq!(account.locked, TESTING_INIT_STAKE - 1);
     }
 
+    #[test]
+    fn test_simulate_transaction() {
+        init_test_logger();
+        let validators = (0..4)
+            .map(|i| AccountId::try_from(format!("test{}", i + 1)).unwrap())
+            .collect::<Vec<_>>();
+        let mut env = TestEnv::new(
+            "test_simulate_transaction",
+            vec![validators.clone(), validators.clone()],
+            4,
+            vec![],
+            vec![],
+            false,
+        );
+        env.step(vec![vec![], vec![]], vec![true, true], ChallengesResult::default());
+        let amount = 10;
+        let transaction = Transaction {
+            signer_id: validators[0].clone(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            nonce: 0,
+            receiver_id: validators[3].clone(),
+            block_hash: env.head.last_block_hash,
+            actions: vec![Action::Transfer(TransferAction { deposit: amount })],
+        };
+        let simulate = |env: &TestEnv, transaction: Transaction, signature: Option<Signature>| {
+            env.runtime
+                .simulate_transaction(
+                    &env.head.last_block_hash,
+                    env.head.height,
+                    0,
+                    env.runtime.genesis_config.min_gas_price,
+                    CryptoHash::default(),
+                    &env.state_roots,
+                    transaction,
+                    signature,
+                )
+                .unwrap()
+        };
+
+        // The signer is not required to sign the transaction.
+        let result = simulate(&env, transaction.clone(), None).unwrap();
+        assert!(!result.incomplete);
+        // The transaction and the transfer receipt, possibly followed by a gas refund receipt.
+        assert!(result.outcomes.len() >= 2);
+        assert_eq!(result.outcomes[0].id, result.transaction.get_hash());
+        assert!(result.state_changes.iter().any(|change| match &change.value {
+            StateChangeValue::AccountUpdate { account_id, account } => {
+                account_id == &validators[3]
+                    && account.amount() == TESTING_INIT_BALANCE - TESTING_INIT_STAKE + amount
+            }
+            _ => false,
+        }));
+        // Nothing is saved.
+        let account = env.view_account(&validators[3]);
+        assert_eq!(account.amount, TESTING_INIT_BALANCE - TESTING_INIT_STAKE);
+
+        // A wrong signature makes the transaction invalid.
+        let signer = InMemorySigner::from_seed(
+            validators[0].clone(),
+            KeyType::ED25519,
+            validators[0].as_ref(),
+        );
+        let mut signed_transaction = transaction;
+        signed_transaction.public_key = signer.public_key();
+        signed_transaction.nonce = 1;
+        let result =
+            simulate(&env, signed_transaction.clone(), Some(Signature::empty(KeyType::ED25519)));
+        assert!(matches!(result, Err(InvalidTxError::InvalidSignature)));
+        let signature = signer.sign(signed_transaction.get_hash_and_size().0.as_ref());
+        let result = simulate(&env, signed_transaction, Some(signature)).unwrap();
+        assert!(result.outcomes.len() >= 2);
+    }
+
     #[test]
     fn test_get_validator_info() {
         let num_nodes = 2;
@@ -2661,6 +3112,7 @@ mod test {
                 prev_epoch_kickout: Default::default(),
                 epoch_start_height: 1,
                 epoch_height: 1,
+                current_slashed: vec![],
             }
         );
         expected_blocks = [0, 0];
@@ -2849,6 +3301,7 @@ mod test {
             vec![true],
             vec![SlashedValidator::new("test2".parse().unwrap(), true)],
         );
+        let slashed_epoch_id = env.head.epoch_id.clone();
         assert_eq!(env.view_account(&"test2".parse().unwrap()).locked, TESTING_INIT_STAKE);
         let mut bps = env
             .runtime
@@ -2866,6 +3319,26 @@ mod test {
                 ("test3".parse().unwrap(), false)
             ]
         );
+        let validator_info = env
+            .runtime
+            .get_validator_info(ValidatorInfoIdentifier::BlockHash(env.head.last_block_hash))
+            .unwrap();
+        assert_eq!(
+            validator_info.current_slashed,
+            vec![SlashedValidatorView {
+                account_id: "test2".parse().unwrap(),
+                is_double_sign: true
+            }]
+        );
+        assert_eq!(
+            validator_info
+                .current_validators
+                .iter()
+                .filter(|info| info.is_slashed)
+                .map(|info| info.account_id.clone())
+                .collect::<Vec<AccountId>>(),
+            vec!["test2".parse::<AccountId>().unwrap()]
+        );
         let msg = vec![0, 1, 2];
         let signer = InMemorySigner::from_seed("test2".parse().unwrap(), KeyType::ED25519, "test2");
         let signature = signer.sign(&msg);
@@ -2883,6 +3356,26 @@ mod test {
         for _ in 2..11 {
             env.step(vec![vec![]], vec![true], vec![]);
         }
+        let validator_info = env
+            .runtime
+            .get_validator_info(ValidatorInfoIdentifier::EpochId(slashed_epoch_id))
+            .unwrap();
+        assert_eq!(
+            validator_info.current_slashed,
+            vec![SlashedValidatorView {
+                account_id: "test2".parse().unwrap(),
+                is_double_sign: true
+            }]
+        );
+        assert_eq!(
+            validator_info
+                .current_validators
+                .iter()
+                .filter(|info| info.is_slashed)
+                .map(|info| info.account_id.clone())
+                .collect::<Vec<AccountId>>(),
+            vec!["test2".parse::<AccountId>().unwrap()]
+        );
         env.step(
             vec![vec![]],
             vec![true],
@@ -3317,6 +3810,113 @@ mod test {
         assert_eq!(env.last_proposals[0].stake(), 0);
     }
 
+    /// Applies the same transfers with sequential and parallel receipt execution and checks that
+    /// both runtimes end up with the same state.
+    #[test]
+    fn test_parallel_receipts() {
+        init_test_logger();
+        let num_nodes = 4;
+        let validators = (0..num_nodes)
+            .map(|i| AccountId::try_from(format!("test{}", i + 1)).unwrap())
+            .collect::<Vec<_>>();
+        let mut envs = vec![false, true]
+            .into_iter()
+            .map(|parallel_receipts| {
+                let mut env = TestEnv::new(
+                    "test_parallel_receipts",
+                    vec![validators.clone()],
+                    4,
+                    vec![],
+                    vec![],
+                    false,
+                );
+                env.runtime = env.runtime.with_parallel_receipts(parallel_receipts);
+                env
+            })
+            .collect::<Vec<_>>();
+        let signers: Vec<_> = validators
+            .iter()
+            .map(|id| InMemorySigner::from_seed(id.clone(), KeyType::ED25519, id.as_ref()))
+            .collect();
+        let transactions: Vec<_> = (0..num_nodes)
+            .map(|i| {
+                SignedTransaction::send_money(
+                    1,
+                    validators[i].clone(),
+                    validators[(i + 1) % num_nodes].clone(),
+                    &signers[i],
+                    (i as Balance + 1) * 10,
+                    CryptoHash::default(),
+                )
+            })
+            .collect();
+        for env in envs.iter_mut() {
+            env.step_default(transactions.clone());
+            for _ in 0..3 {
+                env.step_default(vec![]);
+            }
+        }
+        assert_eq!(envs[0].state_roots, envs[1].state_roots);
+        for (i, account_id) in validators.iter().enumerate() {
+            let received = (((i + num_nodes - 1) % num_nodes) as Balance + 1) * 10;
+            let sent = (i as Balance + 1) * 10;
+            let expected = TESTING_INIT_BALANCE - TESTING_INIT_STAKE + received - sent;
+            for env in envs.iter() {
+                assert_eq!(env.view_account(account_id).amount, expected);
+            }
+        }
+    }
+
+    /// Contract code supplied through state overrides must not be persisted in the compiled
+    /// contract cache.
+    #[test]
+    fn test_call_function_with_code_override_is_not_cached() {
+        init_test_logger();
+        let validators = vec!["test1".parse::<AccountId>().unwrap()];
+        let env = TestEnv::new(
+            "test_call_function_with_code_override_is_not_cached",
+            vec![validators.clone()],
+            4,
+            vec![],
+            vec![],
+            false,
+        );
+        let account_id = validators[0].clone();
+        let mut state_overrides = StateOverrides::new();
+        state_overrides.insert(
+            account_id.clone(),
+            AccountStateOverride {
+                code: Some(near_test_contracts::rs_contract().to_vec()),
+                ..Default::default()
+            },
+        );
+        let shard_id = env.runtime.account_id_to_shard_id(&account_id, &env.head.epoch_id).unwrap();
+        let shard_uid = env.runtime.shard_id_to_uid(shard_id, &env.head.epoch_id).unwrap();
+        let mut logs = vec![];
+        let result = env
+            .runtime
+            .call_function(
+                &shard_uid,
+                env.state_roots[shard_id as usize],
+                env.head.height,
+                0,
+                &env.head.prev_block_hash,
+                &env.head.last_block_hash,
+                0,
+                &env.head.epoch_id,
+                &account_id,
+                "ext_account_balance",
+                &[],
+                &mut logs,
+                &MockEpochInfoProvider::default(),
+                PROTOCOL_VERSION,
+                Some(&state_overrides),
+            )
+            .unwrap();
+        assert_eq!(result, env.view_account(&account_id).amount.to_le_bytes().to_vec());
+        assert!(env.runtime.store.iter(DBCol::ColCachedContractCode).next().is_none());
+    }
+
     #[test]
     fn test_runtime_configs_similarity_mainnet() {
         let genesis = mainnet_genesis();
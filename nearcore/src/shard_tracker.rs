@@ -3,10 +3,12 @@ use std::sync::{Arc, RwLock};
 
 use tracing::info;
 
+use near_chain::{Error, ErrorKind};
 use near_epoch_manager::EpochManager;
+use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout};
-use near_primitives::types::{AccountId, EpochId, ShardId};
+use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId};
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
@@ -28,6 +30,21 @@ pub struct ShardTracker {
     /// TODO: ShardTracker does not work if shard_layout is changed,
     ///       fix this when https://github.com/near/nearcore/pull/4668 is merged
     shard_layout: ShardLayout,
+    /// Tracked accounts and shards set by `set_tracking` that are not in effect yet.
+    pending: Option<PendingTracking>,
+    /// Height of the epoch of the block after the latest processed block, see `update_tracking`.
+    epoch_height: EpochHeight,
+}
+
+/// Tracked accounts and shards that replace the current ones two epochs after the epoch in
+/// which they were set.
+#[derive(Clone)]
+struct PendingTracking {
+    /// Height of the first epoch in which the tracking is in effect.
+    first_epoch_height: EpochHeight,
+    tracked_accounts: HashMap<ShardId, HashSet<AccountId>>,
+    tracked_shards: HashSet<ShardId>,
+    actual_tracked_shards: HashSet<ShardId>,
 }
 
 impl ShardTracker {
@@ -40,24 +57,126 @@ impl ShardTracker {
             let mut epoch_manager = epoch_manager.write().expect(POISONED_LOCK_ERR);
             epoch_manager.get_shard_layout(&EpochId::default()).unwrap().clone()
         };
-        let tracked_accounts = accounts.into_iter().fold(HashMap::new(), |mut acc, x| {
-            let shard_id = account_id_to_shard_id(&x, &shard_layout);
+        let tracked_accounts = Self::group_accounts_by_shard(accounts, &shard_layout);
+        let tracked_shards: HashSet<_> = shards.into_iter().collect();
+        let actual_tracked_shards = Self::union_tracked_shards(&tracked_accounts, &tracked_shards);
+        info!(target: "runtime", "Tracking shards: {:?}", actual_tracked_shards);
+        ShardTracker {
+            tracked_accounts,
+            tracked_shards,
+            actual_tracked_shards,
+            epoch_manager,
+            shard_layout,
+            pending: None,
+            epoch_height: 0,
+        }
+    }
+
+    fn group_accounts_by_shard(
+        accounts: Vec<AccountId>,
+        shard_layout: &ShardLayout,
+    ) -> HashMap<ShardId, HashSet<AccountId>> {
+        accounts.into_iter().fold(HashMap::new(), |mut acc, x| {
+            let shard_id = account_id_to_shard_id(&x, shard_layout);
             acc.entry(shard_id).or_insert_with(HashSet::new).insert(x);
             acc
-        });
-        let tracked_shards: HashSet<_> = shards.into_iter().collect();
+        })
+    }
+
+    fn union_tracked_shards(
+        tracked_accounts: &HashMap<ShardId, HashSet<AccountId>>,
+        tracked_shards: &HashSet<ShardId>,
+    ) -> HashSet<ShardId> {
         let mut actual_tracked_shards = tracked_shards.clone();
         for (shard_id, _) in tracked_accounts.iter() {
             actual_tracked_shards.insert(*shard_id);
         }
-        info!(target: "runtime", "Tracking shards: {:?}", actual_tracked_shards);
-        ShardTracker {
+        actual_tracked_shards
+    }
+
+    /// Height of the epoch of the block after `parent_hash`.
+    fn next_block_epoch_height(&self, parent_hash: &CryptoHash) -> Result<EpochHeight, EpochError> {
+        let mut epoch_manager = self.epoch_manager.write().expect(POISONED_LOCK_ERR);
+        let epoch_id = epoch_manager.get_epoch_id_from_prev_block(parent_hash)?;
+        Ok(epoch_manager.get_epoch_info(&epoch_id)?.epoch_height())
+    }
+
+    /// Replaces the tracked accounts and shards. Unlike `track_accounts` and `track_shards`,
+    /// the change is not immediate: if it is made in epoch `T`, the new shards are tracked
+    /// starting from epoch `T + 2`, while `will_care_about_shard` reports them already during
+    /// epoch `T + 1`. This gives the client a full epoch to catch up the state of the newly
+    /// tracked shards. Returns the height of the epoch from which the change is in effect.
+    ///
+    /// A change made in the same epoch as the previous one replaces it. Otherwise the previous
+    /// change must be in effect already, since the state of its shards may be being caught up.
+    pub fn set_tracking(
+        &mut self,
+        accounts: Vec<AccountId>,
+        shards: Vec<ShardId>,
+        head_hash: &CryptoHash,
+    ) -> Result<EpochHeight, Error> {
+        let epoch_height = self.next_block_epoch_height(head_hash)?;
+        self.advance_epoch_height(epoch_height);
+        if let Some(pending) = &self.pending {
+            if epoch_height + 1 == pending.first_epoch_height {
+                return Err(ErrorKind::Other(
+                    "Previous change of tracked shards is not in effect yet".to_string(),
+                )
+                .into());
+            }
+        }
+        let tracked_accounts = Self::group_accounts_by_shard(accounts, &self.shard_layout);
+        let tracked_shards: HashSet<_> = shards.into_iter().collect();
+        let actual_tracked_shards = Self::union_tracked_shards(&tracked_accounts, &tracked_shards);
+        let first_epoch_height = epoch_height + 2;
+        info!(target: "runtime", "Tracking shards {:?} starting from epoch height {}", actual_tracked_shards, first_epoch_height);
+        self.pending = Some(PendingTracking {
+            first_epoch_height,
             tracked_accounts,
             tracked_shards,
             actual_tracked_shards,
-            epoch_manager,
-            shard_layout,
+        });
+        Ok(first_epoch_height)
+    }
+
+    /// Called for every processed block. Moves the tracker to the epoch of the block after
+    /// `block_hash`, so that `care_about_shard` and `will_care_about_shard` don't need to look
+    /// up the epoch of every block they are asked about.
+    pub fn update_tracking(&mut self, block_hash: &CryptoHash) -> Result<(), EpochError> {
+        let epoch_height = self.next_block_epoch_height(block_hash)?;
+        self.advance_epoch_height(epoch_height);
+        Ok(())
+    }
+
+    /// Brings the tracking set by `set_tracking` into effect once its first epoch is reached.
+    /// Blocks of older epochs, e.g. during sync, don't move the tracker back.
+    fn advance_epoch_height(&mut self, epoch_height: EpochHeight) {
+        if epoch_height <= self.epoch_height {
+            return;
+        }
+        self.epoch_height = epoch_height;
+        if let Some(pending) = self.pending.take() {
+            if epoch_height >= pending.first_epoch_height {
+                info!(target: "runtime", "Tracking shards: {:?}", pending.actual_tracked_shards);
+                self.tracked_accounts = pending.tracked_accounts;
+                self.tracked_shards = pending.tracked_shards;
+                self.actual_tracked_shards = pending.actual_tracked_shards;
+            } else {
+                self.pending = Some(pending);
+            }
+        }
+    }
+
+    /// Shards tracked in the epoch of the block after the latest processed block or, if
+    /// `next_epoch` is set, in the epoch after it.
+    fn get_actual_tracked_shards(&self, next_epoch: bool) -> &HashSet<ShardId> {
+        if let Some(pending) = &self.pending {
+            let epoch_height = if next_epoch { self.epoch_height + 1 } else { self.epoch_height };
+            if epoch_height >= pending.first_epoch_height {
+                return &pending.actual_tracked_shards;
+            }
         }
+        &self.actual_tracked_shards
     }
 
     fn track_account(&mut self, account_id: &AccountId) {
@@ -109,9 +228,9 @@ impl ShardTracker {
             if !is_me {
                 return account_cares_about_shard;
             }
-            account_cares_about_shard || self.actual_tracked_shards.contains(&shard_id)
+            account_cares_about_shard || self.get_actual_tracked_shards(false).contains(&shard_id)
         } else {
-            self.actual_tracked_shards.contains(&shard_id)
+            self.get_actual_tracked_shards(false).contains(&shard_id)
         }
     }
 
@@ -138,7 +257,7 @@ impl ShardTracker {
                 return true;
             }
         }
-        self.actual_tracked_shards.contains(&shard_id)
+        self.get_actual_tracked_shards(true).contains(&shard_id)
     }
 }
 
@@ -148,6 +267,7 @@ mod tests {
     use std::sync::{Arc, RwLock};
 
     use near_crypto::{KeyType, PublicKey};
+    use near_epoch_manager::test_utils::hash_range;
     use near_epoch_manager::{EpochManager, RewardCalculator};
     use near_primitives::epoch_manager::block_info::BlockInfo;
     use near_primitives::epoch_manager::{AllEpochConfig, EpochConfig, ShardConfig};
@@ -262,6 +382,114 @@ mod tests {
         assert_eq!(tracker.actual_tracked_shards, total_tracked_shards);
     }
 
+    #[test]
+    fn test_set_tracking() {
+        let epoch_manager = get_epoch_manager(PROTOCOL_VERSION, 4, None);
+        let mut tracker = ShardTracker::new(vec![], vec![0], epoch_manager.clone());
+        let h = hash_range(8);
+        {
+            let mut epoch_manager = epoch_manager.write().unwrap();
+            record_block(
+                &mut epoch_manager,
+                CryptoHash::default(),
+                h[0],
+                0,
+                vec![],
+                PROTOCOL_VERSION,
+            );
+            for i in 1..h.len() {
+                record_block(
+                    &mut epoch_manager,
+                    h[i - 1],
+                    h[i],
+                    i as u64,
+                    vec![],
+                    PROTOCOL_VERSION,
+                );
+            }
+        }
+        tracker.update_tracking(&h[0]).unwrap();
+        let first_epoch_height = tracker.set_tracking(vec![], vec![1], &h[0]).unwrap();
+        for hash in h.iter() {
+            tracker.update_tracking(hash).unwrap();
+            let epoch_height = tracker.next_block_epoch_height(hash).unwrap();
+            if epoch_height + 1 == first_epoch_height {
+                // The state of the new shards may be being caught up.
+                assert!(tracker.clone().set_tracking(vec![], vec![2], hash).is_err());
+            }
+            let in_effect = epoch_height >= first_epoch_height;
+            assert_eq!(tracker.care_about_shard(None, hash, 0, true), !in_effect);
+            assert_eq!(tracker.care_about_shard(None, hash, 1, true), in_effect);
+            assert_eq!(
+                tracker.will_care_about_shard(None, hash, 1, true),
+                epoch_height + 1 >= first_epoch_height
+            );
+        }
+        assert!(tracker.pending.is_none());
+        assert_eq!(tracker.actual_tracked_shards, vec![1].into_iter().collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn test_set_tracking_promoted_at_epoch_start() {
+        let epoch_manager = get_epoch_manager(PROTOCOL_VERSION, 4, None);
+        let mut tracker = ShardTracker::new(vec![], vec![0], epoch_manager.clone());
+        let h = hash_range(8);
+        {
+            let mut epoch_manager = epoch_manager.write().unwrap();
+            record_block(
+                &mut epoch_manager,
+                CryptoHash::default(),
+                h[0],
+                0,
+                vec![],
+                PROTOCOL_VERSION,
+            );
+            for i in 1..h.len() {
+                record_block(
+                    &mut epoch_manager,
+                    h[i - 1],
+                    h[i],
+                    i as u64,
+                    vec![],
+                    PROTOCOL_VERSION,
+                );
+            }
+        }
+        tracker.update_tracking(&h[0]).unwrap();
+        let first_epoch_height = tracker.set_tracking(vec![], vec![1], &h[0]).unwrap();
+        // A change made in the same epoch replaces the pending one.
+        assert_eq!(tracker.set_tracking(vec![], vec![2], &h[0]).unwrap(), first_epoch_height);
+
+        let mut promoted = false;
+        for hash in h.iter() {
+            let epoch_height = tracker.next_block_epoch_height(hash).unwrap();
+            tracker.update_tracking(hash).unwrap();
+            if epoch_height < first_epoch_height {
+                assert!(tracker.pending.is_some());
+                assert_eq!(
+                    tracker.actual_tracked_shards,
+                    vec![0].into_iter().collect::<HashSet<_>>()
+                );
+            } else {
+                // The pending change is promoted by the first block of its epoch, without
+                // waiting for the tracked shards to be queried.
+                assert!(tracker.pending.is_none());
+                assert_eq!(
+                    tracker.actual_tracked_shards,
+                    vec![2].into_iter().collect::<HashSet<_>>()
+                );
+                promoted = true;
+            }
+        }
+        assert!(promoted);
+
+        // Blocks of older epochs don't move the tracker back.
+        let epoch_height = tracker.epoch_height;
+        tracker.update_tracking(&h[0]).unwrap();
+        assert_eq!(tracker.epoch_height, epoch_height);
+        assert_eq!(tracker.actual_tracked_shards, vec![2].into_iter().collect::<HashSet<_>>());
+    }
+
     /*
     #[test]
     #[cfg(feature = "protocol_feature_simple_nightshade")]
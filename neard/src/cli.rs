@@ -1,17 +1,25 @@
 use super::{DEFAULT_HOME, NEARD_VERSION, NEARD_VERSION_STRING, PROTOCOL_VERSION};
 use clap::{AppSettings, Clap};
 use futures::future::FutureExt;
+use near_chain::{RuntimeAdapter, StoreValidator};
+use near_primitives::runtime::config_store::RuntimeConfigStore;
 use near_primitives::types::{Gas, NumSeats, NumShards};
-use nearcore::get_store_path;
+use near_store::create_store;
+use nearcore::{get_store_path, ConfigReloader, NightshadeRuntime};
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use std::{env, fs, io};
 use tracing::debug;
-#[cfg(feature = "adversarial")]
 use tracing::error;
 use tracing::info;
 use tracing::metadata::LevelFilter;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// How often `config.json` is checked for modifications.
+const CONFIG_CHECK_PERIOD: Duration = Duration::from_secs(5);
 
 /// NEAR Protocol Node
 #[derive(Clap)]
@@ -27,7 +35,7 @@ pub(super) struct NeardCmd {
 impl NeardCmd {
     pub(super) fn parse_and_run() {
         let neard_cmd = Self::parse();
-        neard_cmd.opts.init();
+        let log_filter_handle = neard_cmd.opts.init();
         info!(target: "neard", "Version: {}, Build: {}, Latest Protocol: {}", NEARD_VERSION.version, NEARD_VERSION.build, PROTOCOL_VERSION);
 
         #[cfg(feature = "adversarial")]
@@ -46,7 +54,8 @@ impl NeardCmd {
         match neard_cmd.subcmd {
             NeardSubCommand::Init(cmd) => cmd.run(&home_dir),
             NeardSubCommand::Testnet(cmd) => cmd.run(&home_dir),
-            NeardSubCommand::Run(cmd) => cmd.run(&home_dir),
+            NeardSubCommand::Run(cmd) => cmd.run(&home_dir, log_filter_handle),
+            NeardSubCommand::ValidateStore(cmd) => cmd.run(&home_dir),
 
             NeardSubCommand::UnsafeResetData => {
                 let store_path = get_store_path(&home_dir);
@@ -72,8 +81,8 @@ struct NeardOpts {
 }
 
 impl NeardOpts {
-    fn init(&self) {
-        init_logging(self.verbose.as_deref());
+    fn init(&self) -> LogFilterHandle {
+        init_logging(self.verbose.as_deref())
     }
 }
 
@@ -97,6 +106,10 @@ pub(super) enum NeardSubCommand {
     /// config)
     #[clap(name = "unsafe_reset_data")]
     UnsafeResetData,
+    /// Checks consistency of the data of a stopped node and optionally repairs inconsistencies
+    /// which can be fixed using the data in the storage
+    #[clap(name = "validate_store")]
+    ValidateStore(ValidateStoreCmd),
 }
 
 #[derive(Clap)]
@@ -213,9 +226,12 @@ pub(super) struct RunCmd {
 }
 
 impl RunCmd {
-    pub(super) fn run(self, home_dir: &Path) {
+    pub(super) fn run(self, home_dir: &Path, log_filter_handle: LogFilterHandle) {
         // Load configs from home.
         let mut near_config = nearcore::config::load_config_without_genesis_records(home_dir);
+        if let Some(log_filter) = &near_config.config().log_filter {
+            log_filter_handle.reload(Some(log_filter));
+        }
         // Set current version in client config.
         near_config.client_config.version = super::NEARD_VERSION.clone();
         // Override some parameters from command line.
@@ -275,16 +291,32 @@ impl RunCmd {
 
         let sys = actix::System::new();
         sys.block_on(async move {
-            let nearcore::NearNode { rpc_servers, .. } =
+            let nearcore::NearNode { rpc_servers, mut config_reloader, .. } =
                 nearcore::start_with_config(home_dir, near_config);
 
             let sig = if cfg!(unix) {
                 use tokio::signal::unix::{signal, SignalKind};
                 let mut sigint = signal(SignalKind::interrupt()).unwrap();
                 let mut sigterm = signal(SignalKind::terminate()).unwrap();
-                futures::select! {
-                    _ = sigint .recv().fuse() => "SIGINT",
-                    _ = sigterm.recv().fuse() => "SIGTERM"
+                let mut sighup = signal(SignalKind::hangup()).unwrap();
+                let mut config_check_interval = actix::clock::interval(CONFIG_CHECK_PERIOD);
+                let mut config_modified = modified_time(config_reloader.config_path());
+                loop {
+                    futures::select! {
+                        _ = sigint .recv().fuse() => break "SIGINT",
+                        _ = sigterm.recv().fuse() => break "SIGTERM",
+                        _ = sighup.recv().fuse() => {
+                            info!(target: "neard", "Got SIGHUP, reloading {}", config_reloader.config_path().display());
+                        }
+                        _ = config_check_interval.tick().fuse() => {
+                            let modified = modified_time(config_reloader.config_path());
+                            if modified == config_modified {
+                                continue;
+                            }
+                            config_modified = modified;
+                        }
+                    }
+                    reload_config(&mut config_reloader, &log_filter_handle);
                 }
             } else {
                 tokio::signal::ctrl_c().await.unwrap();
@@ -302,6 +334,74 @@ impl RunCmd {
     }
 }
 
+#[derive(Clap)]
+pub(super) struct ValidateStoreCmd {
+    /// Write all found errors and available repairs as JSON to this file.
+    #[clap(long, parse(from_os_str))]
+    report: Option<PathBuf>,
+    /// Repair dangling refcounts, Blocks missing in ColBlockPerHeight and Chunks left behind by
+    /// garbage collection, then validate the storage again.
+    #[clap(long)]
+    repair: bool,
+}
+
+impl ValidateStoreCmd {
+    pub(super) fn run(self, home_dir: &Path) {
+        let near_config = nearcore::config::load_config_without_genesis_records(home_dir);
+        let store_path = get_store_path(home_dir);
+        if !nearcore::store_path_exists(&store_path) {
+            error!(target: "neard", "No data found in {}", store_path.display());
+            std::process::exit(1);
+        }
+        // Opening the storage fails if it's used by a running node.
+        let store = create_store(&store_path);
+        let runtime_adapter: Arc<dyn RuntimeAdapter> = Arc::new(NightshadeRuntime::new(
+            home_dir,
+            store.clone(),
+            &near_config.genesis,
+            near_config.client_config.tracked_accounts.clone(),
+            near_config.client_config.tracked_shards.clone(),
+            None,
+            None,
+            RuntimeConfigStore::new(None),
+        ));
+        let new_store_validator = || {
+            StoreValidator::new(
+                near_config.validator_signer.as_ref().map(|x| x.validator_id().clone()),
+                near_config.genesis.config.clone(),
+                runtime_adapter.clone(),
+                store.clone(),
+            )
+        };
+
+        let mut store_validator = new_store_validator();
+        store_validator.validate_parallel();
+        for error in store_validator.errors.iter() {
+            error!(target: "neard", "{}  {}  {}", error.col, error.key, error.err);
+        }
+        info!(target: "neard", "Conditions validated: {}, errors found: {}, repairs available: {}",
+              store_validator.tests_done(), store_validator.num_failed(), store_validator.repairs.len());
+        if let Some(report) = &self.report {
+            let report_json = serde_json::to_string_pretty(&store_validator.report())
+                .expect("Failed to serialize the report");
+            fs::write(report, report_json).expect("Failed to write the report");
+            info!(target: "neard", "Report written to {}", report.display());
+        }
+
+        if self.repair && !store_validator.repairs.is_empty() {
+            store_validator.repair().expect("Failed to repair the storage");
+            info!(target: "neard", "Applied {} repairs, validating again", store_validator.repairs.len());
+            store_validator = new_store_validator();
+            store_validator.validate_parallel();
+            info!(target: "neard", "Conditions validated: {}, errors found: {}",
+                  store_validator.tests_done(), store_validator.num_failed());
+        }
+        if store_validator.is_failed() {
+            std::process::exit(1);
+        }
+    }
+}
+
 #[derive(Clap)]
 pub(super) struct TestnetCmd {
     /// Number of non-validators to initialize the testnet with.
@@ -331,7 +431,47 @@ impl TestnetCmd {
     }
 }
 
-fn init_logging(verbose: Option<&str>) {
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Applies changes of `config.json` to the running node, including the log filter.
+fn reload_config(config_reloader: &mut ConfigReloader, log_filter_handle: &LogFilterHandle) {
+    if config_reloader.reload() {
+        log_filter_handle.reload(config_reloader.config().log_filter.as_deref());
+    }
+}
+
+/// Handle to change the log filter of the running process.
+pub(super) struct LogFilterHandle {
+    verbose: Option<String>,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogFilterHandle {
+    /// Replaces the log filter with the default one extended by `log_filter` directives.
+    fn reload(&self, log_filter: Option<&str>) {
+        let env_filter = make_env_filter(self.verbose.as_deref(), log_filter);
+        if let Err(err) = self.handle.reload(env_filter) {
+            error!(target: "neard", "Failed to change the log filter: {}", err);
+        }
+    }
+}
+
+fn add_directives(mut env_filter: EnvFilter, directives: &str) -> EnvFilter {
+    for directive in directives.split(',').filter_map(|s| match s.parse() {
+        Ok(directive) => Some(directive),
+        Err(err) => {
+            eprintln!("Ignoring directive `{}`: {}", s, err);
+            None
+        }
+    }) {
+        env_filter = env_filter.add_directive(directive);
+    }
+    env_filter
+}
+
+fn make_env_filter(verbose: Option<&str>, log_filter: Option<&str>) -> EnvFilter {
     let mut env_filter = EnvFilter::new(
         "tokio_reactor=info,near=info,stats=info,telemetry=info,delay_detector=info,\
          near-performance-metrics=info,near-rust-allocator-proxy=info",
@@ -354,24 +494,31 @@ fn init_logging(verbose: Option<&str>) {
         env_filter = env_filter.add_directive(LevelFilter::WARN.into());
     }
 
+    if let Some(log_filter) = log_filter {
+        if !log_filter.is_empty() {
+            env_filter = add_directives(env_filter, log_filter);
+        }
+    }
+
     if let Ok(rust_log) = env::var("RUST_LOG") {
         if !rust_log.is_empty() {
-            for directive in rust_log.split(',').filter_map(|s| match s.parse() {
-                Ok(directive) => Some(directive),
-                Err(err) => {
-                    eprintln!("Ignoring directive `{}`: {}", s, err);
-                    None
-                }
-            }) {
-                env_filter = env_filter.add_directive(directive);
-            }
+            env_filter = add_directives(env_filter, &rust_log);
         }
     }
-    tracing_subscriber::fmt::Subscriber::builder()
-        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
-        .with_env_filter(env_filter)
-        .with_writer(io::stderr)
+    env_filter
+}
+
+fn init_logging(verbose: Option<&str>) -> LogFilterHandle {
+    let (env_filter, handle) = reload::Layer::new(make_env_filter(verbose, None));
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+                .with_writer(io::stderr),
+        )
         .init();
+    LogFilterHandle { verbose: verbose.map(str::to_string), handle }
 }
 
 #[cfg(test)]
@@ -2510,6 +2510,16 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Gas burnt by the execution so far.
+    pub fn burnt_gas(&self) -> Gas {
+        self.gas_counter.burnt_gas()
+    }
+
+    /// Current contents of the registers, used for debugging.
+    pub fn registers(&self) -> &HashMap<u64, Vec<u8>> {
+        &self.registers
+    }
+
     // TODO: remove, as those costs are incorrectly computed, and we shall account it on deployment.
     pub fn add_contract_compile_fee(&mut self, code_len: u64) -> Result<()> {
         self.gas_counter.pay_per(contract_compile_bytes, code_len)?;
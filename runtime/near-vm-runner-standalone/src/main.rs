@@ -5,8 +5,10 @@
 //! -- --method-name=hello --wasm-file=/tmp/main.wasm
 //! ```
 //! Optional `--context-file=/tmp/context.json --config-file=/tmp/config.json` could be added
-//! to provide custom context and VM config.
+//! to provide custom context and VM config. `--trace-file=/tmp/trace.json` writes a trace of the
+//! host function calls, storage accesses, registers and the Wasm stack at trap time.
 mod script;
+mod tracing_external;
 
 use crate::script::Script;
 use clap::Clap;
@@ -106,6 +108,9 @@ struct CliArgs {
     /// Protocol version.
     #[clap(long)]
     protocol_version: Option<ProtocolVersion>,
+    /// Records an execution trace and writes it as JSON to the given file.
+    #[clap(long)]
+    trace_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -140,6 +145,9 @@ fn main() {
     if let Some(version) = cli_args.protocol_version {
         script.protocol_version(version)
     }
+    if cli_args.trace_file.is_some() {
+        script.trace(true);
+    }
 
     if let Some(state_str) = &cli_args.state {
         script.initial_state(serde_json::from_str(state_str).unwrap());
@@ -171,6 +179,11 @@ fn main() {
     let mut results = script.run();
     let (outcome, err) = results.outcomes.pop().unwrap();
 
+    if let Some(path) = &cli_args.trace_file {
+        let trace = results.traces.pop().unwrap();
+        fs::write(path, serde_json::to_string_pretty(&trace).unwrap()).unwrap();
+    }
+
     println!(
         "{}",
         serde_json::to_string(&StandaloneOutput {
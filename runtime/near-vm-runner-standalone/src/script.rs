@@ -6,9 +6,11 @@ use near_primitives::types::CompiledContractCache;
 use near_primitives_core::runtime::fees::RuntimeFeesConfig;
 use near_vm_logic::mocks::mock_external::MockedExternal;
 use near_vm_logic::types::PromiseResult;
-use near_vm_logic::{ProtocolVersion, VMConfig, VMContext, VMOutcome};
+use near_vm_logic::{External, ProtocolVersion, VMConfig, VMContext, VMOutcome};
+use near_vm_runner::trace::ExecutionTrace;
 use near_vm_runner::{run_vm, MockCompiledContractCache, VMError, VMKind};
 
+use crate::tracing_external::TracingExternal;
 use crate::State;
 
 #[derive(Clone, Copy)]
@@ -24,6 +26,7 @@ pub struct Script {
     contract_cache: Option<Box<dyn CompiledContractCache>>,
     initial_state: Option<State>,
     steps: Vec<Step>,
+    trace: bool,
 }
 
 pub struct Step {
@@ -36,6 +39,8 @@ pub struct Step {
 
 pub struct ScriptResults {
     pub outcomes: Vec<(Option<VMOutcome>, Option<VMError>)>,
+    /// Execution trace of every outcome, empty unless tracing is enabled.
+    pub traces: Vec<ExecutionTrace>,
     pub state: MockedExternal,
 }
 
@@ -49,6 +54,7 @@ impl Default for Script {
             contract_cache: None,
             initial_state: None,
             steps: Vec::new(),
+            trace: false,
         }
     }
 }
@@ -90,6 +96,10 @@ impl Script {
             if yes { Some(Box::new(MockCompiledContractCache::default())) } else { None };
     }
 
+    pub(crate) fn trace(&mut self, yes: bool) {
+        self.trace = yes;
+    }
+
     pub(crate) fn initial_state(&mut self, state: State) {
         self.initial_state = Some(state);
     }
@@ -112,12 +122,21 @@ impl Script {
         }
 
         let mut outcomes = Vec::new();
+        let mut traces = Vec::new();
         for step in &self.steps {
             for _ in 0..step.repeat {
+                let mut tracing_external;
+                let ext: &mut dyn External = if self.trace {
+                    near_vm_runner::trace::start();
+                    tracing_external = TracingExternal::new(&mut external);
+                    &mut tracing_external
+                } else {
+                    &mut external
+                };
                 let res = run_vm(
                     &self.contracts[step.contract.0],
                     &step.method,
-                    &mut external,
+                    ext,
                     step.vm_context.clone(),
                     &self.vm_config,
                     &RuntimeFeesConfig::default(),
@@ -126,10 +145,13 @@ impl Script {
                     self.protocol_version,
                     self.contract_cache.as_deref(),
                 );
+                if self.trace {
+                    traces.push(near_vm_runner::trace::finish().unwrap_or_default());
+                }
                 outcomes.push(res);
             }
         }
-        ScriptResults { outcomes, state: external }
+        ScriptResults { outcomes, traces, state: external }
     }
 }
 
@@ -237,6 +259,35 @@ fn profile_data_is_per_outcome() {
     );
 }
 
+#[test]
+fn trace_records_host_calls_and_storage() {
+    use near_vm_runner::trace::StorageAccess;
+
+    let mut script = Script::default();
+    script.trace(true);
+
+    let contract = script.contract(near_test_contracts::rs_contract().to_vec());
+
+    let mut input = 10u64.to_le_bytes().to_vec();
+    input.extend_from_slice(&20u64.to_le_bytes());
+    script.step(contract, "write_key_value").input(input);
+    script.step(contract, "panic_after_logging");
+    let res = script.run();
+    assert_eq!(res.traces.len(), 2);
+
+    let write = res.traces[0].host_calls.iter().find(|call| call.name == "storage_write").unwrap();
+    assert!(write.gas > 0);
+    assert!(matches!(
+        write.storage.as_slice(),
+        [.., StorageAccess::Write { key, value }]
+            if key == &10u64.to_le_bytes() && value == &20u64.to_le_bytes()
+    ));
+
+    let last_call = res.traces[1].host_calls.last().unwrap();
+    assert_eq!(last_call.name, "panic_utf8");
+    assert!(last_call.error.is_some());
+}
+
 #[cfg(feature = "no_cache")]
 #[test]
 fn test_evm_slow_deserialize_repro() {
@@ -0,0 +1,162 @@
+use near_primitives_core::types::{AccountId, Balance, Gas};
+use near_vm_logic::types::{PublicKey, ReceiptIndex};
+use near_vm_logic::{External, VMLogicError, ValuePtr};
+use near_vm_runner::trace::{record_storage_access, StorageAccess};
+
+type Result<T> = ::std::result::Result<T, VMLogicError>;
+
+/// Wraps an `External` and records all storage accesses into the execution trace.
+pub struct TracingExternal<'a> {
+    inner: &'a mut dyn External,
+}
+
+impl<'a> TracingExternal<'a> {
+    pub fn new(inner: &'a mut dyn External) -> Self {
+        Self { inner }
+    }
+}
+
+impl External for TracingExternal<'_> {
+    fn storage_set(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.inner.storage_set(key, value)?;
+        record_storage_access(StorageAccess::Write { key: key.to_vec(), value: value.to_vec() });
+        Ok(())
+    }
+
+    fn storage_get<'a>(&'a self, key: &[u8]) -> Result<Option<Box<dyn ValuePtr + 'a>>> {
+        let value_ptr = self.inner.storage_get(key)?;
+        let value = value_ptr.as_ref().map(|ptr| ptr.deref()).transpose()?;
+        record_storage_access(StorageAccess::Read { key: key.to_vec(), value });
+        Ok(value_ptr)
+    }
+
+    fn storage_remove(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.storage_remove(key)?;
+        record_storage_access(StorageAccess::Remove { key: key.to_vec() });
+        Ok(())
+    }
+
+    fn storage_remove_subtree(&mut self, prefix: &[u8]) -> Result<()> {
+        self.inner.storage_remove_subtree(prefix)
+    }
+
+    fn storage_has_key(&mut self, key: &[u8]) -> Result<bool> {
+        let found = self.inner.storage_has_key(key)?;
+        record_storage_access(StorageAccess::HasKey { key: key.to_vec(), found });
+        Ok(found)
+    }
+
+    fn create_receipt(
+        &mut self,
+        receipt_indices: Vec<ReceiptIndex>,
+        receiver_id: AccountId,
+    ) -> Result<ReceiptIndex> {
+        self.inner.create_receipt(receipt_indices, receiver_id)
+    }
+
+    fn append_action_create_account(&mut self, receipt_index: ReceiptIndex) -> Result<()> {
+        self.inner.append_action_create_account(receipt_index)
+    }
+
+    fn append_action_deploy_contract(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        code: Vec<u8>,
+    ) -> Result<()> {
+        self.inner.append_action_deploy_contract(receipt_index, code)
+    }
+
+    fn append_action_function_call(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        method_name: Vec<u8>,
+        arguments: Vec<u8>,
+        attached_deposit: Balance,
+        prepaid_gas: Gas,
+    ) -> Result<()> {
+        self.inner.append_action_function_call(
+            receipt_index,
+            method_name,
+            arguments,
+            attached_deposit,
+            prepaid_gas,
+        )
+    }
+
+    fn append_action_transfer(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        amount: Balance,
+    ) -> Result<()> {
+        self.inner.append_action_transfer(receipt_index, amount)
+    }
+
+    fn append_action_stake(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        stake: Balance,
+        public_key: PublicKey,
+    ) -> Result<()> {
+        self.inner.append_action_stake(receipt_index, stake, public_key)
+    }
+
+    fn append_action_add_key_with_full_access(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        public_key: PublicKey,
+        nonce: u64,
+    ) -> Result<()> {
+        self.inner.append_action_add_key_with_full_access(receipt_index, public_key, nonce)
+    }
+
+    fn append_action_add_key_with_function_call(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        public_key: PublicKey,
+        nonce: u64,
+        allowance: Option<Balance>,
+        receiver_id: AccountId,
+        method_names: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        self.inner.append_action_add_key_with_function_call(
+            receipt_index,
+            public_key,
+            nonce,
+            allowance,
+            receiver_id,
+            method_names,
+        )
+    }
+
+    fn append_action_delete_key(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        public_key: PublicKey,
+    ) -> Result<()> {
+        self.inner.append_action_delete_key(receipt_index, public_key)
+    }
+
+    fn append_action_delete_account(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        beneficiary_id: AccountId,
+    ) -> Result<()> {
+        self.inner.append_action_delete_account(receipt_index, beneficiary_id)
+    }
+
+    fn get_touched_nodes_count(&self) -> u64 {
+        self.inner.get_touched_nodes_count()
+    }
+
+    fn reset_touched_nodes_counter(&mut self) {
+        self.inner.reset_touched_nodes_counter()
+    }
+
+    fn validator_stake(&self, account_id: &AccountId) -> Result<Option<Balance>> {
+        self.inner.validator_stake(account_id)
+    }
+
+    fn validator_total_stake(&self) -> Result<Balance> {
+        self.inner.validator_total_stake()
+    }
+}
@@ -0,0 +1,262 @@
+#![no_main]
+//! Differential fuzzing of the VM backends: every generated module is executed under all VM
+//! kinds with identical mocks and the results must match exactly.
+use libfuzzer_sys::fuzz_target;
+use near_primitives::contract::ContractCode;
+use near_primitives::runtime::fees::RuntimeFeesConfig;
+use near_primitives::version::PROTOCOL_VERSION;
+use near_vm_errors::{CompilationError, FunctionCallError, VMError};
+use near_vm_logic::mocks::mock_external::MockedExternal;
+use near_vm_logic::{VMConfig, VMContext, VMOutcome};
+use near_vm_runner::{prepare::prepare_contract, run_vm, VMKind};
+use parity_wasm::elements::ValueType;
+use parity_wasm::elements::ValueType::{I32, I64};
+
+const VM_KINDS: [VMKind; 3] = [VMKind::Wasmer0, VMKind::Wasmer1, VMKind::Wasmtime];
+
+/// Host functions that `near-vm-runner` links into the `env` module, with their Wasm signatures.
+/// `gas` is left out since it is injected by the gas metering, and the Alt BN128 functions since
+/// they are behind a protocol feature.
+const HOST_FUNCTIONS: &[(&str, &[ValueType], Option<ValueType>)] = &[
+    // Registers
+    ("read_register", &[I64, I64], None),
+    ("register_len", &[I64], Some(I64)),
+    ("write_register", &[I64, I64, I64], None),
+    // Context API
+    ("current_account_id", &[I64], None),
+    ("signer_account_id", &[I64], None),
+    ("signer_account_pk", &[I64], None),
+    ("predecessor_account_id", &[I64], None),
+    ("input", &[I64], None),
+    ("block_index", &[], Some(I64)),
+    ("block_timestamp", &[], Some(I64)),
+    ("epoch_height", &[], Some(I64)),
+    ("storage_usage", &[], Some(I64)),
+    // Economics API
+    ("account_balance", &[I64], None),
+    ("account_locked_balance", &[I64], None),
+    ("attached_deposit", &[I64], None),
+    ("prepaid_gas", &[], Some(I64)),
+    ("used_gas", &[], Some(I64)),
+    // Math API
+    ("random_seed", &[I64], None),
+    ("sha256", &[I64, I64, I64], None),
+    ("keccak256", &[I64, I64, I64], None),
+    ("keccak512", &[I64, I64, I64], None),
+    ("ripemd160", &[I64, I64, I64], None),
+    ("ecrecover", &[I64, I64, I64, I64, I64, I64, I64], Some(I64)),
+    // Miscellaneous API
+    ("value_return", &[I64, I64], None),
+    ("panic", &[], None),
+    ("panic_utf8", &[I64, I64], None),
+    ("log_utf8", &[I64, I64], None),
+    ("log_utf16", &[I64, I64], None),
+    ("abort", &[I32, I32, I32, I32], None),
+    // Promises API
+    ("promise_create", &[I64, I64, I64, I64, I64, I64, I64, I64], Some(I64)),
+    ("promise_then", &[I64, I64, I64, I64, I64, I64, I64, I64, I64], Some(I64)),
+    ("promise_and", &[I64, I64], Some(I64)),
+    ("promise_batch_create", &[I64, I64], Some(I64)),
+    ("promise_batch_then", &[I64, I64, I64], Some(I64)),
+    // Promise API actions
+    ("promise_batch_action_create_account", &[I64], None),
+    ("promise_batch_action_deploy_contract", &[I64, I64, I64], None),
+    ("promise_batch_action_function_call", &[I64, I64, I64, I64, I64, I64, I64], None),
+    ("promise_batch_action_transfer", &[I64, I64], None),
+    ("promise_batch_action_stake", &[I64, I64, I64, I64], None),
+    ("promise_batch_action_add_key_with_full_access", &[I64, I64, I64, I64], None),
+    (
+        "promise_batch_action_add_key_with_function_call",
+        &[I64, I64, I64, I64, I64, I64, I64, I64, I64],
+        None,
+    ),
+    ("promise_batch_action_delete_key", &[I64, I64, I64], None),
+    ("promise_batch_action_delete_account", &[I64, I64, I64], None),
+    // Promise API results
+    ("promise_results_count", &[], Some(I64)),
+    ("promise_result", &[I64, I64], Some(I64)),
+    ("promise_return", &[I64], None),
+    // Storage API
+    ("storage_write", &[I64, I64, I64, I64, I64], Some(I64)),
+    ("storage_read", &[I64, I64, I64], Some(I64)),
+    ("storage_remove", &[I64, I64, I64], Some(I64)),
+    ("storage_has_key", &[I64, I64], Some(I64)),
+    ("storage_iter_prefix", &[I64, I64], Some(I64)),
+    ("storage_iter_range", &[I64, I64, I64, I64], Some(I64)),
+    ("storage_iter_next", &[I64, I64, I64], Some(I64)),
+    // Validator API
+    ("validator_stake", &[I64, I64, I64], None),
+    ("validator_total_stake", &[I64], None),
+];
+
+/// Module that only imports `HOST_FUNCTIONS`, the imports generated modules may choose from.
+fn host_functions_module() -> Vec<u8> {
+    use parity_wasm::elements::{
+        External, FunctionType, ImportEntry, ImportSection, Module, Section, Type, TypeSection,
+    };
+    let types = HOST_FUNCTIONS
+        .iter()
+        .map(|(_, params, result)| Type::Function(FunctionType::new(params.to_vec(), *result)))
+        .collect();
+    let imports = HOST_FUNCTIONS
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _, _))| {
+            ImportEntry::new("env".to_string(), name.to_string(), External::Function(index as u32))
+        })
+        .collect();
+    let module = Module::new(vec![
+        Section::Type(TypeSection::with_types(types)),
+        Section::Import(ImportSection::with_entries(imports)),
+    ]);
+    parity_wasm::serialize(module).unwrap()
+}
+
+/// Restricts generated modules to the Wasm features that contracts are allowed to use.
+#[derive(arbitrary::Arbitrary, Debug)]
+struct ContractConfig;
+
+impl wasm_smith::Config for ContractConfig {
+    fn available_imports(&self) -> Option<std::borrow::Cow<'_, [u8]>> {
+        Some(host_functions_module().into())
+    }
+
+    fn max_memories(&self) -> usize {
+        1
+    }
+
+    fn max_tables(&self) -> usize {
+        1
+    }
+
+    fn min_exports(&self) -> usize {
+        1
+    }
+
+    fn allow_start_export(&self) -> bool {
+        false
+    }
+
+    fn max_instructions(&self) -> usize {
+        1000
+    }
+}
+
+fuzz_target!(|module: wasm_smith::ConfiguredModule<ContractConfig>| {
+    let code = module.to_bytes();
+    let config = VMConfig::default();
+    if prepare_contract(&code, &config).is_err() {
+        return;
+    }
+    let methods = match exported_functions(&code) {
+        Some(methods) => methods,
+        None => return,
+    };
+    let code = ContractCode::new(code, None);
+    for method in &methods {
+        let results: Vec<_> =
+            VM_KINDS.iter().map(|vm_kind| run(&code, method, &config, *vm_kind)).collect();
+        if results.iter().any(|result| result.is_nondeterministic()) {
+            continue;
+        }
+        for (vm_kind, result) in VM_KINDS.iter().zip(&results).skip(1) {
+            if result != &results[0] {
+                panic!(
+                    "{:?} and {:?} diverged on method {:?}:\n{:#?}\nvs\n{:#?}",
+                    VM_KINDS[0], vm_kind, method, results[0], result
+                );
+            }
+        }
+    }
+});
+
+#[derive(Debug, PartialEq)]
+struct RunResult {
+    outcome: Option<VMOutcome>,
+    error: Option<VMError>,
+    receipts: String,
+    state: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl RunResult {
+    fn is_nondeterministic(&self) -> bool {
+        matches!(
+            self.error,
+            Some(VMError::FunctionCallError(FunctionCallError::Nondeterministic(_)))
+        )
+    }
+}
+
+fn run(code: &ContractCode, method: &str, config: &VMConfig, vm_kind: VMKind) -> RunResult {
+    let mut external = MockedExternal::new();
+    let (outcome, error) = run_vm(
+        code,
+        method,
+        &mut external,
+        create_context(),
+        config,
+        &RuntimeFeesConfig::default(),
+        &[],
+        vm_kind,
+        PROTOCOL_VERSION,
+        None,
+    );
+    let mut state: Vec<_> = external.fake_trie.clone().into_iter().collect();
+    state.sort();
+    RunResult {
+        outcome,
+        error: error.map(normalize_error),
+        receipts: serde_json::to_string(external.get_receipt_create_calls()).unwrap(),
+        state,
+    }
+}
+
+/// Compilation and link error messages come from the backend, only the error kinds are compared.
+fn normalize_error(error: VMError) -> VMError {
+    match error {
+        VMError::FunctionCallError(FunctionCallError::CompilationError(
+            CompilationError::WasmerCompileError { .. },
+        )) => VMError::FunctionCallError(FunctionCallError::CompilationError(
+            CompilationError::WasmerCompileError { msg: String::new() },
+        )),
+        VMError::FunctionCallError(FunctionCallError::LinkError { .. }) => {
+            VMError::FunctionCallError(FunctionCallError::LinkError { msg: String::new() })
+        }
+        error => error,
+    }
+}
+
+fn exported_functions(code: &[u8]) -> Option<Vec<String>> {
+    use parity_wasm::elements::{deserialize_buffer, Internal, Module};
+    let module: Module = deserialize_buffer(code).ok()?;
+    Some(
+        module
+            .export_section()?
+            .entries()
+            .iter()
+            .filter(|export| matches!(export.internal(), Internal::Function(_)))
+            .map(|export| export.field().to_string())
+            .collect(),
+    )
+}
+
+fn create_context() -> VMContext {
+    VMContext {
+        current_account_id: "alice".parse().unwrap(),
+        signer_account_id: "bob".parse().unwrap(),
+        signer_account_pk: vec![0, 1, 2],
+        predecessor_account_id: "carol".parse().unwrap(),
+        input: vec![],
+        block_index: 10,
+        block_timestamp: 42,
+        epoch_height: 1,
+        account_balance: 2u128,
+        account_locked_balance: 0,
+        storage_usage: 12,
+        attached_deposit: 2u128,
+        prepaid_gas: 10u64.pow(14),
+        random_seed: vec![0, 1, 2],
+        view_config: None,
+        output_data_receivers: vec![],
+    }
+}
@@ -35,6 +35,27 @@ macro_rules! rust2wasm {
     };
 }
 
+/// Calls the host function on `VMLogic`, recording it in the execution trace when the
+/// `execution_trace` feature is enabled.
+macro_rules! call_logic {
+    ($logic:ident, $func:ident, $( $arg_name:ident : $arg_type:ident ),*) => {{
+        #[cfg(feature = "execution_trace")]
+        let gas_before = $logic.burnt_gas();
+        let result = $logic.$func( $( $arg_name as $arg_type, )* );
+        #[cfg(feature = "execution_trace")]
+        if crate::trace::is_enabled() {
+            crate::trace::record_host_call(
+                stringify!($func),
+                vec![$( (stringify!($arg_name), $arg_name as $arg_type as u64) ),*],
+                gas_before,
+                $logic.burnt_gas(),
+                &result,
+            );
+        }
+        result
+    }};
+}
+
 macro_rules! wrapped_imports {
         ( $($(#[$feature_name:tt, $feature:ident])* $func:ident < [ $( $arg_name:ident : $arg_type:ident ),* ] -> [ $( $returns:ident ),* ] >, )* ) => {
             #[cfg(feature = "wasmer0_vm")]
@@ -47,7 +68,7 @@ macro_rules! wrapped_imports {
                     $(#[cfg(feature = $feature_name)])*
                     pub fn $func( ctx: &mut Ctx, $( $arg_name: $arg_type ),* ) -> VMResult<($( $returns ),*)> {
                         let logic: &mut VMLogic<'_> = unsafe { &mut *(ctx.data as *mut VMLogic<'_>) };
-                        logic.$func( $( $arg_name, )* )
+                        call_logic!(logic, $func, $( $arg_name: $arg_type ),*)
                     }
                 )*
             }
@@ -63,7 +84,7 @@ macro_rules! wrapped_imports {
                 $(#[cfg(feature = $feature_name)])*
                 pub fn $func(env: &NearWasmerEnv, $( $arg_name: $arg_type ),* ) -> VMResult<($( $returns ),*)> {
                     let logic: &mut VMLogic = unsafe { &mut *(env.logic.0 as *mut VMLogic<'_>) };
-                    logic.$func( $( $arg_name, )* )
+                    call_logic!(logic, $func, $( $arg_name: $arg_type ),*)
                 }
             )*
             }
@@ -91,7 +112,7 @@ macro_rules! wrapped_imports {
                             }
                         });
                         let logic: &mut VMLogic<'_> = unsafe { &mut *(data as *mut VMLogic<'_>) };
-                        match logic.$func( $( $arg_name as $arg_type, )* ) {
+                        match call_logic!(logic, $func, $( $arg_name: $arg_type ),*) {
                             Ok(result) => Ok(result as ($( rust2wasm!($returns) ),* ) ),
                             Err(err) => {
                                 // Wasmtime doesn't have proper mechanism for wrapping custom errors
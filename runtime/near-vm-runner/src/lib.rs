@@ -11,6 +11,8 @@ mod vm_kind;
 mod preload;
 pub mod prepare;
 mod runner;
+#[cfg(feature = "execution_trace")]
+pub mod trace;
 
 #[cfg(feature = "wasmer0_vm")]
 mod wasmer_runner;
@@ -32,6 +34,7 @@ pub use cache::get_contract_cache_key;
 pub use cache::precompile_contract;
 pub use cache::precompile_contract_vm;
 pub use cache::MockCompiledContractCache;
+pub use errors::{ContractPrecompilatonError, ContractPrecompilatonResult};
 
 // These two are public for the standalone runner, but are an implementation
 // detail of `near-vm-runner`. Public API like `run` should not expose VMKind.
@@ -0,0 +1,188 @@
+//! Opt-in recording of what a contract did during a single `run_vm` call.
+//!
+//! Tracing is scoped to the current thread: call [`start`] before running the contract and
+//! [`finish`] afterwards to collect the [`ExecutionTrace`]. When tracing was not started the
+//! recording hooks are no-ops, apart from a thread-local lookup.
+use near_primitives::types::Gas;
+use near_vm_logic::VMLogicError;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+thread_local! {
+    static TRACE: RefCell<Option<ExecutionTrace>> = RefCell::new(None);
+    /// Storage accesses recorded since the last host call returned.
+    static PENDING_STORAGE: RefCell<Vec<StorageAccess>> = RefCell::new(Vec::new());
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct ExecutionTrace {
+    /// Host functions called by the contract, in call order.
+    pub host_calls: Vec<HostCall>,
+    /// Register contents at the end of the execution.
+    #[serde(serialize_with = "serialize_registers")]
+    pub registers: BTreeMap<u64, Vec<u8>>,
+    /// Wasm call stack at the time of the trap, innermost frame first. Empty if the execution
+    /// did not trap or the VM does not expose frame information.
+    pub trap_stack: Vec<WasmFrame>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HostCall {
+    pub name: &'static str,
+    pub args: Vec<(&'static str, u64)>,
+    /// Gas burnt by the call itself.
+    pub gas: Gas,
+    /// Gas burnt by the whole execution once the call returned.
+    pub burnt_gas: Gas,
+    /// Debug representation of the returned value, if the call succeeded.
+    pub result: Option<String>,
+    pub error: Option<String>,
+    /// Storage accessed by the call.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageAccess>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StorageAccess {
+    Read {
+        #[serde(with = "near_primitives::serialize::base64_format")]
+        key: Vec<u8>,
+        #[serde(with = "near_primitives::serialize::option_base64_format")]
+        value: Option<Vec<u8>>,
+    },
+    Write {
+        #[serde(with = "near_primitives::serialize::base64_format")]
+        key: Vec<u8>,
+        #[serde(with = "near_primitives::serialize::base64_format")]
+        value: Vec<u8>,
+    },
+    Remove {
+        #[serde(with = "near_primitives::serialize::base64_format")]
+        key: Vec<u8>,
+    },
+    HasKey {
+        #[serde(with = "near_primitives::serialize::base64_format")]
+        key: Vec<u8>,
+        found: bool,
+    },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WasmFrame {
+    pub func_index: u32,
+    pub func_name: Option<String>,
+    /// Offset of the trapping instruction from the start of the function body.
+    pub func_offset: usize,
+    /// Offset of the trapping instruction from the start of the module.
+    pub module_offset: usize,
+}
+
+fn serialize_registers<S: serde::Serializer>(
+    registers: &BTreeMap<u64, Vec<u8>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        registers.iter().map(|(id, data)| (id, near_primitives::serialize::to_base64(data))),
+    )
+}
+
+fn with_trace(f: impl FnOnce(&mut ExecutionTrace)) {
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            f(trace)
+        }
+    })
+}
+
+/// Starts recording a trace on the current thread, discarding any unfinished one.
+pub fn start() {
+    TRACE.with(|trace| *trace.borrow_mut() = Some(ExecutionTrace::default()));
+    PENDING_STORAGE.with(|pending| pending.borrow_mut().clear());
+}
+
+/// Stops recording and returns the trace, if one was started.
+pub fn finish() -> Option<ExecutionTrace> {
+    PENDING_STORAGE.with(|pending| pending.borrow_mut().clear());
+    TRACE.with(|trace| trace.borrow_mut().take())
+}
+
+pub fn is_enabled() -> bool {
+    TRACE.with(|trace| trace.borrow().is_some())
+}
+
+/// Records a storage access. It is attached to the host call that is currently executing.
+/// Meant to be called by `External` implementations.
+pub fn record_storage_access(access: StorageAccess) {
+    if is_enabled() {
+        PENDING_STORAGE.with(|pending| pending.borrow_mut().push(access));
+    }
+}
+
+pub(crate) fn record_host_call<T: std::fmt::Debug>(
+    name: &'static str,
+    args: Vec<(&'static str, u64)>,
+    gas_before: Gas,
+    burnt_gas: Gas,
+    result: &Result<T, VMLogicError>,
+) {
+    // The injected gas counter is called for every basic block, only failures are interesting.
+    if name == "gas" && result.is_ok() {
+        return;
+    }
+    with_trace(|trace| {
+        let storage = PENDING_STORAGE.with(|pending| pending.replace(Vec::new()));
+        let (result, error) = match result {
+            Ok(value) => (Some(format!("{:?}", value)), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        trace.host_calls.push(HostCall {
+            name,
+            args,
+            gas: burnt_gas.saturating_sub(gas_before),
+            burnt_gas,
+            result,
+            error,
+            storage,
+        });
+    })
+}
+
+pub(crate) fn record_registers(registers: &HashMap<u64, Vec<u8>>) {
+    with_trace(|trace| {
+        trace.registers = registers.iter().map(|(id, data)| (*id, data.clone())).collect();
+    })
+}
+
+#[cfg(feature = "wasmer1_vm")]
+pub(crate) fn record_wasmer1_trap(error: &wasmer::RuntimeError) {
+    with_trace(|trace| {
+        trace.trap_stack = error
+            .trace()
+            .iter()
+            .map(|frame| WasmFrame {
+                func_index: frame.func_index(),
+                func_name: frame.function_name().map(str::to_string),
+                func_offset: frame.func_offset(),
+                module_offset: frame.module_offset(),
+            })
+            .collect();
+    })
+}
+
+#[cfg(feature = "wasmtime_vm")]
+pub(crate) fn record_wasmtime_trap(trap: &wasmtime::Trap) {
+    with_trace(|trace| {
+        trace.trap_stack = trap
+            .trace()
+            .iter()
+            .map(|frame| WasmFrame {
+                func_index: frame.func_index(),
+                func_name: frame.func_name().map(str::to_string),
+                func_offset: frame.func_offset(),
+                module_offset: frame.module_offset(),
+            })
+            .collect();
+    })
+}
@@ -258,6 +258,8 @@ pub fn run_wasmer1(
     }
 
     let err = run_method(&module, &import_object, method_name).err();
+    #[cfg(feature = "execution_trace")]
+    crate::trace::record_registers(logic.registers());
     (Some(logic.outcome()), err)
 }
 
@@ -273,7 +275,11 @@ fn run_method(module: &Module, import: &ImportObject, method_name: &str) -> Resu
 
     {
         let _span = tracing::debug_span!(target: "vm", "run_method/call").entered();
-        f.call().map_err(|err| err.into_vm_error())?
+        f.call().map_err(|err| {
+            #[cfg(feature = "execution_trace")]
+            crate::trace::record_wasmer1_trap(&err);
+            err.into_vm_error()
+        })?
     }
 
     {
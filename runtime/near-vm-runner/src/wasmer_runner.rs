@@ -281,6 +281,8 @@ pub fn run_wasmer<'a>(
     }
 
     let err = run_method(&module, &import_object, method_name).err();
+    #[cfg(feature = "execution_trace")]
+    crate::trace::record_registers(logic.registers());
     (Some(logic.outcome()), err)
 }
 
@@ -237,10 +237,20 @@ pub mod wasmtime_runner {
         match linker.instantiate(&module) {
             Ok(instance) => match instance.get_func(method_name) {
                 Some(func) => match func.typed::<(), ()>() {
-                    Ok(run) => match run.call(()) {
-                        Ok(_) => (Some(logic.outcome()), None),
-                        Err(err) => (Some(logic.outcome()), Some(err.into_vm_error())),
-                    },
+                    Ok(run) => {
+                        let result = run.call(());
+                        #[cfg(feature = "execution_trace")]
+                        {
+                            if let Err(trap) = &result {
+                                crate::trace::record_wasmtime_trap(trap);
+                            }
+                            crate::trace::record_registers(logic.registers());
+                        }
+                        match result {
+                            Ok(_) => (Some(logic.outcome()), None),
+                            Err(err) => (Some(logic.outcome()), Some(err.into_vm_error())),
+                        }
+                    }
                     Err(err) => (Some(logic.outcome()), Some(err.into_vm_error())),
                 },
                 None => (
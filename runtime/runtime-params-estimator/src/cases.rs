@@ -231,6 +231,98 @@ pub enum Metric {
     cpu_ram_soak_test,
 }
 
+/// Metrics that have to be measured to estimate the given cost. Costs that are computed without
+/// measuring transactions, or are not estimated at all, need no metrics.
+pub fn required_metrics(cost: Cost) -> Vec<Metric> {
+    use Metric::*;
+
+    let fee_metrics = match cost {
+        Cost::ActionReceiptCreation => vec![Receipt],
+        Cost::ActionSirReceiptCreation => vec![SirReceipt],
+        Cost::DataReceiptCreationBase => vec![data_receipt_10b_1000, data_receipt_base_10b_1000],
+        Cost::DataReceiptCreationPerByte => vec![data_receipt_100kib_1000, data_receipt_10b_1000],
+        Cost::ActionCreateAccount => vec![ActionCreateAccount, Receipt],
+        Cost::ActionDeployContractBase => vec![ActionDeploySmallest, SirReceipt],
+        Cost::ActionDeployContractPerByte => vec![ActionDeploy1M, ActionDeploySmallest],
+        Cost::ActionFunctionCallBase => vec![noop, SirReceipt],
+        Cost::ActionFunctionCallPerByte => vec![noop_1MiB, noop],
+        Cost::ActionTransfer => vec![ActionTransfer, Receipt],
+        Cost::ActionStake => vec![ActionStake, SirReceipt],
+        Cost::ActionAddFullAccessKey => vec![ActionAddFullAccessKey, SirReceipt],
+        Cost::ActionAddFunctionAccessKeyBase => vec![ActionAddFunctionAccessKey1Method, SirReceipt],
+        Cost::ActionAddFunctionAccessKeyPerByte => {
+            vec![ActionAddFunctionAccessKey1000Methods, ActionAddFunctionAccessKey1Method]
+        }
+        Cost::ActionDeleteKey => vec![ActionDeleteAccessKey, SirReceipt],
+        Cost::ActionDeleteAccount => vec![ActionDeleteAccount, SirReceipt],
+        _ => vec![],
+    };
+    if !fee_metrics.is_empty() {
+        return fee_metrics;
+    }
+
+    // Host function costs are measured on top of the `noop` function call.
+    let ext_cost_metrics = match cost {
+        Cost::HostFunctionCall => vec![base_1M],
+        Cost::ReadMemoryBase => vec![read_memory_10b_10k],
+        Cost::ReadMemoryByte => vec![read_memory_1Mib_10k],
+        Cost::WriteMemoryBase => vec![write_memory_10b_10k],
+        Cost::WriteMemoryByte => vec![write_memory_1Mib_10k],
+        Cost::ReadRegisterBase => vec![read_register_10b_10k],
+        Cost::ReadRegisterByte => vec![read_register_1Mib_10k],
+        Cost::WriteRegisterBase => vec![write_register_10b_10k],
+        Cost::WriteRegisterByte => vec![write_register_1Mib_10k],
+        Cost::LogBase | Cost::Utf16DecodingBase => vec![utf16_log_10b_10k],
+        Cost::LogByte => vec![utf16_log_10kib_10k],
+        Cost::Utf8DecodingBase => vec![utf8_log_10b_10k],
+        Cost::Utf8DecodingByte => vec![utf8_log_10kib_10k, nul_utf8_log_10kib_10k],
+        Cost::Utf16DecodingByte => vec![utf16_log_10kib_10k, nul_utf16_log_10kib_10k],
+        Cost::Sha256Base => vec![sha256_10b_10k],
+        Cost::Sha256Byte => vec![sha256_10kib_10k],
+        Cost::Keccak256Base => vec![keccak256_10b_10k],
+        Cost::Keccak256Byte => vec![keccak256_10kib_10k],
+        Cost::Keccak512Base => vec![keccak512_10b_10k],
+        Cost::Keccak512Byte => vec![keccak512_10kib_10k],
+        Cost::Ripemd160Base => vec![ripemd160_10b_10k],
+        Cost::Ripemd160Block => vec![ripemd160_10kib_10k],
+        Cost::EcrecoverBase => vec![ecrecover_10k],
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128G1MultiexpBase => vec![alt_bn128_g1_multiexp_1_1k],
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128G1MultiexpByte | Cost::AltBn128G1MultiexpSublinear => {
+            vec![alt_bn128_g1_multiexp_10_1k]
+        }
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128G1SumBase => vec![alt_bn128_g1_sum_1_1k],
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128G1SumByte => vec![alt_bn128_g1_sum_10_1k],
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128PairingCheckBase => vec![alt_bn128_pairing_check_1_1k],
+        #[cfg(feature = "protocol_feature_alt_bn128")]
+        Cost::AltBn128PairingCheckByte => vec![alt_bn128_pairing_check_10_1k],
+        Cost::StorageWriteBase => vec![storage_write_10b_key_10b_value_1k],
+        Cost::StorageWriteKeyByte => vec![storage_write_10kib_key_10b_value_1k],
+        Cost::StorageWriteValueByte => vec![storage_write_10b_key_10kib_value_1k],
+        Cost::StorageWriteEvictedByte => vec![storage_write_10b_key_10kib_value_1k_evict],
+        // `TouchingTrieNode` is derived from `StorageReadBase`.
+        Cost::StorageReadBase | Cost::TouchingTrieNode => vec![storage_read_10b_key_10b_value_1k],
+        Cost::StorageReadKeyByte => vec![storage_read_10kib_key_10b_value_1k],
+        Cost::StorageReadValueByte => vec![storage_read_10b_key_10kib_value_1k],
+        Cost::StorageRemoveBase => vec![storage_remove_10b_key_10b_value_1k],
+        Cost::StorageRemoveKeyByte => vec![storage_remove_10kib_key_10b_value_1k],
+        Cost::StorageRemoveRetValueByte => vec![storage_remove_10b_key_10kib_value_1k],
+        Cost::StorageHasKeyBase => vec![storage_has_key_10b_key_10b_value_1k],
+        Cost::StorageHasKeyByte => vec![storage_has_key_10kib_key_10b_value_1k],
+        Cost::PromiseAndBase => vec![promise_and_100k],
+        Cost::PromiseAndPerPromise => vec![promise_and_100k_on_1k_and],
+        Cost::PromiseReturn => vec![promise_return_100k],
+        // Compilation and wasm instruction costs are always computed directly, the rest is not
+        // estimated yet.
+        _ => return vec![],
+    };
+    ext_cost_metrics.into_iter().chain(std::iter::once(noop)).collect()
+}
+
 #[allow(unused_variables)]
 pub fn run(mut config: Config, only_compile: bool) -> CostTable {
     let mut m = Measurements::new(config.metric);
@@ -22,6 +22,63 @@ impl CostTable {
     pub(crate) fn get(&self, cost: Cost) -> Option<Gas> {
         self.map.get(&cost).copied()
     }
+
+    /// Drops all costs except the given ones.
+    pub fn retain_costs(&mut self, costs: &[Cost]) {
+        let map = std::mem::take(&mut self.map);
+        self.map = map.into_iter().filter(|(cost, _)| costs.contains(cost)).collect();
+    }
+
+    /// Machine-readable representation, a JSON object from cost names to gas.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.map
+            .iter()
+            .map(|(cost, gas)| (cost.to_string(), serde_json::Value::from(*gas)))
+            .collect()
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, ()> {
+        let mut res = CostTable::default();
+        for (cost, gas) in value.as_object().ok_or(())? {
+            res.add(cost.parse()?, gas.as_u64().ok_or(())?)
+        }
+        Ok(res)
+    }
+
+    /// Compares each cost of this table against the `new` one. Costs present in only one of
+    /// the tables are included with the missing side set to `None`.
+    pub fn diff(&self, new: &CostTable) -> Vec<CostDiff> {
+        Cost::all()
+            .map(|cost| CostDiff { cost, old: self.get(cost), new: new.get(cost) })
+            .filter(|diff| diff.old.is_some() || diff.new.is_some())
+            .collect()
+    }
+}
+
+/// Change of a single [`Cost`] between two estimator runs.
+#[derive(Debug, PartialEq)]
+pub struct CostDiff {
+    pub cost: Cost,
+    pub old: Option<Gas>,
+    pub new: Option<Gas>,
+}
+
+impl CostDiff {
+    /// Relative change from the old to the new value, e.g. `0.1` for a 10% increase.
+    pub fn relative_change(&self) -> Option<f64> {
+        match (self.old, self.new) {
+            (Some(old), Some(new)) if old > 0 => Some((new as f64 - old as f64) / old as f64),
+            _ => None,
+        }
+    }
+
+    /// Whether the cost grew by more than `threshold` (relative), or appeared or disappeared.
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        match self.relative_change() {
+            Some(change) => change > threshold,
+            None => self.old != self.new,
+        }
+    }
 }
 
 impl FromStr for CostTable {
@@ -69,6 +126,32 @@ fn separate_thousands(mut n: u64) -> String {
     parts.join("_")
 }
 
+#[test]
+fn test_cost_table_diff() {
+    let old: CostTable = "ActionTransfer 100\nStorageReadBase 1_000\nLogBase 50".parse().unwrap();
+    let mut new = CostTable::default();
+    new.add(Cost::ActionTransfer, 105);
+    new.add(Cost::StorageReadBase, 1_200);
+    new.add(Cost::Sha256Base, 10);
+
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff,
+        vec![
+            CostDiff { cost: Cost::ActionTransfer, old: Some(100), new: Some(105) },
+            CostDiff { cost: Cost::Sha256Base, old: None, new: Some(10) },
+            CostDiff { cost: Cost::LogBase, old: Some(50), new: None },
+            CostDiff { cost: Cost::StorageReadBase, old: Some(1_000), new: Some(1_200) },
+        ]
+    );
+    let regressions: Vec<Cost> =
+        diff.iter().filter(|diff| diff.is_regression(0.1)).map(|diff| diff.cost).collect();
+    assert_eq!(regressions, vec![Cost::Sha256Base, Cost::LogBase, Cost::StorageReadBase]);
+
+    let parsed = CostTable::from_json(&new.to_json()).unwrap();
+    assert!(new.diff(&parsed).iter().all(|diff| diff.old == diff.new));
+}
+
 #[test]
 fn test_separate_thousands() {
     assert_eq!(separate_thousands(0).as_str(), "0");
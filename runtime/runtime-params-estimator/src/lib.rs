@@ -26,7 +26,7 @@ mod gas_metering;
 pub mod testbed_runners;
 
 pub use crate::cost::Cost;
-pub use crate::cost_table::CostTable;
+pub use crate::cost_table::{CostDiff, CostTable};
 pub use crate::costs_to_runtime_config::costs_to_runtime_config;
 
 /// Lazily loads contract's code from a directory in the source tree.
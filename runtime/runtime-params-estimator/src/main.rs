@@ -4,11 +4,11 @@ use genesis_populate::GenesisBuilder;
 use near_store::create_store;
 use near_vm_runner::VMKind;
 use nearcore::{get_default_home, get_store_path, load_config};
-use runtime_params_estimator::cases::run;
+use runtime_params_estimator::cases::{required_metrics, run};
 use runtime_params_estimator::costs_to_runtime_config;
 use runtime_params_estimator::testbed_runners::Config;
 use runtime_params_estimator::testbed_runners::GasMetric;
-use runtime_params_estimator::CostTable;
+use runtime_params_estimator::{Cost, CostTable};
 use std::env;
 use std::fmt::Write;
 use std::fs;
@@ -53,6 +53,15 @@ struct CliArgs {
     /// Only measure the specified metrics, computing a subset of costs.
     #[clap(long)]
     metrics_to_measure: Option<String>,
+    /// Only estimate the specified comma-separated costs, measuring just the metrics they need.
+    #[clap(long, conflicts_with = "metrics-to-measure")]
+    costs: Option<String>,
+    /// Compare two estimator results (`.txt` or `.json`) instead of running the estimator.
+    #[clap(long, number_of_values = 2, value_names = &["OLD", "NEW"])]
+    compare: Option<Vec<PathBuf>>,
+    /// Relative change in percent above which an increased cost is reported as a regression.
+    #[clap(long, default_value = "5")]
+    threshold: f64,
     /// Build and run the estimator inside a docker container via QEMU.
     #[clap(long)]
     docker: bool,
@@ -68,6 +77,10 @@ fn main() -> anyhow::Result<()> {
 
     let cli_args = CliArgs::parse();
 
+    if let Some(paths) = &cli_args.compare {
+        return main_compare(&paths[0], &paths[1], cli_args.threshold / 100.0);
+    }
+
     let state_dump_path = cli_args.home.unwrap_or_else(|| get_default_home().into());
 
     let additional_accounts_num = cli_args.additional_accounts_num as u64;
@@ -105,10 +118,7 @@ fn main() -> anyhow::Result<()> {
     }
 
     if let Some(path) = cli_args.costs_file {
-        let cost_table = fs::read_to_string(&path)
-            .ok()
-            .and_then(|it| it.parse::<CostTable>().ok())
-            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let cost_table = read_cost_table(&path)?;
 
         let runtime_config = costs_to_runtime_config(&cost_table)?;
 
@@ -140,10 +150,32 @@ fn main() -> anyhow::Result<()> {
         "wasmtime" => VMKind::Wasmtime,
         other => unreachable!("Unknown vm_kind {}", other),
     };
-    let metrics_to_measure =
-        cli_args.metrics_to_measure.map(|it| it.split(',').map(str::to_string).collect());
+    let costs = match &cli_args.costs {
+        Some(costs) => Some(
+            costs
+                .split(',')
+                .map(|cost| {
+                    cost.parse::<Cost>().map_err(|()| anyhow::anyhow!("unknown cost: {}", cost))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+    let metrics_to_measure = match &costs {
+        Some(costs) => {
+            let mut metrics: Vec<String> = costs
+                .iter()
+                .flat_map(|cost| required_metrics(*cost))
+                .map(|metric| format!("{:?}", metric))
+                .collect();
+            metrics.sort();
+            metrics.dedup();
+            Some(metrics)
+        }
+        None => cli_args.metrics_to_measure.map(|it| it.split(',').map(str::to_string).collect()),
+    };
 
-    let cost_table = run(
+    let mut cost_table = run(
         Config {
             warmup_iters_per_block,
             iter_per_block,
@@ -157,24 +189,84 @@ fn main() -> anyhow::Result<()> {
         cli_args.compile_only,
     );
 
+    if let Some(costs) = &costs {
+        cost_table.retain_costs(costs);
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let commit = exec("git rev-parse --short HEAD").ok();
     let output_path = {
-        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
-        let commit =
-            exec("git rev-parse --short HEAD").map(|hash| format!("-{}", hash)).unwrap_or_default();
+        let commit = commit.as_ref().map(|hash| format!("-{}", hash)).unwrap_or_default();
         let file_name = format!("costs-{}{}.txt", timestamp, commit);
 
         env::current_dir()?.join(file_name)
     };
     fs::write(&output_path, &cost_table.to_string())?;
+
+    let json_output_path = output_path.with_extension("json");
+    let json = serde_json::json!({
+        "timestamp": timestamp,
+        "commit": commit,
+        "metric": cli_args.metric,
+        "vm_kind": cli_args.vm_kind,
+        "costs": cost_table.to_json(),
+    });
+    fs::write(&json_output_path, serde_json::to_string_pretty(&json)?)?;
     println!(
-        "\nFinished in {:.2?}, output saved to:\n\n    {}",
+        "\nFinished in {:.2?}, output saved to:\n\n    {}\n    {}",
         start.elapsed(),
-        output_path.display()
+        output_path.display(),
+        json_output_path.display()
     );
 
     Ok(())
 }
 
+/// Reads a cost table written by the estimator, either the human-readable `.txt` file or the
+/// `.json` one.
+fn read_cost_table(path: &Path) -> anyhow::Result<CostTable> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let cost_table = if path.extension().map_or(false, |ext| ext == "json") {
+        serde_json::from_str::<serde_json::Value>(&contents)
+            .ok()
+            .and_then(|json| CostTable::from_json(&json["costs"]).ok())
+    } else {
+        contents.parse::<CostTable>().ok()
+    };
+    cost_table.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn main_compare(old_path: &Path, new_path: &Path, threshold: f64) -> anyhow::Result<()> {
+    let old = read_cost_table(old_path)?;
+    let new = read_cost_table(new_path)?;
+
+    let mut regressions = 0;
+    for diff in old.diff(&new) {
+        let show = |gas: Option<u64>| gas.map_or("-".to_string(), |gas| gas.to_string());
+        let change = diff
+            .relative_change()
+            .map_or("-".to_string(), |change| format!("{:+.2}%", change * 100.0));
+        let is_regression = diff.is_regression(threshold);
+        if is_regression {
+            regressions += 1;
+        }
+        println!(
+            "{:<35} {:>25} {:>25} {:>10}{}",
+            diff.cost.to_string(),
+            show(diff.old),
+            show(diff.new),
+            change,
+            if is_regression { "  REGRESSION" } else { "" }
+        );
+    }
+
+    if regressions > 0 {
+        anyhow::bail!("{} costs regressed by more than {}%", regressions, threshold * 100.0);
+    }
+    Ok(())
+}
+
 fn main_docker(state_dump_path: &Path, full: bool) -> anyhow::Result<()> {
     exec("docker --version").context("please install `docker`")?;
 
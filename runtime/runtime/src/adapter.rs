@@ -7,7 +7,7 @@ use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{StateOverrides, ViewStatePageResult, ViewStateResult};
 
 /// Adapter for querying runtime.
 pub trait ViewRuntimeAdapter {
@@ -41,6 +41,7 @@ pub trait ViewRuntimeAdapter {
         logs: &mut Vec<String>,
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
+        state_overrides: Option<&StateOverrides>,
     ) -> Result<Vec<u8>, crate::state_viewer::errors::CallFunctionError>;
 
     fn view_access_key(
@@ -65,4 +66,14 @@ pub trait ViewRuntimeAdapter {
         account_id: &AccountId,
         prefix: &[u8],
     ) -> Result<ViewStateResult, crate::state_viewer::errors::ViewStateError>;
+
+    fn view_state_page(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<ViewStatePageResult, crate::state_viewer::errors::ViewStateError>;
 }
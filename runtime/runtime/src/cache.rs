@@ -1,8 +1,18 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::sync::Arc;
 
+use rayon::prelude::*;
+
 use near_primitives::contract::ContractCode;
 use near_primitives::hash::CryptoHash;
-use near_store::StorageError;
+use near_primitives::trie_key::col;
+use near_primitives::types::{CompiledContractCache, StateRoot};
+use near_store::{DBCol, StorageError, Store, Trie};
+use near_vm_logic::VMConfig;
+use near_vm_runner::{
+    get_contract_cache_key, precompile_contract_vm, ContractPrecompilatonResult, VMKind,
+};
 
 pub(crate) fn get_code(
     code_hash: CryptoHash,
@@ -14,3 +24,171 @@ pub(crate) fn get_code(
         Arc::new(code)
     }))
 }
+
+/// Number of contracts read from the state before compiling them in parallel.
+const PRECOMPILE_BATCH_SIZE: usize = 64;
+
+/// Outcome of precompiling the contracts deployed in a state.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContractsPrecompilationStats {
+    /// Number of distinct contracts that weren't seen before.
+    pub contracts: u64,
+    /// Contracts that were already in the cache.
+    pub cached: u64,
+    /// Contracts that were compiled and added to the cache.
+    pub compiled: u64,
+    /// Contracts that failed to compile. The error is cached instead.
+    pub failed: u64,
+}
+
+impl ContractsPrecompilationStats {
+    pub fn merge(&mut self, other: ContractsPrecompilationStats) {
+        self.contracts += other.contracts;
+        self.cached += other.cached;
+        self.compiled += other.compiled;
+        self.failed += other.failed;
+    }
+}
+
+/// Compiles all contracts deployed in the given state which are not in the cache yet.
+/// Cache keys of the contracts are added to `cache_keys`, contracts with keys that are already
+/// there are skipped. Contracts are compiled in parallel on the given thread pool.
+pub fn precompile_contracts(
+    trie: &Trie,
+    state_root: &StateRoot,
+    vm_kind: VMKind,
+    config: &VMConfig,
+    cache: &dyn CompiledContractCache,
+    cache_keys: &mut HashSet<CryptoHash>,
+    thread_pool: &rayon::ThreadPool,
+) -> Result<ContractsPrecompilationStats, StorageError> {
+    let mut stats = ContractsPrecompilationStats::default();
+    // Wasmtime doesn't use the cache.
+    if let VMKind::Wasmtime = vm_kind {
+        return Ok(stats);
+    }
+    let mut codes = vec![];
+    let mut iter = trie.iter(state_root)?;
+    iter.seek(&[col::CONTRACT_CODE])?;
+    for item in iter {
+        let (key, value) = item?;
+        if key.first() != Some(&col::CONTRACT_CODE) {
+            break;
+        }
+        let code = ContractCode::new(value, None);
+        if cache_keys.insert(get_contract_cache_key(&code, vm_kind, config)) {
+            codes.push(code);
+        }
+        if codes.len() == PRECOMPILE_BATCH_SIZE {
+            let codes = std::mem::take(&mut codes);
+            precompile_batch(codes, vm_kind, config, cache, thread_pool, &mut stats);
+        }
+    }
+    precompile_batch(codes, vm_kind, config, cache, thread_pool, &mut stats);
+    Ok(stats)
+}
+
+fn precompile_batch(
+    codes: Vec<ContractCode>,
+    vm_kind: VMKind,
+    config: &VMConfig,
+    cache: &dyn CompiledContractCache,
+    thread_pool: &rayon::ThreadPool,
+    stats: &mut ContractsPrecompilationStats,
+) {
+    let results: Vec<_> = thread_pool.install(|| {
+        codes
+            .par_iter()
+            .map(|code| precompile_contract_vm(vm_kind, code, config, Some(cache)))
+            .collect()
+    });
+    for result in results {
+        stats.contracts += 1;
+        match result {
+            Ok(ContractPrecompilatonResult::ContractCompiled) => stats.compiled += 1,
+            Ok(ContractPrecompilatonResult::ContractAlreadyInCache) => stats.cached += 1,
+            Ok(ContractPrecompilatonResult::CacheNotAvailable) => {
+                unreachable!("Cache is always given")
+            }
+            Err(_) => stats.failed += 1,
+        }
+    }
+}
+
+/// Removes compiled contracts with keys not in `cache_keys` from the store, e.g. the ones
+/// compiled by other VM versions or with older configs. Returns the number of removed entries.
+pub fn purge_compiled_contracts(
+    store: &Store,
+    cache_keys: &HashSet<CryptoHash>,
+) -> Result<u64, std::io::Error> {
+    let mut store_update = store.store_update();
+    let mut removed = 0;
+    for (key, _) in store.iter(DBCol::ColCachedContractCode) {
+        let is_stale =
+            CryptoHash::try_from(&key[..]).map_or(true, |hash| !cache_keys.contains(&hash));
+        if is_stale {
+            store_update.delete(DBCol::ColCachedContractCode, &key);
+            removed += 1;
+        }
+    }
+    store_update.commit()?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::types::StateChangeCause;
+    use near_store::test_utils::create_tries;
+    use near_store::{set_code, StoreCompiledContractCache};
+
+    use super::*;
+
+    #[test]
+    fn test_precompile_and_purge_contracts() {
+        let tries = create_tries();
+        let mut state_update = tries.new_trie_update(ShardUId::default(), StateRoot::default());
+        let code = ContractCode::new(near_test_contracts::rs_contract().to_vec(), None);
+        for account_id in ["alice", "bob"].iter() {
+            set_code(&mut state_update, account_id.parse().unwrap(), &code);
+        }
+        state_update.commit(StateChangeCause::InitialState);
+        let trie_changes = state_update.finalize().unwrap().0;
+        let (store_update, root) = tries.apply_all(&trie_changes, ShardUId::default()).unwrap();
+        store_update.commit().unwrap();
+
+        let store = tries.get_store();
+        let cache = StoreCompiledContractCache { store: store.clone() };
+        let stale_key = CryptoHash::default();
+        cache.put(stale_key.as_ref(), b"stale").unwrap();
+
+        let trie = tries.get_trie_for_shard(ShardUId::default());
+        let config = VMConfig::default();
+        let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let precompile = |cache_keys: &mut HashSet<CryptoHash>| {
+            precompile_contracts(
+                &trie,
+                &root,
+                VMKind::default(),
+                &config,
+                &cache,
+                cache_keys,
+                &thread_pool,
+            )
+            .unwrap()
+        };
+
+        // Both accounts have the same contract, it's compiled once.
+        let stats = precompile(&mut HashSet::new());
+        assert_eq!((stats.contracts, stats.cached, stats.compiled, stats.failed), (1, 0, 1, 0));
+        let mut cache_keys = HashSet::new();
+        let stats = precompile(&mut cache_keys);
+        assert_eq!((stats.contracts, stats.cached, stats.compiled, stats.failed), (1, 1, 0, 0));
+
+        assert_eq!(purge_compiled_contracts(&store, &cache_keys).unwrap(), 1);
+        assert_eq!(cache.get(stale_key.as_ref()).unwrap(), None);
+        for key in cache_keys {
+            assert!(cache.get(key.as_ref()).unwrap().is_some());
+        }
+    }
+}
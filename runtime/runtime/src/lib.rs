@@ -4,6 +4,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use log::debug;
+use rayon::prelude::*;
 
 use near_chain_configs::Genesis;
 pub use near_crypto;
@@ -23,7 +24,7 @@ use near_primitives::version::{
 use near_primitives::{
     account::Account,
     checked_feature,
-    errors::{ActionError, ActionErrorKind, RuntimeError, TxExecutionError},
+    errors::{ActionError, ActionErrorKind, IntegerOverflowError, RuntimeError, TxExecutionError},
     hash::CryptoHash,
     receipt::{
         ActionReceipt, DataReceipt, DelayedReceiptIndices, Receipt, ReceiptEnum, ReceivedData,
@@ -36,7 +37,7 @@ use near_primitives::{
     trie_key::TrieKey,
     types::{
         validator_stake::ValidatorStake, AccountId, Balance, EpochInfoProvider, Gas,
-        RawStateChangesWithTrieKey, ShardId, StateChangeCause, StateRoot,
+        RawStateChanges, RawStateChangesWithTrieKey, ShardId, StateChangeCause, StateRoot,
     },
     utils::{
         create_action_hash, create_receipt_id_from_receipt, create_receipt_id_from_transaction,
@@ -45,7 +46,7 @@ use near_primitives::{
 use near_store::{
     get, get_account, get_postponed_receipt, get_received_data, remove_postponed_receipt, set,
     set_account, set_postponed_receipt, set_received_data, PartialStorage, ShardTries,
-    StorageError, Trie, TrieChanges, TrieUpdate,
+    SharedTrieStorage, StorageError, Trie, TrieChanges, TrieUpdate,
 };
 #[cfg(feature = "sandbox")]
 use near_store::{set_access_key, set_code};
@@ -59,7 +60,8 @@ use crate::config::{
     exec_fee, safe_add_balance, safe_add_gas, safe_gas_to_balance, total_deposit,
     total_prepaid_exec_fees, total_prepaid_gas, RuntimeConfig,
 };
-use crate::genesis::{GenesisStateApplier, StorageComputer};
+use crate::genesis::GenesisStateApplier;
+pub use crate::genesis::StorageComputer;
 use crate::verifier::validate_receipt;
 pub use crate::verifier::{validate_transaction, verify_and_charge_transaction};
 
@@ -113,6 +115,19 @@ pub struct ApplyStats {
     pub gas_deficit_amount: Balance,
 }
 
+impl ApplyStats {
+    fn merge(&mut self, other: ApplyStats) -> Result<(), IntegerOverflowError> {
+        self.tx_burnt_amount = safe_add_balance(self.tx_burnt_amount, other.tx_burnt_amount)?;
+        self.slashed_burnt_amount =
+            safe_add_balance(self.slashed_burnt_amount, other.slashed_burnt_amount)?;
+        self.other_burnt_amount =
+            safe_add_balance(self.other_burnt_amount, other.other_burnt_amount)?;
+        self.gas_deficit_amount =
+            safe_add_balance(self.gas_deficit_amount, other.gas_deficit_amount)?;
+        Ok(())
+    }
+}
+
 pub struct ApplyResult {
     pub state_root: StateRoot,
     pub trie_changes: TrieChanges,
@@ -184,11 +199,40 @@ impl Default for ActionResult {
     }
 }
 
-pub struct Runtime {}
+/// Result of processing a receipt on a fork of the state.
+struct ForkedReceipt {
+    state_changes: RawStateChanges,
+    outcome: Option<ExecutionOutcomeWithId>,
+    outgoing_receipts: Vec<Receipt>,
+    validator_proposals: Vec<ValidatorStake>,
+    stats: ApplyStats,
+}
+
+lazy_static::lazy_static! {
+    /// Threads processing receipts in parallel. They get the same stack size as the main thread,
+    /// so that contracts behave the same way as with sequential processing.
+    static ref RECEIPTS_THREAD_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|index| format!("receipts-{}", index))
+        .stack_size(8 * 1024 * 1024)
+        .build()
+        .expect("Failed to create receipts thread pool");
+}
+
+pub struct Runtime {
+    /// Whether receipts with different receivers are processed in parallel by `apply`.
+    parallel_receipts: bool,
+}
 
 impl Runtime {
     pub fn new() -> Self {
-        Self {}
+        Self { parallel_receipts: false }
+    }
+
+    /// Enables processing of consecutive receipts with different receivers in parallel.
+    /// `apply` returns exactly the same result as with sequential processing.
+    pub fn with_parallel_receipts(mut self, parallel_receipts: bool) -> Self {
+        self.parallel_receipts = parallel_receipts;
+        self
     }
 
     fn print_log(log: &[LogEntry]) {
@@ -990,6 +1034,71 @@ impl Runtime {
         Ok(None)
     }
 
+    /// Processes receipts with distinct receivers at the same time, each on its own fork of the
+    /// state. A receipt only accesses the state of its receiver, so every fork sees the same state
+    /// as it would if the receipts before it were processed sequentially. The given keys are
+    /// removed on the forks before processing the corresponding receipts.
+    fn process_receipts_in_parallel(
+        &self,
+        shared_storage: &SharedTrieStorage,
+        state_update: &mut TrieUpdate,
+        apply_state: &ApplyState,
+        receipts: &[(&Receipt, Option<TrieKey>)],
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Vec<Result<ForkedReceipt, RuntimeError>> {
+        let _span =
+            tracing::debug_span!(target: "runtime", "Runtime::process_receipts_in_parallel")
+                .entered();
+
+        let root = state_update.get_root();
+        let base = state_update.share_committed();
+        let results = RECEIPTS_THREAD_POOL.install(|| {
+            receipts
+                .par_iter()
+                .map(|(receipt, key)| {
+                    let mut fork =
+                        TrieUpdate::new_fork(Rc::new(shared_storage.trie()), root, base.clone());
+                    if let Some(key) = key {
+                        fork.remove(key.clone());
+                    }
+                    let mut outgoing_receipts = vec![];
+                    let mut validator_proposals = vec![];
+                    let mut stats = ApplyStats::default();
+                    let outcome = self.process_receipt(
+                        &mut fork,
+                        apply_state,
+                        receipt,
+                        &mut outgoing_receipts,
+                        &mut validator_proposals,
+                        &mut stats,
+                        epoch_info_provider,
+                    )?;
+                    Ok(ForkedReceipt {
+                        state_changes: fork.into_fork_changes(),
+                        outcome,
+                        outgoing_receipts,
+                        validator_proposals,
+                        stats,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        state_update.restore_committed(base);
+        results
+    }
+
+    /// Returns the longest prefix of the receipts with distinct receivers, at most `max_len` long.
+    fn independent_receipts<'a>(
+        receipts: impl Iterator<Item = (&'a Receipt, Option<TrieKey>)>,
+        max_len: usize,
+    ) -> Vec<(&'a Receipt, Option<TrieKey>)> {
+        let mut receivers = HashSet::new();
+        receipts
+            .take(max_len)
+            .take_while(|(receipt, _)| receivers.insert(&receipt.receiver_id))
+            .collect()
+    }
+
     /// Iterates over the validators in the current shard and updates their accounts to return stake
     /// and allocate rewards. Also updates protocol treasure account if it belongs to the current
     /// shard.
@@ -1266,41 +1375,94 @@ impl Runtime {
             get(&state_update, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
         let initial_delayed_receipt_indices = delayed_receipts_indices.clone();
 
-        let mut process_receipt = |receipt: &Receipt,
-                                   state_update: &mut TrieUpdate,
-                                   total_gas_burnt: &mut Gas|
-         -> Result<_, RuntimeError> {
-            self.process_receipt(
-                state_update,
-                apply_state,
-                receipt,
-                &mut outgoing_receipts,
-                &mut validator_proposals,
-                &mut stats,
-                epoch_info_provider,
-            )?
-            .into_iter()
-            .try_for_each(
-                |outcome_with_id: ExecutionOutcomeWithId| -> Result<(), RuntimeError> {
+        let gas_limit = apply_state.gas_limit.unwrap_or(Gas::max_value());
+
+        // Receipts are processed in parallel only if the storage can be shared between threads.
+        // The proof of the recorded storage has to be collected from a single trie.
+        let shared_storage = if self.parallel_receipts { trie.shared_storage() } else { None };
+        let max_batch_len = match shared_storage {
+            Some(_) => RECEIPTS_THREAD_POOL.current_num_threads(),
+            None => 1,
+        };
+
+        // Processes a batch of receipts with distinct receivers as if they were processed one by
+        // one, until the gas limit is reached. Returns the number of processed receipts, which is
+        // at least one. The keys given with the receipts are removed from the state right before
+        // processing them.
+        let mut process_receipts = |receipts: &[(&Receipt, Option<TrieKey>)],
+                                    state_update: &mut TrieUpdate,
+                                    total_gas_burnt: &mut Gas|
+         -> Result<usize, RuntimeError> {
+            let forked_receipts = match &shared_storage {
+                Some(shared_storage) if receipts.len() > 1 => self.process_receipts_in_parallel(
+                    shared_storage,
+                    state_update,
+                    apply_state,
+                    receipts,
+                    epoch_info_provider,
+                ),
+                _ => {
+                    let (receipt, key) = &receipts[0];
+                    if let Some(key) = key {
+                        state_update.remove(key.clone());
+                    }
+                    if let Some(outcome_with_id) = self.process_receipt(
+                        state_update,
+                        apply_state,
+                        receipt,
+                        &mut outgoing_receipts,
+                        &mut validator_proposals,
+                        &mut stats,
+                        epoch_info_provider,
+                    )? {
+                        *total_gas_burnt =
+                            safe_add_gas(*total_gas_burnt, outcome_with_id.outcome.gas_burnt)?;
+                        outcomes.push(outcome_with_id);
+                    }
+                    return Ok(1);
+                }
+            };
+            // Results are merged in order, so receipts that would not have been processed
+            // sequentially because of the gas limit are dropped.
+            let mut processed = 0;
+            for forked_receipt in forked_receipts {
+                if *total_gas_burnt >= gas_limit {
+                    break;
+                }
+                let forked_receipt = forked_receipt?;
+                state_update.merge_fork(forked_receipt.state_changes);
+                outgoing_receipts.extend(forked_receipt.outgoing_receipts);
+                validator_proposals.extend(forked_receipt.validator_proposals);
+                stats.merge(forked_receipt.stats)?;
+                if let Some(outcome_with_id) = forked_receipt.outcome {
                     *total_gas_burnt =
                         safe_add_gas(*total_gas_burnt, outcome_with_id.outcome.gas_burnt)?;
                     outcomes.push(outcome_with_id);
-                    Ok(())
-                },
-            )?;
-            Ok(())
+                }
+                processed += 1;
+            }
+            Ok(processed)
         };
 
-        let gas_limit = apply_state.gas_limit.unwrap_or(Gas::max_value());
-
         // We first process local receipts. They contain staking, local contract calls, etc.
-        for receipt in local_receipts.iter() {
+        let mut next_local_receipt = 0;
+        while next_local_receipt < local_receipts.len() {
             if total_gas_burnt < gas_limit {
                 // NOTE: We don't need to validate the local receipt, because it's just validated in
                 // the `verify_and_charge_transaction`.
-                process_receipt(&receipt, &mut state_update, &mut total_gas_burnt)?;
+                let receipts = Self::independent_receipts(
+                    local_receipts[next_local_receipt..].iter().map(|receipt| (receipt, None)),
+                    max_batch_len,
+                );
+                next_local_receipt +=
+                    process_receipts(&receipts, &mut state_update, &mut total_gas_burnt)?;
             } else {
-                Self::delay_receipt(&mut state_update, &mut delayed_receipts_indices, receipt)?;
+                Self::delay_receipt(
+                    &mut state_update,
+                    &mut delayed_receipts_indices,
+                    &local_receipts[next_local_receipt],
+                )?;
+                next_local_receipt += 1;
             }
         }
 
@@ -1327,22 +1489,68 @@ impl Runtime {
                 },
             )?;
 
-            state_update.remove(key);
+            // Look ahead for the receipts that can be processed together with this one. A receipt
+            // that can't be read or is invalid ends the batch, the error is reported once the
+            // receipt is reached.
+            let mut delayed_receipts = vec![(key, receipt)];
+            let mut index = delayed_receipts_indices.first_index + 1;
+            while delayed_receipts.len() < max_batch_len
+                && index < delayed_receipts_indices.next_available_index
+            {
+                let key = TrieKey::DelayedReceipt { index };
+                match get::<Receipt>(&state_update, &key) {
+                    Ok(Some(receipt))
+                        if validate_receipt(
+                            &apply_state.config.wasm_config.limit_config,
+                            &receipt,
+                        )
+                        .is_ok() =>
+                    {
+                        delayed_receipts.push((key, receipt))
+                    }
+                    _ => break,
+                }
+                index += 1;
+            }
+
+            let receipts = Self::independent_receipts(
+                delayed_receipts.iter().map(|(key, receipt)| (receipt, Some(key.clone()))),
+                max_batch_len,
+            );
+            let processed = process_receipts(&receipts, &mut state_update, &mut total_gas_burnt)?;
             // Math checked above: first_index is less than next_available_index
-            delayed_receipts_indices.first_index += 1;
-            process_receipt(&receipt, &mut state_update, &mut total_gas_burnt)?;
+            delayed_receipts_indices.first_index += processed as u64;
         }
 
         // And then we process the new incoming receipts. These are receipts from other shards.
-        for receipt in incoming_receipts.iter() {
+        let mut next_incoming_receipt = 0;
+        while next_incoming_receipt < incoming_receipts.len() {
+            let receipt = &incoming_receipts[next_incoming_receipt];
             // Validating new incoming no matter whether we have available gas or not. We don't
             // want to store invalid receipts in state as delayed.
             validate_receipt(&apply_state.config.wasm_config.limit_config, &receipt)
                 .map_err(RuntimeError::ReceiptValidationError)?;
             if total_gas_burnt < gas_limit {
-                process_receipt(&receipt, &mut state_update, &mut total_gas_burnt)?;
+                // An invalid receipt ends the batch, the error is reported once it's reached.
+                let receipts = Self::independent_receipts(
+                    std::iter::once(receipt)
+                        .chain(incoming_receipts[next_incoming_receipt + 1..].iter().take_while(
+                            |receipt| {
+                                validate_receipt(
+                                    &apply_state.config.wasm_config.limit_config,
+                                    receipt,
+                                )
+                                .is_ok()
+                            },
+                        ))
+                        .map(|receipt| (receipt, None)),
+                    max_batch_len,
+                );
+                next_incoming_receipt +=
+                    process_receipts(&receipts, &mut state_update, &mut total_gas_burnt)?;
             } else {
                 Self::delay_receipt(&mut state_update, &mut delayed_receipts_indices, receipt)?;
+                next_incoming_receipt += 1;
             }
         }
 
@@ -1476,6 +1684,7 @@ impl Runtime {
 
 #[cfg(test)]
 mod tests {
+    use borsh::BorshSerialize;
     use near_crypto::{InMemorySigner, KeyType, Signer};
     use near_primitives::account::AccessKey;
     use near_primitives::contract::ContractCode;
@@ -1891,6 +2100,70 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn test_apply_parallel_receipts_same_result() {
+        let initial_balance = to_yocto(1_000_000);
+        let initial_locked = to_yocto(500_000);
+        let small_transfer = to_yocto(10_000);
+        let (runtime, tries, mut root, mut apply_state, _, epoch_info_provider) =
+            setup_runtime(initial_balance, initial_locked, 1);
+        let parallel_runtime = Runtime::new().with_parallel_receipts(true);
+
+        let receipt_gas_cost = apply_state
+            .config
+            .transaction_costs
+            .action_receipt_creation_config
+            .exec_fee()
+            + apply_state.config.transaction_costs.action_creation_config.transfer_cost.exec_fee();
+        // Not enough gas for all receipts, so some of them go through the delayed queue.
+        apply_state.gas_limit = Some(6 * receipt_gas_cost);
+
+        // Transfers to bob and carol fail because the accounts don't exist and produce refunds.
+        let receivers: [AccountId; 3] = [alice_account(), bob_account(), "carol".parse().unwrap()];
+        let receipts: Vec<Receipt> = generate_receipts(small_transfer, 24)
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut receipt)| {
+                receipt.receiver_id = receivers[i % 5 % 3].clone();
+                receipt
+            })
+            .collect();
+
+        let no_receipts: &[Receipt] = &[];
+        for prev_receipts in receipts.chunks(8).chain(std::iter::repeat(no_receipts).take(3)) {
+            let mut results = [&runtime, &parallel_runtime].iter().map(|runtime| {
+                runtime
+                    .apply(
+                        tries.get_trie_for_shard(ShardUId::default()),
+                        root,
+                        &None,
+                        &apply_state,
+                        prev_receipts,
+                        &[],
+                        &epoch_info_provider,
+                        None,
+                    )
+                    .unwrap()
+            });
+            let expected = results.next().unwrap();
+            let result = results.next().unwrap();
+            assert_eq!(result.state_root, expected.state_root);
+            assert_eq!(result.outcomes, expected.outcomes);
+            assert_eq!(result.outgoing_receipts, expected.outgoing_receipts);
+            assert_eq!(result.validator_proposals, expected.validator_proposals);
+            assert_eq!(format!("{:?}", result.stats), format!("{:?}", expected.stats));
+            assert_eq!(
+                result.state_changes.try_to_vec().unwrap(),
+                expected.state_changes.try_to_vec().unwrap()
+            );
+
+            let (store_update, new_root) =
+                tries.apply_all(&expected.trie_changes, ShardUId::default()).unwrap();
+            root = new_root;
+            store_update.commit().unwrap();
+        }
+    }
+
     #[test]
     fn test_apply_delayed_receipts_local_tx() {
         let initial_balance = to_yocto(1_000_000);
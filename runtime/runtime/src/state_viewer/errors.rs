@@ -38,6 +38,8 @@ pub enum ViewStateError {
     AccountDoesNotExist { requested_account_id: near_primitives::types::AccountId },
     #[error("The state of {requested_account_id} is too large")]
     AccountStateTooLarge { requested_account_id: near_primitives::types::AccountId },
+    #[error("The page limit must be greater than zero")]
+    InvalidPageLimit,
     #[error("Internal error: #{error_message}")]
     InternalError { error_message: String },
 }
@@ -15,11 +15,11 @@ use near_primitives::{
     },
     serialize::to_base64,
     transaction::FunctionCallAction,
-    trie_key::trie_key_parsers,
-    types::{AccountId, EpochInfoProvider, Gas},
-    views::{StateItem, ViewApplyState, ViewStateResult},
+    trie_key::{trie_key_parsers, TrieKey},
+    types::{AccountId, EpochInfoProvider, Gas, StateChangeCause},
+    views::{StateItem, StateOverrides, ViewApplyState, ViewStatePageResult, ViewStateResult},
 };
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
+use near_store::{get_access_key, get_account, get_code, set_account, set_code, TrieUpdate};
 use near_vm_logic::{ReturnData, ViewConfig};
 use std::{str, sync::Arc, time::Instant};
 
@@ -157,6 +157,106 @@ impl TrieViewer {
         Ok(ViewStateResult { values, proof: vec![] })
     }
 
+    /// Returns at most `limit` items of the contract data of `account_id` under `prefix`, starting
+    /// from `start_key`. Unlike `view_state`, works for accounts of any size, the state size limit
+    /// only bounds the size of a single page. The key to continue from is returned in
+    /// `ViewStatePageResult::next_key`.
+    pub fn view_state_page(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        prefix: &[u8],
+        start_key: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<ViewStatePageResult, errors::ViewStateError> {
+        // An empty page would point to itself as the next page.
+        if limit == 0 {
+            return Err(errors::ViewStateError::InvalidPageLimit);
+        }
+        if get_account(state_update, account_id)?.is_none() {
+            return Err(errors::ViewStateError::AccountDoesNotExist {
+                requested_account_id: account_id.clone(),
+            });
+        }
+
+        let mut values = vec![];
+        let mut next_key = None;
+        let mut page_size = 0u64;
+        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
+        let acc_sep_len = query.len() - prefix.len();
+        let mut iter = state_update.trie.iter(&state_update.get_root())?;
+        match start_key {
+            Some(start_key) if start_key > prefix => {
+                let mut seek_key = query[..acc_sep_len].to_vec();
+                seek_key.extend_from_slice(start_key);
+                iter.seek(&seek_key)?;
+            }
+            _ => iter.seek(&query)?,
+        }
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(&query.as_ref()) {
+                break;
+            }
+            let item_size = (key.len() - acc_sep_len + value.len()) as u64;
+            // The first item is always returned, so that every page makes progress.
+            let page_full = values.len() as u64 >= limit
+                || (!values.is_empty()
+                    && self
+                        .state_size_limit
+                        .map_or(false, |size_limit| page_size + item_size > size_limit));
+            if page_full {
+                next_key = Some(to_base64(&key[acc_sep_len..]));
+                break;
+            }
+            page_size += item_size;
+            values.push(StateItem {
+                key: to_base64(&key[acc_sep_len..]),
+                value: to_base64(&value),
+                proof: vec![],
+            });
+        }
+        // TODO(2076): Add proofs for the storage items.
+        Ok(ViewStatePageResult { values, proof: vec![], next_key })
+    }
+
+    /// Layers the given overrides on top of `state_update`. The changes are committed with
+    /// `StateChangeCause::NotWritableToDisk`, so they must only be used for view calls.
+    pub fn apply_state_overrides(
+        &self,
+        state_update: &mut TrieUpdate,
+        state_overrides: &StateOverrides,
+    ) -> Result<(), errors::CallFunctionError> {
+        for (account_id, account_override) in state_overrides {
+            let mut account = get_account(state_update, account_id)?.ok_or_else(|| {
+                errors::CallFunctionError::AccountDoesNotExist {
+                    requested_account_id: account_id.clone(),
+                }
+            })?;
+            if let Some(code) = &account_override.code {
+                let code = ContractCode::new(code.clone(), None);
+                set_code(state_update, account_id.clone(), &code);
+                account.set_code_hash(*code.hash());
+            }
+            if let Some(balance) = account_override.balance {
+                account.set_amount(balance);
+            }
+            for storage_override in &account_override.storage {
+                let trie_key = TrieKey::ContractData {
+                    account_id: account_id.clone(),
+                    key: storage_override.key.as_ref().to_vec(),
+                };
+                match &storage_override.value {
+                    Some(value) => state_update.set(trie_key, value.clone()),
+                    None => state_update.remove(trie_key),
+                }
+            }
+            set_account(state_update, account_id.clone(), &account);
+        }
+        state_update.commit(StateChangeCause::NotWritableToDisk);
+        Ok(())
+    }
+
     pub fn call_function(
         &self,
         mut state_update: TrieUpdate,
@@ -14,7 +14,7 @@ use log::info;
 
 use git_version::git_version;
 use near_crypto::Signer;
-use near_primitives::types::{AccountId, NumSeats, NumShards};
+use near_primitives::types::{AccountId, Balance, Gas, NumSeats, NumShards};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::Version;
 use near_store::{create_store, ColState};
@@ -22,14 +22,16 @@ use nearcore::config::create_testnet_configs;
 use nearcore::{get_default_home, get_store_path};
 use remote_node::RemoteNode;
 
-use crate::transactions_executor::Executor;
-use crate::transactions_generator::TransactionType;
+use crate::transactions_executor::{Executor, LoadConfig};
+use crate::workload::{FunctionCallConfig, Workload, WorkloadMix};
 
 pub mod remote_node;
+pub mod report;
 pub mod sampler;
 pub mod stats;
 pub mod transactions_executor;
 pub mod transactions_generator;
+pub mod workload;
 
 #[allow(dead_code)]
 fn configure_logging(log_level: log::LevelFilter) {
@@ -99,12 +101,60 @@ fn main() {
                     .default_value("10")
                     .help("Duration of load test in seconds"))
             .arg(
-                Arg::with_name("type")
-                    .long("type")
+                Arg::with_name("mix")
+                    .long("mix")
                     .takes_value(true)
-                    .default_value("set")
-                    .possible_values(&["set", "send_money", "heavy_storage"])
-                    .help("Transaction type")))
+                    .default_value("send_money")
+                    .help("Workload mix as comma-separated kind=weight pairs, kinds are send_money, function_call, create_account, access_key_churn, set and heavy_storage"))
+            .arg(
+                Arg::with_name("contract")
+                    .long("contract")
+                    .takes_value(true)
+                    .help("Receiver of the function_call workload"))
+            .arg(
+                Arg::with_name("method")
+                    .long("method")
+                    .takes_value(true)
+                    .help("Method called by the function_call workload"))
+            .arg(
+                Arg::with_name("args")
+                    .long("args")
+                    .takes_value(true)
+                    .default_value("")
+                    .help("Arguments of the function_call workload"))
+            .arg(
+                Arg::with_name("gas")
+                    .long("gas")
+                    .takes_value(true)
+                    .default_value("30000000000000")
+                    .help("Gas attached by the function_call workload"))
+            .arg(
+                Arg::with_name("deposit")
+                    .long("deposit")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Deposit attached by the function_call workload"))
+            .arg(
+                Arg::with_name("shards")
+                    .long("shards")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Number of shards of the network, send_money picks receivers on other shards"))
+            .arg(
+                Arg::with_name("testnet_accounts")
+                    .long("testnet_accounts")
+                    .help("If set, uses {prefix}0, {prefix}1, ... accounts created by `neard testnet`"))
+            .arg(
+                Arg::with_name("finality_timeout")
+                    .long("finality_timeout")
+                    .takes_value(true)
+                    .default_value("60")
+                    .help("Seconds to wait for the final status of each transaction"))
+            .arg(
+                Arg::with_name("report")
+                    .long("report")
+                    .takes_value(true)
+                    .help("Path of the JSON report to write")))
         .subcommand(SubCommand::with_name("load_state_dump").about("Load state dump from genesis-tools and create store for run")
         .arg(
             Arg::with_name("home")
@@ -206,9 +256,26 @@ fn run(matches: &clap::ArgMatches<'_>) {
     let n = value_t_or_exit!(matches, "accounts", u64);
     let prefix = value_t_or_exit!(matches, "prefix", String);
     let massive_accounts = matches.is_present("massive_accounts");
-    let tps = value_t_or_exit!(matches, "tps", u64);
+    let testnet_accounts = matches.is_present("testnet_accounts");
+    let tps = value_t_or_exit!(matches, "tps", f64);
     let duration = value_t_or_exit!(matches, "duration", u64);
-    let transaction_type = value_t_or_exit!(matches, "type", TransactionType);
+    let mix = value_t_or_exit!(matches, "mix", WorkloadMix);
+    let num_shards = value_t_or_exit!(matches, "shards", NumShards);
+    let finality_timeout = value_t_or_exit!(matches, "finality_timeout", u64);
+    let function_call = match (matches.value_of("contract"), matches.value_of("method")) {
+        (Some(contract_id), Some(method_name)) => Some(FunctionCallConfig {
+            contract_id: contract_id.parse().expect("invalid contract account id"),
+            method_name: method_name.to_string(),
+            args: value_t_or_exit!(matches, "args", String).into_bytes(),
+            gas: value_t_or_exit!(matches, "gas", Gas),
+            deposit: value_t_or_exit!(matches, "deposit", Balance),
+        }),
+        _ => None,
+    };
+    if mix.workloads().any(|workload| workload == Workload::FunctionCall) && function_call.is_none()
+    {
+        panic!("function_call workload needs --contract and --method");
+    }
 
     let addr: String;
     let addrs: Vec<_>;
@@ -229,6 +296,8 @@ fn run(matches: &clap::ArgMatches<'_>) {
 
     let accounts: Vec<_> = if massive_accounts {
         (0..n).map(|i| AccountId::try_from(format!("near_{}_{}", i, i)).unwrap()).collect()
+    } else if testnet_accounts {
+        (0..n).map(|i| AccountId::try_from(format!("{}{}", &prefix, i)).unwrap()).collect()
     } else {
         (0..n).map(|i| AccountId::try_from(format!("{}.{}", &prefix, i)).unwrap()).collect()
     };
@@ -245,7 +314,20 @@ fn run(matches: &clap::ArgMatches<'_>) {
         nodes.push(node);
     }
 
-    // Start the executor.
-    let handle = Executor::spawn(nodes, Some(Duration::from_secs(duration)), tps, transaction_type);
-    handle.join().unwrap();
+    let report = Executor::run(
+        nodes,
+        LoadConfig {
+            tps,
+            duration: Duration::from_secs(duration),
+            mix,
+            function_call,
+            num_shards,
+            finality_timeout: Duration::from_secs(finality_timeout),
+        },
+    );
+    println!("{}", report);
+    if let Some(path) = matches.value_of("report") {
+        let json = serde_json::to_string_pretty(&report.to_json()).unwrap();
+        fs::write(path, json).expect("Failed to write the report");
+    }
 }
@@ -202,6 +202,35 @@ impl RemoteNode {
             .boxed()
     }
 
+    /// Queries the final status of the transaction with the `tx` RPC using non-blocking Futures.
+    /// Resolves to `None` while the transaction or some of its receipts are still pending, and
+    /// to whether it succeeded once it has been executed.
+    pub fn tx_status_async(
+        &self,
+        hash: String,
+        signer_id: AccountId,
+    ) -> BoxFuture<'static, Result<Option<bool>, String>> {
+        let message =
+            Message::request("tx".to_string(), Some(serde_json::json!([hash, signer_id])));
+        self.async_client
+            .post(self.url.as_str())
+            .json(&message)
+            .send()
+            .and_then(|r| r.json::<serde_json::Value>())
+            .map_err(|err| format!("{}", err))
+            .map_ok(|j| {
+                let status = &j["result"]["status"];
+                if status.get("SuccessValue").is_some() {
+                    Some(true)
+                } else if status.get("Failure").is_some() {
+                    Some(false)
+                } else {
+                    None
+                }
+            })
+            .boxed()
+    }
+
     /// Sends transactions using `broadcast_tx_sync` using blocking code. Return hash of
     /// the transaction.
     pub fn add_transaction(
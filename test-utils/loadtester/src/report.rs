@@ -0,0 +1,192 @@
+//! Outcomes of the transactions sent during an open-loop run and the JSON report built from them.
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::stats::Stats;
+use crate::workload::{Workload, WorkloadMix};
+
+/// What happened to a single transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Executed successfully, with the time from submission to the final status.
+    Succeeded(Duration),
+    /// Executed with a failure, with the time from submission to the final status.
+    Failed(Duration),
+    /// The node did not accept the transaction.
+    Rejected,
+    /// No final status was observed within the finality timeout.
+    TimedOut,
+}
+
+/// Outcomes of the transactions of a single workload.
+#[derive(Default, Debug)]
+pub struct WorkloadOutcomes {
+    /// Latencies of executed transactions, successful or not, in milliseconds.
+    latencies_ms: Vec<u64>,
+    succeeded: u64,
+    failed: u64,
+    rejected: u64,
+    timed_out: u64,
+}
+
+impl WorkloadOutcomes {
+    pub fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Succeeded(latency) => {
+                self.succeeded += 1;
+                self.latencies_ms.push(latency.as_millis() as u64);
+            }
+            Outcome::Failed(latency) => {
+                self.failed += 1;
+                self.latencies_ms.push(latency.as_millis() as u64);
+            }
+            Outcome::Rejected => self.rejected += 1,
+            Outcome::TimedOut => self.timed_out += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &WorkloadOutcomes) {
+        self.latencies_ms.extend_from_slice(&other.latencies_ms);
+        self.succeeded += other.succeeded;
+        self.failed += other.failed;
+        self.rejected += other.rejected;
+        self.timed_out += other.timed_out;
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.succeeded + self.failed + self.rejected + self.timed_out
+    }
+
+    /// Latency under which the given fraction of executed transactions finished, in milliseconds.
+    pub fn percentile(&self, fraction: f64) -> Option<u64> {
+        let mut latencies = self.latencies_ms.clone();
+        latencies.sort_unstable();
+        percentile(&latencies, fraction)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut latencies = self.latencies_ms.clone();
+        latencies.sort_unstable();
+        json!({
+            "sent": self.sent(),
+            "succeeded": self.succeeded,
+            "failed": self.failed,
+            "rejected": self.rejected,
+            "timed_out": self.timed_out,
+            "latency_ms": {
+                "p50": percentile(&latencies, 0.5),
+                "p90": percentile(&latencies, 0.9),
+                "p99": percentile(&latencies, 0.99),
+                "max": latencies.last(),
+            },
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[u64], fraction: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.max(1).min(sorted.len()) - 1])
+}
+
+/// Summary of an open-loop run.
+pub struct Report {
+    pub target_tps: f64,
+    pub duration: Duration,
+    pub mix: WorkloadMix,
+    pub outcomes: BTreeMap<Workload, WorkloadOutcomes>,
+    pub stats: Stats,
+}
+
+impl Report {
+    pub fn total(&self) -> WorkloadOutcomes {
+        let mut total = WorkloadOutcomes::default();
+        for outcomes in self.outcomes.values() {
+            total.merge(outcomes);
+        }
+        total
+    }
+
+    pub fn to_json(&self) -> Value {
+        let total = self.total();
+        let secs = self.duration.as_secs_f64();
+        let from_height = self.stats.from_height.unwrap_or_default();
+        let to_height = self.stats.to_height.unwrap_or_default();
+        json!({
+            "target_tps": self.target_tps,
+            "duration_secs": secs,
+            "mix": self
+                .mix
+                .weights()
+                .iter()
+                .map(|(workload, weight)| (workload.to_string(), Value::from(*weight)))
+                .collect::<serde_json::Map<_, _>>(),
+            "sent_tps": total.sent() as f64 / secs,
+            "executed_tps": (total.succeeded + total.failed) as f64 / secs,
+            "blocks": {
+                "from_height": from_height,
+                "to_height": to_height,
+                "committed_transactions": self.stats.committed_transactions,
+            },
+            "total": total.to_json(),
+            "workloads": self
+                .outcomes
+                .iter()
+                .map(|(workload, outcomes)| (workload.to_string(), outcomes.to_json()))
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let show = |latency: Option<u64>| latency.map_or("-".to_string(), |ms| format!("{}ms", ms));
+        writeln!(f, "Duration:\t{:.2?}", self.duration)?;
+        writeln!(f, "Target TPS:\t{}", self.target_tps)?;
+        let workloads =
+            self.outcomes.iter().map(|(workload, outcomes)| (workload.to_string(), outcomes));
+        let total = self.total();
+        for (name, outcomes) in workloads.chain(std::iter::once(("total".to_string(), &total))) {
+            writeln!(
+                f,
+                "{:<18} sent {:>7} ok {:>7} failed {:>6} rejected {:>6} timed out {:>6} \
+                 p50 {:>8} p90 {:>8} p99 {:>8}",
+                name,
+                outcomes.sent(),
+                outcomes.succeeded,
+                outcomes.failed,
+                outcomes.rejected,
+                outcomes.timed_out,
+                show(outcomes.percentile(0.5)),
+                show(outcomes.percentile(0.9)),
+                show(outcomes.percentile(0.99)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut outcomes = WorkloadOutcomes::default();
+        assert_eq!(outcomes.percentile(0.5), None);
+        for ms in (1..=100).rev() {
+            outcomes.record(Outcome::Succeeded(Duration::from_millis(ms)));
+        }
+        outcomes.record(Outcome::Rejected);
+        outcomes.record(Outcome::TimedOut);
+        assert_eq!(outcomes.percentile(0.5), Some(50));
+        assert_eq!(outcomes.percentile(0.99), Some(99));
+        assert_eq!(outcomes.percentile(1.0), Some(100));
+        assert_eq!(outcomes.sent(), 102);
+    }
+}
@@ -1,16 +1,30 @@
 //! Executes a single transaction or a list of transactions on a set of nodes.
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
-use std::thread;
-use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-use futures::{future, FutureExt, StreamExt, TryFutureExt};
+use futures::future;
 use log::{debug, info, warn};
-use tokio::time::interval;
+use tokio::task::JoinHandle;
+
+use near_crypto::PublicKey;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout};
+use near_primitives::types::{AccountId, NumShards, ShardId};
 
 use crate::remote_node::{try_wait, wait, RemoteNode};
+use crate::report::{Outcome, Report, WorkloadOutcomes};
+use crate::sampler::sample_one_fn;
 use crate::stats::Stats;
-use crate::transactions_generator::{Generator, TransactionType};
+use crate::transactions_generator::Generator;
+use crate::workload::{FunctionCallConfig, Workload, WorkloadMix};
+
+/// Maximum time we wait for a node to accept a transaction.
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often we poll the status of a submitted transaction.
+const STATUS_POLL_PERIOD: Duration = Duration::from_millis(100);
+/// How often we update the block hash that new transactions refer to.
+const BLOCK_HASH_REFRESH_PERIOD: Duration = Duration::from_secs(1);
 
 pub struct Executor {
     /// Nodes that can be used to generate nonces
@@ -41,108 +55,196 @@ impl Executor {
         }
     }
 
-    pub fn spawn(
-        nodes: Vec<Arc<RwLock<RemoteNode>>>,
-        timeout: Option<Duration>,
-        tps: u64,
-        transaction_type: TransactionType,
-    ) -> JoinHandle<()> {
+    /// Sends transactions of the configured workload mix at Poisson-distributed arrival times,
+    /// independently of how fast the network executes them, and waits for the final status of
+    /// each of them.
+    pub fn run(nodes: Vec<Arc<RwLock<RemoteNode>>>, config: LoadConfig) -> Report {
         // Deploy the testing contract, if needed.
-        if let TransactionType::Set | TransactionType::HeavyStorageBlock = transaction_type {
+        if config.mix.workloads().any(|workload| workload.needs_test_contract()) {
             info!("start deploying contracts");
             Executor::deploy_contract(&nodes);
             info!("finish deploying contracts");
         }
-        let stats = Arc::new(RwLock::new(Stats::new()));
-        thread::spawn(move || {
-            tokio::spawn(future::lazy(move |_| {
-                // Channels into which we can signal to send a transaction.
-                let mut signal_tx = vec![];
-                let all_account_ids: Vec<_> = nodes
+
+        let mut stats = Stats::new();
+        stats.measure_from(&*nodes[0].read().unwrap());
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let pending = runtime.block_on(Executor::send_open_loop(&nodes, &config));
+        stats.measure_to(&*nodes[0].read().unwrap());
+
+        info!("waiting for {} transactions to finish", pending.len());
+        let mut outcomes: BTreeMap<Workload, WorkloadOutcomes> = BTreeMap::new();
+        for result in runtime.block_on(future::join_all(pending)) {
+            match result {
+                Ok((workload, outcome)) => outcomes.entry(workload).or_default().record(outcome),
+                Err(err) => warn!("transaction task failed: {}", err),
+            }
+        }
+        stats.collect_transactions(&*nodes[0].read().unwrap());
+
+        Report {
+            target_tps: config.tps,
+            duration: config.duration,
+            mix: config.mix,
+            outcomes,
+            stats,
+        }
+    }
+
+    async fn send_open_loop(
+        nodes: &[Arc<RwLock<RemoteNode>>],
+        config: &LoadConfig,
+    ) -> Vec<JoinHandle<(Workload, Outcome)>> {
+        let shard_layout = ShardLayout::v0(config.num_shards, 0);
+        let signers: Vec<(usize, usize, ShardId)> = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(node_ind, node)| {
+                let node = node.read().unwrap();
+                node.signers
                     .iter()
-                    .map(|n| {
-                        n.read()
-                            .unwrap()
-                            .signers
-                            .iter()
-                            .map(|s| s.account_id.clone())
-                            .collect::<Vec<_>>()
+                    .enumerate()
+                    .map(|(signer_ind, signer)| {
+                        (
+                            node_ind,
+                            signer_ind,
+                            account_id_to_shard_id(&signer.account_id, &shard_layout),
+                        )
                     })
-                    .flatten()
-                    .collect();
-
-                for node in &nodes {
-                    for (signer_ind, _) in node.read().unwrap().signers.iter().enumerate() {
-                        let stats = stats.clone();
-                        let node = node.clone();
-                        let all_account_ids = all_account_ids.to_vec();
-                        let (tx, rx) = tokio::sync::mpsc::channel(1024);
-                        let rx = tokio_stream::wrappers::ReceiverStream::new(rx);
-                        signal_tx.push(tx);
-                        // Spawn a task that sends transactions only from the given account making
-                        // sure the nonces are correct.
-                        tokio::spawn(
-                            rx.for_each(move |_| {
-                                let stats = stats.clone();
-                                let t = match transaction_type {
-                                    TransactionType::SendMoney => {
-                                        Generator::send_money(&node, signer_ind, &all_account_ids)
-                                    }
-                                    TransactionType::Set => Generator::call_set(&node, signer_ind),
-                                    TransactionType::HeavyStorageBlock => {
-                                        Generator::call_heavy_storage_blocks(&node, signer_ind)
-                                    }
-                                };
-                                node.write().unwrap().add_transaction_committed(t.clone()).unwrap();
-                                let f = { node.write().unwrap().add_transaction_async(t) };
-                                let f = f
-                                    .map_ok(|r| debug!("txn submitted: {}", r))
-                                    .map_err(|e| warn!("error submitting txn: {}", e));
-                                tokio::time::timeout(Duration::from_secs(1), f)
-                                    .map_ok(move |_| {
-                                        stats.read().unwrap().inc_out_tx();
-                                    })
-                                    .map(|_| ()) // Ignore errors.
-                            })
-                            .map(|_| ()),
-                        );
-                    }
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let accounts: Vec<(AccountId, ShardId)> = signers
+            .iter()
+            .map(|(node_ind, signer_ind, shard_id)| {
+                (
+                    nodes[*node_ind].read().unwrap().signers[*signer_ind].account_id.clone(),
+                    *shard_id,
+                )
+            })
+            .collect();
+
+        // Refresh the block hash used by new transactions in the background.
+        let block_hash = Arc::new(RwLock::new(CryptoHash::default()));
+        let refresh_node = nodes[0].clone();
+        let refreshed_hash = block_hash.clone();
+        let refresh = tokio::spawn(async move {
+            loop {
+                let node = refresh_node.clone();
+                let hash = tokio::task::spawn_blocking(move || {
+                    try_wait(|| node.read().unwrap().get_current_block_hash())
+                        .map_err(|err| err.to_string())
+                })
+                .await;
+                match hash {
+                    Ok(Ok(hash)) => *refreshed_hash.write().unwrap() = hash,
+                    Ok(Err(err)) => warn!("failed to get block hash: {}", err),
+                    Err(err) => warn!("failed to get block hash: {}", err),
                 }
+                tokio::time::sleep(BLOCK_HASH_REFRESH_PERIOD).await;
+            }
+        });
+        while *block_hash.read().unwrap() == CryptoHash::default() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
 
-                // Spawn the task that sets the tps.
-                let period = Duration::from_nanos((Duration::from_secs(1).as_nanos() as u64) / tps);
-                let timeout = timeout.map(|t| Instant::now() + t);
-                let task = tokio_stream::wrappers::IntervalStream::new(interval(period))
-                    .take_while(move |_| {
-                        if let Some(t_limit) = timeout {
-                            if t_limit <= Instant::now() {
-                                // We hit timeout.
-                                return future::ready(false);
-                            }
-                        }
-                        future::ready(true)
-                    })
-                    .for_each(move |_| {
-                        let ind = rand::random::<usize>() % signal_tx.len();
-                        let tx = signal_tx[ind].clone();
-                        async move { tx.send(()).await }.map(drop)
+        let mut churn_keys: HashMap<(usize, usize), PublicKey> = HashMap::new();
+        let mut pending = vec![];
+        let start = tokio::time::Instant::now();
+        let mut offset = Duration::default();
+        for i in 0.. {
+            // Exponentially distributed gaps between arrivals give a Poisson process.
+            offset += Duration::from_secs_f64(-(1.0 - rand::random::<f64>()).ln() / config.tps);
+            if offset >= config.duration {
+                break;
+            }
+            tokio::time::sleep_until(start + offset).await;
+
+            let (node_ind, signer_ind, shard_id) = signers[i % signers.len()];
+            let node = nodes[node_ind].clone();
+            let (signer, nonce) = {
+                let mut node = node.write().unwrap();
+                node.nonces[signer_ind] += 1;
+                (node.signers[signer_ind].clone(), node.nonces[signer_ind])
+            };
+            let block_hash = *block_hash.read().unwrap();
+            let workload = config.mix.sample();
+            let tx = match workload {
+                Workload::SendMoney => {
+                    let other_shard = config.num_shards > 1
+                        && accounts.iter().any(|(_, other)| *other != shard_id);
+                    let (receiver_id, _) = sample_one_fn(&accounts, |(account_id, other)| {
+                        account_id != &signer.account_id && (!other_shard || *other != shard_id)
                     });
+                    Generator::transfer(&signer, nonce, receiver_id.clone(), block_hash)
+                }
+                Workload::FunctionCall => Generator::function_call(
+                    &signer,
+                    nonce,
+                    config.function_call.as_ref().expect("function call target is not configured"),
+                    block_hash,
+                ),
+                Workload::CreateAccount => Generator::create_account(&signer, nonce, block_hash),
+                Workload::AccessKeyChurn => match churn_keys.remove(&(node_ind, signer_ind)) {
+                    Some(public_key) => {
+                        Generator::delete_key(&signer, nonce, public_key, block_hash)
+                    }
+                    None => {
+                        let (tx, public_key) = Generator::add_key(&signer, nonce, block_hash);
+                        churn_keys.insert((node_ind, signer_ind), public_key);
+                        tx
+                    }
+                },
+                Workload::Set => Generator::set(&signer, nonce, block_hash),
+                Workload::HeavyStorage => Generator::heavy_storage(&signer, nonce, block_hash),
+            };
 
-                let node = nodes[0].clone();
-                stats.write().unwrap().measure_from(&*node.write().unwrap());
-                tokio::spawn(
-                    task.then(move |_| {
-                        future::lazy(move |_| {
-                            let mut stats = stats.write().unwrap();
-                            stats.measure_to(&*node.write().unwrap());
-                            stats.collect_transactions(&*node.write().unwrap());
-                            println!("{}", stats);
-                            Ok(())
-                        })
-                    })
-                    .map_err(|_: ()| ()),
-                );
+            let finality_timeout = config.finality_timeout;
+            pending.push(tokio::spawn(async move {
+                let submitted = Instant::now();
+                let signer_id = tx.transaction.signer_id.clone();
+                let submit = node.read().unwrap().add_transaction_async(tx);
+                let hash = match tokio::time::timeout(SUBMIT_TIMEOUT, submit).await {
+                    Ok(Ok(hash)) => hash,
+                    Ok(Err(err)) => {
+                        debug!("error submitting txn: {}", err);
+                        return (workload, Outcome::Rejected);
+                    }
+                    Err(_) => return (workload, Outcome::Rejected),
+                };
+                while submitted.elapsed() < finality_timeout {
+                    tokio::time::sleep(STATUS_POLL_PERIOD).await;
+                    let status =
+                        node.read().unwrap().tx_status_async(hash.clone(), signer_id.clone());
+                    match status.await {
+                        Ok(Some(true)) => {
+                            return (workload, Outcome::Succeeded(submitted.elapsed()))
+                        }
+                        Ok(Some(false)) => return (workload, Outcome::Failed(submitted.elapsed())),
+                        Ok(None) => {}
+                        Err(err) => debug!("error querying txn {}: {}", hash, err),
+                    }
+                }
+                (workload, Outcome::TimedOut)
             }));
-        })
+        }
+        refresh.abort();
+        pending
     }
 }
+
+/// Settings of an open-loop run.
+pub struct LoadConfig {
+    /// Average number of transactions sent per second.
+    pub tps: f64,
+    /// For how long to send transactions.
+    pub duration: Duration,
+    pub mix: WorkloadMix,
+    /// Target of the `function_call` workload.
+    pub function_call: Option<FunctionCallConfig>,
+    /// Number of shards of the network, used to pick receivers on other shards.
+    pub num_shards: NumShards,
+    /// How long to wait for the final status of a transaction.
+    pub finality_timeout: Duration,
+}
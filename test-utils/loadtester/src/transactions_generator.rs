@@ -2,66 +2,29 @@
 
 use std::sync::{Arc, RwLock};
 
+use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
+use near_primitives::account::{AccessKey, AccessKeyPermission, FunctionCallPermission};
+use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::{
-    Action, DeployContractAction, FunctionCallAction, SignedTransaction,
+    Action, AddKeyAction, CreateAccountAction, DeleteKeyAction, DeployContractAction,
+    FunctionCallAction, SignedTransaction, TransferAction,
 };
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, Balance, Nonce};
 
 use byteorder::ByteOrder;
 use byteorder::LittleEndian;
 
 use crate::remote_node::RemoteNode;
+use crate::workload::FunctionCallConfig;
 
 use std::mem::size_of;
-use std::str::FromStr;
-
-#[derive(Clone, Copy, Debug)]
-pub enum TransactionType {
-    SendMoney,
-    Set,
-    HeavyStorageBlock,
-}
-
-impl FromStr for TransactionType {
-    type Err = &'static str;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "send_money" => Ok(TransactionType::SendMoney),
-            "set" => Ok(TransactionType::Set),
-            "heavy_storage" => Ok(TransactionType::HeavyStorageBlock),
-            _ => Err("no match"),
-        }
-    }
-}
 
 pub struct Generator {}
 
-impl Generator {
-    /// Create send money transaction.
-    pub fn send_money(
-        node: &Arc<RwLock<RemoteNode>>,
-        signer_ind: usize,
-        all_accounts: &Vec<AccountId>,
-    ) -> SignedTransaction {
-        let (signer_from, nonce, block_hash) = {
-            let mut node = node.write().unwrap();
-            node.nonces[signer_ind] += 1;
-            let block_hash = node.get_current_block_hash().unwrap();
-            (node.signers[signer_ind].clone(), node.nonces[signer_ind], block_hash)
-        };
-
-        let acc_from = signer_from.account_id.clone();
-        let acc_to = loop {
-            let ind = rand::random::<usize>() % all_accounts.len();
-            if all_accounts[ind] != acc_from {
-                break all_accounts[ind].clone();
-            }
-        };
-
-        SignedTransaction::send_money(nonce, acc_from, acc_to, &*signer_from, 1, block_hash)
-    }
+/// Balance given to every account created by the `create_account` workload.
+pub const CREATE_ACCOUNT_DEPOSIT: Balance = 10u128.pow(23);
 
+impl Generator {
     /// Returns transactions that deploy test contract to an every account used by the node.
     pub fn deploy_test_contract(node: &Arc<RwLock<RemoteNode>>) -> Vec<SignedTransaction> {
         let mut res = vec![];
@@ -87,66 +50,166 @@ impl Generator {
         res
     }
 
-    /// Create set key/value transaction.
-    pub fn call_set(node: &Arc<RwLock<RemoteNode>>, signer_ind: usize) -> SignedTransaction {
-        let (signer_from, nonce, block_hash) = {
-            let mut node = node.write().unwrap();
-            node.nonces[signer_ind] += 1;
-            (
-                node.signers[signer_ind].clone(),
-                node.nonces[signer_ind],
-                node.get_current_block_hash().unwrap(),
-            )
-        };
-        let acc_from = signer_from.account_id.clone();
+    /// Create transfer of a single token.
+    pub fn transfer(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        receiver_id: AccountId,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
+        SignedTransaction::send_money(
+            nonce,
+            signer.account_id.clone(),
+            receiver_id,
+            signer,
+            1,
+            block_hash,
+        )
+    }
 
-        let key = rand::random::<u64>() % 1_000;
-        let value = rand::random::<u64>() % 1_000;
-        let mut args = [0u8; 2 * size_of::<u64>()];
-        LittleEndian::write_u64_into(&[key, value], &mut args);
+    /// Create call of the configured contract method.
+    pub fn function_call(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        config: &FunctionCallConfig,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
         SignedTransaction::from_actions(
             nonce,
-            acc_from.clone(),
-            acc_from,
-            &*signer_from,
+            signer.account_id.clone(),
+            config.contract_id.clone(),
+            signer,
             vec![Action::FunctionCall(FunctionCallAction {
-                method_name: "write_key_value".to_string(),
-                args: args.to_vec(),
-                gas: 100000,
-                deposit: 1,
+                method_name: config.method_name.clone(),
+                args: config.args.clone(),
+                gas: config.gas,
+                deposit: config.deposit,
             })],
             block_hash,
         )
     }
 
-    /// Returns a transaction that calls `heavy_storage_blocks` on a contract.
-    pub fn call_heavy_storage_blocks(
-        node: &Arc<RwLock<RemoteNode>>,
-        signer_ind: usize,
+    /// Create transaction that creates a new random sub-account of the signer, funds it and
+    /// gives it the signer's key.
+    pub fn create_account(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        block_hash: CryptoHash,
     ) -> SignedTransaction {
-        let (signer_from, nonce, block_hash) = {
-            let mut node = node.write().unwrap();
-            node.nonces[signer_ind] += 1;
-            (
-                node.signers[signer_ind].clone(),
-                node.nonces[signer_ind],
-                node.get_current_block_hash().unwrap(),
-            )
-        };
-        let acc_from = signer_from.account_id.clone();
+        let new_account_id: AccountId =
+            format!("{:x}.{}", rand::random::<u64>(), signer.account_id).parse().unwrap();
+        SignedTransaction::from_actions(
+            nonce,
+            signer.account_id.clone(),
+            new_account_id,
+            signer,
+            vec![
+                Action::CreateAccount(CreateAccountAction {}),
+                Action::Transfer(TransferAction { deposit: CREATE_ACCOUNT_DEPOSIT }),
+                Action::AddKey(AddKeyAction {
+                    public_key: signer.public_key(),
+                    access_key: AccessKey::full_access(),
+                }),
+            ],
+            block_hash,
+        )
+    }
+
+    /// Create transaction that adds a new function call access key to the signer. Returns the
+    /// key so that it can be deleted later.
+    pub fn add_key(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        block_hash: CryptoHash,
+    ) -> (SignedTransaction, PublicKey) {
+        let public_key = InMemorySigner::from_seed(
+            signer.account_id.clone(),
+            KeyType::ED25519,
+            &format!("{}{}", signer.account_id, nonce),
+        )
+        .public_key();
+        let tx = SignedTransaction::from_actions(
+            nonce,
+            signer.account_id.clone(),
+            signer.account_id.clone(),
+            signer,
+            vec![Action::AddKey(AddKeyAction {
+                public_key: public_key.clone(),
+                access_key: AccessKey {
+                    nonce: 0,
+                    permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+                        allowance: None,
+                        receiver_id: signer.account_id.to_string(),
+                        method_names: vec![],
+                    }),
+                },
+            })],
+            block_hash,
+        );
+        (tx, public_key)
+    }
+
+    /// Create transaction that deletes the given access key of the signer.
+    pub fn delete_key(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        public_key: PublicKey,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
+        SignedTransaction::from_actions(
+            nonce,
+            signer.account_id.clone(),
+            signer.account_id.clone(),
+            signer,
+            vec![Action::DeleteKey(DeleteKeyAction { public_key })],
+            block_hash,
+        )
+    }
+
+    /// Create call of `write_key_value` on the test contract deployed to the signer.
+    pub fn set(signer: &InMemorySigner, nonce: Nonce, block_hash: CryptoHash) -> SignedTransaction {
+        let key = rand::random::<u64>() % 1_000;
+        let value = rand::random::<u64>() % 1_000;
+        let mut args = [0u8; 2 * size_of::<u64>()];
+        LittleEndian::write_u64_into(&[key, value], &mut args);
+        Generator::self_call(signer, nonce, "write_key_value", args.to_vec(), 100000, block_hash)
+    }
 
+    /// Create call of `benchmark_storage_10kib` on the test contract deployed to the signer.
+    pub fn heavy_storage(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
         let mut args = [0u8; size_of::<u64>()];
         LittleEndian::write_u64(&mut args, 1000u64);
+        Generator::self_call(
+            signer,
+            nonce,
+            "benchmark_storage_10kib",
+            args.to_vec(),
+            1000000000,
+            block_hash,
+        )
+    }
 
+    fn self_call(
+        signer: &InMemorySigner,
+        nonce: Nonce,
+        method_name: &str,
+        args: Vec<u8>,
+        gas: u64,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
         SignedTransaction::from_actions(
             nonce,
-            acc_from.clone(),
-            acc_from,
-            &*signer_from,
+            signer.account_id.clone(),
+            signer.account_id.clone(),
+            signer,
             vec![Action::FunctionCall(FunctionCallAction {
-                method_name: "benchmark_storage_10kib".to_string(),
-                args: args.to_vec(),
-                gas: 1000000000,
+                method_name: method_name.to_string(),
+                args,
+                gas,
                 deposit: 1,
             })],
             block_hash,
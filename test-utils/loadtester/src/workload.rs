@@ -0,0 +1,132 @@
+//! Kinds of transactions the load generator can send and how often each of them is sent.
+use std::fmt;
+use std::str::FromStr;
+
+use near_primitives::types::{AccountId, Balance, Gas};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Workload {
+    /// Transfer to an account on a different shard, if there is more than one shard.
+    SendMoney,
+    /// Call of the configured method on the configured contract.
+    FunctionCall,
+    /// Creation of a sub-account of the signer funded with a small deposit.
+    CreateAccount,
+    /// Alternately adds and deletes an access key of the signer.
+    AccessKeyChurn,
+    /// Call of `write_key_value` on the test contract deployed to the signer.
+    Set,
+    /// Call of `benchmark_storage_10kib` on the test contract deployed to the signer.
+    HeavyStorage,
+}
+
+impl Workload {
+    pub fn all() -> &'static [Workload] {
+        &[
+            Workload::SendMoney,
+            Workload::FunctionCall,
+            Workload::CreateAccount,
+            Workload::AccessKeyChurn,
+            Workload::Set,
+            Workload::HeavyStorage,
+        ]
+    }
+
+    /// Whether the test contract has to be deployed to all accounts before sending this kind of
+    /// transactions.
+    pub fn needs_test_contract(&self) -> bool {
+        match self {
+            Workload::Set | Workload::HeavyStorage => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Workload::SendMoney => "send_money",
+            Workload::FunctionCall => "function_call",
+            Workload::CreateAccount => "create_account",
+            Workload::AccessKeyChurn => "access_key_churn",
+            Workload::Set => "set",
+            Workload::HeavyStorage => "heavy_storage",
+        })
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Workload::all()
+            .iter()
+            .find(|workload| workload.to_string() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown workload: {}", s))
+    }
+}
+
+/// Relative weights of the workloads, parsed from `kind=weight,kind=weight,...`.
+#[derive(Clone, Debug)]
+pub struct WorkloadMix {
+    weights: Vec<(Workload, u32)>,
+    total: u32,
+}
+
+impl WorkloadMix {
+    pub fn workloads(&self) -> impl Iterator<Item = Workload> + '_ {
+        self.weights.iter().map(|(workload, _)| *workload)
+    }
+
+    pub fn weights(&self) -> &[(Workload, u32)] {
+        &self.weights
+    }
+
+    /// Picks a workload with probability proportional to its weight.
+    pub fn sample(&self) -> Workload {
+        let mut point = rand::random::<u32>() % self.total;
+        for (workload, weight) in &self.weights {
+            if point < *weight {
+                return *workload;
+            }
+            point -= weight;
+        }
+        unreachable!("weights sum up to the total")
+    }
+}
+
+impl FromStr for WorkloadMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = vec![];
+        for entry in s.split(',') {
+            let (workload, weight) = match entry.find('=') {
+                Some(pos) => (&entry[..pos], &entry[pos + 1..]),
+                None => (entry, "1"),
+            };
+            let workload: Workload = workload.trim().parse()?;
+            let weight: u32 =
+                weight.trim().parse().map_err(|_| format!("invalid weight in {}", entry))?;
+            if weight > 0 {
+                weights.push((workload, weight));
+            }
+        }
+        let total = weights.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return Err("workload mix is empty".to_string());
+        }
+        Ok(WorkloadMix { weights, total })
+    }
+}
+
+/// Target of the `function_call` workload.
+#[derive(Clone, Debug)]
+pub struct FunctionCallConfig {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    pub args: Vec<u8>,
+    pub gas: Gas,
+    pub deposit: Balance,
+}
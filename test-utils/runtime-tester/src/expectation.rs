@@ -0,0 +1,247 @@
+//! Expected outcomes that a scenario can check after a block has been processed.
+use std::collections::HashMap;
+use std::fmt;
+
+use near_client::Client;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::{
+    base64_format, option_base64_format, option_u128_dec_format, to_base64,
+};
+use near_primitives::types::{AccountId, Balance, BlockHeight, Gas};
+use near_primitives::views::{
+    FinalExecutionOutcomeView, FinalExecutionStatus, QueryRequest, QueryResponseKind,
+};
+use serde::{Deserialize, Serialize};
+
+/// A condition on the chain state as seen by a node after processing the block it is attached
+/// to. Transactions are referred to by the height of the block they were submitted with and
+/// their index in that block. Note that a transaction submitted with block `h` is executed in
+/// block `h + 1` and its receipts may need more blocks, so transaction expectations usually
+/// belong to later blocks.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Expectation {
+    /// Final status of the transaction, including all the receipts it produced.
+    TransactionStatus { height: BlockHeight, index: usize, status: ExpectedStatus },
+    /// Every given string is emitted as a log by the transaction or one of its receipts.
+    TransactionLogs { height: BlockHeight, index: usize, contains: Vec<String> },
+    /// Total gas burnt by the transaction and its receipts lies within the range.
+    GasBurnt {
+        height: BlockHeight,
+        index: usize,
+        #[serde(default)]
+        min: Option<Gas>,
+        #[serde(default)]
+        max: Option<Gas>,
+    },
+    /// Liquid balance of the account lies within the range. Set `min` and `max` to the same
+    /// value to check for an exact amount.
+    Balance {
+        account_id: AccountId,
+        #[serde(default, with = "option_u128_dec_format")]
+        min: Option<Balance>,
+        #[serde(default, with = "option_u128_dec_format")]
+        max: Option<Balance>,
+    },
+    /// Value stored by the contract under the given key, `None` if the key must be absent.
+    StorageValue {
+        account_id: AccountId,
+        #[serde(with = "base64_format")]
+        key: Vec<u8>,
+        #[serde(default, with = "option_base64_format")]
+        value: Option<Vec<u8>>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ExpectedStatus {
+    /// Succeeded with any return value.
+    Success,
+    /// Succeeded and returned exactly the given value.
+    SuccessValue {
+        #[serde(with = "base64_format")]
+        value: Vec<u8>,
+    },
+    /// Failed with any error.
+    Failure,
+}
+
+/// An expectation that did not hold.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Mismatch {
+    /// Height of the block after which the expectation was checked.
+    pub height: BlockHeight,
+    /// Index of the node on which the expectation was checked.
+    pub node: usize,
+    pub expectation: Expectation,
+    /// What was observed instead.
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {}, node {}: expected {:?}, got {}",
+            self.height, self.node, self.expectation, self.actual
+        )
+    }
+}
+
+impl Expectation {
+    /// Checks the expectation against the head of the client's chain. Returns a description of
+    /// the actual state if the expectation does not hold.
+    pub(crate) fn check(
+        &self,
+        client: &mut Client,
+        tx_hashes: &HashMap<(BlockHeight, usize), CryptoHash>,
+    ) -> Option<String> {
+        match self {
+            Expectation::TransactionStatus { height, index, status } => {
+                let outcome = match transaction_outcome(client, tx_hashes, *height, *index) {
+                    Ok(outcome) => outcome,
+                    Err(err) => return Some(err),
+                };
+                let matches = match (status, &outcome.status) {
+                    (ExpectedStatus::Success, FinalExecutionStatus::SuccessValue(_)) => true,
+                    (
+                        ExpectedStatus::SuccessValue { value },
+                        FinalExecutionStatus::SuccessValue(actual),
+                    ) => &to_base64(value) == actual,
+                    (ExpectedStatus::Failure, FinalExecutionStatus::Failure(_)) => true,
+                    _ => false,
+                };
+                if matches {
+                    None
+                } else {
+                    Some(format!("{:?}", outcome.status))
+                }
+            }
+            Expectation::TransactionLogs { height, index, contains } => {
+                let outcome = match transaction_outcome(client, tx_hashes, *height, *index) {
+                    Ok(outcome) => outcome,
+                    Err(err) => return Some(err),
+                };
+                let logs: Vec<&String> = std::iter::once(&outcome.transaction_outcome)
+                    .chain(outcome.receipts_outcome.iter())
+                    .flat_map(|outcome| outcome.outcome.logs.iter())
+                    .collect();
+                if contains.iter().all(|expected| logs.contains(&expected)) {
+                    None
+                } else {
+                    Some(format!("logs {:?}", logs))
+                }
+            }
+            Expectation::GasBurnt { height, index, min, max } => {
+                let outcome = match transaction_outcome(client, tx_hashes, *height, *index) {
+                    Ok(outcome) => outcome,
+                    Err(err) => return Some(err),
+                };
+                let gas_burnt: Gas = std::iter::once(&outcome.transaction_outcome)
+                    .chain(outcome.receipts_outcome.iter())
+                    .map(|outcome| outcome.outcome.gas_burnt)
+                    .sum();
+                if in_range(gas_burnt, *min, *max) {
+                    None
+                } else {
+                    Some(format!("{} gas burnt", gas_burnt))
+                }
+            }
+            Expectation::Balance { account_id, min, max } => {
+                let request = QueryRequest::ViewAccount { account_id: account_id.clone() };
+                match query(client, account_id, &request) {
+                    Ok(QueryResponseKind::ViewAccount(account)) => {
+                        if in_range(account.amount, *min, *max) {
+                            None
+                        } else {
+                            Some(format!("balance {}", account.amount))
+                        }
+                    }
+                    Ok(response) => Some(format!("unexpected response {:?}", response)),
+                    Err(err) => Some(err),
+                }
+            }
+            Expectation::StorageValue { account_id, key, value } => {
+                let request = QueryRequest::ViewState {
+                    account_id: account_id.clone(),
+                    prefix: key.clone().into(),
+                };
+                match query(client, account_id, &request) {
+                    Ok(QueryResponseKind::ViewState(state)) => {
+                        let key = to_base64(key);
+                        let actual = state
+                            .values
+                            .into_iter()
+                            .find(|item| item.key == key)
+                            .map(|item| item.value);
+                        if actual == value.as_ref().map(to_base64) {
+                            None
+                        } else {
+                            Some(format!("value {:?}", actual))
+                        }
+                    }
+                    Ok(response) => Some(format!("unexpected response {:?}", response)),
+                    Err(err) => Some(err),
+                }
+            }
+        }
+    }
+}
+
+fn in_range<T: PartialOrd>(value: T, min: Option<T>, max: Option<T>) -> bool {
+    min.map_or(true, |min| min <= value) && max.map_or(true, |max| value <= max)
+}
+
+fn transaction_outcome(
+    client: &mut Client,
+    tx_hashes: &HashMap<(BlockHeight, usize), CryptoHash>,
+    height: BlockHeight,
+    index: usize,
+) -> Result<FinalExecutionOutcomeView, String> {
+    let tx_hash = tx_hashes
+        .get(&(height, index))
+        .ok_or_else(|| format!("no transaction {} submitted at height {}", index, height))?;
+    client
+        .chain
+        .get_final_transaction_result(tx_hash)
+        .map_err(|err| format!("transaction {} has not finished: {}", tx_hash, err))
+}
+
+/// Runs the query against the state of the account's shard after the head block.
+fn query(
+    client: &mut Client,
+    account_id: &AccountId,
+    request: &QueryRequest,
+) -> Result<QueryResponseKind, String> {
+    let head = client.chain.head().map_err(|err| err.to_string())?;
+    let header = client
+        .chain
+        .get_block_header(&head.last_block_hash)
+        .map_err(|err| err.to_string())?
+        .clone();
+    let runtime_adapter = client.runtime_adapter.clone();
+    let shard_id = runtime_adapter
+        .account_id_to_shard_id(account_id, header.epoch_id())
+        .map_err(|err| err.to_string())?;
+    let shard_uid = runtime_adapter
+        .shard_id_to_uid(shard_id, header.epoch_id())
+        .map_err(|err| err.to_string())?;
+    let state_root = *client
+        .chain
+        .get_chunk_extra(header.hash(), &shard_uid)
+        .map_err(|err| format!("shard {} is not tracked: {}", shard_id, err))?
+        .state_root();
+    runtime_adapter
+        .query(
+            shard_uid,
+            &state_root,
+            header.height(),
+            header.raw_timestamp(),
+            header.prev_hash(),
+            header.hash(),
+            header.epoch_id(),
+            request,
+            None,
+        )
+        .map(|response| response.kind)
+        .map_err(|err| err.to_string())
+}
@@ -36,7 +36,7 @@ impl Arbitrary<'_> for Scenario {
 
         let mut scope = Scope::from_seeds(&seeds);
 
-        let network_config = NetworkConfig { seeds };
+        let network_config = NetworkConfig::from_seeds(seeds);
 
         let mut blocks = vec![];
 
@@ -2,11 +2,15 @@
 //! You can create Scenario in rust code or have it in a JSON file.
 //! Scenario::run executes scenario, keeping track of different metrics.
 //! So far, the only metric is how much time block production takes.
+//! Blocks may carry expectations about the resulting state; the ones that do not hold on some
+//! node are reported in RuntimeStats::mismatches.
 //! fuzzing provides Arbitrary trait for Scenario, thus enabling creating random scenarios.
+pub mod expectation;
 pub mod fuzzing;
 pub mod run_test;
 
-pub use crate::run_test::{BlockConfig, NetworkConfig, Scenario, TransactionConfig};
+pub use crate::expectation::{Expectation, ExpectedStatus, Mismatch};
+pub use crate::run_test::{BlockConfig, NetworkConfig, RuntimeStats, Scenario, TransactionConfig};
 
 #[test]
 // Use this test as a base for creating reproducers.
@@ -21,7 +25,7 @@ fn scenario_smoke_test() {
     let accounts: Vec<AccountId> = seeds.iter().map(|id| id.parse().unwrap()).collect();
 
     let mut scenario = Scenario {
-        network_config: NetworkConfig { seeds: seeds },
+        network_config: NetworkConfig::from_seeds(seeds),
         blocks: Vec::new(),
         use_in_memory_store: true,
     };
@@ -48,3 +52,59 @@ fn scenario_smoke_test() {
 
     scenario.run().unwrap();
 }
+
+#[test]
+fn scenario_expectations_test() {
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::transaction::{Action, TransferAction};
+    use near_primitives::types::AccountId;
+    use nearcore::config::TESTING_INIT_BALANCE;
+
+    let seeds: Vec<String> = (0..4).map(|i| format!("test{}", i)).collect();
+    let mut network_config = NetworkConfig::from_seeds(seeds);
+    network_config.num_shards = 2;
+    network_config.num_nodes = 2;
+    let mut scenario = Scenario { network_config, blocks: Vec::new(), use_in_memory_store: true };
+
+    let transfer = |nonce, signer_id: &str, receiver_id: &str| {
+        let signer_id: AccountId = signer_id.parse().unwrap();
+        TransactionConfig {
+            nonce,
+            signer_id: signer_id.clone(),
+            receiver_id: receiver_id.parse().unwrap(),
+            signer: InMemorySigner::from_seed(
+                signer_id.clone(),
+                KeyType::ED25519,
+                signer_id.as_ref(),
+            ),
+            actions: vec![Action::Transfer(TransferAction { deposit: 10 })],
+        }
+    };
+
+    let mut block = BlockConfig::at_height(1);
+    block.transactions.push(transfer(1, "test2", "test3"));
+    block.transactions.push(transfer(1, "test1", "nonexistent"));
+    scenario.blocks.push(block);
+    for h in 2..5 {
+        scenario.blocks.push(BlockConfig::at_height(h));
+    }
+    scenario.blocks[3].expectations = vec![
+        Expectation::TransactionStatus { height: 1, index: 0, status: ExpectedStatus::Success },
+        Expectation::TransactionStatus { height: 1, index: 1, status: ExpectedStatus::Failure },
+        Expectation::Balance {
+            account_id: "test3".parse().unwrap(),
+            min: Some(TESTING_INIT_BALANCE + 10),
+            max: Some(TESTING_INIT_BALANCE + 10),
+        },
+    ];
+    let stats = scenario.run().unwrap();
+    assert!(stats.mismatches.is_empty(), "{:?}", stats.mismatches);
+
+    scenario.blocks[3].expectations = vec![Expectation::Balance {
+        account_id: "test3".parse().unwrap(),
+        min: None,
+        max: Some(TESTING_INIT_BALANCE),
+    }];
+    let stats = scenario.run().unwrap();
+    assert_eq!(stats.mismatches.len(), 2);
+}
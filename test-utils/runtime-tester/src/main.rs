@@ -0,0 +1,54 @@
+use std::path::Path;
+use std::process;
+
+use clap::{App, Arg};
+
+use near_logger_utils::init_integration_logger;
+use runtime_tester::Scenario;
+
+fn main() {
+    init_integration_logger();
+
+    let matches = App::new("runtime-tester")
+        .about("Runs scenario files and reports expectations that do not hold")
+        .arg(
+            Arg::with_name("scenario")
+                .help("Path to a scenario JSON file")
+                .required(true)
+                .multiple(true),
+        )
+        .get_matches();
+
+    let mut failed = 0;
+    for path in matches.values_of("scenario").unwrap() {
+        let scenario = match Scenario::from_file(Path::new(path)) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                println!("{}: failed to read scenario: {}", path, err);
+                failed += 1;
+                continue;
+            }
+        };
+        match scenario.run() {
+            Ok(stats) if stats.mismatches.is_empty() => {
+                println!("{}: ok, {} blocks", path, stats.blocks_stats.len());
+            }
+            Ok(stats) => {
+                println!("{}: {} mismatches", path, stats.mismatches.len());
+                for mismatch in stats.mismatches {
+                    println!("  {}", mismatch);
+                }
+                failed += 1;
+            }
+            Err(err) => {
+                println!("{}: error while running scenario: {}", path, err);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        println!("{} scenarios failed", failed);
+        process::exit(1);
+    }
+}
@@ -1,15 +1,20 @@
+use std::collections::HashMap;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use near_chain::{Block, ChainGenesis, Provenance, RuntimeAdapter};
+use near_chain::types::AcceptedBlock;
+use near_chain::{Block, ChainGenesis, ErrorKind, Provenance, RuntimeAdapter};
 use near_chain_configs::Genesis;
 use near_client::test_utils::TestEnv;
+use near_client::Client;
 use near_client_primitives::types::Error;
 use near_crypto::InMemorySigner;
+use near_network::NetworkRequests;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::transaction::{Action, SignedTransaction};
-use near_primitives::types::{AccountId, BlockHeight, Nonce};
+use near_primitives::types::{AccountId, BlockHeight, Nonce, NumShards};
 use near_store::create_store;
 use near_store::test_utils::create_test_store;
 use nearcore::{config::GenesisExt, NightshadeRuntime};
@@ -17,52 +22,68 @@ use nearcore::{config::GenesisExt, NightshadeRuntime};
 use near_primitives::runtime::config_store::RuntimeConfigStore;
 use serde::{Deserialize, Serialize};
 
+use crate::expectation::{Expectation, Mismatch};
+
 impl Scenario {
     pub fn from_file(path: &Path) -> io::Result<Scenario> {
         serde_json::from_str::<Scenario>(&std::fs::read_to_string(path)?).map_err(io::Error::from)
     }
 
     pub fn run(&self) -> Result<RuntimeStats, Error> {
-        let genesis = Genesis::test(
-            self.network_config.seeds.iter().map(|x| x.parse().unwrap()).collect(),
-            1,
-        );
-
-        let tempdir;
-        let store = if self.use_in_memory_store {
-            create_test_store()
+        let accounts: Vec<AccountId> =
+            self.network_config.seeds.iter().map(|x| x.parse().unwrap()).collect();
+        let num_shards = self.network_config.num_shards;
+        let genesis = if num_shards == 1 {
+            Genesis::test(accounts, 1)
         } else {
-            tempdir = tempfile::tempdir().map_err(|err| {
-                Error::Other(format!("failed to create temporary directory: {}", err))
-            })?;
-            create_store(tempdir.path())
+            Genesis::test_sharded(accounts, 1, vec![1; num_shards as usize])
         };
 
-        let mut env = TestEnv::new_with_runtime(
-            ChainGenesis::from(&genesis),
-            1,
-            1,
-            vec![Arc::new(NightshadeRuntime::new(
+        let num_nodes = self.network_config.num_nodes;
+        if num_nodes == 0 {
+            return Err(Error::Other(String::from("Scenario needs at least one node")));
+        }
+
+        let mut tempdirs = vec![];
+        let mut runtimes = vec![];
+        for _ in 0..num_nodes {
+            let store = if self.use_in_memory_store {
+                create_test_store()
+            } else {
+                let tempdir = tempfile::tempdir().map_err(|err| {
+                    Error::Other(format!("failed to create temporary directory: {}", err))
+                })?;
+                let store = create_store(tempdir.path());
+                tempdirs.push(tempdir);
+                store
+            };
+            runtimes.push(Arc::new(NightshadeRuntime::new(
                 Path::new("."),
                 store,
                 &genesis,
                 vec![],
-                vec![],
+                (0..num_shards).collect(),
                 None,
                 None,
                 RuntimeConfigStore::test(),
-            )) as Arc<dyn RuntimeAdapter>],
-        );
+            )) as Arc<dyn RuntimeAdapter>);
+        }
+
+        let mut env =
+            TestEnv::new_with_runtime(ChainGenesis::from(&genesis), num_nodes, 1, runtimes);
 
         let mut last_block = env.clients[0].chain.get_block_by_height(0).unwrap().clone();
 
         let mut runtime_stats = RuntimeStats::default();
+        let mut tx_hashes = HashMap::new();
 
         for block in &self.blocks {
             let mut block_stats = BlockStats::at_height(block.height);
 
-            for tx in &block.transactions {
-                env.clients[0].process_tx(tx.to_signed_transaction(&last_block), false, false);
+            for (index, tx) in block.transactions.iter().enumerate() {
+                let signed_tx = tx.to_signed_transaction(&last_block);
+                tx_hashes.insert((block.height, index), signed_tx.get_hash());
+                env.clients[0].process_tx(signed_tx, false, false);
             }
 
             let start_time = Instant::now();
@@ -74,6 +95,21 @@ impl Scenario {
 
             block_stats.block_production_time = start_time.elapsed();
 
+            propagate_block(&mut env, &last_block)?;
+
+            for (node, client) in env.clients.iter_mut().enumerate() {
+                for expectation in &block.expectations {
+                    if let Some(actual) = expectation.check(client, &tx_hashes) {
+                        runtime_stats.mismatches.push(Mismatch {
+                            height: block.height,
+                            node,
+                            expectation: expectation.clone(),
+                            actual,
+                        });
+                    }
+                }
+            }
+
             runtime_stats.blocks_stats.push(block_stats);
         }
 
@@ -81,6 +117,76 @@ impl Scenario {
     }
 }
 
+/// Delivers the block produced by node 0 to the other nodes. Node 0 is the only validator and
+/// produces all the chunks, so it also serves the chunk parts the other nodes request.
+fn propagate_block(env: &mut TestEnv, block: &Block) -> Result<(), Error> {
+    let num_nodes = env.clients.len();
+    for node in 1..num_nodes {
+        let (accepted_blocks, result) =
+            env.clients[node].process_block(block.clone(), Provenance::NONE);
+        if let Err(err) = result {
+            match err.kind() {
+                ErrorKind::ChunksMissing(_) => {}
+                _ => return Err(err.into()),
+            }
+        }
+        accept_blocks(&mut env.clients[node], accepted_blocks);
+    }
+
+    let route_back = |node: usize| -> CryptoHash { hash(&node.to_le_bytes()) };
+    loop {
+        for node in 1..num_nodes {
+            while let Some(request) = env.network_adapters[node].pop() {
+                if let NetworkRequests::PartialEncodedChunkRequest { request, .. } = request {
+                    let client = &mut env.clients[0];
+                    client.shards_mgr.process_partial_encoded_chunk_request(
+                        request,
+                        route_back(node),
+                        client.chain.mut_store(),
+                    );
+                }
+            }
+        }
+        let mut delivered = false;
+        while let Some(response) = env.network_adapters[0].pop() {
+            if let NetworkRequests::PartialEncodedChunkResponse { route_back: to, response } =
+                response
+            {
+                if let Some(node) = (1..num_nodes).find(|node| route_back(*node) == to) {
+                    let accepted_blocks =
+                        env.clients[node].process_partial_encoded_chunk_response(response)?;
+                    accept_blocks(&mut env.clients[node], accepted_blocks);
+                    delivered = true;
+                }
+            }
+        }
+        if !delivered {
+            break;
+        }
+    }
+
+    for node in 1..num_nodes {
+        if env.clients[node].chain.head()?.last_block_hash != *block.hash() {
+            return Err(Error::Other(format!(
+                "Node {} failed to process block at height {}",
+                node,
+                block.header().height()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn accept_blocks(client: &mut Client, accepted_blocks: Vec<AcceptedBlock>) {
+    for accepted_block in accepted_blocks {
+        client.on_block_accepted(
+            accepted_block.hash,
+            accepted_block.status,
+            accepted_block.provenance,
+        );
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Scenario {
     pub network_config: NetworkConfig,
@@ -91,12 +197,29 @@ pub struct Scenario {
 #[derive(Serialize, Deserialize)]
 pub struct NetworkConfig {
     pub seeds: Vec<String>,
+    #[serde(default = "default_num_shards")]
+    pub num_shards: NumShards,
+    /// Number of nodes running the scenario. Node 0 produces all blocks and chunks, the others
+    /// track all shards and must agree with it on every expectation.
+    #[serde(default = "default_num_nodes")]
+    pub num_nodes: usize,
+}
+
+fn default_num_shards() -> NumShards {
+    1
+}
+
+fn default_num_nodes() -> usize {
+    1
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct BlockConfig {
     pub height: BlockHeight,
     pub transactions: Vec<TransactionConfig>,
+    /// Checked on every node after the block has been processed.
+    #[serde(default)]
+    pub expectations: Vec<Expectation>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -111,6 +234,8 @@ pub struct TransactionConfig {
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct RuntimeStats {
     pub blocks_stats: Vec<BlockStats>,
+    #[serde(default)]
+    pub mismatches: Vec<Mismatch>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug)]
@@ -127,7 +252,13 @@ impl std::fmt::Debug for Scenario {
 
 impl BlockConfig {
     pub fn at_height(height: BlockHeight) -> Self {
-        Self { height, transactions: vec![] }
+        Self { height, transactions: vec![], expectations: vec![] }
+    }
+}
+
+impl NetworkConfig {
+    pub fn from_seeds(seeds: Vec<String>) -> Self {
+        Self { seeds, num_shards: default_num_shards(), num_nodes: default_num_nodes() }
     }
 }
 
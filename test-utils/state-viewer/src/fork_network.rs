@@ -0,0 +1,214 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use near_chain_configs::{get_initial_supply, Genesis, GenesisRecords};
+use near_crypto::{InMemorySigner, KeyType, Signer};
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::CryptoHash;
+use near_primitives::state_record::StateRecord;
+use near_primitives::types::{AccountId, AccountInfo, BlockHeightDelta, NumSeats};
+use near_primitives::utils::{from_timestamp, get_num_seats_per_shard};
+use nearcore::config::{Config, CONFIG_FILENAME, TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
+
+const RECORDS_FILE: &str = "records.json";
+const FIRST_NETWORK_PORT: u16 = 24567;
+const FIRST_RPC_PORT: u16 = 3030;
+
+/// What to replace in the state of the original network.
+pub struct ForkConfig {
+    pub chain_id: String,
+    pub epoch_length: Option<BlockHeightDelta>,
+    /// Validators of the forked network, each staking `TESTING_INIT_STAKE`.
+    pub validators: Vec<AccountId>,
+    /// Accounts whose access keys are replaced with a local test key, in addition to validators.
+    pub local_key_accounts: Vec<AccountId>,
+}
+
+/// Test key of an account of the forked network. The account id is used as the seed, the same
+/// way `init_configs` creates the keys of test validators.
+fn local_signer(account_id: &AccountId) -> InMemorySigner {
+    InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, account_id.as_ref())
+}
+
+/// Turns the genesis produced by `state_dump` into the genesis of a local network: the validator
+/// set, epoch length and chain id are replaced, all stake of the original network is returned to
+/// the accounts and the access keys of the chosen accounts are replaced with local test keys.
+/// Accounts that don't exist in the original state are created.
+pub fn fork_genesis(genesis: Genesis, fork_config: &ForkConfig) -> Genesis {
+    let Genesis { mut config, records: GenesisRecords(mut records), .. } = genesis;
+    let validators: HashSet<&AccountId> = fork_config.validators.iter().collect();
+    let local_key_accounts: BTreeSet<&AccountId> =
+        fork_config.validators.iter().chain(fork_config.local_key_accounts.iter()).collect();
+
+    records.retain(|record| match record {
+        StateRecord::AccessKey { account_id, .. } => !local_key_accounts.contains(account_id),
+        _ => true,
+    });
+    let mut existing_accounts = HashSet::new();
+    for record in records.iter_mut() {
+        if let StateRecord::Account { account_id, account } = record {
+            let balance = account.amount() + account.locked();
+            let stake = if validators.contains(account_id) { TESTING_INIT_STAKE } else { 0 };
+            account.set_amount(balance.saturating_sub(stake));
+            account.set_locked(stake);
+            existing_accounts.insert(account_id.clone());
+        }
+    }
+    for account_id in local_key_accounts {
+        if !existing_accounts.contains(account_id) {
+            let stake = if validators.contains(account_id) { TESTING_INIT_STAKE } else { 0 };
+            records.push(StateRecord::Account {
+                account_id: account_id.clone(),
+                account: Account::new(TESTING_INIT_BALANCE, stake, CryptoHash::default(), 0),
+            });
+        }
+        records.push(StateRecord::AccessKey {
+            account_id: account_id.clone(),
+            public_key: local_signer(account_id).public_key(),
+            access_key: AccessKey::full_access(),
+        });
+    }
+
+    config.chain_id = fork_config.chain_id.clone();
+    if let Some(epoch_length) = fork_config.epoch_length {
+        config.epoch_length = epoch_length;
+    }
+    // Rewards of the first epoch are computed from the genesis time.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    config.genesis_time = from_timestamp(now);
+    config.validators = fork_config
+        .validators
+        .iter()
+        .map(|account_id| AccountInfo {
+            account_id: account_id.clone(),
+            public_key: local_signer(account_id).public_key(),
+            amount: TESTING_INIT_STAKE,
+        })
+        .collect();
+    let num_validators = fork_config.validators.len() as NumSeats;
+    if config.num_block_producer_seats < num_validators {
+        config.num_block_producer_seats = num_validators;
+        config.num_block_producer_seats_per_shard =
+            get_num_seats_per_shard(config.shard_layout.num_shards(), num_validators);
+    }
+    config.total_supply = get_initial_supply(&records);
+    Genesis::new(config, records.into())
+}
+
+/// Writes a home directory for each validator of the forked network into `output_dir`, created
+/// with `init_configs` and pointing to the forked genesis. The nodes listen on local ports and
+/// share a single records file. Keys of the accounts with local test keys are written to `keys`.
+pub fn write_fork_network(genesis: &Genesis, fork_config: &ForkConfig, output_dir: &Path) {
+    fs::create_dir_all(output_dir).unwrap();
+    genesis.records.to_file(output_dir.join(RECORDS_FILE));
+
+    let mut boot_nodes = String::new();
+    for (i, account_id) in fork_config.validators.iter().enumerate() {
+        let node_dir = output_dir.join(account_id.as_ref());
+        nearcore::init_configs(
+            &node_dir,
+            Some(&fork_config.chain_id),
+            Some(account_id.clone()),
+            Some(account_id.as_ref()),
+            genesis.config.shard_layout.num_shards(),
+            false,
+            None,
+            false,
+            None,
+            false,
+            None,
+            None,
+            None,
+        );
+        let mut config = Config::from_file(&node_dir.join(CONFIG_FILENAME));
+        let network_port = FIRST_NETWORK_PORT + i as u16;
+        config.network.addr = format!("127.0.0.1:{}", network_port);
+        config.set_rpc_addr(format!("127.0.0.1:{}", FIRST_RPC_PORT + i as u16));
+        config.network.boot_nodes = boot_nodes.clone();
+        config.network.skip_sync_wait = fork_config.validators.len() == 1;
+        config.genesis_records_file = Some(format!("../{}", RECORDS_FILE));
+        config.write_to_file(&node_dir.join(CONFIG_FILENAME));
+        genesis.config.to_file(node_dir.join(&config.genesis_file));
+        if i == 0 {
+            let node_key = InMemorySigner::from_file(&node_dir.join(&config.node_key_file));
+            boot_nodes = format!("{}@127.0.0.1:{}", node_key.public_key, network_port);
+        }
+        println!("Generated node home for validator {} in {}", account_id, node_dir.display());
+    }
+
+    let keys_dir = output_dir.join("keys");
+    fs::create_dir_all(&keys_dir).unwrap();
+    let local_key_accounts: BTreeSet<&AccountId> =
+        fork_config.validators.iter().chain(fork_config.local_key_accounts.iter()).collect();
+    for account_id in local_key_accounts {
+        local_signer(account_id).write_to_file(&keys_dir.join(format!("{}.json", account_id)));
+    }
+    println!("Wrote keys of accounts with local test keys into {}", keys_dir.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use nearcore::config::GenesisExt;
+
+    use super::*;
+
+    #[test]
+    fn test_fork_genesis() {
+        let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+        let genesis = Genesis::test(accounts.clone(), 2);
+        let test1_balance = genesis
+            .records
+            .as_ref()
+            .iter()
+            .find_map(|record| match record {
+                StateRecord::Account { account_id, account } if account_id == &accounts[1] => {
+                    Some(account.amount() + account.locked())
+                }
+                _ => None,
+            })
+            .unwrap();
+
+        let fork_config = ForkConfig {
+            chain_id: "fork".to_string(),
+            epoch_length: Some(10),
+            validators: vec!["node0".parse().unwrap()],
+            local_key_accounts: vec![accounts[1].clone()],
+        };
+        let genesis = fork_genesis(genesis, &fork_config);
+        assert_eq!(genesis.config.chain_id, "fork");
+        assert_eq!(genesis.config.epoch_length, 10);
+        assert_eq!(
+            genesis.config.validators,
+            vec![AccountInfo {
+                account_id: "node0".parse().unwrap(),
+                public_key: local_signer(&"node0".parse().unwrap()).public_key(),
+                amount: TESTING_INIT_STAKE,
+            }]
+        );
+
+        for record in genesis.records.as_ref() {
+            match record {
+                StateRecord::Account { account_id, account } if account_id == &accounts[1] => {
+                    // Stake of the original validator is returned.
+                    assert_eq!(account.locked(), 0);
+                    assert_eq!(account.amount(), test1_balance);
+                }
+                StateRecord::AccessKey { account_id, public_key, .. }
+                    if account_id == &accounts[1] =>
+                {
+                    assert_eq!(public_key, &local_signer(account_id).public_key());
+                }
+                _ => {}
+            }
+        }
+        let keys = genesis
+            .records
+            .as_ref()
+            .iter()
+            .filter(|record| matches!(record, StateRecord::AccessKey { account_id, .. } if account_id == &accounts[1]))
+            .count();
+        assert_eq!(keys, 1);
+    }
+}
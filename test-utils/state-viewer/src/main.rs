@@ -11,10 +11,16 @@ use clap::{App, AppSettings, Arg, SubCommand};
 use tracing::info;
 
 use borsh::BorshSerialize;
+use fork_network::{fork_genesis, write_fork_network, ForkConfig};
 use near_chain::chain::collect_receipts_from_response;
 use near_chain::migrations::check_if_block_is_first_with_chunk_of_version;
+use near_chain::state_sync_dump::{write_state_header, write_state_part};
 use near_chain::types::{ApplyTransactionResult, BlockHeaderInfo};
-use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate, RuntimeAdapter};
+use near_chain::{
+    Chain, ChainGenesis, ChainStore, ChainStoreAccess, ChainStoreUpdate, DoomslugThresholdMode,
+    RuntimeAdapter,
+};
+use near_chain_configs::records_dump::RecordsFilter;
 use near_epoch_manager::EpochManager;
 use near_logger_utils::init_integration_logger;
 use near_network::peer_store::PeerStore;
@@ -25,16 +31,20 @@ use near_primitives::runtime::config_store::RuntimeConfigStore;
 use near_primitives::serialize::to_base;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_record::StateRecord;
+use near_primitives::syncing::get_num_state_parts;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, ShardId, StateRoot};
+use near_primitives::types::{AccountId, BlockHeight, ShardId, StateRoot};
 use near_store::test_utils::create_test_store;
 use near_store::{create_store, Store, TrieIterator};
 use nearcore::{get_default_home, get_store_path, load_config, NearConfig, NightshadeRuntime};
 use node_runtime::adapter::ViewRuntimeAdapter;
-use state_dump::state_dump;
+use state_dump::{state_dump, state_dump_records};
+use storage_usage::audit_storage_usage;
 
+mod fork_network;
 mod state_dump;
+mod storage_usage;
 
 #[allow(unused)]
 enum LoadTrieMode {
@@ -666,6 +676,52 @@ fn dump_code(account: &str, contract_code: ContractCode, output: &str) {
     println!("Dump contract of account {} into file {}", account, output);
 }
 
+/// Dumps the state headers and parts of all shards for `sync_hash` into `output_dir`, so that a
+/// node can take the state from there instead of downloading it from peers.
+fn dump_state_parts(
+    store: Arc<Store>,
+    home_dir: &Path,
+    near_config: &NearConfig,
+    sync_hash: Option<CryptoHash>,
+    output_dir: &Path,
+) {
+    let runtime = Arc::new(NightshadeRuntime::new(
+        &home_dir,
+        store,
+        &near_config.genesis,
+        near_config.client_config.tracked_accounts.clone(),
+        near_config.client_config.tracked_shards.clone(),
+        None,
+        near_config.client_config.max_gas_burnt_view,
+        RuntimeConfigStore::new(Some(&near_config.genesis.config.runtime_config)),
+    ));
+    let mut chain = Chain::new(
+        runtime.clone(),
+        &ChainGenesis::from(&near_config.genesis),
+        DoomslugThresholdMode::TwoThirds,
+    )
+    .unwrap();
+    // By default take the first block of the latest epoch.
+    let sync_hash = sync_hash.unwrap_or_else(|| {
+        let head = chain.head().unwrap();
+        let epoch_start_height = runtime.get_epoch_start_height(&head.last_block_hash).unwrap();
+        *chain.get_header_by_height(epoch_start_height).unwrap().hash()
+    });
+    let epoch_id = chain.get_block_header(&sync_hash).unwrap().epoch_id().clone();
+    let num_shards = runtime.num_shards(&epoch_id).unwrap();
+    println!("Dumping state parts for sync hash {} into {}", sync_hash, output_dir.display());
+    for shard_id in 0..num_shards {
+        let header = chain.get_state_response_header(shard_id, sync_hash).unwrap();
+        let num_parts = get_num_state_parts(header.state_root_node().memory_usage);
+        write_state_header(output_dir, &sync_hash, shard_id, &header).unwrap();
+        for part_id in 0..num_parts {
+            let part = chain.get_state_response_part(shard_id, part_id, sync_hash).unwrap();
+            write_state_part(output_dir, &sync_hash, shard_id, part_id, &part).unwrap();
+        }
+        println!("Shard {}: dumped header and {} parts", shard_id, num_parts);
+    }
+}
+
 fn main() {
     init_integration_logger();
 
@@ -689,6 +745,96 @@ fn main() {
                     .takes_value(true),
             ),
         )
+        .subcommand(
+            SubCommand::with_name("dump_state_records")
+                .arg(
+                    Arg::with_name("height")
+                        .long("height")
+                        .help("Desired stop height of state dump")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output_dir")
+                        .long("output_dir")
+                        .help("Directory to write the records of each shard and the genesis config into")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("account_prefix")
+                        .long("account_prefix")
+                        .help("Only dump records of accounts starting with this prefix, can be repeated")
+                        .takes_value(true)
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("record_types")
+                        .long("record_types")
+                        .help("Comma separated record types to dump: account, data, contract, access_key, received_data, postponed_receipt, delayed_receipt (default: all)")
+                        .takes_value(true),
+                )
+                .help("stream the state of all shards into compact checksummed records files, loadable with genesis-populate"),
+        )
+        .subcommand(
+            SubCommand::with_name("fork_network")
+                .arg(
+                    Arg::with_name("height")
+                        .long("height")
+                        .help("Desired stop height of state dump")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output_dir")
+                        .long("output_dir")
+                        .help("Directory to write the home directories of the validators into")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("chain_id")
+                        .long("chain_id")
+                        .help("Chain id of the forked network")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("epoch_length")
+                        .long("epoch_length")
+                        .help("Epoch length of the forked network (default: the one of the original network)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("validators")
+                        .long("validators")
+                        .help("Comma separated validators of the forked network")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("local_key_accounts")
+                        .long("local_key_accounts")
+                        .help("Comma separated accounts whose access keys are replaced with local test keys")
+                        .takes_value(true),
+                )
+                .help("create a local network with own validators starting from the current state"),
+        )
+        .subcommand(
+            SubCommand::with_name("dump_state_parts")
+                .arg(
+                    Arg::with_name("sync_hash")
+                        .long("sync_hash")
+                        .help("First block of the epoch to dump the state for (default: latest epoch)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output_dir")
+                        .long("output_dir")
+                        .help("Directory to dump the state parts into, usable as `state_sync_dump_dir` in config")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .help("dump state headers and parts of all shards for state sync"),
+        )
         .subcommand(
             SubCommand::with_name("chain")
                 .arg(
@@ -855,6 +1001,32 @@ fn main() {
                 )
                 .help("dump contract data in storage of given account to binary file"),
         )
+        .subcommand(
+            SubCommand::with_name("storage_usage")
+                .arg(
+                    Arg::with_name("height")
+                        .long("height")
+                        .help("Height of the block to audit the state before (default: head)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("migration_data")
+                        .long("migration_data")
+                        .help("Write storage usage deltas to this file, in the format of nearcore/res/storage_usage_delta.json")
+                        .takes_value(true),
+                )
+                .help("compare stored storage usage of all accounts with the one computed from the trie"),
+        )
+        .subcommand(
+            SubCommand::with_name("precompile_contracts")
+                .arg(
+                    Arg::with_name("purge")
+                        .long("purge")
+                        .help("Remove cached contracts compiled for other VM versions or configs, or no longer deployed")
+                        .takes_value(false),
+                )
+                .help("compile contracts deployed in the tracked shards into the compiled contracts cache"),
+        )
         .get_matches();
 
     let home_dir = matches.value_of("home").map(|dir| Path::new(dir)).unwrap();
@@ -907,6 +1079,71 @@ fn main() {
             );
             new_genesis.to_file(&output_path);
         }
+        ("dump_state_records", Some(args)) => {
+            let mode = match args.value_of("height") {
+                Some(h) => LoadTrieMode::LastFinalFromHeight(h.parse().unwrap()),
+                None => LoadTrieMode::Latest,
+            };
+            let output_dir = Path::new(args.value_of("output_dir").unwrap());
+            let filter = RecordsFilter {
+                account_prefixes: args
+                    .values_of("account_prefix")
+                    .map(|prefixes| prefixes.map(|prefix| prefix.to_string()).collect())
+                    .unwrap_or_default(),
+                kinds: args.value_of("record_types").map(|kinds| {
+                    kinds.split(',').map(|kind| kind.trim().parse().unwrap()).collect()
+                }),
+            };
+            let (runtime, state_roots, header) =
+                load_trie_stop_at_height(store, home_dir, &near_config, mode);
+            state_dump_records(
+                runtime,
+                state_roots,
+                header,
+                &near_config.genesis.config,
+                output_dir,
+                &filter,
+            )
+            .unwrap();
+            println!("Saved records and genesis config into {}", output_dir.display());
+        }
+        ("fork_network", Some(args)) => {
+            let chain_id = args.value_of("chain_id").unwrap();
+            if ["mainnet", "testnet", "betanet"].contains(&chain_id) {
+                panic!("Chain id of the forked network must differ from {}", chain_id);
+            }
+            let parse_accounts = |name| -> Vec<AccountId> {
+                args.value_of(name)
+                    .map(|accounts| {
+                        accounts.split(',').map(|account| account.trim().parse().unwrap()).collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let fork_config = ForkConfig {
+                chain_id: chain_id.to_string(),
+                epoch_length: args.value_of("epoch_length").map(|s| s.parse().unwrap()),
+                validators: parse_accounts("validators"),
+                local_key_accounts: parse_accounts("local_key_accounts"),
+            };
+            let mode = match args.value_of("height") {
+                Some(h) => LoadTrieMode::LastFinalFromHeight(h.parse().unwrap()),
+                None => LoadTrieMode::Latest,
+            };
+            let (runtime, state_roots, header) =
+                load_trie_stop_at_height(store, home_dir, &near_config, mode);
+            let genesis = state_dump(runtime, state_roots, header, &near_config.genesis.config);
+            let genesis = fork_genesis(genesis, &fork_config);
+            write_fork_network(
+                &genesis,
+                &fork_config,
+                Path::new(args.value_of("output_dir").unwrap()),
+            );
+        }
+        ("dump_state_parts", Some(args)) => {
+            let sync_hash = args.value_of("sync_hash").map(|s| s.parse().unwrap());
+            let output_dir = Path::new(args.value_of("output_dir").unwrap());
+            dump_state_parts(store, home_dir, &near_config, sync_hash, output_dir);
+        }
         ("chain", Some(args)) => {
             let start_index =
                 args.value_of("start_index").map(|s| s.parse::<u64>().unwrap()).unwrap();
@@ -1016,6 +1253,37 @@ fn main() {
             println!("Storage under key {} of account {} not found", storage_key, account_id);
             std::process::exit(1);
         }
+        ("storage_usage", Some(args)) => {
+            let mode = match args.value_of("height") {
+                Some(height) => LoadTrieMode::Height(height.parse().unwrap()),
+                None => LoadTrieMode::Latest,
+            };
+            let (runtime, state_roots, header) =
+                load_trie_stop_at_height(store, &home_dir, &near_config, mode);
+            let protocol_version = runtime.get_epoch_protocol_version(header.epoch_id()).unwrap();
+            let config = RuntimeConfigStore::new(Some(&near_config.genesis.config.runtime_config))
+                .get_config(protocol_version)
+                .clone();
+            let audit = audit_storage_usage(&runtime, &state_roots, &header, &config);
+            println!("{}", audit);
+            if let Some(path) = args.value_of("migration_data") {
+                let written = audit.write_migration_data(Path::new(path)).unwrap();
+                println!("Wrote storage usage deltas of {} accounts into {}", written, path);
+            }
+        }
+        ("precompile_contracts", Some(args)) => {
+            let purge = args.is_present("purge");
+            let (runtime, state_roots, header) = load_trie(store, &home_dir, &near_config);
+            let (stats, purged) =
+                runtime.warmup_contracts_cache(header.prev_hash(), &state_roots, purge).unwrap();
+            println!(
+                "Contracts: {}, already cached: {}, compiled: {}, failed to compile: {}",
+                stats.contracts, stats.cached, stats.compiled, stats.failed
+            );
+            if purge {
+                println!("Purged {} stale compiled contracts", purged);
+            }
+        }
         (_, _) => unreachable!(),
     }
 }
@@ -1,29 +1,33 @@
 use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use borsh::{BorshDeserialize, BorshSerialize};
 
 use near_chain::RuntimeAdapter;
+use near_chain_configs::records_dump::{
+    shard_records_path, RecordsFilter, RecordsWriter, GENESIS_CONFIG_FILE,
+};
 use near_chain_configs::{get_initial_supply, Genesis, GenesisConfig};
+use near_crypto::PublicKey;
+use near_primitives::account::Account;
 use near_primitives::block::BlockHeader;
-use near_primitives::state_record::StateRecord;
-use near_primitives::types::{AccountInfo, StateRoot};
+use near_primitives::state_record::{StateRecord, StateRecordKind};
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_account_key;
+use near_primitives::types::{AccountId, AccountInfo, Balance, StateRoot};
 use near_store::TrieIterator;
 use nearcore::NightshadeRuntime;
 
-pub fn state_dump(
-    runtime: NightshadeRuntime,
-    state_roots: Vec<StateRoot>,
-    last_block_header: BlockHeader,
-    genesis_config: &GenesisConfig,
-) -> Genesis {
-    println!(
-        "Generating genesis from state data of #{} / {}",
-        last_block_header.height(),
-        last_block_header.hash()
-    );
-    let genesis_height = last_block_header.height() + 1;
+/// Validators of the epoch of `last_block_header` that are not slashed.
+fn get_validators(
+    runtime: &NightshadeRuntime,
+    last_block_header: &BlockHeader,
+) -> HashMap<AccountId, (PublicKey, Balance)> {
     let block_producers = runtime
         .get_epoch_block_producers_ordered(&last_block_header.epoch_id(), last_block_header.hash())
         .unwrap();
-    let validators = block_producers
+    block_producers
         .into_iter()
         .filter_map(|(info, is_slashed)| {
             if !is_slashed {
@@ -33,7 +37,58 @@ pub fn state_dump(
                 None
             }
         })
-        .collect::<HashMap<_, _>>();
+        .collect()
+}
+
+/// Returns the locked tokens of accounts that are not validators in the new genesis.
+fn unlock_stake(
+    account_id: &AccountId,
+    account: &mut Account,
+    validators: &HashMap<AccountId, (PublicKey, Balance)>,
+) {
+    if account.locked() > 0 {
+        let stake = *validators.get(account_id).map(|(_, s)| s).unwrap_or(&0);
+        account.set_amount(account.amount() + account.locked() - stake);
+        account.set_locked(stake);
+    }
+}
+
+fn new_genesis_config(
+    runtime: &NightshadeRuntime,
+    last_block_header: &BlockHeader,
+    genesis_config: &GenesisConfig,
+    validators: HashMap<AccountId, (PublicKey, Balance)>,
+    total_supply: Balance,
+) -> GenesisConfig {
+    let mut genesis_config = genesis_config.clone();
+    genesis_config.genesis_height = last_block_header.height() + 1;
+    genesis_config.validators = validators
+        .into_iter()
+        .map(|(account_id, (public_key, amount))| AccountInfo { account_id, public_key, amount })
+        .collect();
+    // Record the protocol version of the latest block. Otherwise, the state
+    // dump ignores the fact that the nodes can be running a newer protocol
+    // version than the protocol version of the genesis.
+    genesis_config.protocol_version = last_block_header.latest_protocol_version();
+    // `total_supply` is expected to change due to the natural processes of burning tokens and
+    // minting tokens every epoch.
+    genesis_config.total_supply = total_supply;
+    genesis_config.shard_layout = runtime.get_shard_layout(last_block_header.epoch_id()).unwrap();
+    genesis_config
+}
+
+pub fn state_dump(
+    runtime: NightshadeRuntime,
+    state_roots: Vec<StateRoot>,
+    last_block_header: BlockHeader,
+    genesis_config: &GenesisConfig,
+) -> Genesis {
+    println!(
+        "Generating genesis from state data of #{} / {}",
+        last_block_header.height(),
+        last_block_header.hash()
+    );
+    let validators = get_validators(&runtime, &last_block_header);
 
     let mut records = vec![];
     for (shard_id, state_root) in state_roots.iter().enumerate() {
@@ -44,32 +99,76 @@ pub fn state_dump(
             let (key, value) = item.unwrap();
             if let Some(mut sr) = StateRecord::from_raw_key_value(key, value) {
                 if let StateRecord::Account { account_id, account } = &mut sr {
-                    if account.locked() > 0 {
-                        let stake = *validators.get(account_id).map(|(_, s)| s).unwrap_or(&0);
-                        account.set_amount(account.amount() + account.locked() - stake);
-                        account.set_locked(stake);
-                    }
+                    unlock_stake(account_id, account, &validators);
                 }
                 records.push(sr);
             }
         }
     }
 
-    let mut genesis_config = genesis_config.clone();
-    genesis_config.genesis_height = genesis_height;
-    genesis_config.validators = validators
+    let total_supply = get_initial_supply(&records);
+    let genesis_config =
+        new_genesis_config(&runtime, &last_block_header, genesis_config, validators, total_supply);
+    Genesis::new(genesis_config, records.into())
+}
+
+/// Streams the state into one records dump file per shard in `output_dir`, see
+/// `near_chain_configs::records_dump`, keeping only the records accepted by `filter`. Unlike
+/// `state_dump`, records are never held in memory. The genesis config for the dumped state is
+/// written next to the records and returned.
+pub fn state_dump_records(
+    runtime: NightshadeRuntime,
+    state_roots: Vec<StateRoot>,
+    last_block_header: BlockHeader,
+    genesis_config: &GenesisConfig,
+    output_dir: &Path,
+    filter: &RecordsFilter,
+) -> io::Result<GenesisConfig> {
+    println!(
+        "Dumping records from state data of #{} / {}",
+        last_block_header.height(),
+        last_block_header.hash()
+    );
+    fs::create_dir_all(output_dir)?;
+    // Validators whose accounts are filtered out would not be backed by any account in the dump.
+    let validators: HashMap<_, _> = get_validators(&runtime, &last_block_header)
         .into_iter()
-        .map(|(account_id, (public_key, amount))| AccountInfo { account_id, public_key, amount })
+        .filter(|(account_id, _)| filter.accepts_account(account_id))
         .collect();
-    // Record the protocol version of the latest block. Otherwise, the state
-    // dump ignores the fact that the nodes can be running a newer protocol
-    // version than the protocol version of the genesis.
-    genesis_config.protocol_version = last_block_header.latest_protocol_version();
-    // `total_supply` is expected to change due to the natural processes of burning tokens and
-    // minting tokens every epoch.
-    genesis_config.total_supply = get_initial_supply(&records);
-    genesis_config.shard_layout = runtime.get_shard_layout(last_block_header.epoch_id()).unwrap();
-    Genesis::new(genesis_config, records.into())
+
+    // The total supply accounts for every account kept by `account_prefixes`, even when `kinds`
+    // excludes the account records themselves.
+    let mut total_supply = 0;
+    for (shard_id, state_root) in state_roots.iter().enumerate() {
+        let shard_id = shard_id as u64;
+        let trie = runtime.get_trie_for_shard(shard_id, last_block_header.prev_hash()).unwrap();
+        let mut writer =
+            RecordsWriter::create(&shard_records_path(output_dir, shard_id), shard_id)?;
+        for item in TrieIterator::new(&trie, &state_root).unwrap() {
+            let (key, value) = item.unwrap();
+            if StateRecordKind::from_raw_key(&key) == Some(StateRecordKind::Account) {
+                let account_id = parse_account_id_from_account_key(&key)?;
+                if !filter.accepts_account(&account_id) {
+                    continue;
+                }
+                let mut account = Account::try_from_slice(&value)?;
+                unlock_stake(&account_id, &mut account, &validators);
+                total_supply += account.amount() + account.locked();
+                if filter.accepts(&key) {
+                    writer.write(&key, &account.try_to_vec()?)?;
+                }
+            } else if filter.accepts(&key) {
+                writer.write(&key, &value)?;
+            }
+        }
+        let num_records = writer.finish()?;
+        println!("Shard {}: dumped {} records", shard_id, num_records);
+    }
+
+    let genesis_config =
+        new_genesis_config(&runtime, &last_block_header, genesis_config, validators, total_supply);
+    genesis_config.to_file(output_dir.join(GENESIS_CONFIG_FILE));
+    Ok(genesis_config)
 }
 
 #[cfg(test)]
@@ -92,8 +191,13 @@ mod test {
     use nearcore::config::TESTING_INIT_STAKE;
     use nearcore::NightshadeRuntime;
 
-    use crate::state_dump::state_dump;
+    use crate::state_dump::{state_dump, state_dump_records};
+    use near_chain_configs::records_dump::{
+        shard_records_path, RecordsFilter, RecordsReader, GENESIS_CONFIG_FILE,
+    };
+    use near_chain_configs::{get_initial_supply, GenesisConfig};
     use near_primitives::runtime::config_store::RuntimeConfigStore;
+    use near_primitives::state_record::{StateRecord, StateRecordKind};
 
     fn setup(epoch_length: NumBlocks) -> (Arc<Store>, Genesis, TestEnv) {
         let mut genesis =
@@ -189,6 +293,111 @@ mod test {
         validate_genesis(&new_genesis);
     }
 
+    /// Test that the streaming dump has the same records and genesis config as the in-memory one.
+    #[test]
+    fn test_dump_state_records() {
+        let epoch_length = 4;
+        let (store, genesis, mut env) = setup(epoch_length);
+        safe_produce_blocks(&mut env, 1, epoch_length + 1);
+
+        let head = env.clients[0].chain.head().unwrap();
+        let last_block = env.clients[0].chain.get_block(&head.last_block_hash).unwrap().clone();
+        let state_roots: Vec<_> =
+            last_block.chunks().iter().map(|chunk| chunk.prev_state_root()).collect();
+        let create_runtime = || {
+            NightshadeRuntime::new(
+                Path::new("."),
+                store.clone(),
+                &genesis,
+                vec![],
+                vec![],
+                None,
+                None,
+                RuntimeConfigStore::test(),
+            )
+        };
+        let new_genesis = state_dump(
+            create_runtime(),
+            state_roots.clone(),
+            last_block.header().clone(),
+            &genesis.config,
+        );
+
+        let dir = tempfile::Builder::new().prefix("state_records").tempdir().unwrap();
+        let genesis_config = state_dump_records(
+            create_runtime(),
+            state_roots.clone(),
+            last_block.header().clone(),
+            &genesis.config,
+            dir.path(),
+            &RecordsFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(genesis_config.total_supply, new_genesis.config.total_supply);
+        assert_eq!(genesis_config.validators.len(), new_genesis.config.validators.len());
+        assert_eq!(
+            GenesisConfig::from_file(dir.path().join(GENESIS_CONFIG_FILE)).total_supply,
+            genesis_config.total_supply
+        );
+
+        let mut num_records = 0;
+        for shard_id in 0..state_roots.len() as u64 {
+            let reader = RecordsReader::open(&shard_records_path(dir.path(), shard_id)).unwrap();
+            assert_eq!(reader.shard_id(), shard_id);
+            for item in reader {
+                let (key, value) = item.unwrap();
+                if StateRecord::from_raw_key_value(key, value).is_some() {
+                    num_records += 1;
+                }
+            }
+        }
+        assert_eq!(num_records, new_genesis.records.as_ref().len());
+
+        let filter = RecordsFilter {
+            account_prefixes: vec!["test1".to_string()],
+            kinds: Some(vec![StateRecordKind::Account].into_iter().collect()),
+        };
+        let genesis_config = state_dump_records(
+            create_runtime(),
+            state_roots.clone(),
+            last_block.header().clone(),
+            &genesis.config,
+            dir.path(),
+            &filter,
+        )
+        .unwrap();
+        let records: Vec<_> = RecordsReader::open(&shard_records_path(dir.path(), 0))
+            .unwrap()
+            .map(|item| {
+                let (key, value) = item.unwrap();
+                StateRecord::from_raw_key_value(key, value).unwrap()
+            })
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(genesis_config.total_supply, get_initial_supply(&records));
+        let validators: Vec<_> =
+            genesis_config.validators.iter().map(|info| info.account_id.as_ref()).collect();
+        assert_eq!(validators, vec!["test1"]);
+
+        // Accounts kept by the prefixes count towards the total supply even when their records
+        // are not dumped.
+        let filter = RecordsFilter {
+            account_prefixes: vec!["test1".to_string()],
+            kinds: Some(vec![StateRecordKind::AccessKey].into_iter().collect()),
+        };
+        let access_keys_config = state_dump_records(
+            create_runtime(),
+            state_roots,
+            last_block.header().clone(),
+            &genesis.config,
+            dir.path(),
+            &filter,
+        )
+        .unwrap();
+        assert_eq!(access_keys_config.total_supply, genesis_config.total_supply);
+        assert_eq!(access_keys_config.validators.len(), 1);
+    }
+
     /// Test that we return locked tokens for accounts that are not validators.
     #[test]
     fn test_dump_state_return_locked() {
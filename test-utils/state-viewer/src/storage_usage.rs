@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use near_chain::RuntimeAdapter;
+use near_primitives::block::BlockHeader;
+use near_primitives::runtime::config::RuntimeConfig;
+use near_primitives::state_record::StateRecord;
+use near_primitives::types::{AccountId, ShardId, StateRoot, StorageUsage};
+use near_store::{Trie, TrieIterator};
+use nearcore::NightshadeRuntime;
+use node_runtime::StorageComputer;
+
+/// Account whose `storage_usage` differs from the storage usage computed from its records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsageDiscrepancy {
+    pub account_id: AccountId,
+    pub shard_id: ShardId,
+    pub stored: StorageUsage,
+    pub computed: StorageUsage,
+}
+
+impl StorageUsageDiscrepancy {
+    pub fn delta(&self) -> i128 {
+        self.computed as i128 - self.stored as i128
+    }
+}
+
+/// Result of comparing the stored storage usage of all accounts with the one computed from the
+/// records in the trie.
+#[derive(Debug, Default)]
+pub struct StorageUsageAudit {
+    pub accounts: u64,
+    pub total_storage_usage: u128,
+    /// Number of accounts whose computed storage usage is in `[2^i, 2^(i+1))` bytes.
+    pub histogram: Vec<u64>,
+    pub discrepancies: Vec<StorageUsageDiscrepancy>,
+    /// Accounts that have data, code or access keys but no account record.
+    pub orphaned: Vec<(AccountId, ShardId)>,
+}
+
+impl StorageUsageAudit {
+    /// Iterates over the trie of a single shard and adds its accounts to the audit. Only the
+    /// accounts of this shard are kept in memory at a time.
+    pub fn audit_shard(
+        &mut self,
+        shard_id: ShardId,
+        trie: &Trie,
+        state_root: &StateRoot,
+        config: &RuntimeConfig,
+    ) {
+        let mut storage_computer = StorageComputer::new(config);
+        let mut stored = HashMap::new();
+        for item in TrieIterator::new(trie, state_root).unwrap() {
+            let (key, value) = item.unwrap();
+            if let Some(record) = StateRecord::from_raw_key_value(key, value) {
+                if let StateRecord::Account { account_id, account } = &record {
+                    stored.insert(account_id.clone(), account.storage_usage());
+                }
+                storage_computer.process_record(&record);
+            }
+        }
+
+        let mut computed: Vec<_> = storage_computer.finalize().into_iter().collect();
+        computed.sort();
+        for (account_id, computed) in computed {
+            let stored = match stored.get(&account_id) {
+                Some(stored) => *stored,
+                None => {
+                    self.orphaned.push((account_id, shard_id));
+                    continue;
+                }
+            };
+            self.accounts += 1;
+            self.total_storage_usage += computed as u128;
+            let bucket = (64 - computed.leading_zeros() as usize).saturating_sub(1);
+            if self.histogram.len() <= bucket {
+                self.histogram.resize(bucket + 1, 0);
+            }
+            self.histogram[bucket] += 1;
+            if stored != computed {
+                self.discrepancies.push(StorageUsageDiscrepancy {
+                    account_id,
+                    shard_id,
+                    stored,
+                    computed,
+                });
+            }
+        }
+    }
+
+    /// Writes the deltas in the format of `MigrationData::storage_usage_delta`, see
+    /// `nearcore/res/storage_usage_delta.json`. The migration can only increase storage usage,
+    /// so accounts that use less storage than recorded are left out.
+    pub fn write_migration_data(&self, path: &Path) -> io::Result<usize> {
+        let deltas: Vec<(AccountId, u64)> = self
+            .discrepancies
+            .iter()
+            .filter(|discrepancy| discrepancy.computed > discrepancy.stored)
+            .map(|discrepancy| {
+                (discrepancy.account_id.clone(), discrepancy.computed - discrepancy.stored)
+            })
+            .collect();
+        serde_json::to_writer_pretty(File::create(path)?, &deltas)?;
+        Ok(deltas.len())
+    }
+}
+
+impl fmt::Display for StorageUsageAudit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Accounts: {}", self.accounts)?;
+        writeln!(f, "Total storage usage: {} bytes", self.total_storage_usage)?;
+        writeln!(f, "Storage usage distribution:")?;
+        for (bucket, count) in self.histogram.iter().enumerate() {
+            if *count > 0 {
+                writeln!(
+                    f,
+                    "  [{:>11}, {:>11}) {:>10}",
+                    1u64 << bucket,
+                    1u64 << (bucket + 1),
+                    count
+                )?;
+            }
+        }
+        writeln!(f, "Discrepancies: {}", self.discrepancies.len())?;
+        for discrepancy in &self.discrepancies {
+            writeln!(
+                f,
+                "  shard {} {}: stored {}, computed {}, delta {}",
+                discrepancy.shard_id,
+                discrepancy.account_id,
+                discrepancy.stored,
+                discrepancy.computed,
+                discrepancy.delta()
+            )?;
+        }
+        if !self.orphaned.is_empty() {
+            writeln!(f, "Records without an account: {}", self.orphaned.len())?;
+            for (account_id, shard_id) in &self.orphaned {
+                writeln!(f, "  shard {} {}", shard_id, account_id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Audits the storage usage of all accounts in the state after `header`'s previous block, using
+/// the runtime config of the protocol version of `header`'s epoch.
+pub fn audit_storage_usage(
+    runtime: &NightshadeRuntime,
+    state_roots: &[StateRoot],
+    header: &BlockHeader,
+    config: &RuntimeConfig,
+) -> StorageUsageAudit {
+    let mut audit = StorageUsageAudit::default();
+    for (shard_id, state_root) in state_roots.iter().enumerate() {
+        let shard_id = shard_id as ShardId;
+        let trie = runtime.get_trie_for_shard(shard_id, header.prev_hash()).unwrap();
+        audit.audit_shard(shard_id, &trie, state_root, config);
+        println!("Shard {}: audited, {} discrepancies so far", shard_id, audit.discrepancies.len());
+    }
+    audit
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::account::{AccessKey, Account};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::trie_key::TrieKey;
+    use near_store::test_utils::{create_tries, test_populate_trie};
+    use near_store::ShardUId;
+
+    use super::*;
+
+    #[test]
+    fn test_audit_storage_usage() {
+        let config = RuntimeConfig::default();
+        let storage_config = &config.transaction_costs.storage_usage_config;
+        let public_key = PublicKey::empty(KeyType::ED25519);
+        let access_key = AccessKey::full_access();
+        let key_usage = storage_config.num_extra_bytes_record
+            + public_key.try_to_vec().unwrap().len() as u64
+            + access_key.try_to_vec().unwrap().len() as u64;
+        let correct_usage = storage_config.num_bytes_account + key_usage;
+
+        let mut changes = vec![];
+        for (account_id, storage_usage) in
+            vec![("alice.near", correct_usage), ("bob.near", correct_usage - 10)]
+        {
+            let account_id: AccountId = account_id.parse().unwrap();
+            let account = Account::new(0, 0, CryptoHash::default(), storage_usage);
+            changes.push((
+                TrieKey::Account { account_id: account_id.clone() }.to_vec(),
+                Some(account.try_to_vec().unwrap()),
+            ));
+            changes.push((
+                TrieKey::AccessKey { account_id, public_key: public_key.clone() }.to_vec(),
+                Some(access_key.try_to_vec().unwrap()),
+            ));
+        }
+        let tries = create_tries();
+        let shard_uid = ShardUId::default();
+        let root = test_populate_trie(&tries, &Trie::empty_root(), shard_uid, changes);
+
+        let mut audit = StorageUsageAudit::default();
+        audit.audit_shard(0, &tries.get_trie_for_shard(shard_uid), &root, &config);
+        assert_eq!(audit.accounts, 2);
+        assert_eq!(audit.total_storage_usage, 2 * correct_usage as u128);
+        assert_eq!(
+            audit.discrepancies,
+            vec![StorageUsageDiscrepancy {
+                account_id: "bob.near".parse().unwrap(),
+                shard_id: 0,
+                stored: correct_usage - 10,
+                computed: correct_usage,
+            }]
+        );
+        assert!(audit.orphaned.is_empty());
+    }
+}